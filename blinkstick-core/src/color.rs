@@ -0,0 +1,142 @@
+use crate::float::{abs, rem_euclid, round};
+use crate::render::{ColorSpace, Easing};
+use alloc::vec::Vec;
+use core::fmt;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// A color as it is sent to an led, with a value for the red, green and blue channel
+///
+/// The default color is off, and colors can be used as map keys.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+/// A color with every channel off
+pub const COLOR_OFF: Color = Color::new(0, 0, 0);
+
+impl Color {
+    /// Every channel off, the same as `COLOR_OFF`
+    pub const BLACK: Color = Color::new(0, 0, 0);
+    /// Every channel at full brightness
+    pub const WHITE: Color = Color::new(255, 255, 255);
+    /// Only the red channel, at full brightness
+    pub const RED: Color = Color::new(255, 0, 0);
+    /// Only the green channel, at full brightness
+    pub const GREEN: Color = Color::new(0, 255, 0);
+    /// Only the blue channel, at full brightness
+    pub const BLUE: Color = Color::new(0, 0, 255);
+    /// Red and green at full brightness
+    pub const YELLOW: Color = Color::new(255, 255, 0);
+    /// Green and blue at full brightness
+    pub const CYAN: Color = Color::new(0, 255, 255);
+    /// Red and blue at full brightness
+    pub const MAGENTA: Color = Color::new(255, 0, 255);
+    /// The CSS orange, full red with some green
+    pub const ORANGE: Color = Color::new(255, 165, 0);
+    /// The CSS purple, red and blue at half brightness
+    pub const PURPLE: Color = Color::new(128, 0, 128);
+    /// The CSS pink, a pale red. Leds show it much whiter than a screen does.
+    pub const PINK: Color = Color::new(255, 192, 203);
+
+    /// Creates a color from its red, green and blue channel, usable in `const` and `static` items
+    ///
+    /// # Example
+    /// Defines a palette at compile time
+    /// ```
+    /// use blinkstick_core::Color;
+    ///
+    /// const STATUS: [Color; 3] = [Color::new(0, 192, 0), Color::new(255, 176, 0), Color::RED];
+    ///
+    /// assert_eq!(STATUS[2], Color {r: 255, g: 0, b: 0});
+    /// ```
+    pub const fn new(r: u8, g: u8, b: u8) -> Color {
+        Color { r, g, b }
+    }
+
+    /// Creates a color from a hue (0.0 - 360.0 degrees), saturation (0.0 - 1.0) and value (0.0 - 1.0)
+    ///
+    /// # Example
+    /// ```
+    /// use blinkstick_core::Color;
+    ///
+    /// assert_eq!(Color::from_hsv(120.0, 1.0, 1.0), Color {r: 0, g: 255, b: 0});
+    /// ```
+    pub fn from_hsv(hue: f32, saturation: f32, value: f32) -> Color {
+        let hue = rem_euclid(hue, 360.0) / 60.0;
+        let saturation = saturation.clamp(0.0, 1.0);
+        let value = value.clamp(0.0, 1.0);
+
+        let chroma = value * saturation;
+        let x = chroma * (1.0 - abs(hue % 2.0 - 1.0));
+        let (r, g, b) = match hue as u8 {
+            0 => (chroma, x, 0.0),
+            1 => (x, chroma, 0.0),
+            2 => (0.0, chroma, x),
+            3 => (0.0, x, chroma),
+            4 => (x, 0.0, chroma),
+            _ => (chroma, 0.0, x),
+        };
+
+        let m = value - chroma;
+        Color {
+            r: round((r + m) * 255.0) as u8,
+            g: round((g + m) * 255.0) as u8,
+            b: round((b + m) * 255.0) as u8,
+        }
+    }
+
+    /// Returns the hue (0.0 - 360.0 degrees), saturation (0.0 - 1.0) and value (0.0 - 1.0) of the color
+    pub fn to_hsv(self) -> (f32, f32, f32) {
+        let (r, g, b) = (self.r as f32 / 255.0, self.g as f32 / 255.0, self.b as f32 / 255.0);
+        let max = r.max(g).max(b);
+        let delta = max - r.min(g).min(b);
+
+        let hue = if delta == 0.0 {
+            0.0
+        } else if max == r {
+            60.0 * rem_euclid((g - b) / delta, 6.0)
+        } else if max == g {
+            60.0 * ((b - r) / delta + 2.0)
+        } else {
+            60.0 * ((r - g) / delta + 4.0)
+        };
+        let saturation = if max == 0.0 { 0.0 } else { delta / max };
+
+        (hue, saturation, max)
+    }
+
+    /// The `steps` colors of a gradient to `target`, excluding this color and ending at `target`
+    ///
+    /// # Arguments
+    /// * `target` - The color the gradient ends at
+    /// * `steps` - The number of colors in the gradient
+    /// * `easing` - Shapes the progress from one step to the next
+    /// * `space` - The color space the colors are interpolated in
+    ///
+    /// # Example
+    /// A perceptually even fade from red to blue for a preview of eight leds
+    /// ```
+    /// use blinkstick_core::render::ColorSpace;
+    /// use blinkstick_core::{Color, Easing};
+    ///
+    /// let red = Color {r: 255, g: 0, b: 0};
+    /// let preview = red.gradient_to(Color {r: 0, g: 0, b: 255}, 8, Easing::EaseInOut, ColorSpace::LinearLight);
+    ///
+    /// assert_eq!(preview.len(), 8);
+    /// ```
+    pub fn gradient_to(self, target: Color, steps: u16, easing: Easing, space: ColorSpace) -> Vec<Color> {
+        crate::render::gradient(self, target, steps, easing, space)
+    }
+}
+
+/// Formats the color in hex, like `#ff8000`
+impl fmt::Display for Color {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{:02x}{:02x}{:02x}", self.r, self.g, self.b)
+    }
+}
@@ -0,0 +1,203 @@
+use crate::render::scale_color;
+use crate::{Color, COLOR_OFF};
+use alloc::vec::Vec;
+use core::fmt;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// The colors of every led on a device, together with a brightness for every led.
+///
+/// The brightness is kept separately from the colors so dimming a frame, for example for a night mode,
+/// never loses the original color values. Only `render` applies the brightness.
+///
+/// # Example
+/// Dims a frame to 10% and back to full brightness again
+/// ```
+/// use blinkstick_core::{Color, LedFrame};
+///
+/// let mut frame = LedFrame::from(vec![Color {r: 200, g: 100, b: 50}; 8]);
+///
+/// frame.set_all_brightness(0.1);
+/// assert_eq!(frame.render()[0], Color {r: 20, g: 10, b: 5});
+///
+/// frame.set_all_brightness(1.0);
+/// assert_eq!(frame.render()[0], Color {r: 200, g: 100, b: 50});
+/// ```
+///
+/// Printing a frame shows every led as a colored block in terminals that support 24 bit colors, and its `Debug`
+/// output adds the hex color and brightness of every led, so failed frame comparisons can be read at a glance.
+#[derive(Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct LedFrame {
+    colors: Vec<Color>,
+    brightness: Vec<f32>,
+}
+
+impl LedFrame {
+    /// Creates a frame of `led_count` leds that are turned off, at full brightness
+    pub fn new(led_count: usize) -> LedFrame {
+        LedFrame::from(vec![COLOR_OFF; led_count])
+    }
+
+    /// The number of leds in the frame
+    pub fn len(&self) -> usize {
+        self.colors.len()
+    }
+
+    /// Returns `true` if the frame contains no leds
+    pub fn is_empty(&self) -> bool {
+        self.colors.is_empty()
+    }
+
+    /// The stored colors of every led, without brightness applied
+    pub fn colors(&self) -> &[Color] {
+        &self.colors
+    }
+
+    /// The stored color of a single led, without brightness applied
+    pub fn color(&self, led: usize) -> Option<Color> {
+        self.colors.get(led).copied()
+    }
+
+    /// Sets the stored color of a single led, leaving its brightness untouched
+    ///
+    /// # Panics
+    /// The call to `set_color` will panic if `led` is out of bounds for the frame.
+    pub fn set_color(&mut self, led: usize, color: Color) {
+        self.colors[led] = color;
+    }
+
+    /// The brightness (0.0 - 1.0) of a single led
+    pub fn brightness(&self, led: usize) -> Option<f32> {
+        self.brightness.get(led).copied()
+    }
+
+    /// Sets the brightness (0.0 - 1.0) of a single led, leaving its stored color untouched
+    ///
+    /// # Panics
+    /// The call to `set_brightness` will panic if `led` is out of bounds for the frame.
+    pub fn set_brightness(&mut self, led: usize, brightness: f32) {
+        self.brightness[led] = brightness.clamp(0.0, 1.0);
+    }
+
+    /// Sets the brightness (0.0 - 1.0) of every led, leaving the stored colors untouched
+    pub fn set_all_brightness(&mut self, brightness: f32) {
+        let brightness = brightness.clamp(0.0, 1.0);
+        self.brightness.iter_mut().for_each(|led| *led = brightness);
+    }
+
+    /// The colors of every led with their brightness applied, as they should appear on the device
+    pub fn render(&self) -> Vec<Color> {
+        self.colors
+            .iter()
+            .zip(self.brightness.iter())
+            .map(|(color, brightness)| scale_color(*color, *brightness))
+            .collect()
+    }
+}
+
+impl From<Vec<Color>> for LedFrame {
+    fn from(colors: Vec<Color>) -> LedFrame {
+        LedFrame {
+            brightness: vec![1.0; colors.len()],
+            colors,
+        }
+    }
+}
+
+impl<'a> From<&'a [Color]> for LedFrame {
+    fn from(colors: &'a [Color]) -> LedFrame {
+        LedFrame::from(colors.to_vec())
+    }
+}
+
+/// Two spaces with the color as their background, in the 24 bit color escape codes of most terminals
+fn terminal_block(f: &mut fmt::Formatter<'_>, color: Color) -> fmt::Result {
+    write!(f, "\x1b[48;2;{};{};{}m  \x1b[0m", color.r, color.g, color.b)
+}
+
+/// Shows the rendered color of every led as a block
+impl fmt::Display for LedFrame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for color in self.render() {
+            terminal_block(f, color)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// A single led of a frame in `Debug` output
+struct DebugLed(Color, f32);
+
+impl fmt::Debug for DebugLed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        terminal_block(f, scale_color(self.0, self.1))?;
+        write!(f, " {}", self.0)?;
+        if self.1 < 1.0 {
+            write!(f, " at {:.0}%", self.1 * 100.0)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Debug for LedFrame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("LedFrame ")?;
+        f.debug_list()
+            .entries(
+                self.colors
+                    .iter()
+                    .zip(self.brightness.iter())
+                    .map(|(color, brightness)| DebugLed(*color, *brightness)),
+            )
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn brightness_is_kept_separately_from_color() {
+        let color = Color { r: 255, g: 128, b: 3 };
+        let mut frame = LedFrame::from(vec![color; 2]);
+
+        frame.set_brightness(1, 0.0);
+
+        assert_eq!(frame.render(), vec![color, COLOR_OFF]);
+        assert_eq!(frame.colors(), &[color, color]);
+
+        frame.set_brightness(1, 1.0);
+        assert_eq!(frame.render(), vec![color, color]);
+    }
+
+    #[test]
+    fn brightness_is_clamped() {
+        let mut frame = LedFrame::new(1);
+
+        frame.set_brightness(0, 4.0);
+        assert_eq!(frame.brightness(0), Some(1.0));
+
+        frame.set_all_brightness(-1.0);
+        assert_eq!(frame.brightness(0), Some(0.0));
+    }
+
+    #[test]
+    fn formats_colors_and_frames() {
+        let mut frame = LedFrame::from(vec![Color::new(255, 128, 0), Color::BLUE]);
+        frame.set_brightness(1, 0.5);
+
+        assert_eq!(format!("{}", Color::new(255, 128, 0)), "#ff8000");
+        assert_eq!(
+            format!("{}", frame),
+            "\x1b[48;2;255;128;0m  \x1b[0m\x1b[48;2;0;0;128m  \x1b[0m"
+        );
+        assert_eq!(
+            format!("{:?}", frame),
+            "LedFrame [\x1b[48;2;255;128;0m  \x1b[0m #ff8000, \x1b[48;2;0;0;128m  \x1b[0m #0000ff at 50%]"
+        );
+    }
+}
@@ -0,0 +1,32 @@
+//! The hardware independent color math of blinkstick-rs: colors, gradients, easing, palettes and frames.
+//!
+//! The crate is `no_std` and only needs an allocator, so embedded projects can render the same effects on other
+//! LED targets. Without the default `std` feature the float functions are taken from `libm`, enable its feature
+//! instead:
+//! ```toml
+//! blinkstick-core = { version = "0.1", default-features = false, features = ["libm"] }
+//! ```
+
+#![no_std]
+
+#[macro_use]
+extern crate alloc;
+#[cfg(feature = "libm")]
+extern crate libm;
+#[cfg(feature = "serde")]
+extern crate serde;
+#[cfg(feature = "std")]
+extern crate std;
+
+#[cfg(not(any(feature = "std", feature = "libm")))]
+compile_error!("blinkstick-core needs either the std or the libm feature for its float functions");
+
+mod color;
+mod float;
+mod frame;
+pub mod palette;
+pub mod render;
+
+pub use color::{Color, COLOR_OFF};
+pub use frame::LedFrame;
+pub use render::{ColorSpace, Easing, WipeDirection};
@@ -0,0 +1,213 @@
+//! Curated palettes and status colors that stay distinguishable with common color vision deficiencies.
+//!
+//! The red-green safe colors are taken from the Okabe-Ito and IBM Design color blind safe palettes, which separate
+//! colors by hue along the blue-orange axis as well as by luminance.
+
+use crate::Color;
+
+/// The Okabe-Ito palette: orange, sky blue, bluish green, yellow, blue, vermillion and reddish purple.
+/// Distinguishable with deuteranopia and protanopia.
+pub const OKABE_ITO: [Color; 7] = [
+    Color {
+        r: 0xE6,
+        g: 0x9F,
+        b: 0x00,
+    },
+    Color {
+        r: 0x56,
+        g: 0xB4,
+        b: 0xE9,
+    },
+    Color {
+        r: 0x00,
+        g: 0x9E,
+        b: 0x73,
+    },
+    Color {
+        r: 0xF0,
+        g: 0xE4,
+        b: 0x42,
+    },
+    Color {
+        r: 0x00,
+        g: 0x72,
+        b: 0xB2,
+    },
+    Color {
+        r: 0xD5,
+        g: 0x5E,
+        b: 0x00,
+    },
+    Color {
+        r: 0xCC,
+        g: 0x79,
+        b: 0xA7,
+    },
+];
+
+/// The IBM Design color blind safe palette: ultramarine, indigo, magenta, orange and gold.
+/// Distinguishable with deuteranopia and protanopia.
+pub const IBM: [Color; 5] = [
+    Color {
+        r: 0x64,
+        g: 0x8F,
+        b: 0xFF,
+    },
+    Color {
+        r: 0x78,
+        g: 0x5E,
+        b: 0xF0,
+    },
+    Color {
+        r: 0xDC,
+        g: 0x26,
+        b: 0x7F,
+    },
+    Color {
+        r: 0xFE,
+        g: 0x61,
+        b: 0x00,
+    },
+    Color {
+        r: 0xFF,
+        g: 0xB0,
+        b: 0x00,
+    },
+];
+
+/// Red, teal, pink and grey, which avoid the blue-green and yellow-violet confusions of tritanopia
+pub const TRITANOPIA_SAFE: [Color; 4] = [
+    Color {
+        r: 0xD0,
+        g: 0x00,
+        b: 0x00,
+    },
+    Color {
+        r: 0x00,
+        g: 0xA0,
+        b: 0xA0,
+    },
+    Color {
+        r: 0xFF,
+        g: 0x8C,
+        b: 0xC6,
+    },
+    Color {
+        r: 0x80,
+        g: 0x80,
+        b: 0x80,
+    },
+];
+
+/// The colors used to show that something is fine, needs attention, or has failed
+///
+/// # Example
+/// Shows an error in colors that are distinguishable with deuteranopia
+/// ```
+/// use blinkstick_core::palette::{ColorVision, StatusColors};
+///
+/// let status = StatusColors::for_vision(ColorVision::Deuteranopia);
+/// assert_eq!(status.error, StatusColors::DEUTERANOPIA.error);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StatusColors {
+    pub ok: Color,
+    pub warn: Color,
+    pub error: Color,
+}
+
+impl StatusColors {
+    /// The conventional green, yellow and red
+    pub const STANDARD: StatusColors = StatusColors {
+        ok: Color {
+            r: 0x00,
+            g: 0xC0,
+            b: 0x00,
+        },
+        warn: Color {
+            r: 0xFF,
+            g: 0xB0,
+            b: 0x00,
+        },
+        error: Color {
+            r: 0xE0,
+            g: 0x00,
+            b: 0x00,
+        },
+    };
+
+    /// Blue, yellow and vermillion from the Okabe-Ito palette
+    pub const DEUTERANOPIA: StatusColors = StatusColors {
+        ok: OKABE_ITO[4],
+        warn: OKABE_ITO[3],
+        error: OKABE_ITO[5],
+    };
+
+    /// Indigo, orange and gold from the IBM palette, avoiding deep reds that appear dark with protanopia. Ok and
+    /// error are blue and yellow, which also tell apart by their brightness.
+    pub const PROTANOPIA: StatusColors = StatusColors {
+        ok: IBM[1],
+        warn: IBM[3],
+        error: IBM[4],
+    };
+
+    /// Teal, pink and red, avoiding the blue-green and yellow-violet pairs confused with tritanopia
+    pub const TRITANOPIA: StatusColors = StatusColors {
+        ok: TRITANOPIA_SAFE[1],
+        warn: TRITANOPIA_SAFE[2],
+        error: TRITANOPIA_SAFE[0],
+    };
+
+    /// Returns the status colors suited for a color vision deficiency
+    pub fn for_vision(vision: ColorVision) -> StatusColors {
+        match vision {
+            ColorVision::Typical => StatusColors::STANDARD,
+            ColorVision::Deuteranopia => StatusColors::DEUTERANOPIA,
+            ColorVision::Protanopia => StatusColors::PROTANOPIA,
+            ColorVision::Tritanopia => StatusColors::TRITANOPIA,
+        }
+    }
+}
+
+impl Default for StatusColors {
+    fn default() -> Self {
+        StatusColors::STANDARD
+    }
+}
+
+/// The kinds of color vision palettes and status colors are available for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorVision {
+    Typical,
+    Deuteranopia,
+    Protanopia,
+    Tritanopia,
+}
+
+/// Returns a palette suited for a color vision deficiency
+pub fn for_vision(vision: ColorVision) -> &'static [Color] {
+    match vision {
+        ColorVision::Typical | ColorVision::Deuteranopia => &OKABE_ITO,
+        ColorVision::Protanopia => &IBM,
+        ColorVision::Tritanopia => &TRITANOPIA_SAFE,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The luminance of a color, taking its channels as linear
+    fn luminance(color: Color) -> f32 {
+        0.2126 * color.r as f32 + 0.7152 * color.g as f32 + 0.0722 * color.b as f32
+    }
+
+    #[test]
+    fn protanopia_statuses_differ_in_brightness() {
+        let status = StatusColors::PROTANOPIA;
+
+        assert!(luminance(status.ok) < luminance(status.warn));
+        assert!(luminance(status.warn) < luminance(status.error));
+        assert!(luminance(status.error) > 1.5 * luminance(status.ok));
+    }
+}
@@ -0,0 +1,517 @@
+//! The hardware independent math behind every effect: gradients, easing, crossfades and brightness scaling.
+//! The encoding of frames into feature reports lives in the `wire` module of blinkstick-rs.
+//!
+//! Everything in this module is a pure function of its arguments, so it can be tested exhaustively and reused by
+//! code that previews effects without a BlinkStick.
+
+use crate::float::{abs, cos, ln, powf, rem_euclid, round, sqrt};
+use crate::{Color, COLOR_OFF};
+use alloc::vec::Vec;
+use core::time::Duration;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Gamma of the leds, used to crossfade in linear light so fades don't dip in brightness halfway
+pub const LED_GAMMA: f32 = 2.2;
+
+/// The rate of change over the course of an animation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Easing {
+    /// Changes at a constant rate
+    Linear,
+    /// Starts slowly and speeds up
+    EaseIn,
+    /// Starts quickly and slows down
+    EaseOut,
+    /// Starts and ends slowly
+    EaseInOut,
+}
+
+impl Easing {
+    /// Maps linear progress (0.0 - 1.0) onto eased progress (0.0 - 1.0)
+    pub fn apply(self, progress: f32) -> f32 {
+        let progress = progress.clamp(0.0, 1.0);
+        match self {
+            Easing::Linear => progress,
+            Easing::EaseIn => progress * progress,
+            Easing::EaseOut => progress * (2.0 - progress),
+            Easing::EaseInOut => progress * progress * (3.0 - 2.0 * progress),
+        }
+    }
+}
+
+/// The color space a gradient interpolates in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum ColorSpace {
+    /// Interpolates the channel values as they are sent to the device
+    Srgb,
+    /// Interpolates in linear light, so fades between colors don't dip in brightness halfway
+    LinearLight,
+    /// Interpolates hue, saturation and value, going the shorter way around the hue circle
+    Hsv,
+}
+
+/// The `steps` colors of a gradient from `start_color` to `target_color`, excluding the start color and ending at
+/// the target color. The progress of every step is shaped by `easing`.
+///
+/// # Example
+/// ```
+/// use blinkstick_core::render::{gradient, ColorSpace};
+/// use blinkstick_core::{Color, Easing};
+///
+/// let black = Color {r: 0, g: 0, b: 0};
+/// let red = Color {r: 200, g: 0, b: 0};
+///
+/// assert_eq!(gradient(black, red, 2, Easing::Linear, ColorSpace::Srgb), vec![Color {r: 100, g: 0, b: 0}, red]);
+/// ```
+pub fn gradient(start_color: Color, target_color: Color, steps: u16, easing: Easing, space: ColorSpace) -> Vec<Color> {
+    (1..=steps)
+        .map(|step| {
+            mix(
+                start_color,
+                target_color,
+                easing.apply(step as f32 / steps as f32),
+                space,
+            )
+        })
+        .collect()
+}
+
+/// Mixes two colors in a color space, `progress` 0.0 being `start` and 1.0 being `target`
+pub fn mix(start: Color, target: Color, progress: f32, space: ColorSpace) -> Color {
+    match space {
+        ColorSpace::Srgb => Color {
+            r: ((start.r as f32 * (1.0 - progress)) + (target.r as f32 * progress)) as u8,
+            g: ((start.g as f32 * (1.0 - progress)) + (target.g as f32 * progress)) as u8,
+            b: ((start.b as f32 * (1.0 - progress)) + (target.b as f32 * progress)) as u8,
+        },
+        ColorSpace::LinearLight => mix_linear_light(start, target, progress),
+        ColorSpace::Hsv => {
+            let (start_hue, start_saturation, start_value) = start.to_hsv();
+            let (target_hue, target_saturation, target_value) = target.to_hsv();
+            // A gray has no hue of its own, it takes the hue of the other color instead of fading through red
+            let start_hue = if start_saturation == 0.0 { target_hue } else { start_hue };
+            let target_hue = if target_saturation == 0.0 {
+                start_hue
+            } else {
+                target_hue
+            };
+            let hue_distance = rem_euclid(target_hue - start_hue + 540.0, 360.0) - 180.0;
+
+            Color::from_hsv(
+                start_hue + hue_distance * progress,
+                start_saturation + (target_saturation - start_saturation) * progress,
+                start_value + (target_value - start_value) * progress,
+            )
+        }
+    }
+}
+
+/// Computes the frames of a gamma-correct crossfade from `from` to `to`, ending at `to`.
+/// Returns no frames at all if both frames are already equal.
+pub fn crossfade_frames(from: &[Color], to: &[Color], steps: u16, easing: Easing) -> Vec<Vec<Color>> {
+    if from == to {
+        return Vec::new();
+    }
+
+    (1..=steps)
+        .map(|step| {
+            let progress = easing.apply(step as f32 / steps as f32);
+            from.iter()
+                .zip(to.iter())
+                .map(|(start, target)| {
+                    if start == target {
+                        *start
+                    } else {
+                        mix_linear_light(*start, *target, progress)
+                    }
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Mixes two colors in linear light, `progress` 0.0 being `start` and 1.0 being `target`
+pub fn mix_linear_light(start: Color, target: Color, progress: f32) -> Color {
+    let mix = |start: u8, target: u8| {
+        let start = powf(start as f32 / 255.0, LED_GAMMA);
+        let target = powf(target as f32 / 255.0, LED_GAMMA);
+        round(powf(start + (target - start) * progress, 1.0 / LED_GAMMA) * 255.0) as u8
+    };
+
+    Color {
+        r: mix(start.r, target.r),
+        g: mix(start.g, target.g),
+        b: mix(start.b, target.b),
+    }
+}
+
+/// Scales every channel of a color by `factor`, clamping the result to the valid channel range
+pub fn scale_color(color: Color, factor: f32) -> Color {
+    Color {
+        r: round(color.r as f32 * factor).clamp(0.0, 255.0) as u8,
+        g: round(color.g as f32 * factor).clamp(0.0, 255.0) as u8,
+        b: round(color.b as f32 * factor).clamp(0.0, 255.0) as u8,
+    }
+}
+
+/// Applies a gamma curve to every channel of a color, a `gamma` of 1.0 leaves the color as it is
+pub fn gamma_correct(color: Color, gamma: f32) -> Color {
+    if gamma == 1.0 {
+        return color;
+    }
+
+    let correct = |channel: u8| round(powf(channel as f32 / 255.0, gamma) * 255.0) as u8;
+    Color {
+        r: correct(color.r),
+        g: correct(color.g),
+        b: correct(color.b),
+    }
+}
+
+/// The channels of a color scaled by `brightness` and gamma corrected, before they are rounded to whole values
+///
+/// `gamma_correct(scale_color(color, brightness), gamma)` rounds twice, which leaves dark colors of slow fades on
+/// the same few values for many frames. Quantizing these exact values with `quantize` spreads the rounding over
+/// frames or neighbouring leds instead.
+pub fn exact_channels(color: Color, brightness: f32, gamma: f32) -> [f32; 3] {
+    let exact = |channel: u8| {
+        let scaled = (channel as f32 * brightness).clamp(0.0, 255.0) / 255.0;
+        if gamma == 1.0 {
+            scaled * 255.0
+        } else {
+            powf(scaled, gamma) * 255.0
+        }
+    };
+
+    [exact(color.r), exact(color.g), exact(color.b)]
+}
+
+/// Rounds exact channel values to a color after adding the rounding error carried over in `error`, which is left
+/// holding the error of this color to carry on
+///
+/// # Example
+/// Half a step shows as every other step
+/// ```
+/// use blinkstick_core::render::quantize;
+/// use blinkstick_core::Color;
+///
+/// let mut error = [0.0; 3];
+/// let first = quantize([0.5, 0.0, 0.0], &mut error);
+/// let second = quantize([0.5, 0.0, 0.0], &mut error);
+///
+/// assert_eq!(first.r + second.r, 1);
+/// ```
+pub fn quantize(channels: [f32; 3], error: &mut [f32; 3]) -> Color {
+    let mut round_channel = |index: usize| {
+        let target = channels[index] + error[index];
+        let rounded = round(target).clamp(0.0, 255.0);
+        error[index] = target - rounded;
+        rounded as u8
+    };
+
+    Color {
+        r: round_channel(0),
+        g: round_channel(1),
+        b: round_channel(2),
+    }
+}
+
+/// Brightness of a heartbeat at `phase` (0.0 - 1.0) of a single beat.
+/// A beat consists of a strong "lub" pulse followed by a weaker "dub" pulse and a rest until the next beat.
+pub fn heartbeat_envelope(phase: f32) -> f32 {
+    let pulse = |center: f32, half_width: f32| {
+        let distance = abs(phase - center);
+        if distance < half_width {
+            0.5 * (1.0 + cos(core::f32::consts::PI * distance / half_width))
+        } else {
+            0.0
+        }
+    };
+
+    pulse(0.08, 0.08) + 0.6 * pulse(0.3, 0.08)
+}
+
+/// The color of a black body glowing at `kelvin`, at full brightness. Candle light is around 1900 K, daylight
+/// around 6500 K.
+///
+/// Follows the curve fitted by Tanner Helland, which is accurate enough for leds between 1000 K and 40000 K.
+pub fn color_temperature(kelvin: f32) -> Color {
+    let temperature = kelvin.clamp(1000.0, 40000.0) / 100.0;
+    let channel = |value: f32| value.clamp(0.0, 255.0) as u8;
+
+    let r = if temperature <= 66.0 {
+        255.0
+    } else {
+        329.698_73 * powf(temperature - 60.0, -0.133_204_76)
+    };
+    let g = if temperature <= 66.0 {
+        99.470_8 * ln(temperature) - 161.119_57
+    } else {
+        288.122_17 * powf(temperature - 60.0, -0.075_514_85)
+    };
+    let b = if temperature >= 66.0 {
+        255.0
+    } else if temperature <= 19.0 {
+        0.0
+    } else {
+        138.517_73 * ln(temperature - 10.0) - 305.044_8
+    };
+
+    Color::new(channel(r), channel(g), channel(b))
+}
+
+/// The color of a sunrise at `progress` (0.0 - 1.0), from darkness through a dim deep red to warm daylight.
+/// The color temperature rises quickly at first while the brightness rises slowly, like the sky at dawn.
+pub fn sunrise_color(progress: f32) -> Color {
+    let progress = progress.clamp(0.0, 1.0);
+    let kelvin = 1000.0 + 4500.0 * Easing::EaseOut.apply(progress);
+
+    scale_color(color_temperature(kelvin), progress * progress)
+}
+
+/// Splits a single strobe flash into the time the leds are on and off, with the frequency capped at `max_frequency`
+///
+/// A frequency too low for its period to fit a `Duration` flashes once every `Duration::MAX`, and a `duty_cycle`
+/// that is not a number keeps the leds off.
+pub fn strobe_timing(frequency: f32, duty_cycle: f32, max_frequency: f32) -> (Duration, Duration) {
+    let frequency = frequency.min(max_frequency);
+    if frequency <= 0.0 {
+        return (Duration::ZERO, Duration::ZERO);
+    }
+
+    let period = Duration::try_from_secs_f32(1.0 / frequency).unwrap_or(Duration::MAX);
+    let duty_cycle = if duty_cycle.is_nan() {
+        0.0
+    } else {
+        duty_cycle.clamp(0.0, 1.0)
+    };
+    let on_time = Duration::try_from_secs_f64(period.as_secs_f64() * duty_cycle as f64)
+        .unwrap_or(period)
+        .min(period);
+    (on_time, period.saturating_sub(on_time))
+}
+
+/// The brightness (0.0 - 1.0) at every position of a ring of `width` at `radius` around `center`, brightest on
+/// the ring itself and fading out towards both of its edges
+pub fn radial_ring(positions: &[(f32, f32)], center: (f32, f32), radius: f32, width: f32) -> Vec<f32> {
+    positions
+        .iter()
+        .map(|(x, y)| {
+            let distance = sqrt((x - center.0) * (x - center.0) + (y - center.1) * (y - center.1));
+            let offset = abs(distance - radius) / (width / 2.0);
+            if offset < 1.0 {
+                0.5 * (1.0 + cos(core::f32::consts::PI * offset))
+            } else {
+                0.0
+            }
+        })
+        .collect()
+}
+
+/// The colors at every position of a wave travelling along `direction`, which cycles through `palette` once every
+/// `wavelength`. `phase` shifts the wave along its direction by a number of wavelengths.
+///
+/// # Example
+/// ```
+/// use blinkstick_core::render::wave_colors;
+/// use blinkstick_core::Color;
+///
+/// let positions = [(0.0, 0.0), (1.0, 0.0), (2.0, 5.0)];
+/// let colors = wave_colors(&positions, (1.0, 0.0), 2.0, 0.0, &[Color::RED, Color::BLUE]);
+///
+/// assert_eq!(colors, vec![Color::RED, Color::BLUE, Color::RED]);
+/// ```
+pub fn wave_colors(
+    positions: &[(f32, f32)],
+    direction: (f32, f32),
+    wavelength: f32,
+    phase: f32,
+    palette: &[Color],
+) -> Vec<Color> {
+    let length = sqrt(direction.0 * direction.0 + direction.1 * direction.1);
+    if palette.is_empty() || length == 0.0 || wavelength <= 0.0 {
+        return vec![COLOR_OFF; positions.len()];
+    }
+
+    positions
+        .iter()
+        .map(|(x, y)| {
+            let along = (x * direction.0 + y * direction.1) / length;
+            let cycle = rem_euclid(along / wavelength - phase, 1.0) * palette.len() as f32;
+            let index = cycle as usize % palette.len();
+            mix(
+                palette[index],
+                palette[(index + 1) % palette.len()],
+                cycle - index as f32,
+                ColorSpace::Srgb,
+            )
+        })
+        .collect()
+}
+
+/// The direction in which a wipe reveals new colors
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum WipeDirection {
+    /// From the zeroth led towards the last led
+    Forward,
+    /// From the last led towards the zeroth led
+    Backward,
+    /// From the middle of the device towards both ends
+    CenterOut,
+    /// From both ends of the device towards the middle
+    EdgesIn,
+}
+
+/// Groups the leds of a `led_count` long device into the stages a wipe reveals them in
+pub fn wipe_stages(led_count: usize, direction: WipeDirection) -> Vec<Vec<usize>> {
+    let half = led_count.div_ceil(2);
+    match direction {
+        WipeDirection::Forward => (0..led_count).map(|led| vec![led]).collect(),
+        WipeDirection::Backward => (0..led_count).rev().map(|led| vec![led]).collect(),
+        WipeDirection::CenterOut => (0..half)
+            .rev()
+            .map(|led| pair_stage(led, led_count - 1 - led))
+            .collect(),
+        WipeDirection::EdgesIn => (0..half).map(|led| pair_stage(led, led_count - 1 - led)).collect(),
+    }
+}
+
+/// A wipe stage revealing two mirrored leds, or one if both sides meet in the middle
+fn pair_stage(left: usize, right: usize) -> Vec<usize> {
+    if left == right {
+        vec![left]
+    } else {
+        vec![left, right]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quantized_channels_average_to_the_exact_value() {
+        let exact = exact_channels(Color::new(3, 0, 255), 0.1, 1.0);
+        let mut error = [0.0; 3];
+        let frames: Vec<Color> = (0..10).map(|_| quantize(exact, &mut error)).collect();
+
+        // 0.3 rounds to 0 on every frame without the carried error, but shows on 3 frames out of 10 with it
+        assert_eq!(frames.iter().map(|color| color.r as u32).sum::<u32>(), 3);
+        assert_eq!(frames.iter().map(|color| color.b as u32).sum::<u32>(), 255);
+        assert!(frames.iter().all(|color| color.g == 0));
+    }
+
+    #[test]
+    fn heartbeat_envelope_double_pulse() {
+        let lub = heartbeat_envelope(0.08);
+        let dub = heartbeat_envelope(0.3);
+
+        assert!((lub - 1.0).abs() < f32::EPSILON);
+        assert!(dub > 0.0 && dub < lub);
+        assert_eq!(heartbeat_envelope(0.19), 0.0);
+        assert_eq!(heartbeat_envelope(0.7), 0.0);
+    }
+
+    #[test]
+    fn strobe_timing_caps_frequency() {
+        assert_eq!(
+            strobe_timing(2.0, 0.25, 3.0),
+            (Duration::from_millis(125), Duration::from_millis(375))
+        );
+        assert_eq!(
+            strobe_timing(50.0, 0.5, 2.0),
+            (Duration::from_millis(250), Duration::from_millis(250))
+        );
+        assert_eq!(strobe_timing(0.0, 0.5, 3.0), (Duration::ZERO, Duration::ZERO));
+        assert_eq!(strobe_timing(1e-30, 1.0, 3.0), (Duration::MAX, Duration::ZERO));
+        assert_eq!(
+            strobe_timing(2.0, f32::NAN, 3.0),
+            (Duration::ZERO, Duration::from_millis(500))
+        );
+    }
+
+    #[test]
+    fn wipe_stages_directions() {
+        assert_eq!(wipe_stages(3, WipeDirection::Forward), vec![vec![0], vec![1], vec![2]]);
+        assert_eq!(wipe_stages(3, WipeDirection::Backward), vec![vec![2], vec![1], vec![0]]);
+        assert_eq!(
+            wipe_stages(5, WipeDirection::CenterOut),
+            vec![vec![2], vec![1, 3], vec![0, 4]]
+        );
+        assert_eq!(wipe_stages(4, WipeDirection::EdgesIn), vec![vec![0, 3], vec![1, 2]]);
+        assert!(wipe_stages(0, WipeDirection::CenterOut).is_empty());
+    }
+
+    #[test]
+    fn radial_ring_lights_the_leds_at_its_radius() {
+        let positions = [(0.0, 0.0), (3.0, 4.0), (10.0, 0.0)];
+
+        assert_eq!(radial_ring(&positions, (0.0, 0.0), 5.0, 2.0), vec![0.0, 1.0, 0.0]);
+    }
+
+    #[test]
+    fn waves_of_an_empty_palette_are_dark() {
+        assert_eq!(
+            wave_colors(&[(0.0, 0.0)], (1.0, 0.0), 2.0, 0.0, &[]),
+            vec![Color::new(0, 0, 0)]
+        );
+    }
+
+    #[test]
+    fn color_temperature_warms_towards_red() {
+        let candle = color_temperature(1900.0);
+        let daylight = color_temperature(6500.0);
+
+        assert_eq!(candle.r, 255);
+        assert!(candle.g < daylight.g && candle.b < daylight.b);
+        assert!(daylight.r > 250 && daylight.g > 240 && daylight.b > 240);
+    }
+
+    #[test]
+    fn sunrises_start_dark_and_end_bright() {
+        assert_eq!(sunrise_color(0.0), Color::new(0, 0, 0));
+        assert!(sunrise_color(0.2).r > sunrise_color(0.2).b);
+        assert_eq!(sunrise_color(1.0), color_temperature(5500.0));
+    }
+
+    #[test]
+    fn crossfade_only_changes_differing_leds() {
+        let unchanged = Color { r: 10, g: 20, b: 30 };
+        let from = vec![unchanged, Color { r: 0, g: 0, b: 0 }];
+        let to = vec![unchanged, Color { r: 200, g: 0, b: 0 }];
+
+        let frames = crossfade_frames(&from, &to, 4, Easing::Linear);
+
+        assert_eq!(frames.len(), 4);
+        assert!(frames.iter().all(|frame| frame[0] == unchanged));
+        assert_eq!(frames.last().unwrap(), &to);
+    }
+
+    #[test]
+    fn crossfade_of_equal_frames_is_empty() {
+        let frame = vec![Color { r: 1, g: 2, b: 3 }; 8];
+
+        assert!(crossfade_frames(&frame, &frame, 10, Easing::Linear).is_empty());
+    }
+
+    #[test]
+    fn linear_light_midpoint_is_brighter_than_naive_midpoint() {
+        let mid = mix_linear_light(Color { r: 0, g: 0, b: 0 }, Color { r: 255, g: 0, b: 0 }, 0.5);
+
+        assert!(mid.r > 128);
+    }
+
+    #[test]
+    fn hsv_gradient_takes_the_short_way_around() {
+        let red = Color { r: 255, g: 0, b: 0 };
+        let magenta = Color { r: 255, g: 0, b: 255 };
+
+        let colors = gradient(red, magenta, 2, Easing::Linear, ColorSpace::Hsv);
+
+        assert_eq!(colors, vec![Color { r: 255, g: 0, b: 128 }, magenta]);
+    }
+}
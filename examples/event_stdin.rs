@@ -0,0 +1,50 @@
+//! Feeds a BlinkStick from standard input, one event per line: a severity, an optional duration in seconds and
+//! a source, for example `warning 10 backup`.
+//!
+//! Other adapters look the same: a notification daemon or system tray app turns whatever it receives into
+//! `LightEvent`s and pushes them onto the bus.
+
+extern crate blinkstick_rs;
+
+use blinkstick_rs::{Animator, BlinkStick, EventBus, LightEvent, Severity};
+use std::io::BufRead;
+use std::time::Duration;
+
+fn parse_event(line: &str) -> Option<LightEvent> {
+    let mut words = line.split_whitespace();
+    let severity = match words.next()? {
+        "info" => Severity::Info,
+        "success" => Severity::Success,
+        "warning" => Severity::Warning,
+        "error" => Severity::Error,
+        "critical" => Severity::Critical,
+        _ => return None,
+    };
+
+    let mut rest: Vec<&str> = words.collect();
+    let duration = rest.first().and_then(|seconds| seconds.parse::<f32>().ok());
+    if duration.is_some() {
+        rest.remove(0);
+    }
+
+    let event = LightEvent::new(&rest.join(" "), severity);
+    Some(match duration {
+        Some(seconds) => event.lasting(Duration::from_secs_f32(seconds)),
+        None => event,
+    })
+}
+
+fn main() {
+    let blinkstick = BlinkStick::new().expect("Could not connect to a BlinkStick");
+    let leds = blinkstick.max_leds as usize;
+    let bus = EventBus::new(Animator::new(blinkstick), leds);
+
+    for line in std::io::stdin().lock().lines() {
+        match parse_event(&line.expect("Could not read from standard input")) {
+            Some(event) => {
+                bus.push(event);
+            }
+            None => eprintln!("Expected a line like `warning 10 backup`"),
+        }
+    }
+}
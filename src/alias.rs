@@ -0,0 +1,149 @@
+use crate::config::{config_lines, ConfigError};
+use crate::{BlinkStick, BlinkStickBuilder, FeatureError, FeatureErrorType};
+use std::collections::BTreeMap;
+use std::fmt::Formatter;
+use std::path::Path;
+
+/// Friendly names for BlinkStick serial numbers, so scripts can open `left-monitor` instead of a hard-coded serial
+///
+/// # Example
+/// ```
+/// use blinkstick_rs::{BlinkStick, DeviceAliases};
+///
+/// let aliases = DeviceAliases::parse("left-monitor BS000001-3.0\n").unwrap();
+/// let blinkstick = BlinkStick::open_by_alias(&aliases, "left-monitor").unwrap();
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct DeviceAliases {
+    serials: BTreeMap<String, String>,
+}
+
+impl DeviceAliases {
+    /// Creates an empty registry
+    pub fn new() -> DeviceAliases {
+        DeviceAliases::default()
+    }
+
+    /// Maps `alias` to `serial`, replacing any serial the alias had before
+    ///
+    /// # Panics
+    /// When the alias is empty or contains whitespace or a `#`, which could not be saved
+    pub fn set(&mut self, alias: &str, serial: &str) {
+        assert!(is_valid_word(alias), "Invalid device alias \"{}\"", alias);
+        assert!(is_valid_word(serial), "Invalid serial number \"{}\"", serial);
+        self.serials.insert(alias.to_string(), serial.to_string());
+    }
+
+    /// Removes an alias, returning the serial it mapped to
+    pub fn remove(&mut self, alias: &str) -> Option<String> {
+        self.serials.remove(alias)
+    }
+
+    /// The serial number an alias maps to
+    pub fn serial(&self, alias: &str) -> Option<&str> {
+        self.serials.get(alias).map(String::as_str)
+    }
+
+    /// Every alias with the serial it maps to, ordered by alias
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.serials
+            .iter()
+            .map(|(alias, serial)| (alias.as_str(), serial.as_str()))
+    }
+
+    /// Parses a registry from its textual form, an alias and a serial number on every line
+    /// (`left-monitor BS000001-3.0`). Everything after a `#` is ignored.
+    pub fn parse(contents: &str) -> Result<DeviceAliases, ConfigError> {
+        let mut aliases = DeviceAliases::new();
+
+        for (line, text) in config_lines(contents) {
+            let parse_error = |message: &str| ConfigError::Parse {
+                line,
+                message: message.to_string(),
+            };
+
+            let mut fields = text.split_whitespace();
+            let (alias, serial) = match (fields.next(), fields.next(), fields.next()) {
+                (Some(alias), Some(serial), None) => (alias, serial),
+                _ => return Err(parse_error("expected an alias and a serial number")),
+            };
+
+            if aliases.serial(alias).is_some() {
+                return Err(parse_error("alias is defined more than once"));
+            }
+            aliases.set(alias, serial);
+        }
+
+        Ok(aliases)
+    }
+
+    /// Reads a registry from a file in the format accepted by `parse`
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<DeviceAliases, ConfigError> {
+        DeviceAliases::parse(&std::fs::read_to_string(path)?)
+    }
+
+    /// Writes the registry to a file in the format accepted by `parse`
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), ConfigError> {
+        Ok(std::fs::write(path, self.to_string())?)
+    }
+}
+
+impl std::fmt::Display for DeviceAliases {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (alias, serial) in self.iter() {
+            writeln!(f, "{} {}", alias, serial)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn is_valid_word(word: &str) -> bool {
+    !word.is_empty() && !word.contains(|c: char| c.is_whitespace() || c == '#')
+}
+
+impl BlinkStickBuilder {
+    /// Opens communication with the `BlinkStick Device` that an alias maps to, see `DeviceAliases`
+    pub fn open_by_alias(self, aliases: &DeviceAliases, alias: &str) -> Result<BlinkStick, FeatureError> {
+        match aliases.serial(alias) {
+            Some(serial) => self.open_by_serial(serial),
+            None => Err(FeatureError {
+                kind: FeatureErrorType::UnknownAlias(alias.to_string()),
+            }),
+        }
+    }
+}
+
+impl BlinkStick {
+    /// Opens communication with the `BlinkStick Device` that an alias maps to, see `DeviceAliases`
+    pub fn open_by_alias(aliases: &DeviceAliases, alias: &str) -> Result<BlinkStick, FeatureError> {
+        BlinkStickBuilder::new().open_by_alias(aliases, alias)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn aliases_round_trip_through_text() {
+        let aliases =
+            DeviceAliases::parse("# desk\nright-monitor BS000002-3.0\nleft-monitor BS000001-3.0 # hub 2\n").unwrap();
+
+        assert_eq!(aliases.serial("left-monitor"), Some("BS000001-3.0"));
+        assert_eq!(aliases.serial("ceiling"), None);
+        assert_eq!(
+            aliases.to_string(),
+            "left-monitor BS000001-3.0\nright-monitor BS000002-3.0\n"
+        );
+        assert_eq!(DeviceAliases::parse(&aliases.to_string()).unwrap(), aliases);
+    }
+
+    #[test]
+    fn rejects_duplicate_aliases() {
+        match DeviceAliases::parse("desk BS000001-3.0\ndesk BS000002-3.0\n") {
+            Err(ConfigError::Parse { line, .. }) => assert_eq!(line, 2),
+            other => panic!("Expected a parse error, got {:?}", other),
+        }
+    }
+}
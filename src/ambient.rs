@@ -0,0 +1,277 @@
+use crate::{BlinkStick, Clock, SystemClock};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// A source of ambient light readings, for example a light sensor next to the BlinkStick
+///
+/// # Example
+/// A sensor that always reports a dim office
+/// ```
+/// use blinkstick_rs::AdaptiveBrightness;
+///
+/// struct DimOffice;
+///
+/// impl AdaptiveBrightness for DimOffice {
+///     fn read_lux(&mut self) -> Option<f32> {
+///         Some(80.0)
+///     }
+/// }
+/// ```
+pub trait AdaptiveBrightness: Send {
+    /// Returns the current ambient light level in lux, or `None` when no reading is available
+    fn read_lux(&mut self) -> Option<f32>;
+}
+
+/// Maps readings of an `AdaptiveBrightness` source onto a brightness factor for the device.
+///
+/// Readings are smoothed with an exponential moving average, and the brightness only follows the smoothed
+/// reading once it moved further than the hysteresis band, so a flickering sensor does not make the leds flicker.
+/// Lux values are mapped logarithmically, matching how the eye perceives ambient light. The source is read at most
+/// once per sample interval, writes in between reuse the last reading, so a slow sensor does not slow down
+/// animations. Leds that stay the same color follow the ambient light once `BlinkStick::refresh` is called.
+///
+/// # Example
+/// Dims the BlinkStick in a dark room and brightens it in daylight
+/// ```
+/// use blinkstick_rs::{AdaptiveBrightness, AmbientLight, BlinkStick};
+///
+/// struct Sensor;
+///
+/// impl AdaptiveBrightness for Sensor {
+///     fn read_lux(&mut self) -> Option<f32> {
+///         Some(250.0)
+///     }
+/// }
+///
+/// let blinkstick = BlinkStick::new().unwrap();
+/// blinkstick.set_adaptive_brightness(Some(AmbientLight::new(Sensor).lux_range(5.0, 1000.0).brightness_range(0.1, 1.0)));
+/// ```
+pub struct AmbientLight {
+    source: Box<dyn AdaptiveBrightness>,
+    min_lux: f32,
+    max_lux: f32,
+    min_brightness: f32,
+    max_brightness: f32,
+    hysteresis: f32,
+    smoothing: f32,
+    smoothed_lux: Option<f32>,
+    applied_lux: Option<f32>,
+    clock: Arc<dyn Clock>,
+    sample_interval: Duration,
+    last_sample: Option<Duration>,
+}
+
+impl AmbientLight {
+    /// Creates a mapping from `source` with a range of 1 to 500 lux onto 5% to full brightness, reading the source
+    /// once a second
+    pub fn new<S: AdaptiveBrightness + 'static>(source: S) -> AmbientLight {
+        AmbientLight {
+            source: Box::new(source),
+            min_lux: 1.0,
+            max_lux: 500.0,
+            min_brightness: 0.05,
+            max_brightness: 1.0,
+            hysteresis: 0.1,
+            smoothing: 0.3,
+            smoothed_lux: None,
+            applied_lux: None,
+            clock: Arc::new(SystemClock::new()),
+            sample_interval: Duration::from_secs(1),
+            last_sample: None,
+        }
+    }
+
+    /// Sets the ambient light levels that map onto the lowest and highest brightness
+    pub fn lux_range(mut self, min_lux: f32, max_lux: f32) -> AmbientLight {
+        self.min_lux = min_lux.max(f32::MIN_POSITIVE);
+        self.max_lux = max_lux.max(self.min_lux);
+        self
+    }
+
+    /// Sets the lowest and highest brightness (0.0 - 1.0) the device is scaled to
+    pub fn brightness_range(mut self, min_brightness: f32, max_brightness: f32) -> AmbientLight {
+        self.min_brightness = min_brightness.clamp(0.0, 1.0);
+        self.max_brightness = max_brightness.clamp(self.min_brightness, 1.0);
+        self
+    }
+
+    /// Sets the relative change (0.1 being 10%) the smoothed reading needs to make before the brightness follows it
+    pub fn hysteresis(mut self, hysteresis: f32) -> AmbientLight {
+        self.hysteresis = hysteresis.max(0.0);
+        self
+    }
+
+    /// Sets how strongly a new reading moves the smoothed reading, from 0.0 (never) to 1.0 (immediately)
+    pub fn smoothing(mut self, smoothing: f32) -> AmbientLight {
+        self.smoothing = smoothing.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Sets the time between two readings of the source, `Duration::ZERO` reads it on every write
+    pub fn sample_interval(mut self, sample_interval: Duration) -> AmbientLight {
+        self.sample_interval = sample_interval;
+        self
+    }
+
+    /// Replaces the wall clock that the sample interval is measured with, for example with a `ManualClock` in tests
+    pub fn clock(mut self, clock: Arc<dyn Clock>) -> AmbientLight {
+        self.clock = clock;
+        self
+    }
+
+    /// Returns the brightness the readings map to, taking a new reading from the source once the sample interval
+    /// has passed
+    pub fn brightness(&mut self) -> f32 {
+        let now = self.clock.now();
+        let sampled_recently = self
+            .last_sample
+            .is_some_and(|last_sample| now.saturating_sub(last_sample) < self.sample_interval);
+        if !sampled_recently {
+            self.last_sample = Some(now);
+            if let Some(lux) = self.source.read_lux() {
+                self.observe(lux.max(0.0));
+            }
+        }
+
+        self.applied_lux.map_or(self.max_brightness, |lux| self.map_lux(lux))
+    }
+
+    /// Feeds a reading through the smoothing filter and the hysteresis band
+    fn observe(&mut self, lux: f32) {
+        let smoothed_lux = match self.smoothed_lux {
+            Some(smoothed_lux) => smoothed_lux + self.smoothing * (lux - smoothed_lux),
+            None => lux,
+        };
+        self.smoothed_lux = Some(smoothed_lux);
+
+        let outside_band = match self.applied_lux {
+            Some(applied_lux) => (smoothed_lux - applied_lux).abs() > applied_lux.max(self.min_lux) * self.hysteresis,
+            None => true,
+        };
+        if outside_band {
+            self.applied_lux = Some(smoothed_lux);
+        }
+    }
+
+    fn map_lux(&self, lux: f32) -> f32 {
+        let lux = lux.clamp(self.min_lux, self.max_lux);
+        let range = (self.max_lux / self.min_lux).ln();
+        let position = if range > 0.0 {
+            (lux / self.min_lux).ln() / range
+        } else {
+            1.0
+        };
+
+        self.min_brightness + (self.max_brightness - self.min_brightness) * position
+    }
+}
+
+impl BlinkStick {
+    /// Scales everything written to the device from now on by the ambient light level, or stops doing so with `None`
+    ///
+    /// The ambient brightness is combined with the dimming schedule, if there is one. Without new writes, `refresh`
+    /// sends the last colors again when the ambient brightness changed, as an idle `Animator` does every second.
+    /// Colors read back from the device are reported as they were written, before they were dimmed.
+    pub fn set_adaptive_brightness(&self, ambient_light: Option<AmbientLight>) {
+        *self.ambient_light.lock().unwrap() = ambient_light;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Color, ManualClock, COLOR_OFF};
+    use std::sync::Mutex;
+
+    struct FakeSensor(Arc<Mutex<Option<f32>>>);
+
+    impl AdaptiveBrightness for FakeSensor {
+        fn read_lux(&mut self) -> Option<f32> {
+            *self.0.lock().unwrap()
+        }
+    }
+
+    fn ambient_light(lux: f32) -> (AmbientLight, Arc<Mutex<Option<f32>>>) {
+        let reading = Arc::new(Mutex::new(Some(lux)));
+        let ambient_light = AmbientLight::new(FakeSensor(reading.clone()))
+            .lux_range(1.0, 100.0)
+            .brightness_range(0.0, 1.0)
+            .smoothing(1.0)
+            .sample_interval(Duration::ZERO);
+
+        (ambient_light, reading)
+    }
+
+    #[test]
+    fn lux_maps_logarithmically_onto_brightness() {
+        let (mut ambient_light, reading) = ambient_light(10.0);
+        assert!((ambient_light.brightness() - 0.5).abs() < 0.001);
+
+        *reading.lock().unwrap() = Some(1000.0);
+        assert_eq!(ambient_light.brightness(), 1.0);
+
+        *reading.lock().unwrap() = Some(0.0);
+        assert_eq!(ambient_light.brightness(), 0.0);
+    }
+
+    #[test]
+    fn source_is_read_once_per_sample_interval() {
+        let clock = Arc::new(ManualClock::new());
+        let (ambient_light, reading) = ambient_light(1.0);
+        let mut ambient_light = ambient_light
+            .sample_interval(Duration::from_secs(1))
+            .clock(clock.clone());
+        assert_eq!(ambient_light.brightness(), 0.0);
+
+        *reading.lock().unwrap() = Some(100.0);
+        clock.advance(Duration::from_millis(500));
+        assert_eq!(ambient_light.brightness(), 0.0);
+
+        clock.advance(Duration::from_millis(500));
+        assert_eq!(ambient_light.brightness(), 1.0);
+    }
+
+    #[test]
+    fn small_changes_stay_within_hysteresis() {
+        let (ambient_light, reading) = ambient_light(50.0);
+        let mut ambient_light = ambient_light.hysteresis(0.2);
+        let initial = ambient_light.brightness();
+
+        *reading.lock().unwrap() = Some(55.0);
+        assert_eq!(ambient_light.brightness(), initial);
+
+        *reading.lock().unwrap() = Some(80.0);
+        assert!(ambient_light.brightness() > initial);
+    }
+
+    #[test]
+    fn refresh_sends_the_last_colors_again_once_the_ambient_light_changed() {
+        let blinkstick = crate::BlinkStickBuilder::new().leds(4).open_disabled().unwrap();
+        let (ambient_light, reading) = ambient_light(1000.0);
+        blinkstick.set_adaptive_brightness(Some(ambient_light));
+        blinkstick.set_all_leds_color(Color::new(200, 100, 0)).unwrap();
+        assert!(!blinkstick.refresh().unwrap());
+
+        *reading.lock().unwrap() = Some(1.0);
+        assert!(blinkstick.refresh().unwrap());
+        assert_eq!(
+            blinkstick.written_colors.lock().unwrap()[0],
+            (Color::new(200, 100, 0), COLOR_OFF)
+        );
+        assert!(!blinkstick.refresh().unwrap());
+    }
+
+    #[test]
+    fn smoothing_moves_gradually_and_keeps_last_value_without_readings() {
+        let (ambient_light, reading) = ambient_light(100.0);
+        let mut ambient_light = ambient_light.smoothing(0.5).hysteresis(0.0);
+        assert_eq!(ambient_light.brightness(), 1.0);
+
+        *reading.lock().unwrap() = Some(1.0);
+        let halfway = ambient_light.brightness();
+        assert!(halfway > 0.0 && halfway < 1.0);
+
+        *reading.lock().unwrap() = None;
+        assert_eq!(ambient_light.brightness(), halfway);
+    }
+}
@@ -0,0 +1,721 @@
+use crate::frame::with_leds;
+use crate::random::Xorshift;
+use crate::render::{
+    gradient, heartbeat_envelope, mix, radial_ring, scale_color, strobe_timing, sunrise_color, wave_colors,
+    wipe_stages, ColorSpace, Easing,
+};
+use crate::{BlinkStick, Color, FeatureError, StepRate, WipeDirection, COLOR_OFF};
+#[cfg(feature = "rand")]
+use rand::{seq::SliceRandom, Rng};
+use std::time::{Duration, Instant};
+
+/// The time between two frames of a heartbeat
+const HEARTBEAT_FRAME_INTERVAL: Duration = Duration::from_millis(20);
+
+/// The time between two frames of the spatial effects, radial pulses and waves
+const SPATIAL_FRAME_INTERVAL: Duration = Duration::from_millis(20);
+
+/// The time between two frames of a sunrise or sunset
+const SUN_FRAME_INTERVAL: Duration = Duration::from_millis(100);
+
+/// The time between two frames of the fades of a ramp
+const RAMP_FRAME_INTERVAL: Duration = Duration::from_millis(20);
+
+/// How long a radial pulse lights up each led as its ring passes
+const RING_DURATION: f32 = 0.2;
+
+/// The most frames of a radial pulse, an hour at `SPATIAL_FRAME_INTERVAL`, so slow pulses over far leds still end
+const MAX_RADIAL_PULSE_FRAMES: u32 = 60 * 60 * 50;
+
+type Steps = Box<dyn Iterator<Item = (Vec<Color>, Duration)> + Send>;
+
+/// The order in which `AnimationIter::assemble` lights up the leds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AssembleOrder {
+    /// Every led lights up at a random moment
+    Random,
+    /// The leds light up by index, which follows the ring of leds on a BlinkStick Square
+    Ring,
+}
+
+impl AssembleOrder {
+    /// The stages of one led each that `led_count` leds light up in, put in a random order by `shuffle`
+    fn stages<F: FnOnce(&mut [usize])>(self, led_count: usize, shuffle: F) -> Vec<Vec<usize>> {
+        let mut leds: Vec<usize> = (0..led_count).collect();
+        if self == AssembleOrder::Random {
+            shuffle(&mut leds);
+        }
+
+        leds.into_iter().map(|led| vec![led]).collect()
+    }
+}
+
+/// The steps of an animation, each a full frame with one color per led and the time it stays visible
+///
+/// Building an animation neither touches the device nor sleeps, so async runtimes, GUIs and game loops can drive
+/// the steps themselves. `BlinkStick::play_animation` drives them by blocking, which is what the deprecated
+/// animation functions of `BlinkStick` do, and an `Animator` plays them on a background thread.
+///
+/// # Example
+/// Steps through a blink without any waiting
+/// ```
+/// use blinkstick_rs::{AnimationIter, Color};
+/// use std::time::Duration;
+///
+/// let off = Color {r: 0, g: 0, b: 0};
+/// let red = Color {r: 50, g: 0, b: 0};
+/// let blink = AnimationIter::blink(vec![off; 8], &[0], red, Duration::from_millis(100), 2);
+///
+/// for (frame, delay) in blink {
+///     assert_eq!(delay, Duration::from_millis(100));
+///     assert!(frame[1..].iter().all(|color| *color == off));
+/// }
+/// ```
+pub struct AnimationIter {
+    steps: Steps,
+}
+
+impl AnimationIter {
+    /// Wraps any iterator of frames and the time each frame stays visible
+    pub fn new<I>(steps: I) -> AnimationIter
+    where
+        I: IntoIterator<Item = (Vec<Color>, Duration)>,
+        I::IntoIter: Send + 'static,
+    {
+        AnimationIter {
+            steps: Box::new(steps.into_iter()),
+        }
+    }
+
+    /// Switches `leds` between `color` and off `blinks` times, leaving the other leds of `frame` as they are. Leds
+    /// past the end of `frame` are skipped.
+    pub fn blink(frame: Vec<Color>, leds: &[u8], color: Color, delay: Duration, blinks: u32) -> AnimationIter {
+        let on = with_leds(&frame, leds, color);
+        let off = with_leds(&frame, leds, COLOR_OFF);
+
+        AnimationIter::new((0..blinks).flat_map(move |_| vec![(on.clone(), delay), (off.clone(), delay)]))
+    }
+
+    /// Lights up each group of leds in its own phase of `period`, in the color of the group, then turns every
+    /// grouped led off. Leds past the end of `frame` are skipped.
+    ///
+    /// # Panics
+    /// The call to `alternate_flash` will panic if `colors` is empty while `groups` is not.
+    pub fn alternate_flash(
+        mut frame: Vec<Color>,
+        groups: &[&[u8]],
+        colors: &[Color],
+        period: Duration,
+        cycles: u32,
+    ) -> AnimationIter {
+        if groups.is_empty() {
+            return AnimationIter::new(Vec::new());
+        }
+        if colors.is_empty() {
+            panic!(
+                "alternate_flash requires at least one color for {} groups",
+                groups.len()
+            )
+        }
+
+        let grouped: Vec<u8> = groups.iter().flat_map(|group| group.iter().copied()).collect();
+        frame = with_leds(&frame, &grouped, COLOR_OFF);
+        let phase = period / groups.len() as u32;
+        let phases: Vec<(Vec<Color>, Duration)> = groups
+            .iter()
+            .enumerate()
+            .map(|(active, group)| (with_leds(&frame, group, colors[active % colors.len()]), phase))
+            .collect();
+
+        AnimationIter::new(
+            (0..cycles)
+                .flat_map(move |_| phases.clone())
+                .chain(Some((frame, Duration::ZERO))),
+        )
+    }
+
+    /// Flashes all `led_count` leds in `color` `frequency` times a second, on for the `duty_cycle` (0.0 - 1.0) of
+    /// every flash, until `duration` has passed. All leds are off once the strobe finishes.
+    ///
+    /// The frequency is capped at `max_frequency` to protect photosensitive viewers, pass
+    /// `BlinkStick::max_strobe_frequency` to honour the cap of the device, or `DEFAULT_MAX_STROBE_FREQUENCY`.
+    ///
+    /// # Example
+    /// Strobes white for three seconds, with short flashes twice a second
+    /// ```
+    /// use blinkstick_rs::{AnimationIter, Color, DEFAULT_MAX_STROBE_FREQUENCY};
+    /// use std::time::Duration;
+    ///
+    /// let white = Color {r: 100, g: 100, b: 100};
+    /// let strobe = AnimationIter::strobe(white, 8, 2.0, 0.1, DEFAULT_MAX_STROBE_FREQUENCY, Duration::from_secs(3));
+    ///
+    /// assert_eq!(strobe.count(), 12);
+    /// ```
+    pub fn strobe(
+        color: Color,
+        led_count: usize,
+        frequency: f32,
+        duty_cycle: f32,
+        max_frequency: f32,
+        duration: Duration,
+    ) -> AnimationIter {
+        let (on_time, off_time) = strobe_timing(frequency, duty_cycle, max_frequency);
+        let period = on_time + off_time;
+        if period == Duration::ZERO {
+            return AnimationIter::new(Vec::new());
+        }
+
+        let flashes = (duration.as_secs_f64() / period.as_secs_f64()).ceil() as usize;
+        let on = vec![color; led_count];
+        let off = vec![COLOR_OFF; led_count];
+
+        AnimationIter::new((0..flashes).flat_map(move |_| vec![(on.clone(), on_time), (off.clone(), off_time)]))
+    }
+
+    /// Shows every color of a (possibly infinite) color sequence on all `led_count` leds, one color per `interval`
+    ///
+    /// # Example
+    /// Smoothly cycles all leds through red, green and blue three times
+    /// ```
+    /// use blinkstick_rs::sequence::{self, ColorSequence};
+    /// use blinkstick_rs::{AnimationIter, BlinkStick, Color};
+    ///
+    /// let blinkstick = BlinkStick::new().unwrap();
+    /// let palette = [Color {r: 50, g: 0, b: 0}, Color {r: 0, g: 50, b: 0}, Color {r: 0, g: 0, b: 50}];
+    /// let colors = sequence::cycle(&palette).smooth(25).take(3 * 3 * 25);
+    /// let leds = blinkstick.max_leds as usize;
+    ///
+    /// blinkstick.play_animation(AnimationIter::play_colors(colors, leds, std::time::Duration::from_millis(20))).unwrap();
+    /// ```
+    pub fn play_colors<I>(colors: I, led_count: usize, interval: Duration) -> AnimationIter
+    where
+        I: IntoIterator<Item = Color>,
+        I::IntoIter: Send + 'static,
+    {
+        AnimationIter::new(colors.into_iter().map(move |color| (vec![color; led_count], interval)))
+    }
+
+    /// Shifts every led of `from` into the color of the same led in `to` in `steps` linear steps over `duration`,
+    /// a number of steps or a rate, see `StepRate`
+    pub fn transform<S: Into<StepRate>>(
+        from: Vec<Color>,
+        to: Vec<Color>,
+        duration: Duration,
+        steps: S,
+    ) -> AnimationIter {
+        let steps = steps.into().steps(duration);
+        let interval = duration / steps.max(1) as u32;
+        let led_gradients: Vec<Vec<Color>> = from
+            .iter()
+            .zip(to.iter())
+            .map(|(start, target)| gradient(*start, *target, steps, Easing::Linear, ColorSpace::Srgb))
+            .collect();
+
+        AnimationIter::new((0..steps as usize).map(move |step| {
+            let frame = led_gradients.iter().map(|gradient| gradient[step]).collect();
+            (frame, interval)
+        }))
+    }
+
+    /// Shifts `from` into `to` and back again, each half taking half of `duration` in `steps`, see `transform`
+    pub fn pulse<S: Into<StepRate>>(from: Vec<Color>, to: Vec<Color>, duration: Duration, steps: S) -> AnimationIter {
+        let steps = steps.into();
+        let there = AnimationIter::transform(from.clone(), to.clone(), duration / 2, steps);
+        let back = AnimationIter::transform(to, from, duration / 2, steps);
+
+        AnimationIter::new(there.chain(back))
+    }
+
+    /// Fades every led of `from` to the color of each segment in turn and holds it before the next segment, like
+    /// the escalating colors of a temperature or alert display. A segment is the color, the time the fade to it
+    /// takes and the time the color is held.
+    ///
+    /// # Example
+    /// ```
+    /// use blinkstick_rs::{AnimationIter, Color};
+    /// use std::time::Duration;
+    ///
+    /// let second = Duration::from_secs(1);
+    /// let segments = [(Color::GREEN, Duration::ZERO, second), (Color::RED, second, 5 * second)];
+    /// let ramp: Vec<_> = AnimationIter::ramp(vec![Color::default(); 8], &segments).collect();
+    ///
+    /// assert_eq!(ramp[0], (vec![Color::GREEN; 8], second));
+    /// assert_eq!(ramp.last().unwrap(), &(vec![Color::RED; 8], 5 * second));
+    /// ```
+    pub fn ramp(from: Vec<Color>, segments: &[(Color, Duration, Duration)]) -> AnimationIter {
+        let led_count = from.len();
+        let mut current = from;
+        let mut parts = Vec::with_capacity(segments.len());
+        for (color, fade, hold) in segments {
+            let target = vec![*color; led_count];
+            let steps = (fade.as_secs_f64() / RAMP_FRAME_INTERVAL.as_secs_f64())
+                .ceil()
+                .min(u16::MAX as f64) as u16;
+
+            let faded = AnimationIter::transform(current, target.clone(), *fade, steps);
+            parts.push(AnimationIter::new(faded.chain(Some((target.clone(), *hold)))));
+            current = target;
+        }
+
+        AnimationIter::new(parts.into_iter().flatten())
+    }
+
+    /// Beats all `led_count` leds like a heart in `color`, with a strong and a weak pulse per beat, forever
+    pub fn heartbeat(color: Color, led_count: usize, bpm: u16) -> AnimationIter {
+        AnimationIter::new((0u64..).map(move |frame| {
+            let phase = cycle_position(frame, HEARTBEAT_FRAME_INTERVAL, bpm as u128);
+            (
+                vec![scale_color(color, heartbeat_envelope(phase)); led_count],
+                HEARTBEAT_FRAME_INTERVAL,
+            )
+        }))
+    }
+
+    /// Sends a ring of `color` outwards from `center` at `speed` units of `positions` per second, over the colors of
+    /// `frame`, until it has passed the farthest led or an hour has passed. A `speed` that is not a positive number
+    /// plays no frames.
+    pub fn radial_pulse(
+        frame: Vec<Color>,
+        positions: Vec<(f32, f32)>,
+        center: (f32, f32),
+        color: Color,
+        speed: f32,
+    ) -> AnimationIter {
+        if !(speed.is_finite() && speed > 0.0) {
+            return AnimationIter::new(Vec::new());
+        }
+
+        let width = speed * RING_DURATION;
+        let farthest = positions
+            .iter()
+            .map(|(x, y)| ((x - center.0).powi(2) + (y - center.1).powi(2)).sqrt())
+            .fold(0.0, f32::max);
+        let frames = ((farthest + width) / speed / SPATIAL_FRAME_INTERVAL.as_secs_f32())
+            .ceil()
+            .min(MAX_RADIAL_PULSE_FRAMES as f32) as u32;
+
+        AnimationIter::new((0..=frames).map(move |step| {
+            let radius = (speed as f64 * SPATIAL_FRAME_INTERVAL.as_secs_f64() * step as f64) as f32;
+            let ring = radial_ring(&positions, center, radius, width);
+            let colors = frame
+                .iter()
+                .zip(ring)
+                .map(|(base, intensity)| mix(*base, color, intensity, ColorSpace::LinearLight))
+                .collect();
+            (colors, SPATIAL_FRAME_INTERVAL)
+        }))
+    }
+
+    /// Moves a wave cycling through `palette` once every `wavelength` across `positions` along `direction`, one
+    /// wavelength per second, forever
+    pub fn wave(
+        positions: Vec<(f32, f32)>,
+        direction: (f32, f32),
+        wavelength: f32,
+        palette: Vec<Color>,
+    ) -> AnimationIter {
+        AnimationIter::new((0u64..).map(move |step| {
+            // One wavelength per second, and the colors repeat with every wavelength
+            let phase = cycle_position(step, SPATIAL_FRAME_INTERVAL, 60);
+            (
+                wave_colors(&positions, direction, wavelength, phase, &palette),
+                SPATIAL_FRAME_INTERVAL,
+            )
+        }))
+    }
+
+    /// Brightens all `led_count` leds from darkness through a deep red to warm daylight over `duration`, see
+    /// `render::sunrise_color`
+    pub fn sunrise(led_count: usize, duration: Duration) -> AnimationIter {
+        let frames = (duration.as_secs_f64() / SUN_FRAME_INTERVAL.as_secs_f64())
+            .ceil()
+            .max(1.0) as u32;
+
+        AnimationIter::new((1..=frames).map(move |frame| {
+            let color = sunrise_color(frame as f32 / frames as f32);
+            (vec![color; led_count], SUN_FRAME_INTERVAL)
+        }))
+    }
+
+    /// Dims all `led_count` leds from warm daylight through a deep red to darkness over `duration`, a sunrise
+    /// played backwards
+    pub fn sunset(led_count: usize, duration: Duration) -> AnimationIter {
+        let frames = (duration.as_secs_f64() / SUN_FRAME_INTERVAL.as_secs_f64())
+            .ceil()
+            .max(1.0) as u32;
+
+        AnimationIter::new((1..=frames).map(move |frame| {
+            let color = sunrise_color(1.0 - frame as f32 / frames as f32);
+            (vec![color; led_count], SUN_FRAME_INTERVAL)
+        }))
+    }
+
+    /// Reveals the colors of `target_colors` in `frame` one stage of leds at a time, the last stage showing up
+    /// after `duration`
+    pub fn reveal(
+        mut frame: Vec<Color>,
+        target_colors: Vec<Color>,
+        stages: Vec<Vec<usize>>,
+        duration: Duration,
+    ) -> AnimationIter {
+        let interval = duration / stages.len().max(1) as u32;
+
+        AnimationIter::new(stages.into_iter().map(move |stage| {
+            for led in stage {
+                frame[led] = target_colors[led];
+            }
+            (frame.clone(), interval)
+        }))
+    }
+
+    /// Reveals the colors of `to` over `from` led by led in a directional sweep, as an alternative to a
+    /// simultaneous crossfade, the last led showing up after `duration`
+    ///
+    /// # Panics
+    /// The call to `wipe` will panic if `from` and `to` differ in length.
+    ///
+    /// # Example
+    /// Sweeps green into every led, starting from the middle of the device
+    /// ```
+    /// use blinkstick_rs::{AnimationIter, Color, WipeDirection};
+    /// use std::time::Duration;
+    ///
+    /// let (off, green) = (Color::default(), Color {r: 0, g: 50, b: 0});
+    /// let wipe = AnimationIter::wipe(vec![off; 8], vec![green; 8], WipeDirection::CenterOut, Duration::from_secs(1));
+    /// let wipe: Vec<_> = wipe.collect();
+    ///
+    /// assert_eq!(wipe.last().unwrap().0, vec![green; 8]);
+    /// ```
+    pub fn wipe(from: Vec<Color>, to: Vec<Color>, direction: WipeDirection, duration: Duration) -> AnimationIter {
+        assert_same_length(&from, &to);
+        let stages = wipe_stages(to.len(), direction);
+        AnimationIter::reveal(from, to, stages, duration)
+    }
+
+    /// Lights up the leds of `from` one by one in `order` until every led shows its color in `to`, the last led
+    /// lighting up after `duration`
+    ///
+    /// # Panics
+    /// The call to `assemble` will panic if `from` and `to` differ in length.
+    ///
+    /// # Example
+    /// Lights up a BlinkStick Square in a circle, ending in blue
+    /// ```
+    /// use blinkstick_rs::{AnimationIter, AssembleOrder, BlinkStick, Color};
+    /// use std::time::Duration;
+    ///
+    /// let blinkstick = BlinkStick::new().unwrap();
+    /// let colors = vec![Color {r: 0, g: 0, b: 50}; blinkstick.max_leds as usize];
+    /// let from = blinkstick.get_color_vec();
+    ///
+    /// blinkstick.play_animation(AnimationIter::assemble(from, colors, AssembleOrder::Ring, Duration::from_secs(2))).unwrap();
+    /// ```
+    pub fn assemble(from: Vec<Color>, to: Vec<Color>, order: AssembleOrder, duration: Duration) -> AnimationIter {
+        assert_same_length(&from, &to);
+        let stages = order.stages(to.len(), |leds| Xorshift::from_time().shuffle(leds));
+        AnimationIter::reveal(from, to, stages, duration)
+    }
+
+    /// Like `assemble` in a random order, but draws the order of the leds from `rng`, so a seeded generator
+    /// repeats the same order
+    ///
+    /// # Panics
+    /// The call to `assemble_with_rng` will panic if `from` and `to` differ in length.
+    #[cfg(feature = "rand")]
+    pub fn assemble_with_rng<R: Rng + ?Sized>(
+        from: Vec<Color>,
+        to: Vec<Color>,
+        rng: &mut R,
+        duration: Duration,
+    ) -> AnimationIter {
+        assert_same_length(&from, &to);
+        let stages = AssembleOrder::Random.stages(to.len(), |leds| leds.shuffle(rng));
+        AnimationIter::reveal(from, to, stages, duration)
+    }
+
+    /// Moves a single lit led across `frame` and back, its color shifting from `start_color` to `target_color`
+    /// on the way there and back again on the way back
+    pub fn carousel(frame: Vec<Color>, start_color: Color, target_color: Color, delay: Duration) -> AnimationIter {
+        let led_count = frame.len();
+        let colors = gradient(
+            start_color,
+            target_color,
+            led_count as u16,
+            Easing::Linear,
+            ColorSpace::Srgb,
+        );
+        let lap = |colors: Vec<Color>| {
+            let mut frame = frame.clone();
+            let mut steps = Vec::with_capacity(led_count + 1);
+            for (led, color) in colors.into_iter().enumerate() {
+                if led > 0 {
+                    frame[led - 1] = COLOR_OFF;
+                }
+                frame[led] = color;
+                let visible = if led + 1 < led_count { delay } else { Duration::ZERO };
+                steps.push((frame.clone(), visible));
+            }
+            if let Some(last) = frame.last_mut() {
+                *last = COLOR_OFF;
+                steps.push((frame, Duration::ZERO));
+            }
+            steps
+        };
+
+        let there = lap(colors.clone());
+        let back = lap(colors.into_iter().rev().collect());
+        AnimationIter::new(there.into_iter().chain(back))
+    }
+}
+
+/// How far into its current cycle (0.0 - 1.0) an effect repeating `cycles_per_minute` times a minute is after `step`
+/// steps of `interval`, exact for effects that run for years
+fn cycle_position(step: u64, interval: Duration, cycles_per_minute: u128) -> f32 {
+    const NANOS_PER_MINUTE: u128 = 60_000_000_000;
+    let position = step as u128 * interval.as_nanos() * cycles_per_minute % NANOS_PER_MINUTE;
+
+    (position as f64 / NANOS_PER_MINUTE as f64) as f32
+}
+
+/// Helper function for the reveals, panics unless `from` and `to` hold a color for the same number of leds
+fn assert_same_length(from: &[Color], to: &[Color]) {
+    if from.len() != to.len() {
+        panic!(
+            "{} target colors do not match the {} leds of the frame",
+            to.len(),
+            from.len()
+        )
+    }
+}
+
+impl Iterator for AnimationIter {
+    type Item = (Vec<Color>, Duration);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.steps.next()
+    }
+}
+
+impl BlinkStick {
+    /// Drives the steps of an animation, showing every frame on the device and blocking for the time it stays
+    /// visible, minus the time it took to send it
+    ///
+    /// The animation is only ever written, and a frame equal to the one before it is not sent again. The effects
+    /// read the colors of the device once, to compute every frame up front.
+    ///
+    /// # Example
+    /// Fades all leds in over half a second
+    /// ```
+    /// use blinkstick_rs::{AnimationIter, BlinkStick, Color};
+    /// use std::time::Duration;
+    ///
+    /// let blinkstick = BlinkStick::new().unwrap();
+    /// let from = blinkstick.get_color_vec();
+    /// let to = vec![Color {r: 0, g: 50, b: 0}; blinkstick.max_leds as usize];
+    ///
+    /// blinkstick.play_animation(AnimationIter::transform(from, to, Duration::from_millis(500), 25)).unwrap();
+    /// ```
+    pub fn play_animation<I>(&self, animation: I) -> Result<(), FeatureError>
+    where
+        I: IntoIterator<Item = (Vec<Color>, Duration)>,
+    {
+        let mut shown: Option<Vec<Color>> = None;
+        for (frame, delay) in animation {
+            let start = Instant::now();
+            if shown.as_ref() != Some(&frame) {
+                self.set_all_leds_colors(&frame)?;
+                shown = Some(frame);
+            }
+            std::thread::sleep(delay.saturating_sub(start.elapsed()));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RED: Color = Color { r: 200, g: 0, b: 0 };
+
+    #[test]
+    fn strobe_rounds_up_to_whole_flashes() {
+        let steps: Vec<_> = AnimationIter::strobe(RED, 2, 2.0, 0.25, 3.0, Duration::from_millis(1400)).collect();
+
+        assert_eq!(steps.len(), 6);
+        assert_eq!(steps[0], (vec![RED; 2], Duration::from_millis(125)));
+        assert_eq!(steps[1], (vec![COLOR_OFF; 2], Duration::from_millis(375)));
+    }
+
+    #[test]
+    fn strobes_are_capped_at_the_max_frequency() {
+        let steps: Vec<_> = AnimationIter::strobe(RED, 2, 50.0, 0.5, 2.0, Duration::from_secs(1)).collect();
+
+        assert_eq!(steps.len(), 4);
+        assert_eq!(steps[0], (vec![RED; 2], Duration::from_millis(250)));
+    }
+
+    #[test]
+    fn leds_past_the_frame_are_skipped() {
+        let blink: Vec<_> = AnimationIter::blink(vec![COLOR_OFF; 2], &[1, 9], RED, Duration::ZERO, 1).collect();
+        assert_eq!(blink[0].0, vec![COLOR_OFF, RED]);
+
+        let flash: Vec<_> =
+            AnimationIter::alternate_flash(vec![COLOR_OFF; 2], &[&[0], &[200]], &[RED], Duration::ZERO, 1).collect();
+        assert_eq!(flash[0].0, vec![RED, COLOR_OFF]);
+        assert_eq!(flash[1].0, vec![COLOR_OFF; 2]);
+    }
+
+    #[test]
+    fn pulse_returns_to_the_start() {
+        let from = vec![COLOR_OFF, RED];
+        let steps: Vec<_> = AnimationIter::pulse(from.clone(), vec![RED; 2], Duration::from_secs(1), 4).collect();
+
+        assert_eq!(steps.len(), 8);
+        assert_eq!(steps[3].0, vec![RED; 2]);
+        assert_eq!(steps[7], (from, Duration::from_millis(125)));
+    }
+
+    #[test]
+    fn assembles_light_every_led_once() {
+        let ring = AssembleOrder::Ring.stages(4, |_| panic!("rings aren't shuffled"));
+        assert_eq!(ring, vec![vec![0], vec![1], vec![2], vec![3]]);
+
+        let random = AssembleOrder::Random.stages(8, |leds| Xorshift::new(7).shuffle(leds));
+        assert!(random.iter().all(|stage| stage.len() == 1));
+        let mut leds: Vec<usize> = random.into_iter().flatten().collect();
+        assert_ne!(leds, (0..8).collect::<Vec<usize>>());
+        leds.sort_unstable();
+        assert_eq!(leds, (0..8).collect::<Vec<usize>>());
+    }
+
+    #[test]
+    #[cfg(feature = "rand")]
+    fn seeded_assembles_repeat_their_order() {
+        use rand::SeedableRng;
+        let assemble = |seed: u64| {
+            let mut rng = rand::rngs::StdRng::seed_from_u64(seed);
+            AnimationIter::assemble_with_rng(vec![COLOR_OFF; 8], vec![RED; 8], &mut rng, Duration::ZERO)
+                .collect::<Vec<_>>()
+        };
+
+        let steps = assemble(7);
+        assert_eq!(assemble(7), steps);
+        assert_eq!(steps.len(), 8);
+        assert_eq!(steps.last().unwrap().0, vec![RED; 8]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn wipes_reject_frames_of_the_wrong_length() {
+        AnimationIter::wipe(vec![COLOR_OFF; 4], vec![RED; 6], WipeDirection::Forward, Duration::ZERO);
+    }
+
+    #[test]
+    #[should_panic]
+    fn assembles_reject_frames_of_the_wrong_length() {
+        AnimationIter::assemble(vec![COLOR_OFF; 4], vec![RED; 2], AssembleOrder::Ring, Duration::ZERO);
+    }
+
+    #[test]
+    fn cycle_positions_stay_exact_after_years() {
+        let frame = Duration::from_millis(20);
+        // 50 frames per beat at 60 bpm, and 100 years of frames
+        let century = 50 * 60 * 60 * 24 * 365 * 100;
+
+        assert_eq!(cycle_position(4, frame, 60), 0.08);
+        assert_eq!(cycle_position(century + 4, frame, 60), 0.08);
+        assert_eq!(cycle_position(century + 25, frame, 72), 0.6);
+        assert_eq!(cycle_position(century + 25, frame, 0), 0.0);
+    }
+
+    #[test]
+    fn ramps_fade_and_hold_every_segment() {
+        let hold = Duration::from_secs(3);
+        let segments = [
+            (RED, Duration::from_millis(100), hold),
+            (COLOR_OFF, Duration::ZERO, hold),
+        ];
+        let steps: Vec<_> = AnimationIter::ramp(vec![COLOR_OFF; 2], &segments).collect();
+
+        assert_eq!(steps.len(), 7);
+        assert_eq!(steps[0].1, RAMP_FRAME_INTERVAL);
+        assert_eq!(steps[4], (vec![RED; 2], RAMP_FRAME_INTERVAL));
+        assert_eq!(steps[5], (vec![RED; 2], hold));
+        assert_eq!(steps[6], (vec![COLOR_OFF; 2], hold));
+    }
+
+    #[test]
+    fn radial_pulse_passes_every_led_and_ends_on_the_frame() {
+        let frame = vec![COLOR_OFF; 3];
+        let positions = vec![(0.0, 0.0), (1.0, 0.0), (2.0, 0.0)];
+        let steps: Vec<_> = AnimationIter::radial_pulse(frame.clone(), positions, (0.0, 0.0), RED, 10.0).collect();
+
+        for led in 0..3 {
+            assert!(steps.iter().any(|(colors, _)| colors[led].r > 150));
+        }
+        assert_eq!(steps.last().unwrap().0, frame);
+    }
+
+    #[test]
+    fn radial_pulses_of_odd_speeds_end() {
+        let positions = vec![(0.0, 0.0), (1.0, 0.0)];
+        let pulse =
+            |speed: f32| AnimationIter::radial_pulse(vec![COLOR_OFF; 2], positions.clone(), (0.0, 0.0), RED, speed);
+
+        assert_eq!(pulse(f32::NAN).count(), 0);
+        assert_eq!(pulse(f32::INFINITY).count(), 0);
+        assert_eq!(pulse(1e-30).count(), MAX_RADIAL_PULSE_FRAMES as usize + 1);
+    }
+
+    #[test]
+    fn waves_repeat_every_second() {
+        let positions = vec![(0.0, 0.0), (0.3, 0.0), (0.7, 0.0)];
+        let second = (Duration::from_secs(1).as_nanos() / SPATIAL_FRAME_INTERVAL.as_nanos()) as usize;
+        let steps: Vec<_> = AnimationIter::wave(positions, (1.0, 0.0), 1.0, vec![RED, Color::BLUE])
+            .take(2 * second + 1)
+            .collect();
+
+        assert_ne!(steps[0], steps[second / 2]);
+        assert_eq!(steps[0], steps[second]);
+        assert_eq!(steps[7], steps[second + 7]);
+        assert_eq!(steps[0], steps[2 * second]);
+    }
+
+    #[test]
+    fn reveals_end_on_the_target_colors() {
+        let target = vec![RED, Color::GREEN, Color::BLUE];
+        let steps: Vec<_> = AnimationIter::reveal(
+            vec![COLOR_OFF; 3],
+            target.clone(),
+            vec![vec![2], vec![0, 1]],
+            Duration::from_secs(1),
+        )
+        .collect();
+
+        assert_eq!(
+            steps[0],
+            (vec![COLOR_OFF, COLOR_OFF, Color::BLUE], Duration::from_millis(500))
+        );
+        assert_eq!(steps[1], (target, Duration::from_millis(500)));
+    }
+
+    #[test]
+    fn sunsets_end_dark() {
+        let steps: Vec<_> = AnimationIter::sunset(2, Duration::from_secs(1)).collect();
+
+        assert_eq!(steps.len(), 10);
+        assert!(steps[0].0[0].r > 200);
+        assert_eq!(steps[9].0, vec![COLOR_OFF; 2]);
+    }
+
+    #[test]
+    fn carousel_ends_dark() {
+        let steps: Vec<_> = AnimationIter::carousel(vec![COLOR_OFF; 3], RED, RED, Duration::from_millis(10)).collect();
+
+        assert_eq!(steps.len(), 8);
+        assert_eq!(steps[1].0, vec![COLOR_OFF, RED, COLOR_OFF]);
+        assert_eq!(steps.last().unwrap().0, vec![COLOR_OFF; 3]);
+    }
+}
@@ -0,0 +1,728 @@
+use crate::{
+    BlinkStick, Clock, Color, Dithering, FeatureErrorType, FrameSink, Parameters, Program, StateEvent, SystemClock,
+};
+use std::any::Any;
+use std::collections::HashMap;
+use std::panic::AssertUnwindSafe;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+/// The number of frames in a row that may fail transiently before an effect is given up on
+const MAX_CONSECUTIVE_FAILED_FRAMES: u32 = 5;
+/// How often the BlinkStick is refreshed while no effect plays, see `BlinkStick::refresh`
+const IDLE_REFRESH_INTERVAL: Duration = Duration::from_secs(1);
+
+type Frames = Box<dyn Iterator<Item = (Vec<Color>, Duration)> + Send>;
+type Callback = Box<dyn Fn(&AnimationEvent) + Send>;
+type Sinks = Mutex<Vec<Box<dyn FrameSink>>>;
+
+/// Something that happened to an effect played by an `Animator`
+#[derive(Debug, Clone, PartialEq)]
+pub enum AnimationEvent {
+    /// The effect with the given id started rendering frames
+    EffectStarted { id: u64, name: String },
+    /// The effect wrote a frame to the device
+    FrameRendered { id: u64, frame: Vec<Color> },
+    /// The effect ran out of frames
+    EffectCompleted { id: u64 },
+    /// The effect was cancelled before it ran out of frames
+    EffectCancelled { id: u64 },
+    /// The effect stopped because the device could not be written to
+    EffectFailed { id: u64, kind: FeatureErrorType },
+    /// The animator thread panicked with the given message, no more effects are played
+    AnimatorPanicked { message: String },
+}
+
+/// How an effect played by an `Animator` ended
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AnimationOutcome {
+    Completed,
+    Cancelled,
+    Failed(FeatureErrorType),
+}
+
+/// A handle to an effect that was submitted to an `Animator`
+#[derive(Clone)]
+pub struct AnimationHandle {
+    id: u64,
+    cancelled: Arc<AtomicBool>,
+    outcome: Arc<(Mutex<Option<AnimationOutcome>>, Condvar)>,
+    worker: std::thread::Thread,
+    parameters: Parameters,
+}
+
+impl AnimationHandle {
+    /// The id the effect is reported with in `AnimationEvent`s
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// The tunable parameters of the effect, empty unless it was submitted with `play_tunable` or `enqueue_tunable`
+    pub fn parameters(&self) -> &Parameters {
+        &self.parameters
+    }
+
+    /// Stops the effect before its next frame, or keeps it from starting if it is still queued
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+        self.worker.unpark();
+    }
+
+    /// Returns `true` once the effect has completed, been cancelled or failed
+    pub fn is_finished(&self) -> bool {
+        self.outcome.0.lock().unwrap().is_some()
+    }
+
+    /// Blocks until the effect has finished and returns how it ended
+    pub fn wait(&self) -> AnimationOutcome {
+        let (outcome, finished) = &*self.outcome;
+        let mut outcome = outcome.lock().unwrap();
+        while outcome.is_none() {
+            outcome = finished.wait(outcome).unwrap();
+        }
+
+        outcome.clone().unwrap()
+    }
+}
+
+struct Job {
+    name: String,
+    frames: Frames,
+    handle: AnimationHandle,
+}
+
+/// The effect the worker is rendering, with the time it started at
+struct Running {
+    name: String,
+    parameters: Parameters,
+    started: Duration,
+}
+
+/// The dithering of the effects that don't use the dithering the BlinkStick is set to
+struct EffectDithering {
+    stick: Option<Arc<BlinkStick>>,
+    effects: Mutex<HashMap<String, Dithering>>,
+}
+
+impl EffectDithering {
+    /// Sets the dithering of the effect `name` on the BlinkStick, returning the dithering it set and the one to
+    /// restore after it
+    fn start(&self, name: &str) -> Option<(Dithering, Dithering)> {
+        let stick = self.stick.as_ref()?;
+        let dithering = *self.effects.lock().unwrap().get(name)?;
+        let previous = stick.dithering();
+        stick.set_dithering(dithering);
+        Some((dithering, previous))
+    }
+
+    /// Restores the dithering from before the effect, unless it was set to something else while the effect played
+    fn finish(&self, started: Option<(Dithering, Dithering)>) {
+        if let (Some(stick), Some((applied, previous))) = (&self.stick, started) {
+            stick.restore_dithering(applied, previous);
+        }
+    }
+}
+
+enum Listener {
+    Channel(Sender<AnimationEvent>),
+    Callback(Callback),
+}
+
+#[derive(Default)]
+struct Listeners(Mutex<Vec<Listener>>);
+
+impl Listeners {
+    /// Hands an event to every listener without holding the lock, so callbacks may subscribe or play effects
+    /// themselves. Only the animator thread emits events.
+    fn emit(&self, event: AnimationEvent) {
+        let mut handed_out = HandedOut {
+            listeners: self,
+            taken: std::mem::take(&mut *self.0.lock().unwrap()),
+        };
+        handed_out.taken.retain(|listener| match listener {
+            Listener::Channel(sender) => sender.send(event.clone()).is_ok(),
+            Listener::Callback(callback) => {
+                callback(&event);
+                true
+            }
+        });
+    }
+}
+
+/// Listeners taken out of `Listeners` to hand out an event, which are put back even when a callback panics, so
+/// they still learn about the panic
+struct HandedOut<'a> {
+    listeners: &'a Listeners,
+    taken: Vec<Listener>,
+}
+
+impl Drop for HandedOut<'_> {
+    fn drop(&mut self) {
+        // Listeners added while the event was handed out come after the ones that received it
+        let mut current = self.listeners.0.lock().unwrap();
+        self.taken.append(&mut current);
+        *current = std::mem::take(&mut self.taken);
+    }
+}
+
+/// Plays effects on a background thread, one at a time, and reports their progress as `AnimationEvent`s
+///
+/// An effect is any iterator of frames, where each frame is a full `Color` vector for the device and the
+/// time the frame stays visible before the next one is rendered. Besides the BlinkStick, frames can be sent to any
+/// number of `FrameSink`s. While no effect plays, the BlinkStick is refreshed every second, so the last frame
+/// follows its dimming schedule and ambient light.
+///
+/// # Example
+/// Fades all leds in and out and waits until the effect is done
+/// ```
+/// use blinkstick_rs::{Animator, BlinkStick, Color};
+/// use std::time::Duration;
+///
+/// let blinkstick = BlinkStick::new().unwrap();
+/// let leds = blinkstick.max_leds as usize;
+/// let animator = Animator::new(blinkstick);
+///
+/// let events = animator.subscribe();
+/// let frames = (0..=50u8).chain((0..50u8).rev()).map(move |level| (vec![Color {r: 0, g: level, b: 0}; leds], Duration::from_millis(20)));
+/// animator.play("fade", frames).wait();
+///
+/// assert!(events.try_iter().count() > 0);
+/// ```
+pub struct Animator {
+    stick: Option<Arc<BlinkStick>>,
+    sinks: Arc<Sinks>,
+    listeners: Arc<Listeners>,
+    pending: Arc<Mutex<Vec<AnimationHandle>>>,
+    running: Arc<Mutex<Option<Running>>>,
+    clock: Arc<dyn Clock>,
+    last_frame: Arc<Mutex<Vec<Color>>>,
+    dithering: Arc<EffectDithering>,
+    jobs: Option<Sender<Job>>,
+    worker: Option<JoinHandle<()>>,
+    next_id: AtomicU64,
+}
+
+impl Animator {
+    /// Starts an animator that renders to `stick`
+    pub fn new<S: Into<Arc<BlinkStick>>>(stick: S) -> Animator {
+        Animator::start(Some(stick.into()), Arc::new(SystemClock::new()))
+    }
+
+    /// Starts an animator that renders to `stick` and times its frames with `clock`
+    pub fn with_clock<S: Into<Arc<BlinkStick>>>(stick: S, clock: Arc<dyn Clock>) -> Animator {
+        Animator::start(Some(stick.into()), clock)
+    }
+
+    /// Starts an animator without a BlinkStick, which only renders to the sinks added with `tee`
+    pub fn headless() -> Animator {
+        Animator::start(None, Arc::new(SystemClock::new()))
+    }
+
+    /// Starts an animator without a BlinkStick that times its frames with `clock`, see `ManualClock`
+    pub fn headless_with_clock(clock: Arc<dyn Clock>) -> Animator {
+        Animator::start(None, clock)
+    }
+
+    fn start(stick: Option<Arc<BlinkStick>>, clock: Arc<dyn Clock>) -> Animator {
+        let mut sinks: Vec<Box<dyn FrameSink>> = Vec::new();
+        if let Some(stick) = &stick {
+            sinks.push(Box::new(stick.clone()));
+        }
+        let sinks = Arc::new(Mutex::new(sinks));
+        let listeners = Arc::new(Listeners::default());
+        let last_frame = Arc::new(Mutex::new(Vec::new()));
+        {
+            let last_frame = last_frame.clone();
+            listeners
+                .0
+                .lock()
+                .unwrap()
+                .push(Listener::Callback(Box::new(move |event| {
+                    if let AnimationEvent::FrameRendered { frame, .. } = event {
+                        last_frame.lock().unwrap().clone_from(frame);
+                    }
+                })));
+        }
+        // The effects become part of the state of the BlinkStick, for its observers
+        if let Some(stick) = &stick {
+            let stick = stick.clone();
+            listeners
+                .0
+                .lock()
+                .unwrap()
+                .push(Listener::Callback(Box::new(move |event| match event {
+                    AnimationEvent::EffectStarted { id, name } => stick.record(|| StateEvent::EffectStarted {
+                        id: *id,
+                        name: name.clone(),
+                    }),
+                    AnimationEvent::EffectCompleted { id }
+                    | AnimationEvent::EffectCancelled { id }
+                    | AnimationEvent::EffectFailed { id, .. } => stick.record(|| StateEvent::EffectEnded { id: *id }),
+                    _ => (),
+                })));
+        }
+        let pending = Arc::new(Mutex::new(Vec::new()));
+        let running = Arc::new(Mutex::new(None));
+        let dithering = Arc::new(EffectDithering {
+            stick: stick.clone(),
+            effects: Mutex::new(HashMap::new()),
+        });
+        let (jobs, queue) = channel();
+
+        let worker = {
+            let (sinks, listeners, pending, running) =
+                (sinks.clone(), listeners.clone(), pending.clone(), running.clone());
+            let (clock, dithering) = (clock.clone(), dithering.clone());
+            std::thread::spawn(move || {
+                let worker =
+                    AssertUnwindSafe(|| run_worker(&*clock, &sinks, &listeners, &pending, &running, &dithering, queue));
+                if let Err(panic) = std::panic::catch_unwind(worker) {
+                    listeners.emit(AnimationEvent::AnimatorPanicked {
+                        message: panic_message(&*panic),
+                    });
+                }
+            })
+        };
+
+        Animator {
+            stick,
+            sinks,
+            listeners,
+            pending,
+            running,
+            clock,
+            last_frame,
+            dithering,
+            jobs: Some(jobs),
+            worker: Some(worker),
+            next_id: AtomicU64::new(0),
+        }
+    }
+
+    /// The BlinkStick the animator renders to, `None` for a headless animator
+    pub fn stick(&self) -> Option<&Arc<BlinkStick>> {
+        self.stick.as_ref()
+    }
+
+    /// The frame rendered last, kept up to date by the animator thread
+    pub(crate) fn last_frame(&self) -> Arc<Mutex<Vec<Color>>> {
+        self.last_frame.clone()
+    }
+
+    /// Rounds the colors of every effect named `name` that starts from now on as `dithering` says, `None` leaves
+    /// them to the dithering of the BlinkStick. The BlinkStick goes back to its own dithering once the effect ends.
+    ///
+    /// # Example
+    /// Dithers a slow, dark fade over time while other effects round their colors as before
+    /// ```
+    /// use blinkstick_rs::{AnimationIter, Animator, BlinkStick, Color, Dithering};
+    /// use std::time::Duration;
+    ///
+    /// let blinkstick = BlinkStick::new().unwrap();
+    /// let leds = blinkstick.max_leds as usize;
+    /// let animator = Animator::new(blinkstick);
+    /// animator.set_effect_dithering("night light", Some(Dithering::Temporal));
+    ///
+    /// let frames = AnimationIter::transform(vec![Color::default(); leds], vec![Color::new(8, 2, 0); leds], Duration::from_secs(60), 3000);
+    /// animator.play("night light", frames).wait();
+    /// ```
+    pub fn set_effect_dithering(&self, name: &str, dithering: Option<Dithering>) {
+        let mut effects = self.dithering.effects.lock().unwrap();
+        match dithering {
+            Some(dithering) => effects.insert(name.to_string(), dithering),
+            None => effects.remove(name),
+        };
+    }
+
+    /// Sends every frame rendered from now on to `sink` as well
+    pub fn tee<S: FrameSink + 'static>(&self, sink: S) {
+        self.sinks.lock().unwrap().push(Box::new(sink));
+    }
+
+    /// Cancels every queued or running effect and plays `frames` instead
+    ///
+    /// # Arguments
+    /// * `name` - A name for the effect, reported in `AnimationEvent::EffectStarted`
+    /// * `frames` - The frames of the effect, each with the time it stays visible
+    pub fn play<I>(&self, name: &str, frames: I) -> AnimationHandle
+    where
+        I: IntoIterator<Item = (Vec<Color>, Duration)>,
+        I::IntoIter: Send + 'static,
+    {
+        self.play_tunable(name, Parameters::new(), frames)
+    }
+
+    /// Cancels every queued or running effect and plays `frames` instead, with parameters that can be changed
+    /// through the returned handle while the effect plays, see `Parameters`
+    pub fn play_tunable<I>(&self, name: &str, parameters: Parameters, frames: I) -> AnimationHandle
+    where
+        I: IntoIterator<Item = (Vec<Color>, Duration)>,
+        I::IntoIter: Send + 'static,
+    {
+        self.cancel_all();
+        self.enqueue_tunable(name, parameters, frames)
+    }
+
+    /// Plays `frames` once every previously submitted effect has finished
+    ///
+    /// # Arguments
+    /// * `name` - A name for the effect, reported in `AnimationEvent::EffectStarted`
+    /// * `frames` - The frames of the effect, each with the time it stays visible
+    pub fn enqueue<I>(&self, name: &str, frames: I) -> AnimationHandle
+    where
+        I: IntoIterator<Item = (Vec<Color>, Duration)>,
+        I::IntoIter: Send + 'static,
+    {
+        self.enqueue_tunable(name, Parameters::new(), frames)
+    }
+
+    /// Plays `frames` once every previously submitted effect has finished, with parameters that can be changed
+    /// through the returned handle while the effect plays, see `Parameters`
+    pub fn enqueue_tunable<I>(&self, name: &str, parameters: Parameters, frames: I) -> AnimationHandle
+    where
+        I: IntoIterator<Item = (Vec<Color>, Duration)>,
+        I::IntoIter: Send + 'static,
+    {
+        let handle = AnimationHandle {
+            id: self.next_id.fetch_add(1, Ordering::Relaxed),
+            cancelled: Arc::new(AtomicBool::new(false)),
+            outcome: Arc::new((Mutex::new(None), Condvar::new())),
+            worker: self.worker.as_ref().unwrap().thread().clone(),
+            parameters,
+        };
+
+        self.pending.lock().unwrap().push(handle.clone());
+        let job = Job {
+            name: name.to_string(),
+            frames: Box::new(frames.into_iter()),
+            handle: handle.clone(),
+        };
+        self.jobs.as_ref().unwrap().send(job).unwrap();
+
+        handle
+    }
+
+    /// Cancels every queued or running effect
+    pub fn cancel_all(&self) {
+        for handle in self.pending.lock().unwrap().iter() {
+            handle.cancel();
+        }
+    }
+
+    /// The effect that is playing right now, to be saved and resumed with `resume`, `None` while nothing plays
+    pub fn program(&self) -> Option<Program> {
+        self.running.lock().unwrap().as_ref().map(|running| Program {
+            effect: running.name.clone(),
+            parameters: running.parameters.values(),
+            phase: self.clock.now().saturating_sub(running.started),
+        })
+    }
+
+    /// Returns a channel receiving every `AnimationEvent` from now on
+    pub fn subscribe(&self) -> Receiver<AnimationEvent> {
+        let (sender, receiver) = channel();
+        self.listeners.0.lock().unwrap().push(Listener::Channel(sender));
+        receiver
+    }
+
+    /// Calls `callback` with every `AnimationEvent` from now on, on the animator thread
+    pub fn on_event<F>(&self, callback: F)
+    where
+        F: Fn(&AnimationEvent) + Send + 'static,
+    {
+        self.listeners
+            .0
+            .lock()
+            .unwrap()
+            .push(Listener::Callback(Box::new(callback)));
+    }
+}
+
+impl Drop for Animator {
+    fn drop(&mut self) {
+        self.cancel_all();
+        self.jobs.take();
+
+        // A panic of the thread was already reported as `AnimationEvent::AnimatorPanicked`
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+/// The message a thread panicked with, if it panicked with a string
+fn panic_message(panic: &(dyn Any + Send)) -> String {
+    match (panic.downcast_ref::<&str>(), panic.downcast_ref::<String>()) {
+        (Some(message), _) => message.to_string(),
+        (_, Some(message)) => message.clone(),
+        _ => "Animator thread panicked".to_string(),
+    }
+}
+
+fn run_worker(
+    clock: &dyn Clock,
+    sinks: &Sinks,
+    listeners: &Listeners,
+    pending: &Mutex<Vec<AnimationHandle>>,
+    running: &Mutex<Option<Running>>,
+    dithering: &EffectDithering,
+    queue: Receiver<Job>,
+) {
+    loop {
+        let job = match queue.recv_timeout(IDLE_REFRESH_INTERVAL) {
+            Ok(job) => job,
+            Err(RecvTimeoutError::Timeout) => {
+                // Lets the dimming schedule and the ambient light change the brightness of the last frame, a
+                // failed write shows up again with the next effect
+                if let Some(stick) = &dithering.stick {
+                    let _ = stick.refresh();
+                }
+                continue;
+            }
+            Err(RecvTimeoutError::Disconnected) => break,
+        };
+        let id = job.handle.id;
+        let outcome = if job.handle.cancelled.load(Ordering::SeqCst) {
+            AnimationOutcome::Cancelled
+        } else {
+            *running.lock().unwrap() = Some(Running {
+                name: job.name.clone(),
+                parameters: job.handle.parameters.clone(),
+                started: clock.now(),
+            });
+            let previous_dithering = dithering.start(&job.name);
+            listeners.emit(AnimationEvent::EffectStarted { id, name: job.name });
+            let outcome = render(clock, sinks, listeners, &job.handle, job.frames);
+            dithering.finish(previous_dithering);
+            *running.lock().unwrap() = None;
+            outcome
+        };
+
+        listeners.emit(match &outcome {
+            AnimationOutcome::Completed => AnimationEvent::EffectCompleted { id },
+            AnimationOutcome::Cancelled => AnimationEvent::EffectCancelled { id },
+            AnimationOutcome::Failed(kind) => AnimationEvent::EffectFailed { id, kind: kind.clone() },
+        });
+
+        pending.lock().unwrap().retain(|handle| handle.id != id);
+        let (finished_outcome, finished) = &*job.handle.outcome;
+        *finished_outcome.lock().unwrap() = Some(outcome);
+        finished.notify_all();
+    }
+}
+
+fn render(
+    clock: &dyn Clock,
+    sinks: &Sinks,
+    listeners: &Listeners,
+    handle: &AnimationHandle,
+    frames: Frames,
+) -> AnimationOutcome {
+    let mut failed_frames = 0;
+    let mut recovered = false;
+    for (frame, delay) in frames {
+        if handle.cancelled.load(Ordering::SeqCst) {
+            return AnimationOutcome::Cancelled;
+        }
+
+        let deadline = clock.now() + delay;
+        // Every sink receives the frame, even when an earlier one failed
+        let mut sent = Ok(());
+        for sink in sinks.lock().unwrap().iter_mut() {
+            sent = sent.and(sink.send_frame(&frame, delay));
+        }
+        match sent {
+            Ok(()) => {
+                failed_frames = 0;
+                listeners.emit(AnimationEvent::FrameRendered { id: handle.id, frame });
+            }
+            // A device that stopped answering is reset once per effect, dropping this frame
+            Err(e) if e.is_wedged() && !recovered => {
+                recovered = true;
+                let mut reset = Ok(());
+                for sink in sinks.lock().unwrap().iter_mut() {
+                    reset = reset.and(sink.recover());
+                }
+                if let Err(e) = reset {
+                    return AnimationOutcome::Failed(e.kind);
+                }
+            }
+            // A transient failure only drops this frame, the next frame retries the device
+            Err(e) if e.is_transient() && failed_frames < MAX_CONSECUTIVE_FAILED_FRAMES => failed_frames += 1,
+            Err(e) => return AnimationOutcome::Failed(e.kind),
+        }
+
+        // The clock may wake up early, which lets a cancellation interrupt long frames
+        let mut now = clock.now();
+        while now < deadline && !handle.cancelled.load(Ordering::SeqCst) {
+            clock.sleep(deadline - now);
+            now = clock.now();
+        }
+    }
+
+    if handle.cancelled.load(Ordering::SeqCst) {
+        AnimationOutcome::Cancelled
+    } else {
+        AnimationOutcome::Completed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn emits_effect_events_in_order() {
+        let blinkstick = crate::BlinkStickBuilder::new().leds(4).open_disabled().unwrap();
+        let frame = vec![Color { r: 5, g: 0, b: 0 }; blinkstick.max_leds as usize];
+        let animator = Animator::new(blinkstick);
+
+        let events = animator.subscribe();
+        let frames = vec![(frame.clone(), Duration::from_millis(10)); 2];
+        let outcome = animator.play("test", frames).wait();
+
+        assert_eq!(outcome, AnimationOutcome::Completed);
+        assert_eq!(
+            events.try_iter().collect::<Vec<AnimationEvent>>(),
+            vec![
+                AnimationEvent::EffectStarted {
+                    id: 0,
+                    name: "test".to_string()
+                },
+                AnimationEvent::FrameRendered {
+                    id: 0,
+                    frame: frame.clone()
+                },
+                AnimationEvent::FrameRendered { id: 0, frame },
+                AnimationEvent::EffectCompleted { id: 0 },
+            ]
+        );
+    }
+
+    #[test]
+    fn callbacks_may_subscribe_while_events_are_emitted() {
+        let listeners = Arc::new(Listeners::default());
+        let (sender, receiver) = channel();
+        {
+            let inner = listeners.clone();
+            listeners
+                .0
+                .lock()
+                .unwrap()
+                .push(Listener::Callback(Box::new(move |event| {
+                    if let AnimationEvent::EffectStarted { .. } = event {
+                        inner.0.lock().unwrap().push(Listener::Channel(sender.clone()));
+                    }
+                })));
+        }
+
+        listeners.emit(AnimationEvent::EffectStarted {
+            id: 0,
+            name: "nested".to_string(),
+        });
+        listeners.emit(AnimationEvent::EffectCompleted { id: 0 });
+
+        assert_eq!(receiver.try_recv(), Ok(AnimationEvent::EffectCompleted { id: 0 }));
+        assert_eq!(listeners.0.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn panics_are_reported_to_the_listeners() {
+        let animator = Animator::headless();
+        let events = animator.subscribe();
+        animator.on_event(|event| {
+            if let AnimationEvent::EffectStarted { .. } = event {
+                panic!("listener failed");
+            }
+        });
+
+        animator.play("test", vec![(vec![crate::COLOR_OFF; 2], Duration::ZERO)]);
+
+        let timeout = Duration::from_secs(5);
+        assert!(matches!(
+            events.recv_timeout(timeout),
+            Ok(AnimationEvent::EffectStarted { .. })
+        ));
+        assert_eq!(
+            events.recv_timeout(timeout),
+            Ok(AnimationEvent::AnimatorPanicked {
+                message: "listener failed".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn headless_animator_tees_frames() {
+        let animator = Animator::headless();
+        let recorder = crate::FrameRecorder::new();
+        animator.tee(recorder.clone());
+
+        let frames: Vec<(Vec<Color>, Duration)> = (0..3u8)
+            .map(|level| (vec![Color { r: level, g: 0, b: 0 }; 2], Duration::from_millis(1)))
+            .collect();
+        let outcome = animator.play("recorded", frames.clone()).wait();
+
+        assert_eq!(outcome, AnimationOutcome::Completed);
+        assert_eq!(recorder.frames(), frames);
+    }
+
+    #[test]
+    fn cancelled_effect_stops_early() {
+        let blinkstick = crate::BlinkStickBuilder::new().leds(4).open_disabled().unwrap();
+        let frame = vec![Color { r: 0, g: 5, b: 0 }; blinkstick.max_leds as usize];
+        let animator = Animator::new(blinkstick);
+
+        let handle = animator.play("endless", std::iter::repeat((frame, Duration::from_millis(50))));
+        std::thread::sleep(Duration::from_millis(120));
+        handle.cancel();
+
+        assert_eq!(handle.wait(), AnimationOutcome::Cancelled);
+    }
+
+    use crate::FeatureError;
+    use std::sync::atomic::AtomicUsize;
+
+    /// A sink that times out until it is recovered
+    struct WedgedSink {
+        recoveries: Arc<AtomicUsize>,
+    }
+
+    impl FrameSink for WedgedSink {
+        fn send_frame(&mut self, _frame: &[Color], _duration: Duration) -> Result<(), FeatureError> {
+            match self.recoveries.load(Ordering::SeqCst) {
+                0 => Err(FeatureError {
+                    kind: FeatureErrorType::Timeout,
+                }),
+                _ => Ok(()),
+            }
+        }
+
+        fn recover(&mut self) -> Result<(), FeatureError> {
+            self.recoveries.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn wedged_sinks_are_recovered() {
+        let animator = Animator::headless();
+        let recoveries = Arc::new(AtomicUsize::new(0));
+        animator.tee(WedgedSink {
+            recoveries: recoveries.clone(),
+        });
+
+        let events = animator.subscribe();
+        let frames = vec![(vec![Color::RED; 2], Duration::from_millis(1)); 2];
+        assert_eq!(animator.play("wedged", frames).wait(), AnimationOutcome::Completed);
+
+        assert_eq!(recoveries.load(Ordering::SeqCst), 1);
+        let rendered = events
+            .try_iter()
+            .filter(|event| matches!(event, AnimationEvent::FrameRendered { .. }))
+            .count();
+        assert_eq!(rendered, 1);
+    }
+}
@@ -0,0 +1,139 @@
+//! A Bevy plugin, so games and interactive installations can drive a BlinkStick from their systems.
+//!
+//! `BlinkStickPlugin` opens the device as the `BlinkStickDevice` resource, falling back to a `NoopBlinkStick` when
+//! none is connected so the game runs the same without one. Effects are played by adding a `BlinkStickEffect`
+//! component to any entity: every frame of the game advances it by the frame time and writes its frame to the
+//! device. The component is removed once the effect has ended. When several effects play at once, the frames of
+//! all of them are written in turn, so play one at a time.
+//!
+//! # Example
+//! Pulses the leds red whenever the player takes damage
+//! ```no_run
+//! extern crate bevy;
+//! extern crate blinkstick_rs;
+//!
+//! use bevy::prelude::*;
+//! use blinkstick_rs::bevy_plugin::{BlinkStickDevice, BlinkStickEffect, BlinkStickPlugin};
+//! use blinkstick_rs::{AnimationIter, Color, LedDevice};
+//!
+//! fn on_damage(mut commands: Commands, device: Res<BlinkStickDevice>) {
+//!     let leds = device.led_count() as usize;
+//!     commands.spawn(BlinkStickEffect::new(AnimationIter::heartbeat(Color {r: 80, g: 0, b: 0}, leds, 1)));
+//! }
+//!
+//! App::new().add_plugins(BlinkStickPlugin::default()).add_systems(Update, on_damage).run();
+//! ```
+
+use crate::{effects, AnimationIter, BlinkStick, EffectStepper, LedDevice, NoopBlinkStick};
+use bevy::prelude::*;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+/// The number of leds of the `NoopBlinkStick` used when no BlinkStick is connected, that of a BlinkStick Square
+const FALLBACK_LEDS: u8 = 8;
+
+/// Opens a BlinkStick as the `BlinkStickDevice` resource and plays `BlinkStickEffect`s
+#[derive(Default)]
+pub struct BlinkStickPlugin {
+    serial: Option<String>,
+}
+
+impl BlinkStickPlugin {
+    /// Opens the BlinkStick with the given serial number instead of the first one found
+    pub fn serial(serial: &str) -> BlinkStickPlugin {
+        BlinkStickPlugin {
+            serial: Some(serial.to_string()),
+        }
+    }
+}
+
+impl Plugin for BlinkStickPlugin {
+    fn build(&self, app: &mut App) {
+        let opened = match &self.serial {
+            Some(serial) => BlinkStick::open_by_serial(serial),
+            None => BlinkStick::new(),
+        };
+        let device: Arc<dyn LedDevice> = match opened {
+            Ok(blinkstick) => Arc::new(blinkstick),
+            Err(e) => {
+                bevy::log::warn!("Could not open BlinkStick, lighting is disabled: {}", e);
+                Arc::new(NoopBlinkStick::new(FALLBACK_LEDS))
+            }
+        };
+
+        app.insert_resource(BlinkStickDevice(device))
+            .add_systems(Update, play_effects);
+    }
+}
+
+/// The device the plugin drives, a BlinkStick or a `NoopBlinkStick` when none is connected
+#[derive(Resource, Clone)]
+pub struct BlinkStickDevice(pub Arc<dyn LedDevice>);
+
+impl std::ops::Deref for BlinkStickDevice {
+    type Target = dyn LedDevice;
+
+    fn deref(&self) -> &Self::Target {
+        &*self.0
+    }
+}
+
+/// An effect playing on the BlinkStick for as long as the component exists, see the module documentation
+#[derive(Component)]
+pub struct BlinkStickEffect {
+    // Systems share components between threads, which an effect's frames may not allow on their own
+    stepper: Mutex<EffectStepper>,
+}
+
+impl BlinkStickEffect {
+    /// Plays the frames of an effect
+    pub fn new(frames: AnimationIter) -> BlinkStickEffect {
+        BlinkStickEffect {
+            stepper: Mutex::new(EffectStepper::new(frames)),
+        }
+    }
+
+    /// Plays the theme or registered effect called `name` on `led_count` leds, see `effects::animation`
+    pub fn named(name: &str, led_count: usize) -> Option<BlinkStickEffect> {
+        effects::animation(name).map(|animation| BlinkStickEffect::new(animation.frames(led_count)))
+    }
+}
+
+/// Advances every effect by the frame time, writing its frames and removing it once it has ended
+///
+/// Apps without the `TimePlugin`, like headless ones built from `MinimalPlugins` without it, have no `Time`
+/// resource. Their effects advance by the wall clock time between two runs of the system instead.
+///
+/// A failed write is logged once, the writes after it only once the device took a frame again.
+fn play_effects(
+    time: Option<Res<Time>>,
+    mut last_run: Local<Option<Instant>>,
+    mut failing: Local<bool>,
+    device: Res<BlinkStickDevice>,
+    mut commands: Commands,
+    mut playing: Query<(Entity, &mut BlinkStickEffect)>,
+) {
+    let now = Instant::now();
+    let delta = match &time {
+        Some(time) => time.delta(),
+        None => last_run.map_or(std::time::Duration::ZERO, |last_run| now.duration_since(last_run)),
+    };
+    *last_run = Some(now);
+
+    for (entity, mut effect) in playing.iter_mut() {
+        let stepper = effect.stepper.get_mut().unwrap();
+        if let Some(frame) = stepper.tick(delta) {
+            match device.set_all_leds_colors(frame) {
+                Ok(()) => *failing = false,
+                Err(e) if !*failing => {
+                    bevy::log::warn!("Could not write effect to BlinkStick: {}", e);
+                    *failing = true;
+                }
+                Err(_) => (),
+            }
+        }
+        if stepper.is_finished() {
+            commands.entity(entity).remove::<BlinkStickEffect>();
+        }
+    }
+}
@@ -0,0 +1,270 @@
+//! A command line interface to BlinkStick devices
+//!
+//! The device is chosen with `--serial`, or by the configuration file read by `Config::load_default`. With `--json`
+//! every command prints a single JSON document, so scripts can consume device state without parsing text.
+
+extern crate blinkstick_rs;
+
+use blinkstick_rs::{BlinkStick, Color, Config, DeviceDescriptor, FrameReadError, FrameReader, Variant};
+use std::process::ExitCode;
+use std::time::{Duration, Instant};
+
+const USAGE: &str = "Usage: blinkstick [--json] [--serial SERIAL] [--interval MILLISECONDS] [--fps FPS] COMMAND
+
+Commands:
+  list    Lists every connected BlinkStick
+  info    Shows the serial number, variant, number of leds and channels of a BlinkStick
+  get     Shows the color and label of every led of a BlinkStick
+  watch   Shows the colors of a BlinkStick whenever they change, polling once every interval
+  pipe    Shows frames read from standard input, one per line, at most FPS frames per second";
+
+/// The options shared by every command
+struct Options {
+    json: bool,
+    serial: Option<String>,
+    interval: Duration,
+    /// The shortest time between two frames shown by `pipe`, from `--fps`
+    frame_interval: Duration,
+}
+
+fn main() -> ExitCode {
+    let mut options = Options {
+        json: false,
+        serial: None,
+        interval: Duration::from_millis(100),
+        frame_interval: Duration::from_secs(1) / 30,
+    };
+    let mut command = None;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--json" => options.json = true,
+            "--serial" => match args.next() {
+                Some(serial) => options.serial = Some(serial),
+                None => return usage_error("--serial requires a serial number"),
+            },
+            "--interval" => match args.next().and_then(|interval| interval.parse().ok()) {
+                Some(milliseconds) => options.interval = Duration::from_millis(milliseconds),
+                None => return usage_error("--interval requires a number of milliseconds"),
+            },
+            "--fps" => match args
+                .next()
+                .and_then(|fps| fps.parse::<f32>().ok())
+                .filter(|fps| *fps > 0.0)
+                .and_then(|fps| Duration::try_from_secs_f32(1.0 / fps).ok())
+            {
+                Some(frame_interval) => options.frame_interval = frame_interval,
+                None => return usage_error("--fps requires a positive number of frames per second"),
+            },
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return ExitCode::SUCCESS;
+            }
+            _ if command.is_none() && !arg.starts_with('-') => command = Some(arg),
+            _ => return usage_error(&format!("unexpected argument {}", arg)),
+        }
+    }
+
+    let result = match command.as_deref() {
+        Some("list") => list(&options),
+        Some("info") => info(&options),
+        Some("get") => get(&options),
+        Some("watch") => watch(&options),
+        Some("pipe") => pipe(&options),
+        Some(command) => return usage_error(&format!("unknown command {}", command)),
+        None => return usage_error("missing command"),
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(message) => {
+            eprintln!("blinkstick: {}", message);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn usage_error(message: &str) -> ExitCode {
+    eprintln!("blinkstick: {}\n\n{}", message, USAGE);
+    ExitCode::from(2)
+}
+
+fn list(options: &Options) -> Result<(), String> {
+    let devices = BlinkStick::list_devices();
+
+    if options.json {
+        let devices: Vec<String> = devices.iter().map(device_json).collect();
+        println!("[{}]", devices.join(","));
+    } else {
+        for device in devices.iter() {
+            println!(
+                "{}\t{}\t{:?}\t{}",
+                device.serial_number.as_deref().unwrap_or("-"),
+                device
+                    .location
+                    .as_ref()
+                    .map_or("-".to_string(), |location| location.to_string()),
+                device_variant(device),
+                device.path
+            );
+        }
+    }
+
+    Ok(())
+}
+
+fn info(options: &Options) -> Result<(), String> {
+    let blinkstick = open(options)?;
+    let serial = blinkstick.serial_number();
+    let channels: Vec<String> = blinkstick.channel_leds().iter().map(|leds| leds.to_string()).collect();
+
+    if options.json {
+        println!(
+            "{{\"serial\":{},\"variant\":{},\"leds\":{},\"channels\":[{}],\"report_length\":{}}}",
+            json_option(serial.as_deref()),
+            json_string(&format!("{:?}", blinkstick.variant())),
+            blinkstick.max_leds,
+            channels.join(","),
+            blinkstick.report_length()
+        );
+    } else {
+        println!("Serial:   {}", serial.as_deref().unwrap_or("-"));
+        println!("Variant:  {:?}", blinkstick.variant());
+        println!("Leds:     {}", blinkstick.max_leds);
+        println!("Channels: {}", channels.join(" "));
+        println!("Report:   {} bytes", blinkstick.report_length());
+    }
+
+    Ok(())
+}
+
+fn get(options: &Options) -> Result<(), String> {
+    let blinkstick = open(options)?;
+    let state = blinkstick.get_state().map_err(|e| e.to_string())?;
+
+    if options.json {
+        let colors: Vec<String> = state.iter().map(|led| json_string(&led.color.to_string())).collect();
+        let labels: Vec<String> = state.iter().map(|led| json_option(led.label.as_deref())).collect();
+        println!(
+            "{{\"serial\":{},\"colors\":[{}],\"labels\":[{}]}}",
+            json_option(blinkstick.serial_number().as_deref()),
+            colors.join(","),
+            labels.join(",")
+        );
+    } else {
+        for led in state.iter() {
+            println!("{}\t{}\t{}", led.led, led.color, led.label.as_deref().unwrap_or(""));
+        }
+    }
+
+    Ok(())
+}
+
+fn watch(options: &Options) -> Result<(), String> {
+    let blinkstick = open(options)?;
+
+    for colors in blinkstick.watch(options.interval) {
+        let colors = colors.map_err(|e| e.to_string())?;
+        if options.json {
+            // One document per line, so consumers can parse the stream line by line
+            let colors: Vec<String> = colors.iter().map(|color| json_string(&color.to_string())).collect();
+            println!("{{\"colors\":[{}]}}", colors.join(","));
+        } else {
+            let blocks: String = colors.iter().map(|color| terminal_block(*color)).collect();
+            let hex: Vec<String> = colors.iter().map(|color| color.to_string()).collect();
+            println!("{} {}", blocks, hex.join(" "));
+        }
+    }
+
+    Ok(())
+}
+
+fn pipe(options: &Options) -> Result<(), String> {
+    let blinkstick = open(options)?;
+    let leds = blinkstick.max_leds as usize;
+
+    for frame in FrameReader::new(std::io::stdin().lock()) {
+        let start = Instant::now();
+        let frame = match frame {
+            // A single color lights up every led
+            Ok(frame) if frame.len() == 1 => vec![frame[0]; leds],
+            Ok(frame) => frame,
+            // Standard input can't be read any further
+            Err(e @ FrameReadError::Io(_)) => return Err(e.to_string()),
+            Err(e) => {
+                eprintln!("blinkstick: {}", e);
+                continue;
+            }
+        };
+
+        if let Err(e) = blinkstick.set_all_leds_colors(&frame) {
+            if !e.is_transient() {
+                return Err(e.to_string());
+            }
+            eprintln!("blinkstick: {}", e);
+        }
+        std::thread::sleep(options.frame_interval.saturating_sub(start.elapsed()));
+    }
+
+    Ok(())
+}
+
+/// Opens the BlinkStick chosen on the command line or in the configuration file, leaving its leds as they are
+fn open(options: &Options) -> Result<BlinkStick, String> {
+    let mut config = Config::load_default().map_err(|e| e.to_string())?;
+    if options.serial.is_some() {
+        config.serial = options.serial.clone();
+    }
+    config.turn_off_on_drop = false;
+
+    config
+        .open_with(BlinkStick::builder().reset_on_open(false))
+        .map_err(|e| e.to_string())
+}
+
+fn device_variant(device: &DeviceDescriptor) -> Variant {
+    Variant::detect(
+        device.serial_number.as_deref(),
+        device.release_number,
+        device.product.as_deref(),
+    )
+}
+
+fn device_json(device: &DeviceDescriptor) -> String {
+    format!(
+        "{{\"serial\":{},\"variant\":{},\"release_number\":{},\"product\":{},\"location\":{},\"path\":{}}}",
+        json_option(device.serial_number.as_deref()),
+        json_string(&format!("{:?}", device_variant(device))),
+        device.release_number,
+        json_option(device.product.as_deref()),
+        json_option(device.location.as_ref().map(|location| location.to_string()).as_deref()),
+        json_string(&device.path)
+    )
+}
+
+/// Two spaces with the color as their background, in the 24 bit color escape codes of most terminals
+fn terminal_block(color: Color) -> String {
+    format!("\x1b[48;2;{};{};{}m  \x1b[0m", color.r, color.g, color.b)
+}
+
+fn json_option(value: Option<&str>) -> String {
+    value.map_or("null".to_string(), json_string)
+}
+
+fn json_string(value: &str) -> String {
+    let mut json = String::with_capacity(value.len() + 2);
+    json.push('"');
+    for character in value.chars() {
+        match character {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            character if (character as u32) < 0x20 => json.push_str(&format!("\\u{:04x}", character as u32)),
+            character => json.push(character),
+        }
+    }
+    json.push('"');
+
+    json
+}
@@ -0,0 +1,786 @@
+use crate::channel::FrameLock;
+use crate::device_events::EventListeners;
+use crate::diagnostics::Diagnostics;
+use crate::dither::Quantizer;
+use crate::observer::Observers;
+use crate::throttle::ReportThrottle;
+use crate::transport::{Loopback, Transport};
+use crate::verify::VerificationCounters;
+use crate::wire::{
+    default_channel_leds, parse_led_count, CHANNEL_COUNT, CHANNEL_LEDS, LEDS_REPORT_HEADER, LEDS_REPORT_ID,
+};
+use crate::{
+    BlinkStick, ChannelOrder, Color, ColorProfile, Config, DimmingSchedule, FeatureError, FeatureErrorType,
+    ResponseCurves, Variant, COLOR_OFF, DEFAULT_MIN_REPORT_GAP, PRODUCT_ID, REPORT_ARRAY_BYTES, VENDOR_ID,
+};
+use std::collections::HashMap;
+use std::ffi::CString;
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// The highest strobe frequency allowed unless overridden, three flashes per second is the
+/// commonly cited photosensitive seizure threshold.
+pub const DEFAULT_MAX_STROBE_FREQUENCY: f32 = 3.0;
+
+/// The environment variable naming the serial number of the BlinkStick `open` opens
+const SERIAL_VARIABLE: &str = "BLINKSTICK_SERIAL";
+
+/// The environment variable capping the brightness of every BlinkStick, a factor (0.0 - 1.0)
+const MAX_BRIGHTNESS_VARIABLE: &str = "BLINKSTICK_MAX_BRIGHTNESS";
+
+/// The environment variable turning the light output of every BlinkStick off, when set to anything but `0`,
+/// `false` or nothing
+const DISABLE_VARIABLE: &str = "BLINKSTICK_DISABLE";
+
+/// The number of leds of a disabled BlinkStick that wasn't given any, those of a BlinkStick Strip
+const DISABLED_LEDS: u8 = 8;
+
+/// Configures how a `BlinkStick` is opened
+///
+/// # Environment
+/// Deployed programs can be constrained without changing their code:
+/// * `BLINKSTICK_SERIAL` selects the BlinkStick `open` and `open_with_api` open by its serial number
+/// * `BLINKSTICK_MAX_BRIGHTNESS` caps the configured brightness at a factor (0.0 - 1.0)
+/// * `BLINKSTICK_DISABLE`, when set to anything but `0`, `false` or nothing, opens a stand-in that answers like a
+///   BlinkStick without any hardware, like a `NoopBlinkStick`, so the program keeps working on CI or a headless
+///   server. It has the leds given to `leds` or `channels`, or 8. A device taken over with `from_hid_device` stays
+///   dark instead.
+///
+/// Values that cannot be parsed are ignored.
+///
+/// # Example
+/// Opens a BlinkStick that may strobe up to ten times a second
+#[cfg_attr(feature = "effects", doc = "```")]
+#[cfg_attr(not(feature = "effects"), doc = "```ignore")]
+/// use blinkstick_rs::BlinkStick;
+///
+/// let blinkstick = BlinkStick::builder().max_strobe_frequency(10.0).open().unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct BlinkStickBuilder {
+    #[cfg(feature = "effects")]
+    max_strobe_frequency: f32,
+    io_timeout: Option<Duration>,
+    min_report_gap: Duration,
+    leds: Option<u8>,
+    channels: Option<Vec<u8>>,
+    brightness: f32,
+    gamma: f32,
+    channel_order: ChannelOrder,
+    color_profile: ColorProfile,
+    response_curves: Option<ResponseCurves>,
+    turn_off_on_drop: bool,
+    reset_on_open: bool,
+    write_only: bool,
+    serialize_frames: bool,
+    calibrate_latency: bool,
+    apply_device_preset: bool,
+    verify_writes: u8,
+    scene: Option<Vec<Color>>,
+    labels: Vec<String>,
+    dimming_schedule: Option<DimmingSchedule>,
+}
+
+impl Default for BlinkStickBuilder {
+    fn default() -> Self {
+        BlinkStickBuilder {
+            #[cfg(feature = "effects")]
+            max_strobe_frequency: DEFAULT_MAX_STROBE_FREQUENCY,
+            io_timeout: None,
+            min_report_gap: DEFAULT_MIN_REPORT_GAP,
+            leds: None,
+            channels: None,
+            brightness: 1.0,
+            gamma: 1.0,
+            channel_order: ChannelOrder::Rgb,
+            color_profile: ColorProfile::default(),
+            response_curves: None,
+            turn_off_on_drop: true,
+            reset_on_open: true,
+            write_only: false,
+            serialize_frames: false,
+            calibrate_latency: false,
+            apply_device_preset: false,
+            verify_writes: 0,
+            scene: None,
+            labels: Vec::new(),
+            dimming_schedule: None,
+        }
+    }
+}
+
+impl BlinkStickBuilder {
+    /// Creates a builder with the default settings
+    pub fn new() -> BlinkStickBuilder {
+        BlinkStickBuilder::default()
+    }
+
+    /// A builder with the settings `stick` has now, to open the device again the same way after it was unplugged
+    pub(crate) fn reopening(stick: &BlinkStick) -> BlinkStickBuilder {
+        BlinkStickBuilder {
+            #[cfg(feature = "effects")]
+            max_strobe_frequency: stick.max_strobe_frequency,
+            io_timeout: stick.io_timeout(),
+            min_report_gap: stick.min_report_gap(),
+            leds: stick.led_override,
+            channels: stick.channel_layout.clone(),
+            brightness: stick.brightness,
+            gamma: stick.gamma,
+            channel_order: stick.channel_order(),
+            color_profile: stick.color_profile(),
+            response_curves: stick.response_curves.lock().unwrap().clone(),
+            turn_off_on_drop: stick.turn_off_on_drop.load(Ordering::Relaxed),
+            reset_on_open: stick.reset_on_open,
+            write_only: stick.write_only.load(Ordering::Relaxed),
+            serialize_frames: stick.frame_lock.is_serialized(),
+            calibrate_latency: stick.calibrate_latency,
+            apply_device_preset: stick.device_preset.is_some(),
+            verify_writes: stick.verify_retries.load(Ordering::Relaxed),
+            scene: stick.opening_scene.clone(),
+            labels: labels_by_led(&stick.labels.lock().unwrap()),
+            dimming_schedule: stick.dimming_schedule(),
+        }
+    }
+
+    /// Overrides the photosensitivity safety cap returned by `BlinkStick::max_strobe_frequency`
+    ///
+    /// # Arguments
+    /// * `frequency` - The highest number of flashes per second the device is allowed to strobe at
+    #[cfg(feature = "effects")]
+    pub fn max_strobe_frequency(mut self, frequency: f32) -> BlinkStickBuilder {
+        self.max_strobe_frequency = frequency;
+        self
+    }
+
+    /// Bounds how long a single read or write may spend retrying, see `BlinkStick::set_io_timeout`
+    pub fn io_timeout(mut self, timeout: Duration) -> BlinkStickBuilder {
+        self.io_timeout = Some(timeout);
+        self
+    }
+
+    /// Waits at least `gap` between two feature reports, see `BlinkStick::set_min_report_gap`
+    pub fn min_report_gap(mut self, gap: Duration) -> BlinkStickBuilder {
+        self.min_report_gap = gap;
+        self
+    }
+
+    /// Overrides the detected number of leds, for devices or firmwares whose led count is detected wrongly.
+    /// Bounds checks and the size of every frame follow the override. A BlinkStick Pro drives more than 64 leds by
+    /// sending every 64 leds to its next channel, of which only the first can be read back. Its three channels hold
+    /// 192 leds, opening a device with more fails with `FeatureErrorType::ChannelCount`, as does opening a device
+    /// with a single channel, like a Flex, with more than 64 leds.
+    ///
+    /// # Example
+    /// Opens a BlinkStick Flex that only has 12 leds attached
+    /// ```
+    /// use blinkstick_rs::BlinkStick;
+    ///
+    /// let blinkstick = BlinkStick::builder().leds(12).open().unwrap();
+    /// assert_eq!(blinkstick.max_leds, 12);
+    /// ```
+    /// # Panics
+    /// When `count` is zero, the call to leds will panic.
+    pub fn leds(mut self, count: u8) -> BlinkStickBuilder {
+        assert!(count > 0, "A BlinkStick needs at least one led");
+        self.leds = Some(count);
+        self.channels = None;
+        self
+    }
+
+    /// Treats the strips on the channels of a BlinkStick Pro as one array of leds, `channel_leds[0]` leds on
+    /// channel 0 followed by `channel_leds[1]` on channel 1 and so on. Frames are split across the channels by
+    /// these counts, so effects never need to know which channel a led is on.
+    ///
+    /// Without it, every channel is filled up to 64 leds before the next is used.
+    ///
+    /// # Example
+    /// Opens a BlinkStick Pro driving strips of 30, 40 and 20 leds as a single array of 90 leds
+    /// ```
+    /// use blinkstick_rs::{BlinkStick, Color};
+    ///
+    /// let blinkstick = BlinkStick::builder().channels(&[30, 40, 20]).open().unwrap();
+    /// assert_eq!(blinkstick.max_leds, 90);
+    ///
+    /// // Led 30 is the first led of the strip on channel 1
+    /// blinkstick.set_led_color(30, Color {r: 0, g: 0, b: 50}).unwrap();
+    /// ```
+    /// Opening a device that drives fewer channels than `channel_leds` holds, like a BlinkStick Flex given two
+    /// channels, fails with `FeatureErrorType::ChannelCount`.
+    ///
+    /// # Panics
+    /// The call to `channels` will panic if there are no channels, a channel has no leds or more than 64, or the
+    /// channels hold more than 255 leds together.
+    pub fn channels(mut self, channel_leds: &[u8]) -> BlinkStickBuilder {
+        assert!(!channel_leds.is_empty(), "A BlinkStick needs at least one channel");
+        assert!(
+            channel_leds
+                .iter()
+                .all(|leds| *leds > 0 && *leds as usize <= CHANNEL_LEDS),
+            "A channel drives 1-{} leds",
+            CHANNEL_LEDS
+        );
+        let count: usize = channel_leds.iter().map(|leds| *leds as usize).sum();
+        assert!(
+            count <= u8::MAX as usize,
+            "A BlinkStick drives at most {} leds",
+            u8::MAX
+        );
+
+        self.leds = Some(count as u8);
+        self.channels = Some(channel_leds.to_vec());
+        self
+    }
+
+    /// Scales every color written to the device, on top of any dimming schedule or ambient light sensor
+    ///
+    /// # Arguments
+    /// * `brightness` - The factor (0.0 - 1.0) every channel is scaled by
+    pub fn brightness(mut self, brightness: f32) -> BlinkStickBuilder {
+        self.brightness = brightness.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Applies a gamma curve to every color written to the device, so evenly spaced colors look evenly spaced.
+    /// The default of 1.0 sends colors as they are.
+    pub fn gamma(mut self, gamma: f32) -> BlinkStickBuilder {
+        self.gamma = gamma;
+        self
+    }
+
+    /// Rearranges the channels of every color for a strip of `order`, see `BlinkStick::set_channel_order`
+    pub fn channel_order(mut self, order: ChannelOrder) -> BlinkStickBuilder {
+        self.channel_order = order;
+        self
+    }
+
+    /// Scales the channels of every color by `profile`, see `BlinkStick::set_color_profile`
+    pub fn color_profile(mut self, profile: ColorProfile) -> BlinkStickBuilder {
+        self.color_profile = profile;
+        self
+    }
+
+    /// Sends every color through the inverse of measured response curves, see `BlinkStick::set_response_curves`
+    pub fn response_curves(mut self, curves: ResponseCurves) -> BlinkStickBuilder {
+        self.response_curves = Some(curves);
+        self
+    }
+
+    /// Decides whether the leds are turned off when the `BlinkStick` is dropped, which they are by default
+    pub fn turn_off_on_drop(mut self, turn_off: bool) -> BlinkStickBuilder {
+        self.turn_off_on_drop = turn_off;
+        self
+    }
+
+    /// Treats the device as write-only, see `BlinkStick::set_write_only`
+    pub fn write_only(mut self, write_only: bool) -> BlinkStickBuilder {
+        self.write_only = write_only;
+        self
+    }
+
+    /// Sends the reports of every frame back to back, so frames that take several reports, like strips spread
+    /// over the channels of a BlinkStick Pro, are never mixed with a frame written from another thread
+    ///
+    /// Every report of a frame is encoded before the first is sent, and writes from other threads wait until the
+    /// last report of the frame was sent. This adds the time one frame takes to send, a few milliseconds per
+    /// report, to the latency of a write that arrives while another frame is being sent.
+    ///
+    /// The device still shows every channel as its report arrives, the firmware has no way to show several
+    /// reports at once. Serializing keeps that window to the few milliseconds between the reports of one frame.
+    pub fn serialize_frames(mut self, serialize: bool) -> BlinkStickBuilder {
+        self.serialize_frames = serialize;
+        self
+    }
+
+    /// Measures the latency of the device when it is opened, see `BlinkStick::calibrate_latency`. This adds a few
+    /// round trips, usually some milliseconds, to opening the device.
+    pub fn calibrate_latency(mut self, calibrate: bool) -> BlinkStickBuilder {
+        self.calibrate_latency = calibrate;
+        self
+    }
+
+    /// Applies the preset stored on the device when it is opened, see `BlinkStick::save_device_preset`: its
+    /// brightness replaces the configured one and its color is shown instead of turning every led off, unless a
+    /// `scene` is set. Devices without a preset, or whose preset can't be read, open as usual.
+    ///
+    /// # Example
+    /// Opens a BlinkStick with its own default look and plays its default theme
+    #[cfg_attr(feature = "animator", doc = "```")]
+    #[cfg_attr(not(feature = "animator"), doc = "```ignore")]
+    /// use blinkstick_rs::{Animator, BlinkStick};
+    ///
+    /// let blinkstick = BlinkStick::builder().apply_device_preset(true).open().unwrap();
+    /// let leds = blinkstick.max_leds as usize;
+    /// let theme = blinkstick.device_preset().and_then(|preset| preset.theme());
+    ///
+    /// let animator = Animator::new(blinkstick);
+    /// if let Some(theme) = theme {
+    ///     animator.play(theme.name, theme.frames(leds));
+    /// }
+    /// ```
+    pub fn apply_device_preset(mut self, apply: bool) -> BlinkStickBuilder {
+        self.apply_device_preset = apply;
+        self
+    }
+
+    /// Reads every frame back after writing it, writing it again up to `retries` times while it differs, see
+    /// `BlinkStick::set_verify_writes`
+    pub fn verify_writes(mut self, retries: u8) -> BlinkStickBuilder {
+        self.verify_writes = retries;
+        self
+    }
+
+    /// Decides whether the leds are reset when the device is opened, which they are by default. Tools that only
+    /// inspect a device keep the colors another program has set.
+    pub fn reset_on_open(mut self, reset: bool) -> BlinkStickBuilder {
+        self.reset_on_open = reset;
+        self
+    }
+
+    /// Shows `colors` when the device is opened instead of turning every led off, and stores them as the scene
+    /// `default`. A single color is shown on every led.
+    pub fn scene(mut self, colors: Vec<Color>) -> BlinkStickBuilder {
+        self.scene = Some(colors);
+        self
+    }
+
+    /// Labels the leds, starting with the zeroth, see `BlinkStick::label`. Empty labels and labels of leds the
+    /// device doesn't have are skipped.
+    pub fn labels(mut self, labels: Vec<String>) -> BlinkStickBuilder {
+        self.labels = labels;
+        self
+    }
+
+    /// Dims everything written to the device by time of day, see `BlinkStick::set_dimming_schedule`
+    pub fn dimming_schedule(mut self, schedule: DimmingSchedule) -> BlinkStickBuilder {
+        self.dimming_schedule = Some(schedule);
+        self
+    }
+
+    /// Applies the brightness, gamma, channel order, drop behavior, default scene, labels and dimming schedule of a
+    /// configuration file, see `Config`
+    pub fn config(mut self, config: &Config) -> BlinkStickBuilder {
+        self = self
+            .brightness(config.brightness)
+            .gamma(config.gamma)
+            .channel_order(config.channel_order)
+            .color_profile(config.white_balance)
+            .turn_off_on_drop(config.turn_off_on_drop)
+            .write_only(config.write_only)
+            .labels(config.labels.clone());
+        if let Some(scene) = &config.scene {
+            self = self.scene(scene.clone());
+        }
+        if let Some(schedule) = &config.dimming {
+            self = self.dimming_schedule(schedule.clone());
+        }
+
+        self
+    }
+
+    /// Opens communication with a `BlinkStick Device` using the configured settings
+    /// # Panics
+    /// When the hid api cannot be initialized, the call to open will panic.
+    pub fn open(self) -> Result<BlinkStick, FeatureError> {
+        if disabled() {
+            return self.open_disabled();
+        }
+        let api = hidapi::HidApi::new().expect("Could not create a hid api");
+        self.open_with_api(&api)
+    }
+
+    /// Opens communication with a `BlinkStick Device` through an existing hid api, for applications that already
+    /// use hidapi for other devices and cannot create a second `HidApi`
+    ///
+    /// # Example
+    /// ```
+    /// use blinkstick_rs::BlinkStick;
+    ///
+    /// let api = hidapi::HidApi::new().unwrap();
+    /// let blinkstick = BlinkStick::builder().open_with_api(&api).unwrap();
+    /// ```
+    pub fn open_with_api(self, api: &hidapi::HidApi) -> Result<BlinkStick, FeatureError> {
+        if disabled() {
+            return self.open_disabled();
+        }
+        let device = match std::env::var(SERIAL_VARIABLE) {
+            Ok(serial) if !serial.is_empty() => api.open_serial(VENDOR_ID, PRODUCT_ID, &serial),
+            _ => api.open(VENDOR_ID, PRODUCT_ID),
+        };
+        match device {
+            Ok(device) => self.from_hid_device(device),
+            Err(_) => Err(FeatureError {
+                kind: FeatureErrorType::Disconnected,
+            }),
+        }
+    }
+
+    /// Opens communication with the `BlinkStick Device` with the given serial number, such as `BS000001-3.0`
+    /// # Panics
+    /// When the hid api cannot be initialized, the call to open will panic.
+    pub fn open_by_serial(self, serial: &str) -> Result<BlinkStick, FeatureError> {
+        if disabled() {
+            return self.open_disabled();
+        }
+        let api = hidapi::HidApi::new().expect("Could not create a hid api");
+        match api.open_serial(VENDOR_ID, PRODUCT_ID, serial) {
+            Ok(device) => self.from_hid_device(device),
+            Err(_) => Err(FeatureError {
+                kind: FeatureErrorType::Disconnected,
+            }),
+        }
+    }
+
+    /// Opens communication with the `BlinkStick Device` at a platform specific hid path, such as
+    /// `/dev/hidraw3` on Linux or `\\?\hid#vid_20a0&pid_41e5#...` on Windows
+    ///
+    /// Paths stay unique when several devices report an empty or identical serial number. A path naming a device
+    /// other than a BlinkStick fails with `FeatureErrorType::NotABlinkStick`, see `from_hid_device`.
+    /// # Panics
+    /// When the hid api cannot be initialized, the call to open will panic.
+    pub fn open_by_path(self, path: &str) -> Result<BlinkStick, FeatureError> {
+        if disabled() {
+            return self.open_disabled();
+        }
+        let api = hidapi::HidApi::new().expect("Could not create a hid api");
+        // A path with an interior nul byte cannot name any device
+        let device = CString::new(path).ok().and_then(|path| api.open_path(&path).ok());
+        match device {
+            Some(device) => self.from_hid_device(device),
+            None => Err(FeatureError {
+                kind: FeatureErrorType::Disconnected,
+            }),
+        }
+    }
+
+    /// Takes over communication with a BlinkStick that was already opened through hidapi. A device other than a
+    /// BlinkStick fails with `FeatureErrorType::NotABlinkStick` before anything is sent to it.
+    pub fn from_hid_device(self, device: hidapi::HidDevice) -> Result<BlinkStick, FeatureError> {
+        let info = blinkstick_info(&device)?;
+        let variant = Variant::detect(
+            device.get_serial_number_string().ok().flatten().as_deref(),
+            info.release_number(),
+            info.product_string(),
+        );
+
+        self.open_transport(Transport::Hid(device), Some(info.path().to_owned()), variant)
+    }
+
+    /// Opens a stand-in for a BlinkStick that answers every report without any hardware, as `BLINKSTICK_DISABLE`
+    /// does
+    pub(crate) fn open_disabled(self) -> Result<BlinkStick, FeatureError> {
+        let leds = self.leds.unwrap_or(DISABLED_LEDS);
+        self.open_transport(Transport::Loopback(Loopback::new(leds)), None, Variant::Unknown)
+    }
+
+    fn open_transport(
+        self,
+        transport: Transport,
+        path: Option<CString>,
+        variant: Variant,
+    ) -> Result<BlinkStick, FeatureError> {
+        let (max_leds, report_length) = probe_leds(&transport, variant, self.leds, self.channels.as_deref())?;
+
+        let mut blinkstick = BlinkStick {
+            path: Mutex::new(path),
+            device: Mutex::new(transport),
+            variant,
+            led_override: self.leds,
+            channel_layout: self.channels,
+            reset_on_open: self.reset_on_open,
+            opening_scene: self.scene.clone(),
+            calibrate_latency: self.calibrate_latency,
+            max_leds,
+            report_length,
+            #[cfg(feature = "effects")]
+            max_strobe_frequency: self.max_strobe_frequency,
+            brightness: capped_brightness(
+                self.brightness,
+                std::env::var(MAX_BRIGHTNESS_VARIABLE).ok().as_deref(),
+                std::env::var(DISABLE_VARIABLE).ok().as_deref(),
+            ),
+            gamma: self.gamma,
+            quantizer: Mutex::new(Quantizer::default()),
+            channel_order: Mutex::new(self.channel_order),
+            color_profile: Mutex::new(self.color_profile),
+            response_curves: Mutex::new(self.response_curves),
+            turn_off_on_drop: AtomicBool::new(self.turn_off_on_drop),
+            scenes: Mutex::new(HashMap::new()),
+            labels: Mutex::new(
+                self.labels
+                    .into_iter()
+                    .enumerate()
+                    .filter(|(led, label)| *led < max_leds as usize && !label.is_empty())
+                    .map(|(led, label)| (led as u8, label))
+                    .collect(),
+            ),
+            masked_leds: Mutex::new(Vec::new()),
+            layout: Mutex::new(None),
+            dimming_schedule: Mutex::new(self.dimming_schedule),
+            ambient_light: Mutex::new(None),
+            io_timeout: Mutex::new(self.io_timeout),
+            report_latency: Mutex::new(None),
+            write_only: AtomicBool::new(self.write_only),
+            inverted: AtomicBool::new(false),
+            throttle: ReportThrottle::new(self.min_report_gap),
+            frame_lock: FrameLock::new(self.serialize_frames),
+            written_colors: Mutex::new(vec![(COLOR_OFF, COLOR_OFF); max_leds as usize]),
+            written_brightness: Mutex::new(None),
+            event_listeners: EventListeners::default(),
+            observers: Observers::default(),
+            diagnostics: Arc::new(Diagnostics::default()),
+            proxy: Mutex::new(None),
+            verify_retries: AtomicU8::new(self.verify_writes),
+            verification: VerificationCounters::default(),
+            device_preset: None,
+            #[cfg(feature = "metrics")]
+            metrics: Arc::new(crate::metrics::Counters::new()),
+        };
+        blinkstick.observe(blinkstick.diagnostics.clone());
+        #[cfg(feature = "metrics")]
+        blinkstick.observe(blinkstick.metrics.clone());
+
+        blinkstick.detect_inversion();
+
+        if self.apply_device_preset {
+            // Write-only devices and devices whose info block can't be read open without a preset
+            blinkstick.device_preset = blinkstick.read_device_preset().ok().flatten();
+        }
+        if let Some(preset) = blinkstick.device_preset {
+            blinkstick.brightness = capped_brightness(
+                preset.brightness,
+                std::env::var(MAX_BRIGHTNESS_VARIABLE).ok().as_deref(),
+                std::env::var(DISABLE_VARIABLE).ok().as_deref(),
+            );
+        }
+
+        // If the light is already on, we want to reset it before giving the user a way to interact with it.
+        let scene = self
+            .scene
+            .or_else(|| blinkstick.device_preset.map(|preset| vec![preset.color]));
+        match scene {
+            _ if !self.reset_on_open => (),
+            Some(scene) => {
+                let mut frame = match scene.as_slice() {
+                    [color] => vec![*color; max_leds as usize],
+                    _ => scene,
+                };
+                frame.resize(max_leds as usize, COLOR_OFF);
+                blinkstick.set_all_leds_colors(&frame)?;
+                blinkstick.save_scene("default", frame);
+            }
+            None => blinkstick.set_all_leds_color(COLOR_OFF)?,
+        }
+        if self.calibrate_latency {
+            blinkstick.calibrate_latency()?;
+        }
+
+        Ok(blinkstick)
+    }
+}
+
+/// The information hidapi has about `device`, failing unless its vendor and product id are those of a BlinkStick
+fn blinkstick_info(device: &hidapi::HidDevice) -> Result<hidapi::DeviceInfo, FeatureError> {
+    let info = device
+        .get_device_info()
+        .map_err(|e| FeatureError::from_hid_error(FeatureErrorType::Get, &e))?;
+    if (info.vendor_id(), info.product_id()) == (VENDOR_ID, PRODUCT_ID) {
+        Ok(info)
+    } else {
+        Err(FeatureError {
+            kind: FeatureErrorType::NotABlinkStick {
+                vendor_id: info.vendor_id(),
+                product_id: info.product_id(),
+            },
+        })
+    }
+}
+
+/// The labels of a BlinkStick as `BlinkStickBuilder::labels` takes them, an empty label for every led without one
+fn labels_by_led(labels: &HashMap<u8, String>) -> Vec<String> {
+    let mut by_led = vec![String::new(); labels.keys().max().map_or(0, |led| *led as usize + 1)];
+    for (led, label) in labels {
+        by_led[*led as usize] = label.clone();
+    }
+    by_led
+}
+
+/// Returns `true` if `BLINKSTICK_DISABLE` asks for a stand-in instead of a device
+fn disabled() -> bool {
+    is_disabled(std::env::var(DISABLE_VARIABLE).ok().as_deref())
+}
+
+fn is_disabled(disable: Option<&str>) -> bool {
+    disable.is_some_and(|disable| !matches!(disable.trim(), "" | "0" | "false"))
+}
+
+/// Caps `brightness` by the value of `BLINKSTICK_MAX_BRIGHTNESS`, and turns it down to nothing when
+/// `BLINKSTICK_DISABLE` is set
+fn capped_brightness(brightness: f32, max_brightness: Option<&str>, disable: Option<&str>) -> f32 {
+    if is_disabled(disable) {
+        return 0.0;
+    }
+
+    match max_brightness.and_then(|max| max.trim().parse::<f32>().ok()) {
+        Some(max) if !max.is_nan() => brightness.min(max.clamp(0.0, 1.0)),
+        _ => brightness,
+    }
+}
+
+/// Reads the leds report of a device to determine its number of leds and the length of its leds report
+///
+/// The size of the leds report is cross-checked with the variant. The BlinkStick Flex has 32 leds with 3 channels,
+/// which is the maximum of any device that fits the report: 32 * 3 + 2 = 98 bytes
+pub(crate) fn probe_leds(
+    device: &Transport,
+    variant: Variant,
+    led_override: Option<u8>,
+    channel_layout: Option<&[u8]>,
+) -> Result<(u8, usize), FeatureError> {
+    if let Some(layout) = channel_layout {
+        check_channel_layout(variant, layout)?;
+    }
+
+    let mut buf = vec![0u8; REPORT_ARRAY_BYTES.max(LEDS_REPORT_HEADER + led_override.unwrap_or(0) as usize * 3)];
+    buf[0] = LEDS_REPORT_ID;
+    let bytes_read = device
+        .get_feature_report(&mut buf)
+        .map_err(|e| FeatureError::from_hid_error(FeatureErrorType::Get, &e))?;
+
+    let report_leds = parse_led_count(&buf[..bytes_read.min(buf.len())])?;
+    let max_leds = led_override.unwrap_or_else(|| variant.led_count(report_leds));
+    // Reports keep the size the device sent, even when it holds more leds than the device has. Frames of more
+    // leds than a channel holds are sent one channel at a time.
+    let channel_leds = match channel_layout {
+        Some(layout) => layout.iter().copied().max().unwrap_or(0),
+        None => {
+            check_led_count(max_leds as usize)?;
+            let layout = default_channel_leds(max_leds as usize);
+            check_channel_layout(variant, &layout)?;
+            layout[0]
+        }
+    };
+    let report_length = LEDS_REPORT_HEADER + report_leds.max(channel_leds) as usize * 3;
+
+    Ok((max_leds, report_length))
+}
+
+/// Fails with `FeatureErrorType::ChannelCount` when a channel layout has more channels than the variant drives.
+/// Devices of an unknown variant are trusted with any layout.
+fn check_channel_layout(variant: Variant, layout: &[u8]) -> Result<(), FeatureError> {
+    if variant != Variant::Unknown && layout.len() > variant.channel_count() as usize {
+        return Err(FeatureError {
+            kind: FeatureErrorType::ChannelCount {
+                channels: layout.len(),
+                max_channels: variant.channel_count(),
+            },
+        });
+    }
+    Ok(())
+}
+
+/// Fails with `FeatureErrorType::ChannelCount` when `led_count` leds don't fit on the channels a frame is split
+/// across, `CHANNEL_LEDS` leds on each of `CHANNEL_COUNT` channels
+fn check_led_count(led_count: usize) -> Result<(), FeatureError> {
+    if led_count > CHANNEL_COUNT * CHANNEL_LEDS {
+        return Err(FeatureError {
+            kind: FeatureErrorType::ChannelCount {
+                channels: led_count.div_ceil(CHANNEL_LEDS),
+                max_channels: CHANNEL_COUNT as u8,
+            },
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn channel_layouts_are_bound_by_the_variant() {
+        assert!(check_channel_layout(Variant::Pro, &[30, 40, 20]).is_ok());
+        assert!(check_channel_layout(Variant::Unknown, &[30, 40, 20, 10]).is_ok());
+        assert_eq!(
+            check_channel_layout(Variant::Pro, &[30, 40, 20, 10]).unwrap_err().kind,
+            FeatureErrorType::ChannelCount {
+                channels: 4,
+                max_channels: 3
+            }
+        );
+        assert!(check_channel_layout(Variant::Flex, &[16, 16]).is_err());
+        assert!(check_channel_layout(Variant::Flex, &[32]).is_ok());
+        // Overriding the led count spreads a single channel device across channels it doesn't have
+        assert!(check_channel_layout(Variant::Flex, &default_channel_leds(100)).is_err());
+        assert!(check_channel_layout(Variant::Pro, &default_channel_leds(150)).is_ok());
+    }
+
+    #[test]
+    fn leds_past_the_last_channel_are_rejected() {
+        assert!(check_led_count(192).is_ok());
+        assert_eq!(
+            check_led_count(200).unwrap_err().kind,
+            FeatureErrorType::ChannelCount {
+                channels: 4,
+                max_channels: 3
+            }
+        );
+    }
+
+    #[test]
+    fn the_environment_caps_the_brightness() {
+        assert_eq!(capped_brightness(0.8, None, None), 0.8);
+        assert_eq!(capped_brightness(0.8, Some("0.5"), None), 0.5);
+        assert_eq!(capped_brightness(0.3, Some("0.5"), Some("false")), 0.3);
+        assert_eq!(capped_brightness(0.8, Some("bright"), Some("0")), 0.8);
+        assert_eq!(capped_brightness(0.8, None, Some("1")), 0.0);
+        assert!(is_disabled(Some("yes")));
+        assert!(!is_disabled(Some(" 0 ")));
+    }
+
+    #[test]
+    fn disabled_blinksticks_work_without_a_device() {
+        let blinkstick = BlinkStickBuilder::new()
+            .channels(&[30, 40, 20])
+            .open_disabled()
+            .unwrap();
+        assert_eq!(blinkstick.max_leds, 90);
+        assert_eq!(blinkstick.serial_number(), None);
+
+        let frame: Vec<Color> = (0..90).map(|led| Color { r: led, g: 1, b: 2 }).collect();
+        blinkstick.set_all_leds_colors(&frame).unwrap();
+        blinkstick.set_led_color(35, Color::RED).unwrap();
+        assert_eq!(blinkstick.get_led_color(35).unwrap(), Color::RED);
+        assert_eq!(blinkstick.get_all_led_colors().unwrap()[..35], frame[..35]);
+        assert!(blinkstick.reset().is_ok());
+
+        let blinkstick = BlinkStickBuilder::new().open_disabled().unwrap();
+        assert_eq!(
+            blinkstick.get_all_led_colors().unwrap(),
+            vec![COLOR_OFF; DISABLED_LEDS as usize]
+        );
+    }
+
+    #[test]
+    fn reopening_keeps_every_setting() {
+        let labels = vec!["build".to_string(), String::new(), "deploy".to_string()];
+        let stick = BlinkStickBuilder::new()
+            .leds(4)
+            .min_report_gap(Duration::from_millis(3))
+            .serialize_frames(true)
+            .reset_on_open(false)
+            .scene(vec![Color::RED])
+            .labels(labels.clone())
+            .open_disabled()
+            .unwrap();
+
+        let builder = BlinkStickBuilder::reopening(&stick);
+        assert_eq!(builder.leds, Some(4));
+        assert_eq!(builder.min_report_gap, Duration::from_millis(3));
+        assert!(builder.serialize_frames);
+        assert!(!builder.reset_on_open);
+        assert!(!builder.calibrate_latency);
+        assert_eq!(builder.scene, Some(vec![Color::RED]));
+        assert_eq!(builder.labels, labels);
+    }
+}
@@ -0,0 +1,245 @@
+use crate::wire::{encode_info_block_report, parse_info_block_report, INFO_BLOCK_2_REPORT_ID, INFO_BLOCK_LENGTH};
+use crate::{BlinkStick, Color, FeatureError, StateEvent};
+use std::fmt::{Display, Formatter};
+
+/// The amount a channel scale is lowered by for every tint reported during `BlinkStick::calibrate_white`
+const CALIBRATION_STEP: f32 = 0.05;
+
+/// The number of tints `BlinkStick::calibrate_white` asks about at most
+const MAX_CALIBRATION_STEPS: usize = 60;
+
+/// Marks a color profile stored in the second info block
+const PROFILE_MAGIC: &[u8; 2] = b"WB";
+
+/// Where a color profile is stored in the second info block: its last bytes, leaving the rest to applications
+pub(crate) const PROFILE_OFFSET: usize = INFO_BLOCK_LENGTH - PROFILE_MAGIC.len() - 3;
+
+/// The factor every channel is scaled by before it is sent, so white looks white on leds whose channels differ in
+/// brightness
+///
+/// Profiles are found with `BlinkStick::calibrate_white`, and kept with the device by
+/// `BlinkStick::save_color_profile` or in the configuration as `white_balance = [1.0, 0.9, 0.8]`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ColorProfile {
+    /// The factor (0.0 - 1.0) the red channel is scaled by
+    pub red: f32,
+    /// The factor (0.0 - 1.0) the green channel is scaled by
+    pub green: f32,
+    /// The factor (0.0 - 1.0) the blue channel is scaled by
+    pub blue: f32,
+}
+
+impl Default for ColorProfile {
+    fn default() -> Self {
+        ColorProfile {
+            red: 1.0,
+            green: 1.0,
+            blue: 1.0,
+        }
+    }
+}
+
+impl ColorProfile {
+    /// Creates a profile scaling the channels by the given factors (0.0 - 1.0)
+    pub fn new(red: f32, green: f32, blue: f32) -> ColorProfile {
+        ColorProfile {
+            red: red.clamp(0.0, 1.0),
+            green: green.clamp(0.0, 1.0),
+            blue: blue.clamp(0.0, 1.0),
+        }
+    }
+
+    /// Scales every channel of `color` by its factor
+    pub fn apply(&self, color: Color) -> Color {
+        let scale = |channel: u8, factor: f32| (channel as f32 * factor).round() as u8;
+        Color::new(
+            scale(color.r, self.red),
+            scale(color.g, self.green),
+            scale(color.b, self.blue),
+        )
+    }
+
+    /// Scales exact channel values like `apply`, before they are rounded
+    pub(crate) fn apply_exact(&self, channels: [f32; 3]) -> [f32; 3] {
+        [
+            channels[0] * self.red,
+            channels[1] * self.green,
+            channels[2] * self.blue,
+        ]
+    }
+
+    /// Lowers the channels of a tint that white shows too much of, scaling the result so its strongest channel is
+    /// back at 1.0
+    fn correct(&self, tint: Color) -> ColorProfile {
+        let lower = |factor: f32, channel: u8| factor - CALIBRATION_STEP * channel as f32 / 255.0;
+        let corrected = ColorProfile::new(
+            lower(self.red, tint.r),
+            lower(self.green, tint.g),
+            lower(self.blue, tint.b),
+        );
+
+        let strongest = corrected.red.max(corrected.green).max(corrected.blue);
+        if strongest <= 0.0 {
+            return *self;
+        }
+        ColorProfile::new(
+            corrected.red / strongest,
+            corrected.green / strongest,
+            corrected.blue / strongest,
+        )
+    }
+
+    /// The bytes a profile is stored as in an info block
+    fn to_bytes(self) -> [u8; 5] {
+        let byte = |factor: f32| (factor * 255.0).round() as u8;
+        [
+            PROFILE_MAGIC[0],
+            PROFILE_MAGIC[1],
+            byte(self.red),
+            byte(self.green),
+            byte(self.blue),
+        ]
+    }
+
+    /// Reads a profile from the bytes written by `to_bytes`, `None` if they hold none
+    fn from_bytes(bytes: &[u8]) -> Option<ColorProfile> {
+        match bytes {
+            [m1, m2, red, green, blue, ..] if [*m1, *m2] == *PROFILE_MAGIC => Some(ColorProfile::new(
+                *red as f32 / 255.0,
+                *green as f32 / 255.0,
+                *blue as f32 / 255.0,
+            )),
+            _ => None,
+        }
+    }
+}
+
+impl Display for ColorProfile {
+    /// Writes the profile as the value of `white_balance` in the configuration
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[{:.2}, {:.2}, {:.2}]", self.red, self.green, self.blue)
+    }
+}
+
+impl BlinkStick {
+    /// Scales the channels of every color written from now on by `profile`
+    pub fn set_color_profile(&self, profile: ColorProfile) {
+        *self.color_profile.lock().unwrap() = profile;
+        self.record(|| StateEvent::ColorProfileChanged(profile));
+    }
+
+    /// The factors the channels are scaled by, 1.0 each unless set
+    pub fn color_profile(&self) -> ColorProfile {
+        *self.color_profile.lock().unwrap()
+    }
+
+    /// Shows `target` on every led and lowers the channel scales while `observe` reports a tint, until it confirms
+    /// that the leds show white. Stores the profile found with `set_color_profile` and returns it.
+    ///
+    /// `observe` is shown the profile tried and returns the color the white is tinted towards, like
+    /// `Color::BLUE` for a cold white or `Color::new(255, 255, 0)` for a yellowish one, or `None` once it looks
+    /// white. Every tint lowers its channels by a small step. The leds show their previous colors again afterwards.
+    ///
+    /// # Example
+    /// Asks for the tint of the leds and stores the profile in the configuration
+    /// ```
+    /// use blinkstick_rs::{BlinkStick, Color};
+    ///
+    /// let blinkstick = BlinkStick::new().unwrap();
+    /// let profile = blinkstick.calibrate_white(Color::new(128, 128, 128), |_| {
+    ///     println!("Which color is the white tinted towards? (red, green, blue or nothing when it looks white)");
+    ///     let mut answer = String::new();
+    ///     std::io::stdin().read_line(&mut answer).ok()?;
+    ///     match answer.trim() {
+    ///         "red" => Some(Color::RED),
+    ///         "green" => Some(Color::GREEN),
+    ///         "blue" => Some(Color::BLUE),
+    ///         _ => None,
+    ///     }
+    /// }).unwrap();
+    ///
+    /// println!("Add white_balance = {} to config.toml", profile);
+    /// ```
+    pub fn calibrate_white<F>(&self, target: Color, mut observe: F) -> Result<ColorProfile, FeatureError>
+    where
+        F: FnMut(&ColorProfile) -> Option<Color>,
+    {
+        let previous_frame: Vec<Color> = self
+            .written_colors
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(color, _)| *color)
+            .collect();
+        let previous_profile = self.color_profile();
+
+        let mut profile = previous_profile;
+        for _ in 0..MAX_CALIBRATION_STEPS {
+            self.set_color_profile(profile);
+            if let Err(e) = self.set_all_leds_color(target) {
+                self.set_color_profile(previous_profile);
+                return Err(e);
+            }
+
+            match observe(&profile) {
+                Some(tint) => profile = profile.correct(tint),
+                None => break,
+            }
+        }
+
+        self.set_color_profile(profile);
+        self.set_all_leds_colors(&previous_frame)?;
+        Ok(profile)
+    }
+
+    /// Stores the color profile in the last bytes of the device's second info block, so it travels with the
+    /// device to other computers. The rest of the info block is kept.
+    pub fn save_color_profile(&self) -> Result<(), FeatureError> {
+        let mut data = parse_info_block_report(
+            INFO_BLOCK_2_REPORT_ID,
+            &self.get_feature_from_blinkstick(INFO_BLOCK_2_REPORT_ID)?,
+        )?;
+        data[PROFILE_OFFSET..].copy_from_slice(&self.color_profile().to_bytes());
+
+        self.send_feature_to_blinkstick(&encode_info_block_report(INFO_BLOCK_2_REPORT_ID, &data))
+    }
+
+    /// Reads the color profile stored by `save_color_profile` and applies it, returning `None` without changing
+    /// anything if the device holds none
+    pub fn load_color_profile(&self) -> Result<Option<ColorProfile>, FeatureError> {
+        let data = parse_info_block_report(
+            INFO_BLOCK_2_REPORT_ID,
+            &self.get_feature_from_blinkstick(INFO_BLOCK_2_REPORT_ID)?,
+        )?;
+
+        let profile = ColorProfile::from_bytes(&data[PROFILE_OFFSET..]);
+        if let Some(profile) = profile {
+            self.set_color_profile(profile);
+        }
+        Ok(profile)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tints_lower_their_channels() {
+        let profile = ColorProfile::default().correct(Color::BLUE).correct(Color::BLUE);
+        assert_eq!(profile.apply(Color::WHITE), Color::new(255, 255, 230));
+
+        // Lowering every channel keeps the brightest at full scale
+        assert_eq!(ColorProfile::default().correct(Color::WHITE), ColorProfile::default());
+    }
+
+    #[test]
+    fn profiles_survive_the_info_block() {
+        let profile = ColorProfile::new(1.0, 0.8, 0.6);
+        let mut data = [0; INFO_BLOCK_LENGTH];
+        data[PROFILE_OFFSET..].copy_from_slice(&profile.to_bytes());
+
+        assert_eq!(ColorProfile::from_bytes(&data[PROFILE_OFFSET..]), Some(profile));
+        assert_eq!(ColorProfile::from_bytes(&[0; 5]), None);
+    }
+}
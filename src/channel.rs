@@ -0,0 +1,202 @@
+use crate::wire::{parse_leds_report, ReportError, LEDS_REPORT_HEADER};
+use crate::{BlinkStick, Color, FeatureError, FeatureErrorType};
+use std::ops::Range;
+use std::sync::{Mutex, MutexGuard};
+
+/// Keeps the reports of a frame together when frames are serialized, see `BlinkStickBuilder::serialize_frames`
+pub(crate) struct FrameLock(Option<Mutex<()>>);
+
+impl FrameLock {
+    pub(crate) fn new(serialize: bool) -> FrameLock {
+        FrameLock(if serialize { Some(Mutex::new(())) } else { None })
+    }
+
+    /// Returns `true` if the reports of every frame are sent back to back
+    pub(crate) fn is_serialized(&self) -> bool {
+        self.0.is_some()
+    }
+
+    /// Holds off the reports of other frames until the guard is dropped, if frames are serialized
+    pub(crate) fn hold(&self) -> Option<MutexGuard<'_, ()>> {
+        self.0.as_ref().map(|lock| lock.lock().unwrap())
+    }
+}
+
+impl BlinkStick {
+    /// Gets the colors of the leds on one channel of a BlinkStick Pro, channel 0 for every other device
+    ///
+    /// The firmware reads back the channel written last, which it names in the leds report. The leds of the other
+    /// channels report the color last written to them, so `get_led_color` and `get_all_led_colors` agree with
+    /// this whichever channel a led is on.
+    ///
+    /// # Panics
+    /// The call to `get_channel_frame` will panic if the device has no such channel, see `channel_leds`.
+    ///
+    /// # Example
+    /// Reads back the leds on the third channel of a Pro driving 150 leds
+    /// ```
+    /// use blinkstick_rs::{BlinkStick, Color};
+    ///
+    /// let blinkstick = BlinkStick::builder().leds(150).open().unwrap();
+    /// blinkstick.set_all_leds_color(Color {r: 0, g: 0, b: 50}).unwrap();
+    ///
+    /// let frame = blinkstick.get_channel_frame(2).unwrap();
+    /// assert_eq!(frame, vec![Color {r: 0, g: 0, b: 50}; 22]);
+    /// ```
+    pub fn get_channel_frame(&self, channel: u8) -> Result<Vec<Color>, FeatureError> {
+        let channel_leds = self.channel_leds();
+        if channel as usize >= channel_leds.len() {
+            panic!(
+                "BlinkStick device does not have channel {}. Valid channels are 0-{} (zero-indexed)",
+                channel,
+                channel_leds.len() - 1
+            );
+        }
+
+        Ok(self.get_all_led_colors()?[channel_range(&channel_leds, channel as usize)].to_vec())
+    }
+
+    /// The channel a led is on and its index on the channel, failing with `FeatureErrorType::LedOutOfBounds` for
+    /// a led past the last channel
+    pub(crate) fn channel_position(&self, led: u8) -> Result<(u8, u8), FeatureError> {
+        let channel_leds = self.channel_leds();
+        channel_position(&channel_leds, led).ok_or(FeatureError {
+            kind: FeatureErrorType::LedOutOfBounds {
+                led: led as usize,
+                max_leds: channel_leds.iter().map(|leds| *leds as usize).sum(),
+            },
+        })
+    }
+}
+
+/// The channel a led is on and its index on the channel, `None` if the channels end before the led
+fn channel_position(channel_leds: &[u8], led: u8) -> Option<(u8, u8)> {
+    let mut first = 0;
+    for (channel, leds) in channel_leds.iter().enumerate() {
+        if (led as usize) < first + *leds as usize {
+            return Some((channel as u8, (led as usize - first) as u8));
+        }
+        first += *leds as usize;
+    }
+
+    None
+}
+
+/// The leds on a channel, empty for a channel that doesn't exist
+fn channel_range(channel_leds: &[u8], channel: usize) -> Range<usize> {
+    let first: usize = channel_leds.iter().take(channel).map(|leds| *leds as usize).sum();
+    first..first + channel_leds.get(channel).map_or(0, |leds| *leds as usize)
+}
+
+/// Merges a leds report read from a device with the colors last sent to the leds it doesn't cover, into the colors
+/// every led shows. The leds are spread across channels as `channel_leds` says, see `BlinkStick::channel_leds`.
+pub(crate) fn read_back_outputs(
+    report: &[u8],
+    sent: &[Color],
+    channel_leds: &[u8],
+) -> Result<Vec<Color>, FeatureError> {
+    let device_colors = parse_leds_report(report)?;
+    // The header names the channel the report holds, devices that never split their frames report the first
+    let channel = match report.get(LEDS_REPORT_HEADER - 1) {
+        Some(&channel) if (channel as usize) < channel_leds.len() => channel as usize,
+        _ => 0,
+    };
+    let leds = channel_range(channel_leds, channel);
+    if device_colors.len() < leds.len() {
+        return Err(ReportError::MissingLeds {
+            expected: leds.len(),
+            actual: device_colors.len(),
+        }
+        .into());
+    }
+
+    let mut outputs = sent.to_vec();
+    outputs.resize(channel_leds.iter().map(|leds| *leds as usize).sum(), Color::default());
+    let read = leds.len();
+    outputs[leds].copy_from_slice(&device_colors[..read]);
+    Ok(outputs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wire::{encode_channel_reports, encode_leds_reports};
+
+    #[test]
+    fn serialized_frames_send_their_reports_together() {
+        let stick = std::sync::Arc::new(
+            crate::BlinkStickBuilder::new()
+                .channels(&[30, 40, 20])
+                .min_report_gap(std::time::Duration::from_millis(1))
+                .serialize_frames(true)
+                .open_disabled()
+                .unwrap(),
+        );
+        let (proxy, reports) = crate::ProxyTransport::channel();
+        stick.set_proxy(Some(proxy));
+
+        let writers: Vec<_> = (1..=4u8)
+            .map(|frame| {
+                let stick = stick.clone();
+                std::thread::spawn(move || {
+                    for _ in 0..5 {
+                        stick.set_all_leds_color(Color { r: frame, g: 0, b: 0 }).unwrap();
+                    }
+                })
+            })
+            .collect();
+        writers.into_iter().for_each(|writer| writer.join().unwrap());
+
+        // Every report of a frame carries the frame's color, so a frame mixed with another shows two colors
+        let sent: Vec<(u8, u8)> = reports
+            .try_iter()
+            .map(|report| (report.bytes[1], report.bytes[3]))
+            .collect();
+        assert_eq!(sent.len(), 4 * 5 * 3);
+        for reports in sent.chunks(3) {
+            assert_eq!(
+                reports.iter().map(|(channel, _)| *channel).collect::<Vec<_>>(),
+                vec![0, 1, 2]
+            );
+            assert!(reports.iter().all(|(_, frame)| *frame == reports[0].1), "{:?}", sent);
+        }
+        assert!(FrameLock::new(false).hold().is_none());
+    }
+
+    #[test]
+    fn leds_are_found_on_their_channel() {
+        assert_eq!(channel_position(&[30, 40, 20], 0), Some((0, 0)));
+        assert_eq!(channel_position(&[30, 40, 20], 30), Some((1, 0)));
+        assert_eq!(channel_position(&[30, 40, 20], 89), Some((2, 19)));
+        assert_eq!(channel_position(&[30, 40, 20], 90), None);
+        assert_eq!(channel_position(&[64, 64, 64], 195), None);
+    }
+
+    #[test]
+    fn reports_are_read_back_into_their_channel() {
+        let blue = Color { r: 0, g: 0, b: 9 };
+        let red = Color { r: 9, g: 0, b: 0 };
+        let sent = vec![blue; 150];
+        let mut shown = sent.clone();
+        shown[130] = red;
+
+        // The device names the last channel it was written
+        let report = encode_leds_reports(&shown).pop().unwrap();
+        let outputs = read_back_outputs(&report, &sent, &[64, 64, 22]).unwrap();
+
+        assert_eq!(outputs[130], red);
+        assert_eq!(outputs[..128], sent[..128]);
+
+        // Strips of uneven length split the frame by their own lengths
+        let reports = encode_channel_reports(&shown[..90], &[30, 40, 20]);
+        let outputs = read_back_outputs(&reports[1], &sent[..90], &[30, 40, 20]).unwrap();
+        assert_eq!(outputs, shown[..90]);
+        assert_eq!(
+            read_back_outputs(&report[..10], &sent, &[64, 64, 22]).unwrap_err().kind,
+            FeatureErrorType::MalformedReport(ReportError::MissingLeds {
+                expected: 22,
+                actual: 2
+            })
+        );
+    }
+}
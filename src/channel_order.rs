@@ -0,0 +1,243 @@
+use crate::{BlinkStick, Color, FeatureError, StateEvent};
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+/// The colors a led strip shows for the red, green and blue channel the device sends, in that order
+///
+/// Most strips wired to a BlinkStick Pro are `Rgb` or `Grb`. Colors are rearranged before they are sent, so
+/// `set_led_color` shows the same color on every strip, see `BlinkStick::set_channel_order`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum ChannelOrder {
+    /// The strip shows every channel as sent
+    #[default]
+    Rgb,
+    /// The strip swaps green and blue
+    Rbg,
+    /// The strip swaps red and green, common for WS2812 strips
+    Grb,
+    /// The strip shows the red channel in green, green in blue and blue in red
+    Gbr,
+    /// The strip shows the red channel in blue, green in red and blue in green
+    Brg,
+    /// The strip swaps red and blue
+    Bgr,
+}
+
+/// A primary color, as one of the letters of a channel order
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Primary {
+    Red,
+    Green,
+    Blue,
+}
+
+impl Primary {
+    fn of(self, color: Color) -> u8 {
+        match self {
+            Primary::Red => color.r,
+            Primary::Green => color.g,
+            Primary::Blue => color.b,
+        }
+    }
+
+    /// The position of the primary in red, green and blue channels
+    fn index(self) -> usize {
+        match self {
+            Primary::Red => 0,
+            Primary::Green => 1,
+            Primary::Blue => 2,
+        }
+    }
+
+    /// The channel of `color` that is brighter than both others
+    fn dominant(color: Color) -> Option<Primary> {
+        match (color.r, color.g, color.b) {
+            (r, g, b) if r > g && r > b => Some(Primary::Red),
+            (r, g, b) if g > r && g > b => Some(Primary::Green),
+            (r, g, b) if b > r && b > g => Some(Primary::Blue),
+            _ => None,
+        }
+    }
+}
+
+const ORDERS: [(ChannelOrder, [Primary; 3], &str); 6] = [
+    (ChannelOrder::Rgb, [Primary::Red, Primary::Green, Primary::Blue], "rgb"),
+    (ChannelOrder::Rbg, [Primary::Red, Primary::Blue, Primary::Green], "rbg"),
+    (ChannelOrder::Grb, [Primary::Green, Primary::Red, Primary::Blue], "grb"),
+    (ChannelOrder::Gbr, [Primary::Green, Primary::Blue, Primary::Red], "gbr"),
+    (ChannelOrder::Brg, [Primary::Blue, Primary::Red, Primary::Green], "brg"),
+    (ChannelOrder::Bgr, [Primary::Blue, Primary::Green, Primary::Red], "bgr"),
+];
+
+impl ChannelOrder {
+    fn primaries(self) -> [Primary; 3] {
+        ORDERS.iter().find(|(order, _, _)| *order == self).unwrap().1
+    }
+
+    /// Rearranges `color` into the channels the device sends, so a strip of this order shows `color`
+    ///
+    /// # Example
+    /// ```
+    /// use blinkstick_rs::{ChannelOrder, Color};
+    ///
+    /// assert_eq!(ChannelOrder::Grb.apply(Color::new(1, 2, 3)), Color::new(2, 1, 3));
+    /// ```
+    pub fn apply(self, color: Color) -> Color {
+        let [r, g, b] = self.primaries();
+        Color::new(r.of(color), g.of(color), b.of(color))
+    }
+
+    /// Rearranges exact channel values like `apply`, before they are rounded
+    pub(crate) fn apply_exact(self, channels: [f32; 3]) -> [f32; 3] {
+        self.primaries().map(|primary| channels[primary.index()])
+    }
+
+    /// Infers the order of a strip from the colors it showed for a pure red, green and blue sent in that order,
+    /// `None` if the colors seen don't name every primary once
+    pub fn from_observations(red: Color, green: Color, blue: Color) -> Option<ChannelOrder> {
+        let seen = [
+            Primary::dominant(red)?,
+            Primary::dominant(green)?,
+            Primary::dominant(blue)?,
+        ];
+
+        ORDERS
+            .iter()
+            .find(|(_, primaries, _)| *primaries == seen)
+            .map(|(order, _, _)| *order)
+    }
+}
+
+impl Display for ChannelOrder {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let name = ORDERS.iter().find(|(order, _, _)| order == self).unwrap().2;
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for ChannelOrder {
+    type Err = String;
+
+    /// Parses an order like `grb`, in any case
+    fn from_str(name: &str) -> Result<ChannelOrder, String> {
+        ORDERS
+            .iter()
+            .find(|(_, _, order_name)| order_name.eq_ignore_ascii_case(name))
+            .map(|(order, _, _)| *order)
+            .ok_or_else(|| format!("unknown channel order {}, expected one like rgb or grb", name))
+    }
+}
+
+impl BlinkStick {
+    /// Rearranges the channels of every color written from now on for a strip of `order`
+    pub fn set_channel_order(&self, order: ChannelOrder) {
+        *self.channel_order.lock().unwrap() = order;
+        self.record(|| StateEvent::ChannelOrderChanged(order));
+    }
+
+    /// The channel order colors are rearranged into, `ChannelOrder::Rgb` unless set
+    pub fn channel_order(&self) -> ChannelOrder {
+        *self.channel_order.lock().unwrap()
+    }
+
+    /// Shows a pure red, green and blue on `led` in sequence and infers the channel order of the strip from the
+    /// colors `observe` reports for each, storing it with `set_channel_order`
+    ///
+    /// The device only reports the colors it was sent, so what the strip actually shows has to come from
+    /// `observe`: a color sensor, a camera or a person asked which color they see. Returns `None` and keeps the
+    /// previous order if an observation is missing or the observations are inconclusive. The led shows its
+    /// previous color again afterwards.
+    ///
+    /// # Panics
+    /// The call to `detect_channel_order` will panic if the specified `led` is out of bounds for the connected BlinkStick device.
+    ///
+    /// # Example
+    /// Asks which color the zeroth led shows and stores the order in the configuration
+    /// ```
+    /// use blinkstick_rs::{BlinkStick, Color};
+    ///
+    /// let blinkstick = BlinkStick::new().unwrap();
+    /// let order = blinkstick.detect_channel_order(0, |_| {
+    ///     println!("Which color does the led show? (red, green or blue)");
+    ///     let mut answer = String::new();
+    ///     std::io::stdin().read_line(&mut answer).ok()?;
+    ///     match answer.trim() {
+    ///         "red" => Some(Color::RED),
+    ///         "green" => Some(Color::GREEN),
+    ///         "blue" => Some(Color::BLUE),
+    ///         _ => None,
+    ///     }
+    /// }).unwrap();
+    ///
+    /// if let Some(order) = order {
+    ///     println!("Add channel_order = \"{}\" to config.toml", order);
+    /// }
+    /// ```
+    pub fn detect_channel_order<F>(&self, led: u8, mut observe: F) -> Result<Option<ChannelOrder>, FeatureError>
+    where
+        F: FnMut(Color) -> Option<Color>,
+    {
+        self.assert_leds(&[led]);
+        let previous_color = self.written_colors.lock().unwrap()[led as usize].0;
+        let previous_order = self.channel_order();
+
+        // The primaries are sent as they are, so what the strip shows names the channel they were sent on
+        self.set_channel_order(ChannelOrder::Rgb);
+        let mut seen = Vec::with_capacity(3);
+        for primary in [Color::RED, Color::GREEN, Color::BLUE].iter() {
+            if let Err(e) = self.set_led_color(led, *primary) {
+                self.set_channel_order(previous_order);
+                return Err(e);
+            }
+            seen.push(observe(*primary));
+        }
+
+        let detected = match seen[..] {
+            [Some(red), Some(green), Some(blue)] => ChannelOrder::from_observations(red, green, blue),
+            _ => None,
+        };
+        self.set_channel_order(detected.unwrap_or(previous_order));
+        self.set_led_color(led, previous_color)?;
+
+        Ok(detected)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn applying_an_inferred_order_shows_the_intended_color() {
+        // A GRB strip shows the red channel in green and the green channel in red
+        let order = ChannelOrder::from_observations(Color::GREEN, Color::RED, Color::BLUE).unwrap();
+        assert_eq!(order, ChannelOrder::Grb);
+
+        let orange = Color::new(255, 128, 0);
+        let sent = order.apply(orange);
+        let shown = Color::new(sent.g, sent.r, sent.b);
+        assert_eq!(shown, orange);
+    }
+
+    #[test]
+    fn inconclusive_observations_infer_nothing() {
+        assert_eq!(
+            ChannelOrder::from_observations(Color::RED, Color::RED, Color::BLUE),
+            None
+        );
+        assert_eq!(
+            ChannelOrder::from_observations(Color::WHITE, Color::GREEN, Color::BLUE),
+            None
+        );
+    }
+
+    #[test]
+    fn orders_parse_their_names() {
+        for (order, _, name) in ORDERS.iter() {
+            assert_eq!(name.parse::<ChannelOrder>(), Ok(*order));
+            assert_eq!(order.to_string(), *name);
+        }
+        assert_eq!("GRB".parse::<ChannelOrder>(), Ok(ChannelOrder::Grb));
+        assert!("rgbw".parse::<ChannelOrder>().is_err());
+    }
+}
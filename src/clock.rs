@@ -0,0 +1,111 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// The time source animations are timed with
+///
+/// `SystemClock` follows the wall clock. `ManualClock` only moves when told to, which lets tests run animations
+/// frame by frame and deterministically, without waiting.
+pub trait Clock: Send + Sync {
+    /// The time since a fixed point, usually the creation of the clock
+    fn now(&self) -> Duration;
+
+    /// Waits for up to `duration`. The wait may end early, for example when an animation is cancelled, so callers
+    /// check `now` again afterwards.
+    fn sleep(&self, duration: Duration);
+}
+
+/// A `Clock` following the wall clock
+#[derive(Debug, Clone, Copy)]
+pub struct SystemClock {
+    start: Instant,
+}
+
+impl SystemClock {
+    /// Creates a clock that starts at zero now
+    pub fn new() -> SystemClock {
+        SystemClock { start: Instant::now() }
+    }
+}
+
+impl Default for SystemClock {
+    fn default() -> Self {
+        SystemClock::new()
+    }
+}
+
+impl Clock for SystemClock {
+    fn now(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        // Parking instead of sleeping lets the thread be woken early
+        std::thread::park_timeout(duration);
+    }
+}
+
+/// A `Clock` that only moves when it is advanced or slept on, in which case it jumps ahead by the slept time
+///
+/// Meant for the tests of applications, not only of this crate: animations timed by it play instantly and the same
+/// on every run. Together with `Animator::headless_with_clock` and a `FrameRecorder`, effects can be tested frame
+/// by frame without a device.
+///
+/// # Example
+/// Plays a ten second effect instantly and checks every frame
+#[cfg_attr(feature = "animator", doc = "```")]
+#[cfg_attr(not(feature = "animator"), doc = "```ignore")]
+/// use blinkstick_rs::{Animator, Clock, Color, FrameRecorder, ManualClock};
+/// use std::sync::Arc;
+/// use std::time::Duration;
+///
+/// let clock = Arc::new(ManualClock::new());
+/// let animator = Animator::headless_with_clock(clock.clone());
+/// let recorder = FrameRecorder::new();
+/// animator.tee(recorder.clone());
+///
+/// let frames = (0..10u8).map(|level| (vec![Color {r: level, g: 0, b: 0}; 8], Duration::from_secs(1)));
+/// animator.play("slow", frames).wait();
+///
+/// assert_eq!(recorder.frames().len(), 10);
+/// assert_eq!(clock.now(), Duration::from_secs(10));
+/// ```
+#[derive(Debug, Default)]
+pub struct ManualClock {
+    now: Mutex<Duration>,
+}
+
+impl ManualClock {
+    /// Creates a clock that stands at zero
+    pub fn new() -> ManualClock {
+        ManualClock::default()
+    }
+
+    /// Moves the clock forward
+    pub fn advance(&self, duration: Duration) {
+        *self.now.lock().unwrap() += duration;
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Duration {
+        *self.now.lock().unwrap()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        self.advance(duration);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn manual_clock_jumps_when_slept_on() {
+        let clock = ManualClock::new();
+        clock.advance(Duration::from_millis(5));
+        clock.sleep(Duration::from_secs(2));
+
+        assert_eq!(clock.now(), Duration::from_millis(2005));
+    }
+}
@@ -0,0 +1,130 @@
+use crate::group::{write_parallel, GroupError};
+use crate::{BlinkStick, Color, FeatureError, FeatureErrorType};
+use std::sync::Arc;
+
+/// Several BlinkSticks concatenated into one logical strip of leds
+///
+/// Led 0 of the strip is led 0 of the first BlinkStick, followed by the leds of every BlinkStick in the order
+/// they were added. Frames are split into one segment per device and written to all devices in parallel, so a
+/// frame takes about as long as a frame on a single device.
+///
+/// # Example
+/// Lights up the first led of the second stick of two Squares
+/// ```
+/// use blinkstick_rs::{BlinkStick, Color, CompositeStrip};
+///
+/// let mut strip = CompositeStrip::new();
+/// strip.push(BlinkStick::open_by_serial("BS000001-3.0").unwrap());
+/// strip.push(BlinkStick::open_by_serial("BS000002-3.0").unwrap());
+///
+/// strip.set_led_color(8, Color {r: 0, g: 0, b: 50}).unwrap();
+/// ```
+#[derive(Default)]
+pub struct CompositeStrip {
+    segments: Vec<Arc<BlinkStick>>,
+}
+
+impl CompositeStrip {
+    /// Creates a strip without any leds
+    pub fn new() -> CompositeStrip {
+        CompositeStrip::default()
+    }
+
+    /// Appends the leds of a BlinkStick to the end of the strip
+    pub fn push<S: Into<Arc<BlinkStick>>>(&mut self, stick: S) {
+        self.segments.push(stick.into());
+    }
+
+    /// The BlinkSticks making up the strip, in order
+    pub fn sticks(&self) -> &[Arc<BlinkStick>] {
+        &self.segments
+    }
+
+    /// The number of leds in the strip
+    pub fn len(&self) -> usize {
+        self.segments.iter().map(|stick| stick.max_leds as usize).sum()
+    }
+
+    /// Returns `true` if the strip contains no leds
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Sets a single led of the strip to a color
+    ///
+    /// # Arguments
+    /// * `led` - A zero-indexed led number of the whole strip
+    /// * `color` - A struct holding color values for R,G and B channel respectively
+    pub fn set_led_color(&self, led: usize, color: Color) -> Result<(), FeatureError> {
+        let mut first_led = 0;
+        for stick in &self.segments {
+            if led < first_led + stick.max_leds as usize {
+                return stick.set_led_color((led - first_led) as u8, color);
+            }
+            first_led += stick.max_leds as usize;
+        }
+
+        Err(FeatureError {
+            kind: FeatureErrorType::LedOutOfBounds {
+                led,
+                max_leds: first_led,
+            },
+        })
+    }
+
+    /// Sets every led of the strip to the same color
+    pub fn set_all_leds_color(&self, color: Color) -> Result<(), GroupError> {
+        self.set_all_leds_colors(&vec![color; self.len()])
+    }
+
+    /// Sets every led of the strip to its own color, writing all devices in parallel
+    ///
+    /// A device that fails does not keep the frame from being written to the others. When `colors` does not hold
+    /// a color for every led, the devices whose segment is incomplete fail with `FeatureErrorType::FrameLength`.
+    ///
+    /// # Arguments
+    /// * `colors` - One color for every led of the strip
+    pub fn set_all_leds_colors(&self, colors: &[Color]) -> Result<(), GroupError> {
+        let mut remaining = colors;
+        let writes = self
+            .segments
+            .iter()
+            .enumerate()
+            .map(|(i, stick)| {
+                // The last device takes every remaining color, so a frame that is too long is reported as well
+                let segment_length = if i + 1 == self.segments.len() {
+                    remaining.len()
+                } else {
+                    remaining.len().min(stick.max_leds as usize)
+                };
+                let (segment, rest) = remaining.split_at(segment_length);
+                remaining = rest;
+                (i, &**stick, segment)
+            })
+            .collect();
+
+        write_parallel(writes, BlinkStick::set_all_leds_colors)
+    }
+
+    /// Returns the colors of every led of the strip
+    pub fn get_all_led_colors(&self) -> Result<Vec<Color>, FeatureError> {
+        let mut colors = Vec::with_capacity(self.len());
+        for stick in &self.segments {
+            colors.extend(stick.get_all_led_colors()?);
+        }
+
+        Ok(colors)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn leds_past_the_strip_are_reported_in_full() {
+        let error = CompositeStrip::new().set_led_color(300, Color::RED).unwrap_err();
+
+        assert_eq!(error.kind, FeatureErrorType::LedOutOfBounds { led: 300, max_leds: 0 });
+    }
+}
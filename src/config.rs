@@ -0,0 +1,462 @@
+use crate::reader::parse_hex_digits;
+use crate::{BlinkStick, BlinkStickBuilder, ChannelOrder, Color, ColorProfile, DimmingSchedule, FeatureError};
+use std::error::Error;
+use std::fmt::Formatter;
+use std::path::{Path, PathBuf};
+
+/// An error while reading one of the crate's configuration files
+#[derive(Debug)]
+pub enum ConfigError {
+    /// The file could not be read or written
+    Io(std::io::Error),
+    /// A line of the file could not be understood
+    Parse { line: usize, message: String },
+}
+
+impl std::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigError::Io(e) => write!(f, "Could not access configuration file: {}", e),
+            ConfigError::Parse { line, message } => write!(f, "Invalid configuration on line {}: {}", line, message),
+        }
+    }
+}
+
+impl Error for ConfigError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ConfigError::Io(e) => Some(e),
+            ConfigError::Parse { .. } => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for ConfigError {
+    fn from(e: std::io::Error) -> ConfigError {
+        ConfigError::Io(e)
+    }
+}
+
+/// Returns the non-empty, non-comment lines of a configuration file with their one-indexed line number
+pub(crate) fn config_lines(contents: &str) -> impl Iterator<Item = (usize, &str)> {
+    contents
+        .lines()
+        .enumerate()
+        .map(|(index, line)| (index + 1, line.split('#').next().unwrap_or("").trim()))
+        .filter(|(_, line)| !line.is_empty())
+}
+
+/// Writes a name as a single word of a configuration line, percent-encoding whitespace, `#` and `%` so the name
+/// reads back unchanged with `decode_word`
+#[cfg(feature = "animator")]
+pub(crate) fn encode_word(name: &str) -> String {
+    let mut word = String::with_capacity(name.len());
+    for character in name.chars() {
+        if character.is_whitespace() || character == '#' || character == '%' {
+            for byte in character.encode_utf8(&mut [0; 4]).bytes() {
+                word.push_str(&format!("%{:02X}", byte));
+            }
+        } else {
+            word.push(character);
+        }
+    }
+
+    word
+}
+
+/// Reads a name written by `encode_word`, `None` if a percent-encoding is invalid
+#[cfg(feature = "animator")]
+pub(crate) fn decode_word(word: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(word.len());
+    let mut rest = word.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        if byte == b'%' {
+            let hex = tail.get(..2).filter(|hex| hex.iter().all(u8::is_ascii_hexdigit))?;
+            bytes.push(u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok()?);
+            rest = &tail[2..];
+        } else {
+            bytes.push(byte);
+            rest = tail;
+        }
+    }
+
+    String::from_utf8(bytes).ok()
+}
+
+/// The defaults of a daemon or tool driving a BlinkStick, read from `config.toml` in the platform's configuration
+/// directory so every program shares them
+///
+/// The file holds `key = value` lines in TOML syntax:
+/// ```toml
+/// serial = "BS000001-3.0"       # or path = "/dev/hidraw3", the first BlinkStick is used otherwise
+/// brightness = 0.5
+/// gamma = 2.2
+/// channel_order = "grb"         # the order of the strip, see BlinkStick::detect_channel_order
+/// white_balance = [1.0, 0.9, 0.8] # the red, green and blue scales, see BlinkStick::calibrate_white
+/// turn_off_on_drop = false
+/// write_only = true             # for leds that cannot be read back, like WS2812 strips
+/// scene = ["#ff0000", "#000000"] # or a single color for every led
+/// labels = ["build: api", "", "deploy"] # what every led indicates, empty for none
+/// dimming = ["utc_offset +01:00", "08:00 1.0", "22:00 0.2"] # lines of a DimmingSchedule
+/// ```
+///
+/// # Example
+/// ```
+/// use blinkstick_rs::Config;
+///
+/// let blinkstick = Config::load_default().unwrap().open().unwrap();
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Config {
+    /// The serial number of the BlinkStick to open
+    pub serial: Option<String>,
+    /// The hid path of the BlinkStick to open, used when no serial number is configured
+    pub path: Option<String>,
+    /// The factor (0.0 - 1.0) every color is scaled by
+    pub brightness: f32,
+    /// The gamma curve applied to every color, 1.0 sends colors as they are
+    pub gamma: f32,
+    /// The order the attached strip shows the channels in, see `BlinkStick::set_channel_order`
+    pub channel_order: ChannelOrder,
+    /// The factors the channels are scaled by, see `BlinkStick::set_color_profile`
+    pub white_balance: ColorProfile,
+    /// Whether the leds are turned off when the program lets go of the BlinkStick
+    pub turn_off_on_drop: bool,
+    /// Whether the device is treated as write-only, see `BlinkStick::set_write_only`
+    pub write_only: bool,
+    /// The colors shown when the BlinkStick is opened, instead of turning every led off
+    pub scene: Option<Vec<Color>>,
+    /// The label of every led, starting with the zeroth, empty for leds without one. See `BlinkStick::label`.
+    pub labels: Vec<String>,
+    /// The brightness curve over the day, see `BlinkStick::set_dimming_schedule`
+    pub dimming: Option<DimmingSchedule>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            serial: None,
+            path: None,
+            brightness: 1.0,
+            gamma: 1.0,
+            channel_order: ChannelOrder::Rgb,
+            white_balance: ColorProfile::default(),
+            turn_off_on_drop: true,
+            write_only: false,
+            scene: None,
+            labels: Vec::new(),
+            dimming: None,
+        }
+    }
+}
+
+impl Config {
+    /// The location of the configuration file: `$XDG_CONFIG_HOME/blinkstick/config.toml` or
+    /// `~/.config/blinkstick/config.toml` on Linux, `~/Library/Application Support/blinkstick/config.toml` on macOS
+    /// and `%APPDATA%\blinkstick\config.toml` on Windows. `None` if the environment names no home directory.
+    pub fn default_path() -> Option<PathBuf> {
+        let env_dir = |name: &str| std::env::var_os(name).filter(|dir| !dir.is_empty()).map(PathBuf::from);
+        let config_dir = if cfg!(windows) {
+            env_dir("APPDATA")
+        } else if cfg!(target_os = "macos") {
+            env_dir("HOME").map(|home| home.join("Library").join("Application Support"))
+        } else {
+            env_dir("XDG_CONFIG_HOME").or_else(|| env_dir("HOME").map(|home| home.join(".config")))
+        };
+
+        config_dir.map(|dir| dir.join("blinkstick").join("config.toml"))
+    }
+
+    /// Reads the configuration file at `default_path`, falling back to the defaults if there is none
+    pub fn load_default() -> Result<Config, ConfigError> {
+        match Config::default_path() {
+            Some(path) if path.exists() => Config::load(path),
+            _ => Ok(Config::default()),
+        }
+    }
+
+    /// Reads a configuration from a file in the format accepted by `parse`
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Config, ConfigError> {
+        Config::parse(&std::fs::read_to_string(path)?)
+    }
+
+    /// Parses a configuration from `key = value` lines in TOML syntax, see `Config`. Keys that are missing keep
+    /// their default.
+    pub fn parse(contents: &str) -> Result<Config, ConfigError> {
+        let mut config = Config::default();
+
+        for (line, text) in contents.lines().enumerate() {
+            let line = line + 1;
+            let parse_error = |message: &str| ConfigError::Parse {
+                line,
+                message: message.to_string(),
+            };
+
+            let text = strip_toml_comment(text).trim();
+            if text.is_empty() {
+                continue;
+            }
+
+            let (key, value) = text
+                .split_once('=')
+                .ok_or_else(|| parse_error("expected a key and a value"))?;
+            let value = value.trim();
+            match key.trim() {
+                "serial" => {
+                    config.serial = Some(parse_toml_string(value).ok_or_else(|| parse_error("invalid serial"))?)
+                }
+                "path" => config.path = Some(parse_toml_string(value).ok_or_else(|| parse_error("invalid path"))?),
+                "brightness" => {
+                    config.brightness = value
+                        .parse()
+                        .ok()
+                        .filter(|brightness: &f32| (0.0..=1.0).contains(brightness))
+                        .ok_or_else(|| parse_error("invalid brightness, expected a number from 0.0 to 1.0"))?
+                }
+                "gamma" => config.gamma = value.parse().map_err(|_| parse_error("invalid gamma"))?,
+                "channel_order" => {
+                    config.channel_order = parse_toml_string(value)
+                        .ok_or_else(|| parse_error("invalid channel order"))?
+                        .parse()
+                        .map_err(|e: String| parse_error(&e))?
+                }
+                "white_balance" => {
+                    config.white_balance = parse_white_balance(value).ok_or_else(|| {
+                        parse_error("invalid white balance, expected three scales like [1.0, 0.9, 0.8]")
+                    })?
+                }
+                "turn_off_on_drop" => {
+                    config.turn_off_on_drop = value.parse().map_err(|_| parse_error("expected true or false"))?
+                }
+                "write_only" => config.write_only = value.parse().map_err(|_| parse_error("expected true or false"))?,
+                "scene" => {
+                    let colors = match value.strip_prefix('[').and_then(|value| value.strip_suffix(']')) {
+                        Some(colors) => colors.split(',').filter(|color| !color.trim().is_empty()).collect(),
+                        None => vec![value],
+                    };
+                    let scene = colors
+                        .iter()
+                        .map(|color| parse_toml_string(color.trim()).and_then(|color| parse_hex_color(&color)))
+                        .collect::<Option<Vec<Color>>>()
+                        .filter(|scene| !scene.is_empty())
+                        .ok_or_else(|| parse_error("invalid scene, expected colors like \"#ff0000\""))?;
+                    config.scene = Some(scene);
+                }
+                "labels" => {
+                    config.labels = parse_toml_string_array(value)
+                        .ok_or_else(|| parse_error("invalid labels, expected an array of strings"))?
+                }
+                "dimming" => {
+                    let lines = parse_toml_string_array(value)
+                        .ok_or_else(|| parse_error("invalid dimming schedule, expected an array of strings"))?;
+                    let schedule = DimmingSchedule::parse(&lines.join("\n")).map_err(|e| match e {
+                        ConfigError::Parse { line: entry, message } => {
+                            parse_error(&format!("invalid dimming schedule entry {}: {}", entry, message))
+                        }
+                        e => e,
+                    })?;
+                    config.dimming = Some(schedule);
+                }
+                key => return Err(parse_error(&format!("unknown key {}", key))),
+            }
+        }
+
+        Ok(config)
+    }
+
+    /// A builder with the brightness, gamma, drop behavior, default scene and dimming schedule of the configuration
+    pub fn builder(&self) -> BlinkStickBuilder {
+        BlinkStickBuilder::new().config(self)
+    }
+
+    /// Opens the configured BlinkStick, by serial number, hid path or the first one found
+    pub fn open(&self) -> Result<BlinkStick, FeatureError> {
+        self.open_with(BlinkStickBuilder::new())
+    }
+
+    /// Opens the configured BlinkStick with the configuration applied to `builder`
+    pub fn open_with(&self, builder: BlinkStickBuilder) -> Result<BlinkStick, FeatureError> {
+        let builder = builder.config(self);
+        match (&self.serial, &self.path) {
+            (Some(serial), _) => builder.open_by_serial(serial),
+            (None, Some(path)) => builder.open_by_path(path),
+            (None, None) => builder.open(),
+        }
+    }
+}
+
+/// Removes a `#` comment from a line of TOML, leaving `#` inside strings alone
+fn strip_toml_comment(line: &str) -> &str {
+    let mut in_string = false;
+    for (index, character) in line.char_indices() {
+        match character {
+            '"' => in_string = !in_string,
+            '#' if !in_string => return &line[..index],
+            _ => (),
+        }
+    }
+
+    line
+}
+
+/// Parses a double quoted TOML string without escape sequences
+fn parse_toml_string(value: &str) -> Option<String> {
+    value
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+        .filter(|value| !value.contains('"') && !value.contains('\\'))
+        .map(str::to_string)
+}
+
+/// Parses an array of double quoted TOML strings without escape sequences
+fn parse_toml_string_array(value: &str) -> Option<Vec<String>> {
+    let inner = value.strip_prefix('[')?.strip_suffix(']')?;
+
+    // Splitting at the quotes alternates between the separators and the strings
+    let parts: Vec<&str> = inner.split('"').collect();
+    if parts.len().is_multiple_of(2) || parts.iter().any(|part| part.contains('\\')) {
+        return None;
+    }
+
+    let mut strings = Vec::new();
+    for (index, part) in parts.iter().enumerate() {
+        if index % 2 == 1 {
+            strings.push(part.to_string());
+            continue;
+        }
+
+        let separator = part.trim();
+        let valid = match index {
+            0 => separator.is_empty(),
+            _ if index == parts.len() - 1 => separator.is_empty() || separator == ",",
+            _ => separator == ",",
+        };
+        if !valid {
+            return None;
+        }
+    }
+
+    Some(strings)
+}
+
+/// Parses the red, green and blue scales of a color profile written as a TOML array of three numbers
+fn parse_white_balance(value: &str) -> Option<ColorProfile> {
+    let scales = value
+        .strip_prefix('[')?
+        .strip_suffix(']')?
+        .split(',')
+        .map(|scale| {
+            scale
+                .trim()
+                .parse::<f32>()
+                .ok()
+                .filter(|scale| (0.0..=1.0).contains(scale))
+        })
+        .collect::<Option<Vec<f32>>>()?;
+
+    match scales[..] {
+        [red, green, blue] => Some(ColorProfile::new(red, green, blue)),
+        _ => None,
+    }
+}
+
+/// Parses a color written as `#rrggbb`
+fn parse_hex_color(color: &str) -> Option<Color> {
+    color.strip_prefix('#').and_then(parse_hex_digits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(feature = "animator")]
+    fn encoded_words_read_back_unchanged() {
+        for name in [
+            "glow",
+            "night light",
+            "#1 effect",
+            "100%",
+            "tab\there",
+            "nuit\u{a0}étoilée",
+        ] {
+            let word = encode_word(name);
+            assert_eq!(word.split_whitespace().count(), 1);
+            assert!(!word.contains('#'));
+            assert_eq!(decode_word(&word).as_deref(), Some(name));
+        }
+        assert_eq!(encode_word("night light"), "night%20light");
+        assert_eq!(decode_word("100%2"), None);
+        assert_eq!(decode_word("%+1"), None);
+    }
+
+    #[test]
+    fn parses_toml_keys() {
+        let config = Config::parse(
+            "# daemon defaults\nserial = \"BS000001-3.0\"\nbrightness = 0.5 # dim\nscene = [\"#ff0000\", \"#00FF00\"]\n",
+        )
+        .unwrap();
+
+        assert_eq!(config.serial.as_deref(), Some("BS000001-3.0"));
+        assert_eq!(config.brightness, 0.5);
+        assert_eq!(
+            config.scene,
+            Some(vec![Color { r: 255, g: 0, b: 0 }, Color { r: 0, g: 255, b: 0 }])
+        );
+        assert!(config.turn_off_on_drop);
+        assert!(Config::parse("write_only = true").unwrap().write_only);
+        assert_eq!(
+            Config::parse("white_balance = [1.0, 0.9, 0.8]").unwrap().white_balance,
+            ColorProfile::new(1.0, 0.9, 0.8)
+        );
+        assert!(Config::parse("white_balance = [1.0, 0.9]").is_err());
+        assert_eq!(
+            Config::parse("channel_order = \"grb\"").unwrap().channel_order,
+            ChannelOrder::Grb
+        );
+        assert_eq!(
+            Config::parse("labels = [\"build: api, web\", \"\",\"deploy\"]")
+                .unwrap()
+                .labels,
+            vec!["build: api, web", "", "deploy"]
+        );
+        assert!(Config::parse("labels = [\"a\" \"b\"]").is_err());
+    }
+
+    #[test]
+    fn rejects_brightness_outside_of_the_range() {
+        for brightness in ["1.5", "-0.1", "NaN", "inf", "bright"] {
+            match Config::parse(&format!("brightness = {}", brightness)) {
+                Err(ConfigError::Parse { line, .. }) => assert_eq!(line, 1),
+                other => panic!("Unexpected parse result for {}: {:?}", brightness, other),
+            }
+        }
+        assert_eq!(Config::parse("brightness = 0").unwrap().brightness, 0.0);
+    }
+
+    #[test]
+    fn parses_the_dimming_schedule() {
+        let config =
+            Config::parse("gamma = 2.2\ndimming = [\"utc_offset +01:00\", \"08:00 1.0\", \"22:00 0.2\"]").unwrap();
+        assert_eq!(
+            config.dimming,
+            Some(DimmingSchedule::new().utc_offset(60).at(8, 0, 1.0).at(22, 0, 0.2))
+        );
+
+        match Config::parse("gamma = 2.2\ndimming = [\"08:00 1.0\", \"22:00 2.0\"]") {
+            Err(ConfigError::Parse { line, message }) => {
+                assert_eq!(line, 2);
+                assert!(message.contains("entry 2"), "{}", message);
+            }
+            other => panic!("Unexpected parse result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn rejects_unknown_keys() {
+        match Config::parse("gamma = 2.2\nbrightnes = 0.5\n") {
+            Err(ConfigError::Parse { line, .. }) => assert_eq!(line, 2),
+            other => panic!("Unexpected parse result {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,492 @@
+//! The plumbing every small BlinkStick daemon needs, so it runs the same under systemd, launchd and as a Windows
+//! service.
+//!
+//! A `Daemon` applies its configuration, then waits for a stop or reload request. `SIGTERM` and `SIGINT` stop it,
+//! `SIGHUP` reads the configuration again, keeping the last working one if the new one fails. Under systemd it
+//! reports its state to the service manager, and it answers every connection to its status socket with one JSON
+//! line of its state. On Windows `Ctrl+C`, closing the console and shutting down stop it, which is what service
+//! wrappers like WinSW or NSSM send when the service is stopped.
+//!
+//! A systemd unit for a daemon built on this module:
+//! ```ini
+//! [Service]
+//! Type=notify
+//! ExecStart=/usr/local/bin/busylight
+//! ExecReload=/bin/kill -HUP $MAINPID
+//! ```
+//!
+//! A launchd agent only needs to keep the program running in the foreground, it is stopped with `SIGTERM`.
+//!
+//! # Example
+//! ```no_run
+//! use blinkstick_rs::daemon::Daemon;
+//! use blinkstick_rs::{BlinkStick, Color};
+//!
+//! let mut blinkstick: Option<BlinkStick> = None;
+//! Daemon::new("busylight")
+//!     .run(|config| {
+//!         blinkstick = None;
+//!         let opened = config.open()?;
+//!         opened.set_all_leds_color(Color {r: 0, g: 30, b: 0})?;
+//!         blinkstick = Some(opened);
+//!         Ok(())
+//!     })
+//!     .unwrap();
+//! ```
+
+use crate::json;
+use crate::{Config, ConfigError};
+use std::error::Error;
+use std::fmt::Formatter;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// How often a running `Daemon` checks for stop and reload requests and connections to its status socket
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// The error of an application applying its configuration
+pub type ApplyError = Box<dyn Error + Send + Sync>;
+
+/// What a `Daemon` is doing
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DaemonState {
+    /// Applying the configuration for the first time
+    Starting,
+    /// Waiting for a stop or reload request
+    Running,
+    /// Reading and applying the configuration again
+    Reloading,
+    /// Shutting down
+    Stopping,
+}
+
+impl DaemonState {
+    fn name(self) -> &'static str {
+        match self {
+            DaemonState::Starting => "starting",
+            DaemonState::Running => "running",
+            DaemonState::Reloading => "reloading",
+            DaemonState::Stopping => "stopping",
+        }
+    }
+}
+
+/// The state of a `Daemon`, as served on its status socket
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DaemonStatus {
+    /// What the daemon is doing
+    pub state: DaemonState,
+    /// How often the configuration was applied again, including reloads that failed
+    pub reloads: u64,
+    /// Why the last reload failed, `None` if it succeeded
+    pub last_error: Option<String>,
+}
+
+impl DaemonStatus {
+    /// The status as a single line of JSON, with the name of the daemon and how long it has been running
+    fn to_json(&self, name: &str, uptime: Duration) -> String {
+        format!(
+            "{{\"name\":{},\"state\":\"{}\",\"uptime_secs\":{},\"reloads\":{},\"last_error\":{}}}\n",
+            json::string(name),
+            self.state.name(),
+            uptime.as_secs(),
+            self.reloads,
+            self.last_error.as_deref().map_or("null".to_string(), json::string)
+        )
+    }
+}
+
+/// An error that stopped a `Daemon` from starting
+#[derive(Debug)]
+pub enum DaemonError {
+    /// The configuration could not be read
+    Config(ConfigError),
+    /// The application could not apply the configuration
+    Apply(ApplyError),
+    /// The status socket could not be created
+    Io(std::io::Error),
+}
+
+impl std::fmt::Display for DaemonError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DaemonError::Config(e) => write!(f, "Could not read the configuration: {}", e),
+            DaemonError::Apply(e) => write!(f, "Could not apply the configuration: {}", e),
+            DaemonError::Io(e) => write!(f, "Could not create the status socket: {}", e),
+        }
+    }
+}
+
+impl Error for DaemonError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            DaemonError::Config(e) => Some(e),
+            DaemonError::Apply(e) => Some(e.as_ref()),
+            DaemonError::Io(e) => Some(e),
+        }
+    }
+}
+
+/// The requests and status a `Daemon` shares with its handles
+struct Control {
+    stop: AtomicBool,
+    reload: AtomicBool,
+    status: Mutex<DaemonStatus>,
+}
+
+/// Stops or reloads a running `Daemon` from another thread, like a tray icon or a control endpoint
+#[derive(Clone)]
+pub struct DaemonHandle(Arc<Control>);
+
+impl DaemonHandle {
+    /// Asks the daemon to shut down, `Daemon::run` returns once it has
+    pub fn stop(&self) {
+        self.0.stop.store(true, Ordering::Relaxed);
+    }
+
+    /// Asks the daemon to read and apply its configuration again, as `SIGHUP` does
+    pub fn reload(&self) {
+        self.0.reload.store(true, Ordering::Relaxed);
+    }
+
+    /// The state of the daemon
+    pub fn status(&self) -> DaemonStatus {
+        self.0.status.lock().unwrap().clone()
+    }
+}
+
+/// Runs an application as a service, see the module documentation
+pub struct Daemon {
+    name: String,
+    config_path: Option<PathBuf>,
+    #[cfg(unix)]
+    status_socket: Option<PathBuf>,
+    handle_signals: bool,
+    control: Arc<Control>,
+}
+
+impl Daemon {
+    /// Creates a daemon reading the configuration file at `Config::default_path` and handling signals
+    pub fn new(name: &str) -> Daemon {
+        Daemon {
+            name: name.to_string(),
+            config_path: None,
+            #[cfg(unix)]
+            status_socket: None,
+            handle_signals: true,
+            control: Arc::new(Control {
+                stop: AtomicBool::new(false),
+                reload: AtomicBool::new(false),
+                status: Mutex::new(DaemonStatus {
+                    state: DaemonState::Starting,
+                    reloads: 0,
+                    last_error: None,
+                }),
+            }),
+        }
+    }
+
+    /// Reads the configuration from `path` instead of `Config::default_path`
+    pub fn config_path<P: AsRef<Path>>(mut self, path: P) -> Daemon {
+        self.config_path = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Answers every connection to the Unix socket at `path` with one line of JSON holding the name, state,
+    /// uptime, reload count and last reload error of the daemon. A stale socket left at `path` is replaced, and the
+    /// socket is removed again when the daemon stops.
+    #[cfg(unix)]
+    pub fn status_socket<P: AsRef<Path>>(mut self, path: P) -> Daemon {
+        self.status_socket = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Whether the process signals stop and reload the daemon, which is the default. Without them it is only
+    /// controlled through its `DaemonHandle`.
+    pub fn handle_signals(mut self, handle_signals: bool) -> Daemon {
+        self.handle_signals = handle_signals;
+        self
+    }
+
+    /// A handle stopping or reloading the daemon once it runs
+    pub fn handle(&self) -> DaemonHandle {
+        DaemonHandle(Arc::clone(&self.control))
+    }
+
+    /// Applies the configuration with `apply`, then waits for stop and reload requests until the daemon is stopped
+    ///
+    /// `apply` is called again with the new configuration on every reload. A reload whose configuration cannot be
+    /// read or applied is logged and reported on the status socket, and the daemon keeps running.
+    pub fn run<F>(self, mut apply: F) -> Result<(), DaemonError>
+    where
+        F: FnMut(&Config) -> Result<(), ApplyError>,
+    {
+        if self.handle_signals {
+            signals::install();
+        }
+
+        apply(&self.load_config().map_err(DaemonError::Config)?).map_err(DaemonError::Apply)?;
+        #[cfg(unix)]
+        let status_socket = match &self.status_socket {
+            Some(path) => Some(StatusSocket::bind(path).map_err(DaemonError::Io)?),
+            None => None,
+        };
+
+        #[cfg(unix)]
+        let started = std::time::Instant::now();
+        self.set_state(DaemonState::Running);
+        notify_service_manager("READY=1");
+
+        while !self.take_request(&self.control.stop, signals::take_stop) {
+            if self.take_request(&self.control.reload, signals::take_reload) {
+                self.set_state(DaemonState::Reloading);
+                notify_service_manager("RELOADING=1");
+                self.reload(&mut apply);
+                self.set_state(DaemonState::Running);
+                notify_service_manager("READY=1");
+            }
+
+            #[cfg(unix)]
+            if let Some(socket) = &status_socket {
+                socket.answer(&self.handle().status().to_json(&self.name, started.elapsed()));
+            }
+            std::thread::sleep(POLL_INTERVAL);
+        }
+
+        self.set_state(DaemonState::Stopping);
+        notify_service_manager("STOPPING=1");
+        Ok(())
+    }
+
+    fn load_config(&self) -> Result<Config, ConfigError> {
+        match &self.config_path {
+            Some(path) => Config::load(path),
+            None => Config::load_default(),
+        }
+    }
+
+    /// Applies the configuration again, keeping the one applied last if that fails
+    fn reload<F: FnMut(&Config) -> Result<(), ApplyError>>(&self, apply: &mut F) {
+        let result = match self.load_config() {
+            Ok(config) => apply(&config).map_err(|e| e.to_string()),
+            Err(e) => Err(e.to_string()),
+        };
+        if let Err(message) = &result {
+            eprintln!(
+                "{}: reload failed, keeping the previous configuration: {}",
+                self.name, message
+            );
+        }
+
+        let mut status = self.control.status.lock().unwrap();
+        status.reloads += 1;
+        status.last_error = result.err();
+    }
+
+    /// Returns whether a request was made through the handle or, if signals are handled, by a signal
+    fn take_request(&self, requested: &AtomicBool, signaled: fn() -> bool) -> bool {
+        let signaled = self.handle_signals && signaled();
+        requested.swap(false, Ordering::Relaxed) || signaled
+    }
+
+    fn set_state(&self, state: DaemonState) {
+        self.control.status.lock().unwrap().state = state;
+    }
+}
+
+/// Tells systemd about the state of the service, if it started the process with `Type=notify`. Does nothing
+/// without a service manager listening.
+fn notify_service_manager(state: &str) {
+    #[cfg(unix)]
+    if let Some(socket) = std::env::var_os("NOTIFY_SOCKET").filter(|socket| !socket.is_empty()) {
+        if let Ok(datagram) = std::os::unix::net::UnixDatagram::unbound() {
+            let _ = datagram.send_to(state.as_bytes(), socket);
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = state;
+}
+
+/// The listening socket behind `Daemon::status_socket`
+#[cfg(unix)]
+struct StatusSocket {
+    path: PathBuf,
+    listener: std::os::unix::net::UnixListener,
+}
+
+#[cfg(unix)]
+impl StatusSocket {
+    fn bind(path: &Path) -> std::io::Result<StatusSocket> {
+        use std::os::unix::fs::FileTypeExt;
+
+        // Only a socket is replaced, so a mistyped path cannot delete a file
+        if std::fs::symlink_metadata(path).is_ok_and(|metadata| metadata.file_type().is_socket()) {
+            std::fs::remove_file(path)?;
+        }
+        let listener = std::os::unix::net::UnixListener::bind(path)?;
+        listener.set_nonblocking(true)?;
+
+        Ok(StatusSocket {
+            path: path.to_path_buf(),
+            listener,
+        })
+    }
+
+    /// Writes `status` to every client waiting for it
+    fn answer(&self, status: &str) {
+        use std::io::Write;
+
+        while let Ok((mut client, _)) = self.listener.accept() {
+            let _ = client.set_nonblocking(false);
+            let _ = client.set_write_timeout(Some(POLL_INTERVAL));
+            let _ = client.write_all(status.as_bytes());
+        }
+    }
+}
+
+#[cfg(unix)]
+impl Drop for StatusSocket {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// The signals stopping and reloading a daemon, recorded by process wide handlers and taken by the daemon loop
+mod signals {
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    static STOP: AtomicBool = AtomicBool::new(false);
+    #[cfg(unix)]
+    static RELOAD: AtomicBool = AtomicBool::new(false);
+
+    pub(super) fn take_stop() -> bool {
+        STOP.swap(false, Ordering::Relaxed)
+    }
+
+    #[cfg(unix)]
+    pub(super) fn take_reload() -> bool {
+        RELOAD.swap(false, Ordering::Relaxed)
+    }
+
+    #[cfg(not(unix))]
+    pub(super) fn take_reload() -> bool {
+        false
+    }
+
+    // The numbers of these signals are the same on Linux, macOS and the BSDs
+    #[cfg(unix)]
+    const SIGHUP: i32 = 1;
+    #[cfg(unix)]
+    const SIGINT: i32 = 2;
+    #[cfg(unix)]
+    const SIGTERM: i32 = 15;
+
+    #[cfg(unix)]
+    extern "C" {
+        fn signal(signum: i32, handler: extern "C" fn(i32)) -> usize;
+    }
+
+    #[cfg(unix)]
+    extern "C" fn on_signal(signum: i32) {
+        match signum {
+            SIGHUP => RELOAD.store(true, Ordering::Relaxed),
+            _ => STOP.store(true, Ordering::Relaxed),
+        }
+    }
+
+    #[cfg(unix)]
+    pub(super) fn install() {
+        for signum in [SIGHUP, SIGINT, SIGTERM] {
+            // Safe as the handler only stores to an atomic, which is async-signal-safe
+            unsafe {
+                signal(signum, on_signal);
+            }
+        }
+    }
+
+    #[cfg(windows)]
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn SetConsoleCtrlHandler(handler: extern "system" fn(u32) -> i32, add: i32) -> i32;
+    }
+
+    /// Handles `Ctrl+C`, `Ctrl+Break`, closing the console, logging off and shutting down
+    #[cfg(windows)]
+    extern "system" fn on_console_event(_event: u32) -> i32 {
+        STOP.store(true, Ordering::Relaxed);
+        1
+    }
+
+    #[cfg(windows)]
+    pub(super) fn install() {
+        unsafe {
+            SetConsoleCtrlHandler(on_console_event, 1);
+        }
+    }
+
+    #[cfg(not(any(unix, windows)))]
+    pub(super) fn install() {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn failed_reloads_keep_the_daemon_running() {
+        let path = std::env::temp_dir().join(format!("blinkstick-daemon-{}.toml", std::process::id()));
+        std::fs::write(&path, "brightness = 0.5\n").unwrap();
+
+        let daemon = Daemon::new("test").config_path(&path).handle_signals(false);
+        let handle = daemon.handle();
+        let runner = std::thread::spawn(move || {
+            let mut applied = Vec::new();
+            daemon
+                .run(|config| {
+                    applied.push(config.brightness);
+                    Ok(())
+                })
+                .map(|_| applied)
+        });
+
+        let wait_for_reloads = |reloads| {
+            while handle.status().state != DaemonState::Running || handle.status().reloads < reloads {
+                std::thread::sleep(Duration::from_millis(5));
+            }
+        };
+        wait_for_reloads(0);
+        std::fs::write(&path, "brightness = dim\n").unwrap();
+        handle.reload();
+        wait_for_reloads(1);
+        assert!(handle.status().last_error.is_some());
+
+        std::fs::write(&path, "brightness = 0.25\n").unwrap();
+        handle.reload();
+        wait_for_reloads(2);
+        assert_eq!(handle.status().last_error, None);
+
+        handle.stop();
+        assert_eq!(runner.join().unwrap().unwrap(), vec![0.5, 0.25]);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn status_lines_are_json() {
+        let status = DaemonStatus {
+            state: DaemonState::Running,
+            reloads: 2,
+            last_error: Some("no \"brightness\"".to_string()),
+        };
+        let line = status.to_json("busylight", Duration::from_secs(90));
+
+        let parsed = json::Json::parse(line.trim_end()).unwrap();
+        assert_eq!(parsed.get("state").and_then(json::Json::as_str), Some("running"));
+        assert_eq!(parsed.get("uptime_secs").and_then(json::Json::as_f64), Some(90.0));
+        assert_eq!(
+            parsed.get("last_error").and_then(json::Json::as_str),
+            Some("no \"brightness\"")
+        );
+    }
+}
@@ -0,0 +1,234 @@
+use crate::{BlinkStick, Color, FeatureError, FeatureErrorType, COLOR_OFF};
+use std::sync::Mutex;
+
+/// The led operations shared by a `BlinkStick` and a `NoopBlinkStick`, so an application can run with its lighting
+/// disabled without checking whether it is enabled before every call
+///
+/// # Example
+/// Falls back to a device that only records what it is told when no BlinkStick is connected, like on a CI server
+/// ```
+/// use blinkstick_rs::{BlinkStick, Color, LedDevice, NoopBlinkStick};
+///
+/// let device: Box<dyn LedDevice> = match BlinkStick::new() {
+///     Ok(blinkstick) => Box::new(blinkstick),
+///     Err(_) => Box::new(NoopBlinkStick::new(8).on_call(|call| println!("Lighting disabled, ignoring {:?}", call))),
+/// };
+///
+/// device.set_all_leds_color(Color {r: 0, g: 50, b: 0}).unwrap();
+/// ```
+pub trait LedDevice: Send + Sync {
+    /// The number of leds of the device
+    fn led_count(&self) -> u8;
+
+    /// Sets a single led to a color, panicking if the led is out of bounds
+    fn set_led_color(&self, led: u8, color: Color) -> Result<(), FeatureError>;
+
+    /// Sets a different color for every led, see `BlinkStick::set_all_leds_colors`
+    fn set_all_leds_colors(&self, colors: &[Color]) -> Result<(), FeatureError>;
+
+    /// Gets the color of every led
+    fn get_all_led_colors(&self) -> Result<Vec<Color>, FeatureError>;
+
+    /// Sets the same color for every led
+    fn set_all_leds_color(&self, color: Color) -> Result<(), FeatureError> {
+        self.set_all_leds_colors(&vec![color; self.led_count() as usize])
+    }
+
+    /// Recovers a device that stopped answering, see `BlinkStick::reset`. Does nothing for devices without a
+    /// connection to lose.
+    fn reset(&self) -> Result<(), FeatureError> {
+        Ok(())
+    }
+}
+
+impl LedDevice for BlinkStick {
+    fn led_count(&self) -> u8 {
+        self.max_leds
+    }
+
+    fn set_led_color(&self, led: u8, color: Color) -> Result<(), FeatureError> {
+        BlinkStick::set_led_color(self, led, color)
+    }
+
+    fn set_all_leds_colors(&self, colors: &[Color]) -> Result<(), FeatureError> {
+        BlinkStick::set_all_leds_colors(self, colors)
+    }
+
+    fn get_all_led_colors(&self) -> Result<Vec<Color>, FeatureError> {
+        BlinkStick::get_all_led_colors(self)
+    }
+
+    fn set_all_leds_color(&self, color: Color) -> Result<(), FeatureError> {
+        BlinkStick::set_all_leds_color(self, color)
+    }
+
+    fn reset(&self) -> Result<(), FeatureError> {
+        BlinkStick::reset(self)
+    }
+}
+
+type CallCallback = Box<dyn Fn(&DeviceCall) + Send + Sync>;
+
+/// A write a `NoopBlinkStick` received
+#[derive(Debug, Clone, PartialEq)]
+pub enum DeviceCall {
+    SetLed { led: u8, color: Color },
+    SetAllLeds(Vec<Color>),
+}
+
+/// A device that accepts every call without any hardware, for running with the lighting disabled on CI or
+/// headless servers
+///
+/// Reads return the colors written last. Writes are only kept when recording is turned on, so a device left
+/// running for days doesn't grow, and can be passed to a callback, for example to log them.
+///
+/// # Example
+/// ```
+/// use blinkstick_rs::{Color, DeviceCall, LedDevice, NoopBlinkStick};
+///
+/// let device = NoopBlinkStick::new(8).recording(true);
+/// device.set_led_color(2, Color::RED).unwrap();
+///
+/// assert_eq!(device.calls(), vec![DeviceCall::SetLed { led: 2, color: Color::RED }]);
+/// assert_eq!(device.get_all_led_colors().unwrap()[2], Color::RED);
+/// ```
+pub struct NoopBlinkStick {
+    colors: Mutex<Vec<Color>>,
+    calls: Option<Mutex<Vec<DeviceCall>>>,
+    on_call: Option<CallCallback>,
+}
+
+impl std::fmt::Debug for NoopBlinkStick {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("NoopBlinkStick")
+            .field("colors", &self.colors)
+            .field("calls", &self.calls)
+            .finish_non_exhaustive()
+    }
+}
+
+impl NoopBlinkStick {
+    /// Creates a device with `led_count` leds, all off
+    pub fn new(led_count: u8) -> NoopBlinkStick {
+        NoopBlinkStick {
+            colors: Mutex::new(vec![COLOR_OFF; led_count as usize]),
+            calls: None,
+            on_call: None,
+        }
+    }
+
+    /// Decides whether every write is kept to be returned by `calls`, which it is not by default. A recording
+    /// device keeps every write until `clear_calls`, tests inspect them, long running applications leave this off.
+    pub fn recording(mut self, recording: bool) -> NoopBlinkStick {
+        self.calls = recording.then(|| Mutex::new(Vec::new()));
+        self
+    }
+
+    /// Calls `callback` with every write, on the thread that wrote it
+    pub fn on_call<F>(mut self, callback: F) -> NoopBlinkStick
+    where
+        F: Fn(&DeviceCall) + Send + Sync + 'static,
+    {
+        self.on_call = Some(Box::new(callback));
+        self
+    }
+
+    /// Every write received so far, oldest first, empty unless the device is recording
+    pub fn calls(&self) -> Vec<DeviceCall> {
+        self.calls
+            .as_ref()
+            .map_or_else(Vec::new, |calls| calls.lock().unwrap().clone())
+    }
+
+    /// Forgets every write received so far, keeping the colors they set
+    pub fn clear_calls(&self) {
+        if let Some(calls) = &self.calls {
+            calls.lock().unwrap().clear();
+        }
+    }
+
+    fn record(&self, call: DeviceCall) {
+        if let Some(on_call) = &self.on_call {
+            on_call(&call);
+        }
+        if let Some(calls) = &self.calls {
+            calls.lock().unwrap().push(call);
+        }
+    }
+}
+
+impl LedDevice for NoopBlinkStick {
+    fn led_count(&self) -> u8 {
+        self.colors.lock().unwrap().len() as u8
+    }
+
+    fn set_led_color(&self, led: u8, color: Color) -> Result<(), FeatureError> {
+        match self.colors.lock().unwrap().get_mut(led as usize) {
+            Some(current) => *current = color,
+            None => panic!("Led {} is out of bounds for Blinkstick device", led),
+        }
+        self.record(DeviceCall::SetLed { led, color });
+
+        Ok(())
+    }
+
+    fn set_all_leds_colors(&self, colors: &[Color]) -> Result<(), FeatureError> {
+        let mut current = self.colors.lock().unwrap();
+        if colors.len() != current.len() {
+            return Err(FeatureError {
+                kind: FeatureErrorType::FrameLength {
+                    expected: current.len(),
+                    actual: colors.len(),
+                },
+            });
+        }
+        current.copy_from_slice(colors);
+        drop(current);
+        self.record(DeviceCall::SetAllLeds(colors.to_vec()));
+
+        Ok(())
+    }
+
+    fn get_all_led_colors(&self) -> Result<Vec<Color>, FeatureError> {
+        Ok(self.colors.lock().unwrap().clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn noop_devices_record_writes() {
+        let device = NoopBlinkStick::new(2).recording(true);
+        device.set_all_leds_color(Color::BLUE).unwrap();
+        device.set_led_color(1, Color::RED).unwrap();
+
+        assert_eq!(
+            device.calls(),
+            vec![
+                DeviceCall::SetAllLeds(vec![Color::BLUE; 2]),
+                DeviceCall::SetLed {
+                    led: 1,
+                    color: Color::RED
+                }
+            ]
+        );
+        assert_eq!(device.get_all_led_colors().unwrap(), vec![Color::BLUE, Color::RED]);
+        assert!(device.set_all_leds_colors(&[COLOR_OFF]).is_err());
+    }
+
+    #[test]
+    fn noop_devices_only_keep_writes_when_recording() {
+        let seen = std::sync::Arc::new(Mutex::new(0));
+        let counter = seen.clone();
+        let device = NoopBlinkStick::new(2).on_call(move |_| *counter.lock().unwrap() += 1);
+        for _ in 0..100 {
+            device.set_all_leds_color(Color::BLUE).unwrap();
+        }
+
+        assert!(device.calls().is_empty());
+        assert_eq!(*seen.lock().unwrap(), 100);
+        assert_eq!(device.get_all_led_colors().unwrap(), vec![Color::BLUE; 2]);
+    }
+}
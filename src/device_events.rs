@@ -0,0 +1,185 @@
+use crate::{BlinkStick, Color, DeviceDescriptor, FeatureErrorType};
+use std::sync::mpsc::{channel, Receiver};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Something that happened to a BlinkStick, see `BlinkStick::subscribe` and `BlinkStick::watch_hotplug`
+#[derive(Debug, Clone, PartialEq)]
+pub enum DeviceEvent {
+    /// A BlinkStick was plugged in
+    Arrived(DeviceDescriptor),
+    /// A BlinkStick was unplugged
+    Removed(DeviceDescriptor),
+    /// A transfer with the device failed, after any retries
+    Error(FeatureErrorType),
+    /// A frame was written to the device, with the color of every led
+    FrameWritten(Vec<Color>),
+}
+
+/// Receives events until it returns `false`
+type Listener = Box<dyn FnMut(&DeviceEvent) -> bool + Send>;
+
+/// The listeners for the events of a BlinkStick
+#[derive(Default)]
+pub(crate) struct EventListeners(Mutex<Vec<Listener>>);
+
+impl EventListeners {
+    pub(crate) fn add<F: FnMut(&DeviceEvent) -> bool + Send + 'static>(&self, listener: F) {
+        self.0.lock().unwrap().push(Box::new(listener));
+    }
+
+    pub(crate) fn is_listened(&self) -> bool {
+        !self.0.lock().unwrap().is_empty()
+    }
+
+    /// Hands an event to every listener, only creating it if anyone listens
+    pub(crate) fn emit<F: FnOnce() -> DeviceEvent>(&self, event: F) {
+        let mut listeners = self.0.lock().unwrap();
+        if listeners.is_empty() {
+            return;
+        }
+
+        let event = event();
+        listeners.retain_mut(|listener| listener(&event));
+    }
+}
+
+impl BlinkStick {
+    /// Returns a channel receiving every frame written to the device and every transfer that failed, until the
+    /// receiver is dropped
+    ///
+    /// # Example
+    /// Logs failed transfers on another thread
+    /// ```
+    /// use blinkstick_rs::{BlinkStick, DeviceEvent};
+    ///
+    /// let blinkstick = BlinkStick::new().unwrap();
+    /// let events = blinkstick.subscribe();
+    /// std::thread::spawn(move || {
+    ///     for event in events {
+    ///         if let DeviceEvent::Error(kind) = event {
+    ///             eprintln!("BlinkStick failed: {:?}", kind);
+    ///         }
+    ///     }
+    /// });
+    /// ```
+    pub fn subscribe(&self) -> Receiver<DeviceEvent> {
+        let (sender, receiver) = channel();
+        self.event_listeners
+            .add(move |event| sender.send(event.clone()).is_ok());
+        receiver
+    }
+
+    /// Lists the connected BlinkSticks every `interval` on a background thread, returning a channel that receives
+    /// an event whenever one is plugged in or unplugged. BlinkSticks that are connected already are not reported.
+    /// The thread ends with the first change after the receiver was dropped, as a channel only tells it is closed
+    /// when sending. `BlinkStick::hotplug_stream` ends its thread with the next poll instead.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use blinkstick_rs::{BlinkStick, DeviceEvent};
+    /// use std::time::Duration;
+    ///
+    /// for event in BlinkStick::watch_hotplug(Duration::from_secs(1)) {
+    ///     if let DeviceEvent::Arrived(device) = event {
+    ///         println!("Plugged in: {:?}", device.serial_number);
+    ///     }
+    /// }
+    /// ```
+    pub fn watch_hotplug(interval: Duration) -> Receiver<DeviceEvent> {
+        let (sender, receiver) = channel();
+        spawn_hotplug_watcher(interval, || false, move |event| sender.send(event).is_ok());
+        receiver
+    }
+}
+
+/// Polls the connected BlinkSticks every `interval` and hands every change to `send`, until it returns `false` or
+/// `is_closed` says nobody receives the changes any more
+pub(crate) fn spawn_hotplug_watcher<C, F>(interval: Duration, is_closed: C, mut send: F)
+where
+    C: Fn() -> bool + Send + 'static,
+    F: FnMut(DeviceEvent) -> bool + Send + 'static,
+{
+    std::thread::spawn(move || {
+        let mut api = match hidapi::HidApi::new() {
+            Ok(api) => api,
+            Err(_) => return,
+        };
+        let mut connected = BlinkStick::list_devices_with_api(&api);
+        loop {
+            std::thread::sleep(interval);
+            if is_closed() {
+                return;
+            }
+            // A failed refresh keeps the previous list, the next poll tries again
+            if api.refresh_devices().is_err() {
+                continue;
+            }
+
+            let now = BlinkStick::list_devices_with_api(&api);
+            for event in hotplug_changes(&connected, &now) {
+                if !send(event) {
+                    return;
+                }
+            }
+            connected = now;
+        }
+    });
+}
+
+/// The devices unplugged and plugged in between two lists of connected devices, told apart by their path
+fn hotplug_changes(before: &[DeviceDescriptor], after: &[DeviceDescriptor]) -> Vec<DeviceEvent> {
+    let removed = before
+        .iter()
+        .filter(|device| !after.iter().any(|other| other.path == device.path))
+        .map(|device| DeviceEvent::Removed(device.clone()));
+    let arrived = after
+        .iter()
+        .filter(|device| !before.iter().any(|other| other.path == device.path))
+        .map(|device| DeviceEvent::Arrived(device.clone()));
+
+    removed.chain(arrived).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn device(path: &str) -> DeviceDescriptor {
+        DeviceDescriptor {
+            path: path.to_string(),
+            serial_number: None,
+            release_number: 0x0300,
+            product: None,
+            location: None,
+        }
+    }
+
+    #[test]
+    fn hotplug_changes_are_found_by_path() {
+        let before = [device("/dev/hidraw1"), device("/dev/hidraw2")];
+        let after = [device("/dev/hidraw2"), device("/dev/hidraw3")];
+
+        assert_eq!(
+            hotplug_changes(&before, &after),
+            vec![
+                DeviceEvent::Removed(device("/dev/hidraw1")),
+                DeviceEvent::Arrived(device("/dev/hidraw3"))
+            ]
+        );
+    }
+
+    #[test]
+    fn listeners_are_dropped_when_they_stop() {
+        let listeners = EventListeners::default();
+        let (sender, receiver) = channel();
+        listeners.add(move |event| sender.send(event.clone()).is_ok());
+
+        listeners.emit(|| DeviceEvent::Error(FeatureErrorType::Timeout));
+        assert_eq!(receiver.recv(), Ok(DeviceEvent::Error(FeatureErrorType::Timeout)));
+
+        drop(receiver);
+        listeners.emit(|| DeviceEvent::Error(FeatureErrorType::Timeout));
+        assert!(listeners.0.lock().unwrap().is_empty());
+    }
+}
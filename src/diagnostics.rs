@@ -0,0 +1,220 @@
+use crate::json::string;
+use crate::{BlinkStick, FeatureError, FeatureErrorType, StateEvent, StateObserver};
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt::Write;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// The number of feature reports kept for `BlinkStick::export_diagnostics`
+const REPORT_LOG_LENGTH: usize = 64;
+
+/// A feature report exchanged with the device
+struct LoggedReport {
+    /// Milliseconds since the Unix epoch
+    timestamp: u128,
+    sent: bool,
+    bytes: Vec<u8>,
+    error: Option<FeatureErrorType>,
+}
+
+/// The recent reports and the errors of a device, kept for bug reports
+#[derive(Default)]
+pub(crate) struct Diagnostics {
+    reports: Mutex<VecDeque<LoggedReport>>,
+    errors: Mutex<BTreeMap<String, u64>>,
+}
+
+impl Diagnostics {
+    /// Logs a report sent to or read from the device, forgetting the oldest once the log is full
+    pub(crate) fn report(&self, sent: bool, bytes: &[u8], result: &Result<(), FeatureError>) {
+        let mut reports = self.reports.lock().unwrap();
+        if reports.len() == REPORT_LOG_LENGTH {
+            reports.pop_front();
+        }
+
+        reports.push_back(LoggedReport {
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |since_epoch| since_epoch.as_millis()),
+            sent,
+            bytes: bytes.to_vec(),
+            error: result.as_ref().err().map(|error| error.kind.clone()),
+        });
+    }
+}
+
+impl StateObserver for Arc<Diagnostics> {
+    /// Counts the transfers that failed after retrying, by kind
+    fn observe(&mut self, event: &StateEvent) {
+        if let StateEvent::TransferFailed(kind) = event {
+            *self.errors.lock().unwrap().entry(error_name(kind)).or_insert(0) += 1;
+        }
+    }
+}
+
+/// The name of an error kind without its details, so errors of a kind are counted together
+fn error_name(kind: &FeatureErrorType) -> String {
+    let name = format!("{:?}", kind);
+    match name.find([' ', '(']) {
+        Some(end) => name[..end].to_string(),
+        None => name,
+    }
+}
+
+impl BlinkStick {
+    /// Writes everything useful for a bug report to a JSON file: the device and its firmware version, the settings
+    /// of the crate, the last feature reports exchanged with the device, the errors counted by kind and the
+    /// measured timing. Attach the file when reporting a device that acts incorrectly.
+    ///
+    /// # Example
+    /// ```
+    /// use blinkstick_rs::{BlinkStick, Color};
+    ///
+    /// let blinkstick = BlinkStick::new().unwrap();
+    /// blinkstick.set_all_leds_color(Color {r: 0, g: 50, b: 0}).unwrap();
+    ///
+    /// blinkstick.export_diagnostics(std::env::temp_dir().join("blinkstick-diagnostics.json")).unwrap();
+    /// ```
+    pub fn export_diagnostics<P: AsRef<Path>>(&self, path: P) -> std::io::Result<()> {
+        std::fs::write(path, self.diagnostics_json())
+    }
+
+    /// The diagnostics written by `export_diagnostics`, as a JSON document
+    pub fn diagnostics_json(&self) -> String {
+        let info = self.device.lock().unwrap().get_device_info().ok();
+        let optional = |value: Option<String>| value.map_or("null".to_string(), |value| string(&value));
+        let millis = |duration: Option<Duration>| {
+            duration.map_or("null".to_string(), |duration| {
+                format!("{:.3}", duration.as_secs_f64() * 1000.0)
+            })
+        };
+
+        let mut json = String::from("{\n");
+        let _ = writeln!(json, "  \"crate_version\": {},", string(env!("CARGO_PKG_VERSION")));
+        let _ = writeln!(json, "  \"device\": {{");
+        let _ = writeln!(json, "    \"variant\": {},", string(&format!("{:?}", self.variant)));
+        let _ = writeln!(json, "    \"serial_number\": {},", optional(self.serial_number()));
+        let _ = writeln!(
+            json,
+            "    \"path\": {},",
+            optional(
+                self.path
+                    .lock()
+                    .unwrap()
+                    .as_ref()
+                    .map(|path| path.to_string_lossy().into_owned())
+            )
+        );
+        let _ = writeln!(
+            json,
+            "    \"product\": {},",
+            optional(info.as_ref().and_then(|info| info.product_string().map(str::to_string)))
+        );
+        let _ = writeln!(
+            json,
+            "    \"firmware_version\": {},",
+            optional(info.as_ref().map(|info| {
+                let release = info.release_number();
+                format!("{:x}.{:02x}", release >> 8, release & 0xff)
+            }))
+        );
+        let _ = writeln!(json, "    \"leds\": {},", self.max_leds);
+        let _ = writeln!(json, "    \"report_length\": {}", self.report_length);
+        let _ = writeln!(json, "  }},");
+
+        let _ = writeln!(json, "  \"settings\": {{");
+        let _ = writeln!(json, "    \"brightness\": {},", self.brightness);
+        let _ = writeln!(json, "    \"gamma\": {},", self.gamma);
+        let _ = writeln!(
+            json,
+            "    \"channel_order\": {},",
+            string(&self.channel_order().to_string())
+        );
+        let _ = writeln!(json, "    \"write_only\": {},", self.is_write_only());
+        let _ = writeln!(json, "    \"verify_writes\": {}", self.verify_writes());
+        let _ = writeln!(json, "  }},");
+
+        let stats = self.verification_stats();
+        let _ = writeln!(json, "  \"timing\": {{");
+        let _ = writeln!(json, "    \"report_latency_ms\": {},", millis(self.report_latency()));
+        let _ = writeln!(
+            json,
+            "    \"min_report_gap_ms\": {},",
+            millis(Some(self.min_report_gap()))
+        );
+        let _ = writeln!(json, "    \"io_timeout_ms\": {}", millis(self.io_timeout()));
+        let _ = writeln!(json, "  }},");
+        let _ = writeln!(json, "  \"verification\": {{");
+        let _ = writeln!(json, "    \"frames_verified\": {},", stats.frames_verified);
+        let _ = writeln!(json, "    \"mismatches\": {},", stats.mismatches);
+        let _ = writeln!(json, "    \"failures\": {}", stats.failures);
+        let _ = writeln!(json, "  }},");
+
+        let errors = self.diagnostics.errors.lock().unwrap();
+        let errors: Vec<String> = errors
+            .iter()
+            .map(|(kind, count)| format!("{}: {}", string(kind), count))
+            .collect();
+        let _ = writeln!(json, "  \"errors\": {{{}}},", errors.join(", "));
+
+        let reports = self.diagnostics.reports.lock().unwrap();
+        let reports: Vec<String> = reports
+            .iter()
+            .map(|report| {
+                let hex: String = report.bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+                format!(
+                    "    {{\"timestamp_ms\": {}, \"direction\": {}, \"bytes\": {}, \"error\": {}}}",
+                    report.timestamp,
+                    string(if report.sent { "sent" } else { "received" }),
+                    string(&hex),
+                    optional(report.error.as_ref().map(|kind| format!("{:?}", kind)))
+                )
+            })
+            .collect();
+        if reports.is_empty() {
+            json.push_str("  \"reports\": []\n");
+        } else {
+            let _ = writeln!(json, "  \"reports\": [\n{}\n  ]", reports.join(",\n"));
+        }
+        json.push_str("}\n");
+
+        json
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_report_log_keeps_the_latest_reports() {
+        let diagnostics = Diagnostics::default();
+        for byte in 0..REPORT_LOG_LENGTH as u8 + 2 {
+            diagnostics.report(true, &[byte], &Ok(()));
+        }
+
+        let reports = diagnostics.reports.lock().unwrap();
+        assert_eq!(reports.len(), REPORT_LOG_LENGTH);
+        assert_eq!(reports[0].bytes, vec![2]);
+    }
+
+    #[test]
+    fn errors_are_counted_by_kind() {
+        let mut diagnostics = Arc::new(Diagnostics::default());
+        diagnostics.observe(&StateEvent::TransferFailed(FeatureErrorType::FrameLength {
+            expected: 8,
+            actual: 2,
+        }));
+        diagnostics.observe(&StateEvent::TransferFailed(FeatureErrorType::FrameLength {
+            expected: 8,
+            actual: 3,
+        }));
+        diagnostics.observe(&StateEvent::TransferRetried(FeatureErrorType::Timeout));
+        diagnostics.observe(&StateEvent::TransferFailed(FeatureErrorType::Timeout));
+
+        let errors = diagnostics.errors.lock().unwrap();
+        assert_eq!(errors.get("FrameLength"), Some(&2));
+        assert_eq!(errors.get("Timeout"), Some(&1));
+    }
+}
@@ -0,0 +1,270 @@
+use crate::config::{config_lines, ConfigError};
+use crate::{BlinkStick, FeatureError};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+
+/// A brightness curve over the day, applied to everything written to a BlinkStick
+///
+/// The schedule consists of points in time at which the brightness changes, each brightness lasting until the
+/// next point. Times are in UTC unless a UTC offset is configured. The offset is fixed: it doesn't follow daylight
+/// saving time, so a schedule written in local time runs an hour off for half of the year unless its offset is
+/// changed along with the clocks.
+///
+/// The leds only change brightness when they are written, see `BlinkStick::refresh` to apply a point of the
+/// schedule to leds that stay the same color.
+///
+/// # Example
+/// Full brightness during work hours, dimmed in the evening and off after midnight, in UTC+2
+/// ```
+/// use blinkstick_rs::DimmingSchedule;
+///
+/// let schedule = DimmingSchedule::new()
+///     .utc_offset(120)
+///     .at(8, 0, 1.0)
+///     .at(18, 0, 0.3)
+///     .at(0, 0, 0.0);
+///
+/// assert_eq!(schedule.brightness_at(std::time::Duration::from_secs(12 * 3600)), 1.0);
+/// ```
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct DimmingSchedule {
+    points: Vec<(u32, f32)>,
+    utc_offset_minutes: i32,
+}
+
+impl DimmingSchedule {
+    /// Creates an empty schedule, which keeps the brightness at 1.0 all day
+    pub fn new() -> DimmingSchedule {
+        DimmingSchedule::default()
+    }
+
+    /// Changes the brightness to `brightness` (0.0 - 1.0) every day at `hour`:`minute`
+    pub fn at(mut self, hour: u8, minute: u8, brightness: f32) -> DimmingSchedule {
+        let second_of_day = (hour as u32 % 24) * 3600 + (minute as u32 % 60) * 60;
+
+        self.points.retain(|(second, _)| *second != second_of_day);
+        self.points.push((second_of_day, brightness.clamp(0.0, 1.0)));
+        self.points.sort_by_key(|(second, _)| *second);
+        self
+    }
+
+    /// Sets the offset in minutes between UTC and the local time the schedule is written in, which stays the same
+    /// when daylight saving time begins or ends
+    pub fn utc_offset(mut self, minutes: i32) -> DimmingSchedule {
+        self.utc_offset_minutes = minutes;
+        self
+    }
+
+    /// Parses a schedule from its textual form
+    ///
+    /// Every line holds either a time and a brightness from 0.0 to 1.0 (`22:00 0.3`) or a UTC offset
+    /// (`utc_offset +02:00`). Everything after a `#` is ignored. The `dimming` key of a `Config` holds these lines.
+    ///
+    /// # Example
+    /// ```
+    /// use blinkstick_rs::DimmingSchedule;
+    ///
+    /// let schedule = DimmingSchedule::parse("utc_offset +01:00\n08:00 1.0\n22:00 0.2 # night\n").unwrap();
+    ///
+    /// assert_eq!(schedule, DimmingSchedule::new().utc_offset(60).at(8, 0, 1.0).at(22, 0, 0.2));
+    /// ```
+    pub fn parse(contents: &str) -> Result<DimmingSchedule, ConfigError> {
+        let mut schedule = DimmingSchedule::new();
+
+        for (line, text) in config_lines(contents) {
+            let parse_error = |message: &str| ConfigError::Parse {
+                line,
+                message: message.to_string(),
+            };
+
+            let mut fields = text.split_whitespace();
+            let (key, value) = match (fields.next(), fields.next(), fields.next()) {
+                (Some(key), Some(value), None) => (key, value),
+                _ => return Err(parse_error("expected a time and a brightness")),
+            };
+
+            if key == "utc_offset" {
+                let minutes = parse_utc_offset(value).ok_or_else(|| parse_error("invalid utc offset"))?;
+                schedule = schedule.utc_offset(minutes);
+            } else {
+                let (hour, minute) = parse_time(key).ok_or_else(|| parse_error("invalid time, expected HH:MM"))?;
+                let brightness: f32 = value
+                    .parse()
+                    .ok()
+                    .filter(|brightness: &f32| (0.0..=1.0).contains(brightness))
+                    .ok_or_else(|| parse_error("invalid brightness, expected a number from 0.0 to 1.0"))?;
+                schedule = schedule.at(hour, minute, brightness);
+            }
+        }
+
+        Ok(schedule)
+    }
+
+    /// The brightness at a time of day, given as the time since midnight in the schedule's local time
+    pub fn brightness_at(&self, time_of_day: Duration) -> f32 {
+        let second_of_day = (time_of_day.as_secs() % SECONDS_PER_DAY as u64) as u32;
+
+        self.points
+            .iter()
+            .rev()
+            .find(|(second, _)| *second <= second_of_day)
+            .or_else(|| self.points.last())
+            .map_or(1.0, |(_, brightness)| *brightness)
+    }
+
+    /// The brightness at this moment
+    pub fn brightness_now(&self) -> f32 {
+        self.brightness_at(local_time_of_day(self.utc_offset_minutes))
+    }
+}
+
+/// The time since midnight at this moment, in the local time `utc_offset_minutes` away from UTC
+pub(crate) fn local_time_of_day(utc_offset_minutes: i32) -> Duration {
+    let utc_seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since_epoch| since_epoch.as_secs() as i64);
+    let local_seconds = (utc_seconds + utc_offset_minutes as i64 * 60).rem_euclid(SECONDS_PER_DAY);
+
+    Duration::from_secs(local_seconds as u64)
+}
+
+/// Parses a UTC offset like `+02:00` or `-05:30` into minutes
+pub(crate) fn parse_utc_offset(offset: &str) -> Option<i32> {
+    let (sign, offset) = match offset.strip_prefix('-') {
+        Some(offset) => (-1, offset),
+        None => (1, offset.trim_start_matches('+')),
+    };
+    let (hours, minutes) = parse_time(offset)?;
+
+    Some(sign * (hours as i32 * 60 + minutes as i32))
+}
+
+/// Parses `HH:MM` into hours and minutes
+pub(crate) fn parse_time(time: &str) -> Option<(u8, u8)> {
+    let (hours, minutes) = time.split_once(':')?;
+    let (hours, minutes): (u8, u8) = (hours.parse().ok()?, minutes.parse().ok()?);
+
+    if hours < 24 && minutes < 60 {
+        Some((hours, minutes))
+    } else {
+        None
+    }
+}
+
+impl BlinkStick {
+    /// Applies a brightness schedule to everything written to the device from now on, or removes it with `None`
+    ///
+    /// Leds that aren't written again keep their brightness until `refresh` is called, which an `Animator` does
+    /// while it plays no effect. Colors read back from the device are reported as they were written, before the
+    /// schedule dimmed them.
+    ///
+    /// # Example
+    /// Dims the BlinkStick to 20% between 22:00 and 07:00 UTC
+    /// ```
+    /// use blinkstick_rs::{BlinkStick, Color, DimmingSchedule};
+    ///
+    /// let blinkstick = BlinkStick::new().unwrap();
+    /// blinkstick.set_dimming_schedule(Some(DimmingSchedule::new().at(7, 0, 1.0).at(22, 0, 0.2)));
+    ///
+    /// blinkstick.set_all_leds_color(Color {r: 100, g: 100, b: 100}).unwrap();
+    /// ```
+    pub fn set_dimming_schedule(&self, schedule: Option<DimmingSchedule>) {
+        *self.dimming_schedule.lock().unwrap() = schedule;
+    }
+
+    /// The brightness schedule currently applied to the device
+    pub fn dimming_schedule(&self) -> Option<DimmingSchedule> {
+        self.dimming_schedule.lock().unwrap().clone()
+    }
+
+    /// Writes the last colors again if the brightness they were written with is out of date, because the dimming
+    /// schedule reached its next point or the ambient light changed, and returns whether they were written
+    ///
+    /// # Example
+    /// Keeps a status color at the brightness of the schedule without writing it more often than needed
+    /// ```no_run
+    /// use blinkstick_rs::{BlinkStick, Color, DimmingSchedule};
+    /// use std::time::Duration;
+    ///
+    /// let blinkstick = BlinkStick::new().unwrap();
+    /// blinkstick.set_dimming_schedule(Some(DimmingSchedule::new().at(7, 0, 1.0).at(22, 0, 0.2)));
+    /// blinkstick.set_all_leds_color(Color {r: 0, g: 100, b: 0}).unwrap();
+    ///
+    /// loop {
+    ///     blinkstick.refresh().unwrap();
+    ///     std::thread::sleep(Duration::from_secs(1));
+    /// }
+    /// ```
+    pub fn refresh(&self) -> Result<bool, FeatureError> {
+        let brightness = self.output_brightness();
+        match *self.written_brightness.lock().unwrap() {
+            Some(written) if written != brightness => (),
+            _ => return Ok(false),
+        }
+
+        self.write_all_leds_at(&self.written_frame(), brightness)?;
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Color;
+
+    fn hours(hours: u64) -> Duration {
+        Duration::from_secs(hours * 3600)
+    }
+
+    #[test]
+    fn brightness_follows_schedule_and_wraps_around_midnight() {
+        let schedule = DimmingSchedule::new().at(8, 0, 1.0).at(18, 0, 0.3).at(1, 0, 0.0);
+
+        assert_eq!(schedule.brightness_at(hours(0)), 0.3);
+        assert_eq!(schedule.brightness_at(hours(3)), 0.0);
+        assert_eq!(schedule.brightness_at(hours(12)), 1.0);
+        assert_eq!(schedule.brightness_at(hours(20)), 0.3);
+    }
+
+    #[test]
+    fn empty_schedule_keeps_full_brightness() {
+        assert_eq!(DimmingSchedule::new().brightness_at(hours(23)), 1.0);
+    }
+
+    #[test]
+    fn parse_reports_invalid_lines() {
+        match DimmingSchedule::parse("08:00 1.0\n25:00 0.5\n") {
+            Err(ConfigError::Parse { line, .. }) => assert_eq!(line, 2),
+            other => panic!("Unexpected parse result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn refresh_applies_a_new_brightness_to_the_last_colors() {
+        let blinkstick = crate::BlinkStickBuilder::new().leds(4).open_disabled().unwrap();
+        blinkstick.set_all_leds_color(Color::new(200, 100, 0)).unwrap();
+        assert!(!blinkstick.refresh().unwrap());
+
+        blinkstick.set_dimming_schedule(Some(DimmingSchedule::new().at(0, 0, 0.5)));
+        assert!(blinkstick.refresh().unwrap());
+        assert_eq!(
+            blinkstick.written_colors.lock().unwrap()[3],
+            (Color::new(200, 100, 0), Color::new(100, 50, 0))
+        );
+        assert!(!blinkstick.refresh().unwrap());
+    }
+
+    #[test]
+    fn parse_rejects_brightness_outside_of_the_range() {
+        assert!(DimmingSchedule::parse("08:00 1.5").is_err());
+        assert!(DimmingSchedule::parse("08:00 NaN").is_err());
+    }
+
+    #[test]
+    fn parse_negative_utc_offset() {
+        let schedule = DimmingSchedule::parse("utc_offset -05:30").unwrap();
+
+        assert_eq!(schedule, DimmingSchedule::new().utc_offset(-330));
+    }
+}
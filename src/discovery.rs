@@ -0,0 +1,163 @@
+use crate::{BlinkStick, PRODUCT_ID, VENDOR_ID};
+use std::fmt::Formatter;
+use std::path::Path;
+
+/// A connected `BlinkStick Device` found by `BlinkStick::list_devices`, which can be opened with
+/// `BlinkStick::open_by_path`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeviceDescriptor {
+    /// The platform specific hid path of the device
+    pub path: String,
+    /// The serial number, when the device reports a readable one
+    pub serial_number: Option<String>,
+    /// The device release number (bcdDevice), which tells BlinkStick variants apart
+    pub release_number: u16,
+    /// The product name reported by the device
+    pub product: Option<String>,
+    /// Where the device is plugged in, when the hid backend exposes it
+    pub location: Option<UsbLocation>,
+}
+
+/// The physical position of a usb device: the bus it is on and the chain of hub ports leading to it
+///
+/// Displays in the Linux sysfs notation, `1-2.3` is port 3 of the hub on port 2 of bus 1.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct UsbLocation {
+    pub bus: u8,
+    pub ports: Vec<u8>,
+}
+
+impl UsbLocation {
+    /// Parses a location in the Linux sysfs notation, optionally followed by a configuration and
+    /// interface such as `1-2.3:1.0`
+    pub fn parse(location: &str) -> Option<UsbLocation> {
+        let location = location.split(':').next()?;
+        let (bus, ports) = location.split_once('-')?;
+        let ports = ports
+            .split('.')
+            .map(|port| port.parse().ok())
+            .collect::<Option<Vec<u8>>>()?;
+
+        Some(UsbLocation {
+            bus: bus.parse().ok()?,
+            ports,
+        })
+    }
+}
+
+impl std::fmt::Display for UsbLocation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}-", self.bus)?;
+        for (i, port) in self.ports.iter().enumerate() {
+            if i > 0 {
+                write!(f, ".")?;
+            }
+            write!(f, "{}", port)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl BlinkStick {
+    /// Lists every connected `BlinkStick Device` without opening any of them
+    ///
+    /// # Example
+    /// Prints where every BlinkStick is plugged in
+    /// ```
+    /// use blinkstick_rs::BlinkStick;
+    ///
+    /// for device in BlinkStick::list_devices() {
+    ///     match device.location {
+    ///         Some(location) => println!("{:?} on port {}", device.serial_number, location),
+    ///         None => println!("{:?} at {}", device.serial_number, device.path),
+    ///     }
+    /// }
+    /// ```
+    /// # Panics
+    /// When the hid api cannot be initialized, the call to list_devices will panic.
+    pub fn list_devices() -> Vec<DeviceDescriptor> {
+        let api = hidapi::HidApi::new().expect("Could not create a hid api");
+        BlinkStick::list_devices_with_api(&api)
+    }
+
+    /// Lists every connected `BlinkStick Device` known to an existing hid api
+    pub fn list_devices_with_api(api: &hidapi::HidApi) -> Vec<DeviceDescriptor> {
+        api.device_list()
+            .filter(|info| info.vendor_id() == VENDOR_ID && info.product_id() == PRODUCT_ID)
+            .map(|info| {
+                let path = info.path().to_string_lossy().into_owned();
+                DeviceDescriptor {
+                    location: location_from_path(&path),
+                    path,
+                    // Some firmware reports an empty serial, which is no more useful than none
+                    serial_number: info
+                        .serial_number()
+                        .filter(|serial| !serial.is_empty())
+                        .map(String::from),
+                    release_number: info.release_number(),
+                    product: info.product_string().map(String::from),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Derives the usb location from a hid path. The libusb backend uses the location as its path, the hidraw
+/// backend needs the device link in sysfs to be followed.
+fn location_from_path(path: &str) -> Option<UsbLocation> {
+    if let Some(location) = UsbLocation::parse(path) {
+        return Some(location);
+    }
+
+    let hidraw = Path::new(path).file_name()?.to_str()?;
+    if !hidraw.starts_with("hidraw") {
+        return None;
+    }
+
+    let device = std::fs::canonicalize(Path::new("/sys/class/hidraw").join(hidraw).join("device")).ok()?;
+    location_from_sysfs(&device)
+}
+
+/// Finds the usb device in a sysfs path such as
+/// `/sys/devices/pci0000:00/0000:00:14.0/usb1/1-2/1-2.3/1-2.3:1.0/0003:20A0:41E5.0001`
+fn location_from_sysfs(device: &Path) -> Option<UsbLocation> {
+    device
+        .ancestors()
+        .filter_map(|ancestor| ancestor.file_name()?.to_str())
+        .find(|name| !name.contains(':') && name.contains('-'))
+        .and_then(UsbLocation::parse)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_usb_locations() {
+        let location = UsbLocation::parse("1-2.3:1.0").unwrap();
+        assert_eq!(
+            location,
+            UsbLocation {
+                bus: 1,
+                ports: vec![2, 3]
+            }
+        );
+        assert_eq!(location.to_string(), "1-2.3");
+
+        assert_eq!(UsbLocation::parse("/dev/hidraw0"), None);
+        assert_eq!(UsbLocation::parse("0001:0005:00"), None);
+    }
+
+    #[test]
+    fn finds_location_in_sysfs_path() {
+        let device = Path::new("/sys/devices/pci0000:00/0000:00:14.0/usb1/1-2/1-2.3/1-2.3:1.0/0003:20A0:41E5.0001");
+        assert_eq!(
+            location_from_sysfs(device),
+            Some(UsbLocation {
+                bus: 1,
+                ports: vec![2, 3]
+            })
+        );
+    }
+}
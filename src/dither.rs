@@ -0,0 +1,168 @@
+use crate::render::{exact_channels, quantize};
+use crate::{BlinkStick, Color, StateEvent};
+
+/// How the fractional channel values left by brightness and gamma are rounded to the 8 bits a led is sent
+///
+/// Rounding every color on its own leaves slow fades through dark colors on the same value for many frames, which
+/// shows as visible steps. Dithering carries the rounding error on, so the leds average the exact color instead,
+/// see `BlinkStick::set_dithering` and `Animator::set_effect_dithering`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Dithering {
+    /// Rounds every color on its own
+    #[default]
+    Off,
+    /// Carries the rounding error of every led into its next frame, so each led averages the exact color over time
+    Temporal,
+    /// Carries the rounding error of every led into the next led of the frame, so neighbouring leds average the
+    /// exact color
+    Spatial,
+}
+
+/// Rounds the colors written to a device, keeping the rounding error of every led for temporal dithering
+///
+/// Colors are rounded once, last: `output` maps the exact channels left by brightness and gamma to the channels
+/// the device is sent, like the color profile and the response curves do, before they are rounded.
+#[derive(Debug, Default)]
+pub(crate) struct Quantizer {
+    dithering: Dithering,
+    errors: Vec<[f32; 3]>,
+}
+
+impl Quantizer {
+    /// The colors a frame shows after `brightness`, `gamma` and `output`, rounded as the dithering says
+    pub(crate) fn frame(
+        &mut self,
+        colors: &[Color],
+        brightness: f32,
+        gamma: f32,
+        output: impl Fn([f32; 3]) -> [f32; 3],
+    ) -> Vec<Color> {
+        let exact = |color: &Color| output(exact_channels(*color, brightness, gamma));
+        match self.dithering {
+            Dithering::Off => colors
+                .iter()
+                .map(|color| quantize(exact(color), &mut [0.0; 3]))
+                .collect(),
+            Dithering::Temporal => {
+                self.errors.resize(colors.len(), [0.0; 3]);
+                colors
+                    .iter()
+                    .zip(self.errors.iter_mut())
+                    .map(|(color, error)| quantize(exact(color), error))
+                    .collect()
+            }
+            Dithering::Spatial => {
+                let mut error = [0.0; 3];
+                colors.iter().map(|color| quantize(exact(color), &mut error)).collect()
+            }
+        }
+    }
+
+    /// The color a single led shows after `brightness`, `gamma` and `output`, which only carries its error on over
+    /// time
+    pub(crate) fn led(
+        &mut self,
+        led: usize,
+        color: Color,
+        brightness: f32,
+        gamma: f32,
+        output: impl Fn([f32; 3]) -> [f32; 3],
+    ) -> Color {
+        let exact = output(exact_channels(color, brightness, gamma));
+        match self.dithering {
+            Dithering::Temporal => {
+                if self.errors.len() <= led {
+                    self.errors.resize(led + 1, [0.0; 3]);
+                }
+                quantize(exact, &mut self.errors[led])
+            }
+            Dithering::Off | Dithering::Spatial => quantize(exact, &mut [0.0; 3]),
+        }
+    }
+}
+
+impl BlinkStick {
+    /// Rounds every color written from now on as `dithering` says, dropping the rounding errors carried so far
+    ///
+    /// # Example
+    /// Fades in slowly without visible steps
+    /// ```
+    /// use blinkstick_rs::{BlinkStick, Color, Dithering};
+    ///
+    /// let blinkstick = BlinkStick::builder().brightness(0.1).open().unwrap();
+    /// blinkstick.set_dithering(Dithering::Temporal);
+    ///
+    /// for level in 0..=255 {
+    ///     blinkstick.set_all_leds_color(Color {r: level, g: level, b: 0}).unwrap();
+    ///     std::thread::sleep(std::time::Duration::from_millis(20));
+    /// }
+    /// ```
+    pub fn set_dithering(&self, dithering: Dithering) {
+        *self.quantizer.lock().unwrap() = Quantizer {
+            dithering,
+            errors: Vec::new(),
+        };
+        self.record(|| StateEvent::DitheringChanged(dithering));
+    }
+
+    /// How the colors written to the device are rounded
+    pub fn dithering(&self) -> Dithering {
+        self.quantizer.lock().unwrap().dithering
+    }
+
+    /// Goes back to the `previous` dithering after an effect, unless the dithering was changed from `applied` in
+    /// the meantime
+    #[cfg(feature = "animator")]
+    pub(crate) fn restore_dithering(&self, applied: Dithering, previous: Dithering) {
+        {
+            let mut quantizer = self.quantizer.lock().unwrap();
+            if quantizer.dithering != applied {
+                return;
+            }
+            *quantizer = Quantizer {
+                dithering: previous,
+                errors: Vec::new(),
+            };
+        }
+        self.record(|| StateEvent::DitheringChanged(previous));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn neighbouring_leds_average_the_exact_color() {
+        let dark = vec![Color::new(5, 0, 0); 10];
+        let mut quantizer = Quantizer {
+            dithering: Dithering::Spatial,
+            errors: Vec::new(),
+        };
+
+        // 5 at a tenth of the brightness is half a step, which every led rounds up to a whole step without dithering
+        let frame = quantizer.frame(&dark, 0.1, 1.0, |exact| exact);
+        assert_eq!(frame.iter().map(|color| color.r as u32).sum::<u32>(), 5);
+
+        quantizer.dithering = Dithering::Off;
+        assert_eq!(
+            quantizer.frame(&dark, 0.1, 1.0, |exact| exact),
+            vec![Color::new(1, 0, 0); 10]
+        );
+    }
+
+    #[test]
+    fn colors_are_rounded_after_the_output_transforms() {
+        let mut quantizer = Quantizer {
+            dithering: Dithering::Temporal,
+            errors: Vec::new(),
+        };
+        let halve = |[r, g, b]: [f32; 3]| [r * 0.5, g * 0.5, b * 0.5];
+
+        // 3 at half the brightness rounds to 2, which halved rounds to 1 every frame when rounded in between
+        let sum: u32 = (0..4)
+            .map(|_| quantizer.frame(&[Color::new(3, 0, 0)], 0.5, 1.0, halve)[0].r as u32)
+            .sum();
+        assert_eq!(sum, 3);
+    }
+}
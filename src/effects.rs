@@ -0,0 +1,296 @@
+//! Themed effects for holidays and occasions, selectable by name so apps can offer a theme picker without knowing
+//! every effect.
+//!
+//! Every theme plays forever, on any number of leds. The flickers and twinkles look random, but repeat the same
+//! way every time a theme is played.
+//!
+//! # Example
+//! Plays the theme a user picked by name on an `Animator`
+#![cfg_attr(feature = "animator", doc = "```no_run")]
+#![cfg_attr(not(feature = "animator"), doc = "```ignore")]
+//! use blinkstick_rs::{effects, Animator, BlinkStick};
+//!
+//! let blinkstick = BlinkStick::new().unwrap();
+//! let leds = blinkstick.max_leds as usize;
+//! let animator = Animator::new(blinkstick);
+//!
+//! for theme in effects::THEMES.iter() {
+//!     println!("{}: {}", theme.name, theme.description);
+//! }
+//!
+//! let theme = effects::by_name("christmas").unwrap();
+//! animator.play(theme.name, theme.frames(leds));
+//! ```
+//!
+//! # Registry
+//! Other crates can add their own effects by implementing `Animation` and registering them under a name with
+//! `register`. Everything that launches effects by name, like `animation` or the `/blinkstick/effect/{name}` OSC
+//! address, finds them next to the themes.
+
+use crate::random::Xorshift;
+use crate::render::{mix, scale_color, ColorSpace};
+use crate::{AnimationIter, Color, EffectStepper};
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+
+/// The time between two frames of a theme
+const THEME_FRAME_INTERVAL: Duration = Duration::from_millis(50);
+
+/// A named effect of a theme pack, see `by_name`
+#[derive(Debug, Clone, Copy)]
+pub struct Theme {
+    /// The name the theme is selected by
+    pub name: &'static str,
+    /// What the theme shows, for a theme picker
+    pub description: &'static str,
+    render: fn(usize) -> AnimationIter,
+}
+
+impl Theme {
+    /// The never ending frames of the theme on `led_count` leds
+    pub fn frames(&self, led_count: usize) -> AnimationIter {
+        (self.render)(led_count)
+    }
+}
+
+/// Every theme, in the order a theme picker shows them
+pub static THEMES: [Theme; 4] = [
+    Theme {
+        name: "christmas",
+        description: "Red and green leds twinkling white",
+        render: christmas,
+    },
+    Theme {
+        name: "halloween",
+        description: "Flickering orange candles between purple glows",
+        render: halloween,
+    },
+    Theme {
+        name: "pride",
+        description: "A rainbow flowing along the leds",
+        render: pride,
+    },
+    Theme {
+        name: "national_day",
+        description: "Gold chasing across red, in the style of National Day lights",
+        render: national_day,
+    },
+];
+
+/// The theme called `name`, in any case
+///
+/// # Example
+/// ```
+/// use blinkstick_rs::effects;
+///
+/// assert_eq!(effects::by_name("Halloween").unwrap().name, "halloween");
+/// assert!(effects::by_name("easter").is_none());
+/// ```
+pub fn by_name(name: &str) -> Option<&'static Theme> {
+    THEMES.iter().find(|theme| theme.name.eq_ignore_ascii_case(name))
+}
+
+/// An effect that can be launched by name, see `register`
+///
+/// # Example
+/// An effect of another crate that fills the leds with a single color
+/// ```
+/// use blinkstick_rs::{effects, effects::Animation, AnimationIter, Color};
+/// use std::time::Duration;
+///
+/// struct Solid(Color);
+///
+/// impl Animation for Solid {
+///     fn frames(&self, led_count: usize) -> AnimationIter {
+///         AnimationIter::new(vec![(vec![self.0; led_count], Duration::ZERO)])
+///     }
+/// }
+///
+/// effects::register("solid_blue", Solid(Color::BLUE));
+/// assert!(effects::animation("solid_blue").is_some());
+/// ```
+pub trait Animation: Send + Sync {
+    /// The frames of the effect on `led_count` leds
+    fn frames(&self, led_count: usize) -> AnimationIter;
+
+    /// What the effect shows, for an effect picker
+    fn description(&self) -> &str {
+        ""
+    }
+
+    /// The frames of the effect on `led_count` leds, advanced by the ticks of a game loop, see `EffectStepper`
+    fn stepper(&self, led_count: usize) -> EffectStepper {
+        EffectStepper::new(self.frames(led_count))
+    }
+}
+
+impl Animation for Theme {
+    fn frames(&self, led_count: usize) -> AnimationIter {
+        Theme::frames(self, led_count)
+    }
+
+    fn description(&self) -> &str {
+        self.description
+    }
+}
+
+/// The effects registered at runtime, by their lowercase name
+fn registry() -> &'static Mutex<BTreeMap<String, Arc<dyn Animation>>> {
+    static REGISTRY: OnceLock<Mutex<BTreeMap<String, Arc<dyn Animation>>>> = OnceLock::new();
+    REGISTRY.get_or_init(|| Mutex::new(BTreeMap::new()))
+}
+
+/// Registers `animation` under `name`, in any case, replacing an effect registered under the same name before.
+/// Registered effects take precedence over themes of the same name.
+pub fn register<A: Animation + 'static>(name: &str, animation: A) {
+    registry()
+        .lock()
+        .unwrap()
+        .insert(name.to_ascii_lowercase(), Arc::new(animation));
+}
+
+/// Removes the effect registered under `name`, returning whether there was one
+pub fn unregister(name: &str) -> bool {
+    registry().lock().unwrap().remove(&name.to_ascii_lowercase()).is_some()
+}
+
+/// The effect called `name`, in any case, either registered or a theme
+pub fn animation(name: &str) -> Option<Arc<dyn Animation>> {
+    if let Some(animation) = registry().lock().unwrap().get(&name.to_ascii_lowercase()) {
+        return Some(Arc::clone(animation));
+    }
+
+    by_name(name).map(|theme| Arc::new(*theme) as Arc<dyn Animation>)
+}
+
+/// The names of every effect `animation` knows, the themes first and then the registered effects in alphabetical
+/// order
+pub fn names() -> Vec<String> {
+    let registered = registry().lock().unwrap();
+    let themes = THEMES
+        .iter()
+        .map(|theme| theme.name.to_string())
+        .filter(|name| !registered.contains_key(name));
+
+    themes.chain(registered.keys().cloned()).collect()
+}
+
+fn christmas(led_count: usize) -> AnimationIter {
+    let base: Vec<Color> = (0..led_count)
+        .map(|led| {
+            if led % 2 == 0 {
+                Color::new(200, 0, 0)
+            } else {
+                Color::new(0, 150, 0)
+            }
+        })
+        .collect();
+    let mut sparkles = vec![0.0f32; led_count];
+    let mut random = Xorshift::new(0x2512_2024);
+
+    AnimationIter::new(std::iter::repeat_with(move || {
+        for sparkle in sparkles.iter_mut() {
+            *sparkle = if random.next_unit() < 0.02 {
+                1.0
+            } else {
+                *sparkle * 0.85
+            };
+        }
+        let frame = base
+            .iter()
+            .zip(sparkles.iter())
+            .map(|(color, sparkle)| mix(*color, Color::WHITE, *sparkle, ColorSpace::LinearLight))
+            .collect();
+        (frame, THEME_FRAME_INTERVAL)
+    }))
+}
+
+fn halloween(led_count: usize) -> AnimationIter {
+    let mut random = Xorshift::new(0x3110_1031);
+
+    AnimationIter::new(std::iter::repeat_with(move || {
+        let frame = (0..led_count)
+            .map(|led| {
+                let flicker = 0.5 + 0.5 * random.next_unit();
+                if led % 4 == 3 {
+                    scale_color(Color::new(120, 0, 160), 0.6 + 0.2 * flicker)
+                } else {
+                    scale_color(Color::new(255, 80, 0), flicker)
+                }
+            })
+            .collect();
+        (frame, THEME_FRAME_INTERVAL)
+    }))
+}
+
+fn pride(led_count: usize) -> AnimationIter {
+    AnimationIter::new((0u32..).map(move |step| {
+        let frame = (0..led_count)
+            .map(|led| {
+                let hue = led as f32 / led_count as f32 * 360.0 + step as f32 * 4.0;
+                Color::from_hsv(hue % 360.0, 1.0, 1.0)
+            })
+            .collect();
+        (frame, THEME_FRAME_INTERVAL)
+    }))
+}
+
+fn national_day(led_count: usize) -> AnimationIter {
+    AnimationIter::new((0usize..).map(move |step| {
+        let frame = (0..led_count)
+            .map(|led| {
+                if (led + 8 - step % 8) % 8 < 3 {
+                    Color::new(255, 180, 0)
+                } else {
+                    Color::new(200, 0, 0)
+                }
+            })
+            .collect();
+        (frame, THEME_FRAME_INTERVAL * 2)
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_theme_fills_every_led() {
+        for theme in THEMES.iter() {
+            for (frame, delay) in theme.frames(5).take(20) {
+                assert_eq!(frame.len(), 5, "{}", theme.name);
+                assert!(delay > Duration::ZERO);
+            }
+        }
+    }
+
+    #[test]
+    fn theme_names_are_unique() {
+        for theme in THEMES.iter() {
+            assert!(std::ptr::eq(by_name(theme.name).unwrap(), theme));
+        }
+    }
+
+    struct Blank;
+
+    impl Animation for Blank {
+        fn frames(&self, led_count: usize) -> AnimationIter {
+            AnimationIter::new(vec![(vec![Color::BLACK; led_count], Duration::ZERO)])
+        }
+    }
+
+    #[test]
+    fn registered_effects_are_found_by_name() {
+        register("Registry_Test", Blank);
+
+        assert!(names().contains(&"registry_test".to_string()));
+        assert_eq!(animation("REGISTRY_TEST").unwrap().frames(3).count(), 1);
+        assert_eq!(
+            animation("pride").unwrap().description(),
+            "A rainbow flowing along the leds"
+        );
+        assert!(unregister("registry_test"));
+        assert!(animation("registry_test").is_none());
+    }
+}
@@ -0,0 +1,225 @@
+use crate::{AnimationHandle, AnimationIter, Animator, Color, COLOR_OFF};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+type Effect = Box<dyn Fn(&LightEvent, usize) -> AnimationIter + Send + Sync>;
+
+/// How urgent a `LightEvent` is, events of a higher severity take priority over lower ones
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    /// Something happened that is worth a glance
+    Info,
+    /// Something finished successfully
+    Success,
+    /// Something needs attention soon
+    Warning,
+    /// Something failed
+    Error,
+    /// Something needs attention right away
+    Critical,
+}
+
+impl Severity {
+    /// The color the default effect of the severity is shown in
+    pub fn color(self) -> Color {
+        match self {
+            Severity::Info => Color { r: 0, g: 0, b: 60 },
+            Severity::Success => Color { r: 0, g: 60, b: 0 },
+            Severity::Warning => Color { r: 60, g: 35, b: 0 },
+            Severity::Error | Severity::Critical => Color { r: 80, g: 0, b: 0 },
+        }
+    }
+}
+
+/// Something that happened outside of the crate and should be shown on a BlinkStick, like a desktop notification
+/// or a failed build
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LightEvent {
+    /// Where the event comes from, reported as the name of the effect it is shown with
+    pub source: String,
+    /// How urgent the event is
+    pub severity: Severity,
+    /// How long the effect of the event repeats for, `None` to show it once
+    pub duration: Option<Duration>,
+}
+
+impl LightEvent {
+    /// Creates an event that is shown once
+    pub fn new(source: &str, severity: Severity) -> LightEvent {
+        LightEvent {
+            source: source.to_string(),
+            severity,
+            duration: None,
+        }
+    }
+
+    /// Repeats the effect of the event until `duration` has passed
+    pub fn lasting(mut self, duration: Duration) -> LightEvent {
+        self.duration = Some(duration);
+        self
+    }
+}
+
+/// Maps `LightEvent`s pushed by other crates, like notification daemons or system tray apps, to effects on an
+/// `Animator`
+///
+/// Every severity has its own effect, which can be replaced with `set_effect`. An event interrupts the effect of
+/// an event of the same or a lower severity, and waits for the effect of an event of a higher severity to finish.
+///
+/// # Example
+/// Shows a warning for five seconds, unless something more urgent happens
+/// ```
+/// use blinkstick_rs::{Animator, BlinkStick, EventBus, LightEvent, Severity};
+/// use std::time::Duration;
+///
+/// let blinkstick = BlinkStick::new().unwrap();
+/// let leds = blinkstick.max_leds as usize;
+/// let bus = EventBus::new(Animator::new(blinkstick), leds);
+///
+/// bus.push(LightEvent::new("disk", Severity::Warning).lasting(Duration::from_secs(5)));
+/// ```
+pub struct EventBus {
+    animator: Animator,
+    led_count: usize,
+    effects: Mutex<HashMap<Severity, Effect>>,
+    current: Mutex<Option<(Severity, AnimationHandle)>>,
+}
+
+impl EventBus {
+    /// Creates a bus showing events on the `led_count` leds driven by `animator`
+    pub fn new(animator: Animator, led_count: usize) -> EventBus {
+        EventBus {
+            animator,
+            led_count,
+            effects: Mutex::new(HashMap::new()),
+            current: Mutex::new(None),
+        }
+    }
+
+    /// The animator the effects of events are played on
+    pub fn animator(&self) -> &Animator {
+        &self.animator
+    }
+
+    /// Replaces the effect events of `severity` are shown with
+    ///
+    /// The effect receives the event and the number of leds and returns a single run of the effect, which is
+    /// repeated for events with a duration.
+    pub fn set_effect<F>(&self, severity: Severity, effect: F)
+    where
+        F: Fn(&LightEvent, usize) -> AnimationIter + Send + Sync + 'static,
+    {
+        self.effects.lock().unwrap().insert(severity, Box::new(effect));
+    }
+
+    /// Shows an event, interrupting the effect of a less or equally severe event
+    pub fn push(&self, event: LightEvent) -> AnimationHandle {
+        let steps: Vec<(Vec<Color>, Duration)> = match self.effects.lock().unwrap().get(&event.severity) {
+            Some(effect) => effect(&event, self.led_count).collect(),
+            None => default_effect(event.severity, self.led_count).collect(),
+        };
+        let frames = match event.duration {
+            Some(duration) => AnimationIter::new(repeat_for(steps, duration)),
+            None => AnimationIter::new(steps),
+        };
+
+        let mut current = self.current.lock().unwrap();
+        let outranked = current
+            .as_ref()
+            .is_some_and(|(severity, handle)| *severity > event.severity && !handle.is_finished());
+        if outranked {
+            return self.animator.enqueue(&event.source, frames);
+        }
+
+        let handle = self.animator.play(&event.source, frames);
+        *current = Some((event.severity, handle.clone()));
+        handle
+    }
+}
+
+/// The effect events of a severity are shown with unless it is replaced, flashes stay below three per second
+fn default_effect(severity: Severity, led_count: usize) -> AnimationIter {
+    let on = vec![severity.color(); led_count];
+    let off = vec![COLOR_OFF; led_count];
+    let blink = |delay: Duration, blinks: usize| {
+        let blink = [(on.clone(), delay), (off.clone(), delay)];
+        AnimationIter::new(blink.iter().cloned().cycle().take(2 * blinks).collect::<Vec<_>>())
+    };
+
+    match severity {
+        Severity::Info | Severity::Success => AnimationIter::pulse(off.clone(), on.clone(), Duration::from_secs(1), 25),
+        Severity::Warning => blink(Duration::from_millis(250), 2),
+        Severity::Error => blink(Duration::from_millis(200), 3),
+        Severity::Critical => blink(Duration::from_millis(170), 6),
+    }
+}
+
+/// Repeats `steps` until they have been visible for `duration`, finishing the run that is playing by then. The
+/// runs are cloned as they are played, so long durations take no more memory than a single run.
+pub(crate) fn repeat_for(
+    steps: Vec<(Vec<Color>, Duration)>,
+    duration: Duration,
+) -> impl Iterator<Item = (Vec<Color>, Duration)> + Send + 'static {
+    let run: Duration = steps.iter().map(|(_, delay)| *delay).sum();
+    let runs = if run == Duration::ZERO {
+        1
+    } else {
+        (duration.as_secs_f64() / run.as_secs_f64()).ceil().max(1.0) as usize
+    };
+
+    let len = steps.len();
+    steps.into_iter().cycle().take(len.saturating_mul(runs))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FrameRecorder, ManualClock};
+    use std::sync::Arc;
+
+    #[test]
+    fn default_effects_blink_every_led_of_long_strips() {
+        let steps: Vec<_> = default_effect(Severity::Error, 300).collect();
+
+        assert_eq!(steps.len(), 6);
+        assert_eq!(
+            steps[0],
+            (vec![Severity::Error.color(); 300], Duration::from_millis(200))
+        );
+        assert_eq!(steps[5], (vec![COLOR_OFF; 300], Duration::from_millis(200)));
+    }
+
+    #[test]
+    fn long_repeats_are_played_lazily() {
+        let blink = vec![
+            (vec![COLOR_OFF; 2], Duration::from_millis(10)),
+            (vec![Severity::Error.color(); 2], Duration::from_millis(10)),
+        ];
+        let year = Duration::from_secs(365 * 24 * 60 * 60);
+        let mut steps = repeat_for(blink.clone(), year);
+
+        let runs = 365 * 24 * 60 * 60 * 50;
+        assert_eq!(steps.size_hint(), (2 * runs, Some(2 * runs)));
+        assert_eq!(steps.next(), Some(blink[0].clone()));
+        assert_eq!(steps.nth(2), Some(blink[1].clone()));
+        assert_eq!(repeat_for(blink, Duration::ZERO).count(), 2);
+    }
+
+    #[test]
+    fn lasting_events_repeat_their_effect() {
+        let animator = Animator::headless_with_clock(Arc::new(ManualClock::new()));
+        let recorder = FrameRecorder::new();
+        animator.tee(recorder.clone());
+        let bus = EventBus::new(animator, 2);
+        let white = Color { r: 9, g: 9, b: 9 };
+        bus.set_effect(Severity::Info, move |_, leds| {
+            AnimationIter::new(vec![(vec![white; leds], Duration::from_millis(400))])
+        });
+
+        bus.push(LightEvent::new("test", Severity::Info).lasting(Duration::from_secs(1)))
+            .wait();
+
+        assert_eq!(recorder.frames(), vec![(vec![white; 2], Duration::from_millis(400)); 3]);
+    }
+}
@@ -0,0 +1,137 @@
+use crate::{BlinkStick, Color, FeatureError, LedFrame, Transition};
+
+impl BlinkStick {
+    /// Returns a frame with an appropriate length for the plugged in BlinkStick device, with all leds turned off
+    pub fn get_led_frame(&self) -> LedFrame {
+        LedFrame::new(self.max_leds as usize)
+    }
+
+    /// Shows a frame on the device, with the brightness of every led applied
+    ///
+    /// # Arguments
+    /// * `frame` - A frame of equal length to the number of leds available on the device.
+    ///
+    /// # Example
+    /// Shows a frame with the zeroth led at half brightness
+    /// ```
+    /// use blinkstick_rs::{BlinkStick, Color};
+    /// let blinkstick = BlinkStick::new().unwrap();
+    ///
+    /// let mut frame = blinkstick.get_led_frame();
+    /// frame.set_color(0, Color {r: 100, g: 0, b: 100});
+    /// frame.set_brightness(0, 0.5);
+    ///
+    /// blinkstick.write_frame(&frame).unwrap();
+    /// ```
+    pub fn write_frame(&self, frame: &LedFrame) -> Result<(), FeatureError> {
+        self.set_all_leds_colors(&frame.render())
+    }
+
+    /// Moves from the current colors to a frame, see `Transition`
+    ///
+    /// # Arguments
+    /// * `frame` - A frame of equal length to the number of leds available on the device.
+    /// * `transition` - How the device moves to the frame
+    ///
+    /// # Example
+    /// Pushes a green frame in from the end of the device
+    /// ```
+    /// use blinkstick_rs::{BlinkStick, Color, LedFrame, Transition};
+    /// use std::time::Duration;
+    /// let blinkstick = BlinkStick::new().unwrap();
+    ///
+    /// let frame = LedFrame::from(vec![Color {r: 0, g: 50, b: 0}; blinkstick.max_leds as usize]);
+    /// blinkstick.write_frame_with_transition(&frame, Transition::PushLeft(Duration::from_millis(500))).unwrap();
+    /// ```
+    pub fn write_frame_with_transition(&self, frame: &LedFrame, transition: Transition) -> Result<(), FeatureError> {
+        let colors = frame.render();
+        self.validate_frame(&colors)?;
+
+        let current_colors = self.current_colors()?;
+        self.play_transition(&current_colors, &colors, transition)
+    }
+
+    /// Sets a different color for every led unless every channel of every led is within `tolerance` of the colors
+    /// written last, saving the usb writes of noisy inputs like sensor readings. Returns whether the frame was
+    /// written.
+    ///
+    /// The colors are compared with what was last written, not read back, so writes of another program go unnoticed.
+    /// A `tolerance` of 0 only skips frames that are exactly the same.
+    ///
+    /// # Errors
+    /// Returns a `FeatureErrorType::FrameLength` error if the length of the color vector differs from the number of available leds
+    ///
+    /// # Example
+    /// Follows a noisy light sensor, ignoring changes of up to 3 per channel
+    /// ```
+    /// use blinkstick_rs::{BlinkStick, Color};
+    ///
+    /// let blinkstick = BlinkStick::new().unwrap();
+    /// let frame = vec![Color {r: 0, g: 0, b: 100}; blinkstick.max_leds as usize];
+    /// let noisy = vec![Color {r: 0, g: 2, b: 98}; blinkstick.max_leds as usize];
+    ///
+    /// assert!(blinkstick.set_if_changed(&frame, 3).unwrap());
+    /// assert!(!blinkstick.set_if_changed(&noisy, 3).unwrap());
+    /// ```
+    pub fn set_if_changed(&self, colors: &[Color], tolerance: u8) -> Result<bool, FeatureError> {
+        self.validate_frame(colors)?;
+
+        if within_tolerance(&self.written_frame(), colors, tolerance) {
+            return Ok(false);
+        }
+
+        self.set_all_leds_colors(colors)?;
+        Ok(true)
+    }
+
+    /// The colors written to the leds last, as they were passed in
+    pub(crate) fn written_frame(&self) -> Vec<Color> {
+        self.written_colors
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(color, _)| *color)
+            .collect()
+    }
+}
+
+/// Returns `true` if every channel of every led differs by at most `tolerance` between two frames
+pub(crate) fn within_tolerance(frame: &[Color], other: &[Color], tolerance: u8) -> bool {
+    frame.len() == other.len()
+        && frame.iter().zip(other.iter()).all(|(a, b)| {
+            a.r.abs_diff(b.r) <= tolerance && a.g.abs_diff(b.g) <= tolerance && a.b.abs_diff(b.b) <= tolerance
+        })
+}
+
+/// A copy of `frame` with `leds` set to `color`, skipping leds past its end
+pub(crate) fn with_leds(frame: &[Color], leds: &[u8], color: Color) -> Vec<Color> {
+    let mut frame = frame.to_vec();
+    for led in leds {
+        if let Some(slot) = frame.get_mut(*led as usize) {
+            *slot = color;
+        }
+    }
+    frame
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frames_within_tolerance_are_unchanged() {
+        let frame = [Color::new(10, 20, 30), Color::new(200, 200, 200)];
+
+        assert!(within_tolerance(
+            &frame,
+            &[Color::new(12, 18, 30), Color::new(200, 202, 199)],
+            2
+        ));
+        assert!(!within_tolerance(
+            &frame,
+            &[Color::new(13, 20, 30), Color::new(200, 200, 200)],
+            2
+        ));
+        assert!(!within_tolerance(&frame, &frame[..1], 2));
+    }
+}
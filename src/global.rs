@@ -0,0 +1,36 @@
+use crate::{BlinkStick, FeatureError};
+use std::sync::{Mutex, OnceLock};
+
+/// The BlinkStick shared by the whole process, see `BlinkStick::global`
+static GLOBAL: OnceLock<BlinkStick> = OnceLock::new();
+
+/// Held while the global BlinkStick is opened, so two threads never open it at once
+static OPENING: Mutex<()> = Mutex::new(());
+
+impl BlinkStick {
+    /// The BlinkStick shared by the whole process, opened on the first call, so scripts and tests can grab it in
+    /// one line without opening the device twice
+    ///
+    /// It is opened like every builder opens a device, configured by the environment variables listed at
+    /// `BlinkStickBuilder`. A failed open is tried again on the next call.
+    ///
+    /// # Example
+    /// ```
+    /// use blinkstick_rs::{BlinkStick, Color};
+    ///
+    /// BlinkStick::global().unwrap().set_all_leds_color(Color::GREEN).unwrap();
+    /// ```
+    pub fn global() -> Result<&'static BlinkStick, FeatureError> {
+        if let Some(blinkstick) = GLOBAL.get() {
+            return Ok(blinkstick);
+        }
+
+        let _opening = OPENING.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if GLOBAL.get().is_none() {
+            // Only this thread sets the BlinkStick while it holds the opening lock
+            let _ = GLOBAL.set(BlinkStick::builder().open()?);
+        }
+
+        Ok(GLOBAL.get().unwrap())
+    }
+}
@@ -0,0 +1,534 @@
+use crate::reconnect::Attempt;
+use crate::{
+    Backoff, BlinkStick, BlinkStickBuilder, Clock, Color, FeatureError, FeatureErrorType, SystemClock, COLOR_OFF,
+};
+use std::error::Error;
+use std::fmt::Formatter;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::Duration;
+
+/// The failures of an operation on several BlinkSticks, each with the position of the failed device in its
+/// group or strip. Devices that are not listed completed the operation.
+#[derive(Debug)]
+pub struct GroupError {
+    pub failures: Vec<(usize, FeatureError)>,
+}
+
+impl GroupError {
+    /// The positions of the devices that failed
+    pub fn failed_members(&self) -> impl Iterator<Item = usize> + '_ {
+        self.failures.iter().map(|(member, _)| *member)
+    }
+
+    /// Returns `true` if every failure may go away when the operation is retried, see `FeatureError::is_transient`
+    pub fn is_transient(&self) -> bool {
+        self.failures.iter().all(|(_, error)| error.is_transient())
+    }
+}
+
+impl std::fmt::Display for GroupError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} BlinkStick device(s) failed", self.failures.len())?;
+        for (member, error) in &self.failures {
+            write!(f, "; device {}: {}", member, error)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Error for GroupError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.failures.first().map(|(_, error)| error as &(dyn Error + 'static))
+    }
+}
+
+/// Runs `write` for every device on its own thread and waits for all of them, so a slow or dead device delays
+/// the others by at most its own transfer time. Failures are reported with the member number given with the device.
+pub(crate) fn write_parallel<T, F>(writes: Vec<(usize, &BlinkStick, T)>, write: F) -> Result<(), GroupError>
+where
+    T: Send,
+    F: Fn(&BlinkStick, T) -> Result<(), FeatureError> + Sync,
+{
+    let results: Vec<(usize, Result<(), FeatureError>)> = if writes.len() == 1 {
+        writes
+            .into_iter()
+            .map(|(member, stick, data)| (member, write(stick, data)))
+            .collect()
+    } else {
+        std::thread::scope(|scope| {
+            let write = &write;
+            let writers: Vec<_> = writes
+                .into_iter()
+                .map(|(member, stick, data)| (member, scope.spawn(move || write(stick, data))))
+                .collect();
+
+            writers
+                .into_iter()
+                .map(|(member, writer)| (member, writer.join().expect("BlinkStick writer thread panicked")))
+                .collect()
+        })
+    };
+
+    let failures: Vec<(usize, FeatureError)> = results
+        .into_iter()
+        .filter_map(|(member, result)| result.err().map(|error| (member, error)))
+        .collect();
+
+    if failures.is_empty() {
+        Ok(())
+    } else {
+        Err(GroupError { failures })
+    }
+}
+
+/// The time before the first attempt to reopen a member that dropped out under `FailurePolicy::RetryInBackground`
+const REJOIN_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The longest time between two attempts to reopen a member that dropped out
+const MAX_REJOIN_INTERVAL: Duration = Duration::from_secs(30);
+
+/// What a `BlinkStickGroup` does when some of its members cannot be written to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FailurePolicy {
+    /// The operation fails with a `GroupError`, after the other members have been written to
+    #[default]
+    AbortAll,
+    /// Transient failures are ignored. Members that fail otherwise drop out of the group and are skipped from
+    /// then on.
+    SkipAndContinue,
+    /// Like `SkipAndContinue`, but members that dropped out are reopened by their serial number in the background
+    /// and rejoin the group once they are plugged back in, with the brightness, gamma, channel order and other
+    /// settings they had. Members without a serial number can't be reopened and are reported as
+    /// `GroupEvent::MemberLost` instead.
+    RetryInBackground,
+}
+
+/// A change in the members of a `BlinkStickGroup` that can be written to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GroupEvent {
+    /// The member stopped responding and is skipped from now on
+    MemberDropped { member: usize, kind: FeatureErrorType },
+    /// The member was reopened and takes part in the group again
+    MemberRejoined { member: usize },
+    /// The member dropped out under `FailurePolicy::RetryInBackground` but has no serial number to be reopened by,
+    /// so it stays out of the group
+    MemberLost { member: usize },
+}
+
+type Listeners = Arc<Mutex<Vec<Sender<GroupEvent>>>>;
+
+fn emit(listeners: &Listeners, event: GroupEvent) {
+    listeners
+        .lock()
+        .unwrap()
+        .retain(|listener| listener.send(event.clone()).is_ok());
+}
+
+struct Slot {
+    stick: Arc<BlinkStick>,
+    connected: bool,
+}
+
+struct Member {
+    slot: Arc<Mutex<Slot>>,
+    serial: Option<String>,
+    offset: Duration,
+}
+
+/// Several BlinkSticks driven together, so effects stay in phase across all of them
+///
+/// Every effect played on a group is timed by one clock shared by all members. A member can be given an offset,
+/// which makes it run the effect that much behind the others, for waves that travel across a row of sticks.
+///
+/// How the group reacts to members that fail is set with a `FailurePolicy`.
+///
+/// # Example
+/// A rainbow that rolls across two sticks, the second one following the first by a quarter of a second
+/// ```
+/// use blinkstick_rs::{BlinkStick, BlinkStickGroup, Color};
+/// use std::time::Duration;
+///
+/// let mut group = BlinkStickGroup::new();
+/// group.push(BlinkStick::open_by_serial("BS000001-3.0").unwrap());
+/// group.push(BlinkStick::open_by_serial("BS000002-3.0").unwrap());
+/// group.stagger(Duration::from_millis(250));
+///
+/// let rainbow = |time: Duration, leds: &mut [Color]| {
+///     let hue = time.as_secs_f32() * 120.0;
+///     leds.iter_mut().for_each(|led| *led = Color::from_hsv(hue % 360.0, 1.0, 0.2));
+/// };
+/// group.play(rainbow, Duration::from_secs(5), Duration::from_millis(20)).unwrap();
+/// ```
+pub struct BlinkStickGroup {
+    members: Vec<Member>,
+    policy: FailurePolicy,
+    listeners: Listeners,
+    clock: Arc<dyn Clock>,
+}
+
+impl Default for BlinkStickGroup {
+    fn default() -> Self {
+        BlinkStickGroup {
+            members: Vec::new(),
+            policy: FailurePolicy::default(),
+            listeners: Listeners::default(),
+            clock: Arc::new(SystemClock::new()),
+        }
+    }
+}
+
+impl BlinkStickGroup {
+    /// Creates an empty group
+    pub fn new() -> BlinkStickGroup {
+        BlinkStickGroup::default()
+    }
+
+    /// Replaces the wall clock that effects are timed with, for example with a `ManualClock` in tests
+    pub fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        self.clock = clock;
+    }
+
+    /// Adds a BlinkStick to the group, in phase with the group clock
+    pub fn push<S: Into<Arc<BlinkStick>>>(&mut self, stick: S) {
+        self.push_with_offset(stick, Duration::ZERO);
+    }
+
+    /// Adds a BlinkStick to the group that runs effects `offset` behind the group clock
+    pub fn push_with_offset<S: Into<Arc<BlinkStick>>>(&mut self, stick: S, offset: Duration) {
+        let stick = stick.into();
+        self.members.push(Member {
+            serial: stick.serial_number(),
+            slot: Arc::new(Mutex::new(Slot { stick, connected: true })),
+            offset,
+        });
+    }
+
+    /// Changes how far behind the group clock a member runs effects
+    ///
+    /// # Panics
+    /// The call to `set_offset` will panic if `member` is out of bounds for the group.
+    pub fn set_offset(&mut self, member: usize, offset: Duration) {
+        self.members[member].offset = offset;
+    }
+
+    /// Offsets every member by `step` more than the member before it, the first member being in phase
+    pub fn stagger(&mut self, step: Duration) {
+        for (i, member) in self.members.iter_mut().enumerate() {
+            member.offset = step * i as u32;
+        }
+    }
+
+    /// Sets what the group does when members fail, `FailurePolicy::AbortAll` by default
+    pub fn set_failure_policy(&mut self, policy: FailurePolicy) {
+        self.policy = policy;
+    }
+
+    /// What the group does when members fail
+    pub fn failure_policy(&self) -> FailurePolicy {
+        self.policy
+    }
+
+    /// Returns a channel receiving every `GroupEvent` from now on
+    pub fn subscribe(&self) -> Receiver<GroupEvent> {
+        let (sender, receiver) = channel();
+        self.listeners.lock().unwrap().push(sender);
+        receiver
+    }
+
+    /// The number of BlinkSticks in the group, including members that dropped out
+    pub fn len(&self) -> usize {
+        self.members.len()
+    }
+
+    /// Returns `true` if the group contains no BlinkSticks
+    pub fn is_empty(&self) -> bool {
+        self.members.is_empty()
+    }
+
+    /// Returns `false` if the member dropped out of the group after failing
+    ///
+    /// # Panics
+    /// The call to `is_connected` will panic if `member` is out of bounds for the group.
+    pub fn is_connected(&self, member: usize) -> bool {
+        self.members[member].slot.lock().unwrap().connected
+    }
+
+    /// The BlinkSticks in the group, in the order they were added
+    pub fn sticks(&self) -> Vec<Arc<BlinkStick>> {
+        self.members
+            .iter()
+            .map(|member| member.slot.lock().unwrap().stick.clone())
+            .collect()
+    }
+
+    /// Sets every led of every member to the same color, writing all members in parallel
+    pub fn set_all_leds_color(&self, color: Color) -> Result<(), GroupError> {
+        let sticks = self.connected_sticks();
+        let writes = sticks
+            .iter()
+            .map(|(member, stick)| (*member, &**stick, color))
+            .collect();
+        self.handle_failures(write_parallel(writes, BlinkStick::set_all_leds_color))
+    }
+
+    /// Plays an effect on every member, phase synchronized through the group clock
+    ///
+    /// Every frame is written to all members in parallel. Failing members never keep a frame from reaching the
+    /// others, what happens next depends on the `FailurePolicy`.
+    ///
+    /// # Arguments
+    /// * `effect` - Fills the leds of one member for a point in time, given as the time since the effect started
+    ///   minus the member's offset. The leds are turned off before every call.
+    /// * `duration` - How long the effect plays
+    /// * `interval` - The time between two frames
+    pub fn play<F>(&self, effect: F, duration: Duration, interval: Duration) -> Result<(), GroupError>
+    where
+        F: Fn(Duration, &mut [Color]),
+    {
+        play_in_phase(&*self.clock, duration, interval, |elapsed| {
+            let frames: Vec<(usize, Arc<BlinkStick>, Vec<Color>)> = self
+                .connected_sticks()
+                .into_iter()
+                .map(|(member, stick)| {
+                    let mut frame = vec![COLOR_OFF; stick.max_leds as usize];
+                    effect(elapsed.saturating_sub(self.members[member].offset), &mut frame);
+                    (member, stick, frame)
+                })
+                .collect();
+
+            let writes = frames
+                .iter()
+                .map(|(member, stick, frame)| (*member, &**stick, frame.as_slice()))
+                .collect();
+            self.handle_failures(write_parallel(writes, BlinkStick::set_all_leds_colors))
+        })
+    }
+
+    /// The members that have not dropped out, with their number
+    fn connected_sticks(&self) -> Vec<(usize, Arc<BlinkStick>)> {
+        self.members
+            .iter()
+            .enumerate()
+            .filter_map(|(i, member)| {
+                let slot = member.slot.lock().unwrap();
+                slot.connected.then(|| (i, slot.stick.clone()))
+            })
+            .collect()
+    }
+
+    /// Applies the failure policy to the result of a group operation
+    fn handle_failures(&self, result: Result<(), GroupError>) -> Result<(), GroupError> {
+        for (i, failure) in dropped_members(self.policy, result)? {
+            let member = &self.members[i];
+            member.slot.lock().unwrap().connected = false;
+            emit(
+                &self.listeners,
+                GroupEvent::MemberDropped {
+                    member: i,
+                    kind: failure.kind,
+                },
+            );
+
+            match (self.policy, &member.serial) {
+                (FailurePolicy::RetryInBackground, Some(serial)) => {
+                    let (slot, serial, listeners) =
+                        (Arc::downgrade(&member.slot), serial.clone(), self.listeners.clone());
+                    std::thread::spawn(move || rejoin(i, &slot, &serial, &listeners));
+                }
+                (FailurePolicy::RetryInBackground, None) => emit(&self.listeners, GroupEvent::MemberLost { member: i }),
+                _ => (),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Shows a frame every `interval` for `duration` through `show_frame`, which is given the time since the first frame
+/// and ends the effect early by failing
+fn play_in_phase<F>(
+    clock: &dyn Clock,
+    duration: Duration,
+    interval: Duration,
+    mut show_frame: F,
+) -> Result<(), GroupError>
+where
+    F: FnMut(Duration) -> Result<(), GroupError>,
+{
+    let start = clock.now();
+    let mut frame_number = 0;
+    loop {
+        let elapsed = clock.now().saturating_sub(start);
+        show_frame(elapsed)?;
+
+        if elapsed >= duration {
+            return Ok(());
+        }
+
+        // Frames are scheduled from the start of the effect, so slow writes never let the members drift
+        frame_number += 1;
+        let next_frame = start + (interval * frame_number).min(duration);
+        let mut now = clock.now();
+        while now < next_frame {
+            clock.sleep(next_frame - now);
+            now = clock.now();
+        }
+    }
+}
+
+/// The members that drop out of a group under `policy` after an operation ended with `result`, or the error the
+/// operation fails with
+fn dropped_members(
+    policy: FailurePolicy,
+    result: Result<(), GroupError>,
+) -> Result<Vec<(usize, FeatureError)>, GroupError> {
+    match (result, policy) {
+        (Err(error), FailurePolicy::SkipAndContinue | FailurePolicy::RetryInBackground) => Ok(error
+            .failures
+            .into_iter()
+            .filter(|(_, failure)| !failure.is_transient())
+            .collect()),
+        (result, _) => result.map(|()| Vec::new()),
+    }
+}
+
+/// Reopens a member that dropped out until it is plugged back in, or until its group is dropped
+fn rejoin(member: usize, slot: &Weak<Mutex<Slot>>, serial: &str, listeners: &Listeners) {
+    let backoff = Backoff::new(REJOIN_INTERVAL, MAX_REJOIN_INTERVAL);
+
+    backoff.retry(u32::MAX, |_| {
+        let builder = match slot.upgrade() {
+            Some(slot) => BlinkStickBuilder::reopening(&slot.lock().unwrap().stick),
+            None => return Attempt::Abandoned,
+        };
+        let stick = match builder.open_by_serial(serial) {
+            Ok(stick) => stick,
+            Err(_) => return Attempt::Failed,
+        };
+
+        if let Some(slot) = slot.upgrade() {
+            *slot.lock().unwrap() = Slot {
+                stick: Arc::new(stick),
+                connected: true,
+            };
+            emit(listeners, GroupEvent::MemberRejoined { member });
+        }
+        Attempt::Done(())
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ManualClock;
+
+    #[test]
+    fn group_error_lists_every_failure() {
+        let error = GroupError {
+            failures: vec![
+                (
+                    1,
+                    FeatureError {
+                        kind: FeatureErrorType::Timeout,
+                    },
+                ),
+                (
+                    3,
+                    FeatureError {
+                        kind: FeatureErrorType::Disconnected,
+                    },
+                ),
+            ],
+        };
+
+        assert_eq!(error.failed_members().collect::<Vec<usize>>(), vec![1, 3]);
+        assert!(!error.is_transient());
+        assert_eq!(
+            error.to_string(),
+            "2 BlinkStick device(s) failed; device 1: BlinkStick device did not respond in time; \
+             device 3: BlinkStick device is not connected"
+        );
+    }
+
+    #[test]
+    fn slow_frames_keep_to_the_schedule() {
+        let clock = ManualClock::new();
+        let mut shown = Vec::new();
+
+        play_in_phase(
+            &clock,
+            Duration::from_millis(90),
+            Duration::from_millis(20),
+            |elapsed| {
+                shown.push(elapsed);
+                clock.advance(Duration::from_millis(7));
+                Ok(())
+            },
+        )
+        .unwrap();
+
+        let millis: Vec<u64> = shown.iter().map(|elapsed| elapsed.as_millis() as u64).collect();
+        assert_eq!(millis, vec![0, 20, 40, 60, 80, 90]);
+    }
+
+    fn failures(kinds: &[(usize, FeatureErrorType)]) -> Result<(), GroupError> {
+        Err(GroupError {
+            failures: kinds
+                .iter()
+                .map(|(member, kind)| (*member, FeatureError { kind: kind.clone() }))
+                .collect(),
+        })
+    }
+
+    #[test]
+    fn abort_all_fails_the_operation_and_keeps_every_member() {
+        let result = dropped_members(
+            FailurePolicy::AbortAll,
+            failures(&[(0, FeatureErrorType::Timeout), (2, FeatureErrorType::Disconnected)]),
+        );
+
+        assert_eq!(result.unwrap_err().failed_members().collect::<Vec<usize>>(), vec![0, 2]);
+        assert!(dropped_members(FailurePolicy::AbortAll, Ok(())).unwrap().is_empty());
+    }
+
+    #[test]
+    fn skipping_policies_drop_members_that_fail_for_good() {
+        for policy in [FailurePolicy::SkipAndContinue, FailurePolicy::RetryInBackground] {
+            let dropped = dropped_members(
+                policy,
+                failures(&[(0, FeatureErrorType::Timeout), (2, FeatureErrorType::Disconnected)]),
+            )
+            .unwrap();
+
+            assert_eq!(dropped.len(), 1);
+            assert_eq!(dropped[0].0, 2);
+            assert!(dropped_members(policy, Ok(())).unwrap().is_empty());
+        }
+    }
+
+    #[test]
+    fn members_without_a_serial_number_are_lost_for_good() {
+        let mut group = BlinkStickGroup::new();
+        group.push(crate::BlinkStickBuilder::new().open_disabled().unwrap());
+        group.set_failure_policy(FailurePolicy::RetryInBackground);
+        let events = group.subscribe();
+
+        group
+            .handle_failures(failures(&[(0, FeatureErrorType::Disconnected)]))
+            .unwrap();
+
+        assert!(!group.is_connected(0));
+        assert_eq!(
+            events.try_iter().collect::<Vec<_>>(),
+            vec![
+                GroupEvent::MemberDropped {
+                    member: 0,
+                    kind: FeatureErrorType::Disconnected
+                },
+                GroupEvent::MemberLost { member: 0 },
+            ]
+        );
+    }
+}
@@ -0,0 +1,355 @@
+//! A minimal HTTP/1.1 server for the network control layers, so they don't need an async runtime or web framework.
+//!
+//! Connections are served one at a time and closed after every response, which is plenty for the occasional
+//! request of a home automation system or a dashboard. Every client gets `REQUEST_TIMEOUT` to send its whole request,
+//! however slowly it trickles in, so a stalled client holds up the others for a few seconds at most.
+
+use crate::json;
+use crate::{BlinkStick, LedState};
+use std::io::{BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+/// How often `HttpServer::run` checks its stop flag while no connections arrive
+const ACCEPT_POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// How long a client may take to send its whole request, and to take in the response
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The largest request body that is accepted
+const MAX_BODY_BYTES: usize = 64 * 1024;
+
+/// The most bytes the request line and headers together may take
+const MAX_HEADER_BYTES: usize = 8 * 1024;
+
+/// A request received by an `HttpServer`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Request {
+    /// The request method, like `GET` or `POST`
+    pub method: String,
+    /// The request path without its query string
+    pub path: String,
+    /// The request body, empty for requests without one
+    pub body: String,
+}
+
+impl Request {
+    /// Reads a request from a client
+    pub fn read<R: BufRead>(mut input: R) -> std::io::Result<Request> {
+        let invalid = |message: &str| std::io::Error::new(std::io::ErrorKind::InvalidData, message.to_string());
+
+        let mut line = String::new();
+        let mut header_bytes = MAX_HEADER_BYTES;
+        read_header_line(&mut input, &mut line, &mut header_bytes)?;
+        let mut fields = line.split_whitespace();
+        let (method, target) = match (fields.next(), fields.next()) {
+            (Some(method), Some(target)) => (method.to_string(), target),
+            _ => return Err(invalid("invalid request line")),
+        };
+        let path = target.split('?').next().unwrap_or(target).to_string();
+
+        let mut content_length = 0;
+        loop {
+            line.clear();
+            if read_header_line(&mut input, &mut line, &mut header_bytes)? == 0 || line.trim().is_empty() {
+                break;
+            }
+            if let Some((name, value)) = line.split_once(':') {
+                if name.trim().eq_ignore_ascii_case("content-length") {
+                    content_length = value.trim().parse().map_err(|_| invalid("invalid content length"))?;
+                }
+            }
+        }
+
+        if content_length > MAX_BODY_BYTES {
+            return Err(invalid("request body too large"));
+        }
+        let mut body = vec![0; content_length];
+        input.read_exact(&mut body)?;
+
+        Ok(Request {
+            method,
+            path,
+            body: String::from_utf8(body).map_err(|_| invalid("request body is not UTF-8"))?,
+        })
+    }
+}
+
+/// Reads a line of the request head, failing once the head takes more than `remaining` bytes so a client cannot
+/// hold the server on an endless header
+fn read_header_line<R: BufRead>(input: &mut R, line: &mut String, remaining: &mut usize) -> std::io::Result<usize> {
+    let read = std::io::Read::take(input, *remaining as u64).read_line(line)?;
+    *remaining -= read;
+    if *remaining == 0 {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            "request headers too large",
+        ));
+    }
+
+    Ok(read)
+}
+
+/// A response to a `Request`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Response {
+    /// The status code, like 200
+    pub status: u16,
+    /// The media type of the body
+    pub content_type: &'static str,
+    /// The response body
+    pub body: String,
+}
+
+impl Response {
+    /// A successful response with a JSON body
+    pub fn json(body: String) -> Response {
+        Response {
+            status: 200,
+            content_type: "application/json",
+            body,
+        }
+    }
+
+    /// A successful response with a plain text body
+    pub fn text(body: String) -> Response {
+        Response {
+            status: 200,
+            content_type: "text/plain; charset=utf-8",
+            body,
+        }
+    }
+
+    /// An error response with the message as its body
+    pub fn error(status: u16, message: &str) -> Response {
+        Response {
+            status,
+            content_type: "text/plain; charset=utf-8",
+            body: message.to_string(),
+        }
+    }
+
+    /// Writes the response to a client
+    pub fn write<W: Write>(&self, mut output: W) -> std::io::Result<()> {
+        let reason = match self.status {
+            200 => "OK",
+            400 => "Bad Request",
+            404 => "Not Found",
+            405 => "Method Not Allowed",
+            500 => "Internal Server Error",
+            _ => "Error",
+        };
+
+        write!(
+            output,
+            "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            self.status,
+            reason,
+            self.content_type,
+            self.body.len(),
+            self.body
+        )?;
+        output.flush()
+    }
+}
+
+/// Answers a `GET /state` request with the color and label of every led, as
+/// `{"leds":[{"led":0,"label":"build: api","color":"#ff0000"}]}`
+///
+/// # Example
+/// Serves the state of a BlinkStick until the program is stopped
+/// ```no_run
+/// use blinkstick_rs::http::{state_response, HttpServer, Response};
+/// use blinkstick_rs::BlinkStick;
+/// use std::sync::atomic::AtomicBool;
+///
+/// let blinkstick = BlinkStick::new().unwrap();
+/// blinkstick.label(0, "build: api");
+///
+/// let server = HttpServer::bind("127.0.0.1:8080").unwrap();
+/// server.run(&AtomicBool::new(false), |request| match request.path.as_str() {
+///     "/state" => state_response(&blinkstick),
+///     _ => Response::error(404, "not found"),
+/// }).unwrap();
+/// ```
+pub fn state_response(stick: &BlinkStick) -> Response {
+    match stick.get_state() {
+        Ok(state) => Response::json(state_json(&state)),
+        Err(e) => Response::error(500, &e.to_string()),
+    }
+}
+
+fn state_json(state: &[LedState]) -> String {
+    let leds: Vec<String> = state
+        .iter()
+        .map(|led| {
+            format!(
+                "{{\"led\":{},\"label\":{},\"color\":\"{}\"}}",
+                led.led,
+                led.label.as_deref().map_or("null".to_string(), json::string),
+                led.color
+            )
+        })
+        .collect();
+
+    format!("{{\"leds\":[{}]}}", leds.join(","))
+}
+
+/// Listens for HTTP requests and answers them with a handler
+///
+/// # Example
+/// Answers every request with the number of requests so far, until the program is stopped
+/// ```no_run
+/// use blinkstick_rs::http::{HttpServer, Response};
+/// use std::sync::atomic::AtomicBool;
+///
+/// let server = HttpServer::bind("127.0.0.1:8080").unwrap();
+/// let mut requests = 0;
+/// server.run(&AtomicBool::new(false), |_| {
+///     requests += 1;
+///     Response::text(requests.to_string())
+/// }).unwrap();
+/// ```
+pub struct HttpServer {
+    listener: TcpListener,
+}
+
+impl HttpServer {
+    /// Listens for connections on `address`
+    pub fn bind<A: ToSocketAddrs>(address: A) -> std::io::Result<HttpServer> {
+        let listener = TcpListener::bind(address)?;
+        listener.set_nonblocking(true)?;
+
+        Ok(HttpServer { listener })
+    }
+
+    /// The address the server listens on, useful after binding to port 0
+    pub fn local_addr(&self) -> std::io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Answers requests with `handler` until `stop` is set. A client sending an invalid request gets an error
+    /// response and doesn't stop the server.
+    pub fn run<F: FnMut(&Request) -> Response>(&self, stop: &AtomicBool, mut handler: F) -> std::io::Result<()> {
+        while !stop.load(Ordering::Relaxed) {
+            match self.listener.accept() {
+                Ok((stream, _)) => {
+                    // A client that goes away mid-request only affects its own connection
+                    let _ = serve(stream, &mut handler);
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => std::thread::sleep(ACCEPT_POLL_INTERVAL),
+                Err(e) => return Err(e),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn serve<F: FnMut(&Request) -> Response>(stream: TcpStream, handler: &mut F) -> std::io::Result<()> {
+    stream.set_nonblocking(false)?;
+    stream.set_write_timeout(Some(REQUEST_TIMEOUT))?;
+
+    let input = DeadlineReader {
+        stream: &stream,
+        deadline: Instant::now() + REQUEST_TIMEOUT,
+    };
+    let response = match Request::read(BufReader::new(input)) {
+        Ok(request) => handler(&request),
+        Err(e) => Response::error(400, &e.to_string()),
+    };
+    response.write(&stream)
+}
+
+/// Reads from a client until a deadline, instead of giving up only when a single read takes too long, which a client
+/// sending a byte now and then never triggers
+struct DeadlineReader<'a> {
+    stream: &'a TcpStream,
+    deadline: Instant,
+}
+
+impl std::io::Read for DeadlineReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let timed_out = || std::io::Error::new(std::io::ErrorKind::TimedOut, "request took too long");
+        let remaining = self.deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Err(timed_out());
+        }
+
+        self.stream.set_read_timeout(Some(remaining))?;
+        match std::io::Read::read(&mut self.stream, buf) {
+            // Depending on the platform, a read running into its timeout fails with either kind
+            Err(e) if matches!(e.kind(), std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut) => {
+                Err(timed_out())
+            }
+            read => read,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_requests_with_bodies() {
+        let input = "POST /json/state?v=1 HTTP/1.1\r\nHost: stick\r\nContent-Length: 11\r\n\r\n{\"on\":true}";
+        let request = Request::read(input.as_bytes()).unwrap();
+
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.path, "/json/state");
+        assert_eq!(request.body, "{\"on\":true}");
+        assert!(Request::read("garbage\r\n\r\n".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn rejects_endless_headers() {
+        let input = format!("GET / HTTP/1.1\r\nX-Padding: {}\r\n\r\n", "a".repeat(MAX_HEADER_BYTES));
+        assert!(Request::read(input.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn gives_up_on_requests_that_trickle_in() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (stream, _) = listener.accept().unwrap();
+        std::thread::spawn(move || {
+            client.write_all(b"GET / HTTP/1.1\r\n").unwrap();
+            // A header byte every few milliseconds never lets a single read time out
+            while client.write_all(b"a").is_ok() {
+                std::thread::sleep(Duration::from_millis(10));
+            }
+        });
+
+        let started = Instant::now();
+        let input = DeadlineReader {
+            stream: &stream,
+            deadline: started + Duration::from_millis(200),
+        };
+        let error = Request::read(BufReader::new(input)).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::TimedOut);
+        assert!(started.elapsed() < Duration::from_secs(2));
+    }
+
+    #[test]
+    fn writes_labelled_state() {
+        let state = [
+            LedState {
+                led: 0,
+                label: Some("build: \"api\"".to_string()),
+                color: crate::Color { r: 255, g: 0, b: 16 },
+            },
+            LedState {
+                led: 1,
+                label: None,
+                color: crate::COLOR_OFF,
+            },
+        ];
+
+        assert_eq!(
+            state_json(&state),
+            r##"{"leds":[{"led":0,"label":"build: \"api\"","color":"#ff0010"},{"led":1,"label":null,"color":"#000000"}]}"##
+        );
+    }
+}
@@ -0,0 +1,282 @@
+//! Just enough JSON for the network control layers and the diagnostics, which can't assume a serde dependency
+
+// Only the WLED layer parses JSON, the others just write it
+#![cfg_attr(not(feature = "wled"), allow(dead_code))]
+
+/// How deeply arrays and objects may be nested, so a hostile document cannot overflow the stack
+const MAX_DEPTH: usize = 64;
+
+/// A parsed JSON value
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    /// Parses a complete JSON document
+    pub(crate) fn parse(text: &str) -> Result<Json, &'static str> {
+        let mut parser = Parser {
+            text: text.as_bytes(),
+            position: 0,
+            depth: 0,
+        };
+        let value = parser.value()?;
+        parser.skip_whitespace();
+        if parser.position != parser.text.len() {
+            return Err("trailing characters after JSON value");
+        }
+
+        Ok(value)
+    }
+
+    /// The member `key` of an object
+    pub(crate) fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(members) => members.iter().find(|(name, _)| name == key).map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn as_f64(&self) -> Option<f64> {
+        match self {
+            Json::Number(number) => Some(*number),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn as_bool(&self) -> Option<bool> {
+        match self {
+            Json::Bool(boolean) => Some(*boolean),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::String(string) => Some(string),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn as_array(&self) -> Option<&[Json]> {
+        match self {
+            Json::Array(values) => Some(values),
+            _ => None,
+        }
+    }
+}
+
+/// Writes `value` as a JSON string
+pub(crate) fn string(value: &str) -> String {
+    let mut json = String::with_capacity(value.len() + 2);
+    json.push('"');
+    for character in value.chars() {
+        match character {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            character if (character as u32) < 0x20 => json.push_str(&format!("\\u{:04x}", character as u32)),
+            character => json.push(character),
+        }
+    }
+    json.push('"');
+
+    json
+}
+
+struct Parser<'a> {
+    text: &'a [u8],
+    position: usize,
+    depth: usize,
+}
+
+impl Parser<'_> {
+    fn skip_whitespace(&mut self) {
+        while self.text.get(self.position).is_some_and(u8::is_ascii_whitespace) {
+            self.position += 1;
+        }
+    }
+
+    fn peek(&mut self) -> Option<u8> {
+        self.skip_whitespace();
+        self.text.get(self.position).copied()
+    }
+
+    fn expect(&mut self, literal: &str, value: Json) -> Result<Json, &'static str> {
+        if self.text[self.position..].starts_with(literal.as_bytes()) {
+            self.position += literal.len();
+            Ok(value)
+        } else {
+            Err("invalid literal")
+        }
+    }
+
+    fn value(&mut self) -> Result<Json, &'static str> {
+        match self.peek().ok_or("unexpected end of JSON")? {
+            b'n' => self.expect("null", Json::Null),
+            b't' => self.expect("true", Json::Bool(true)),
+            b'f' => self.expect("false", Json::Bool(false)),
+            b'"' => self.string().map(Json::String),
+            b'[' => self.nested(Parser::array),
+            b'{' => self.nested(Parser::object),
+            _ => self.number(),
+        }
+    }
+
+    /// Parses an array or object one level deeper, failing past `MAX_DEPTH`
+    fn nested(&mut self, parse: fn(&mut Self) -> Result<Json, &'static str>) -> Result<Json, &'static str> {
+        if self.depth == MAX_DEPTH {
+            return Err("JSON nested too deeply");
+        }
+
+        self.depth += 1;
+        let value = parse(self);
+        self.depth -= 1;
+        value
+    }
+
+    fn number(&mut self) -> Result<Json, &'static str> {
+        let start = self.position;
+        while self
+            .text
+            .get(self.position)
+            .is_some_and(|byte| byte.is_ascii_digit() || b"+-.eE".contains(byte))
+        {
+            self.position += 1;
+        }
+
+        std::str::from_utf8(&self.text[start..self.position])
+            .ok()
+            .and_then(|number| number.parse().ok())
+            .map(Json::Number)
+            .ok_or("invalid number")
+    }
+
+    fn string(&mut self) -> Result<String, &'static str> {
+        // Skips the opening quote
+        self.position += 1;
+        let mut string = Vec::new();
+        loop {
+            let byte = *self.text.get(self.position).ok_or("unterminated string")?;
+            self.position += 1;
+            match byte {
+                b'"' => break,
+                b'\\' => {
+                    let escaped = *self.text.get(self.position).ok_or("unterminated string")?;
+                    self.position += 1;
+                    match escaped {
+                        b'n' => string.push(b'\n'),
+                        b't' => string.push(b'\t'),
+                        b'r' => string.push(b'\r'),
+                        b'b' => string.push(0x08),
+                        b'f' => string.push(0x0c),
+                        b'u' => {
+                            let hex = self
+                                .text
+                                .get(self.position..self.position + 4)
+                                .ok_or("invalid escape")?;
+                            self.position += 4;
+                            let code = std::str::from_utf8(hex)
+                                .ok()
+                                .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                                .and_then(char::from_u32)
+                                .unwrap_or(char::REPLACEMENT_CHARACTER);
+                            string.extend_from_slice(code.encode_utf8(&mut [0; 4]).as_bytes());
+                        }
+                        other => string.push(other),
+                    }
+                }
+                other => string.push(other),
+            }
+        }
+
+        String::from_utf8(string).map_err(|_| "string is not UTF-8")
+    }
+
+    fn array(&mut self) -> Result<Json, &'static str> {
+        self.position += 1;
+        let mut values = Vec::new();
+        if self.peek() == Some(b']') {
+            self.position += 1;
+            return Ok(Json::Array(values));
+        }
+
+        loop {
+            values.push(self.value()?);
+            match self.peek() {
+                Some(b',') => self.position += 1,
+                Some(b']') => {
+                    self.position += 1;
+                    return Ok(Json::Array(values));
+                }
+                _ => return Err("expected , or ] in array"),
+            }
+        }
+    }
+
+    fn object(&mut self) -> Result<Json, &'static str> {
+        self.position += 1;
+        let mut members = Vec::new();
+        if self.peek() == Some(b'}') {
+            self.position += 1;
+            return Ok(Json::Object(members));
+        }
+
+        loop {
+            if self.peek() != Some(b'"') {
+                return Err("expected a member name");
+            }
+            let name = self.string()?;
+            if self.peek() != Some(b':') {
+                return Err("expected : after member name");
+            }
+            self.position += 1;
+            members.push((name, self.value()?));
+
+            match self.peek() {
+                Some(b',') => self.position += 1,
+                Some(b'}') => {
+                    self.position += 1;
+                    return Ok(Json::Object(members));
+                }
+                _ => return Err("expected , or } in object"),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_documents_nested_too_deeply() {
+        assert!(Json::parse(&format!("{}{}", "[".repeat(MAX_DEPTH), "]".repeat(MAX_DEPTH))).is_ok());
+        assert_eq!(Json::parse(&"[".repeat(60000)), Err("JSON nested too deeply"));
+    }
+
+    #[test]
+    fn parses_nested_documents() {
+        let json =
+            Json::parse(r#" {"on": true, "seg": [{"col": [[255, 0, 0]]}], "name": "a\"bA", "x": null} "#).unwrap();
+
+        assert_eq!(json.get("on"), Some(&Json::Bool(true)));
+        assert_eq!(json.get("name").and_then(Json::as_str), Some("a\"bA"));
+        let color = &json.get("seg").unwrap().as_array().unwrap()[0].get("col").unwrap();
+        assert_eq!(
+            color.as_array().unwrap()[0].as_array().unwrap()[0].as_f64(),
+            Some(255.0)
+        );
+        assert!(Json::parse("{\"on\": }").is_err());
+        assert!(Json::parse("[1, 2] 3").is_err());
+        assert_eq!(
+            Json::parse(&string("say \"hi\"\n")),
+            Ok(Json::String("say \"hi\"\n".to_string()))
+        );
+    }
+}
@@ -0,0 +1,66 @@
+use crate::{BlinkStick, Color, FeatureError};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// The state of a single led: its color and the label it was given, see `BlinkStick::label`
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct LedState {
+    /// The zero-indexed led number
+    pub led: u8,
+    /// What the led indicates, if it was labelled
+    pub label: Option<String>,
+    /// The color the led shows
+    pub color: Color,
+}
+
+impl BlinkStick {
+    /// Labels a led with what it indicates, like `"build: api"`, so state queries name it alongside its color.
+    /// Useful when one BlinkStick multiplexes many independent indicators.
+    ///
+    /// # Panics
+    /// The call to label will panic if the specified `led` is out of bounds for the BlinkStick device.
+    ///
+    /// # Example
+    /// ```
+    /// use blinkstick_rs::BlinkStick;
+    ///
+    /// let blinkstick = BlinkStick::new().unwrap();
+    /// blinkstick.label(0, "build: api");
+    ///
+    /// assert_eq!(blinkstick.get_state().unwrap()[0].label.as_deref(), Some("build: api"));
+    /// ```
+    pub fn label(&self, led: u8, label: &str) {
+        if led >= self.max_leds {
+            panic!("Led {} is out of bounds for Blinkstick device", led)
+        }
+
+        self.labels.lock().unwrap().insert(led, label.to_string());
+    }
+
+    /// Removes the label of a led
+    pub fn clear_label(&self, led: u8) {
+        self.labels.lock().unwrap().remove(&led);
+    }
+
+    /// The label of a led, if it has one
+    pub fn led_label(&self, led: u8) -> Option<String> {
+        self.labels.lock().unwrap().get(&led).cloned()
+    }
+
+    /// Reads the color of every led from the device, together with its label
+    pub fn get_state(&self) -> Result<Vec<LedState>, FeatureError> {
+        let colors = self.get_all_led_colors()?;
+        let labels = self.labels.lock().unwrap();
+
+        Ok(colors
+            .into_iter()
+            .enumerate()
+            .map(|(led, color)| LedState {
+                led: led as u8,
+                label: labels.get(&(led as u8)).cloned(),
+                color,
+            })
+            .collect())
+    }
+}
@@ -0,0 +1,90 @@
+use crate::{BlinkStick, FeatureError, FeatureErrorType};
+
+impl BlinkStick {
+    /// Places every led at a physical position, so spatial effects follow the real shape of a strip wrapped
+    /// around a monitor or an object instead of the order of its leds
+    ///
+    /// The coordinates are in any unit, as long as both axes share it.
+    ///
+    /// # Arguments
+    /// * `positions` - The `(x, y)` position of every led, starting with the zeroth. Positions that are NaN or
+    ///   infinite fail with `FeatureErrorType::InvalidPosition`.
+    ///
+    /// # Example
+    /// Lays out the leds of a Flex strip around the edge of a monitor, 10 along the top and bottom and 6 up the sides
+    /// ```
+    /// use blinkstick_rs::BlinkStick;
+    ///
+    /// let blinkstick = BlinkStick::builder().leds(32).open().unwrap();
+    /// let top = (0..10).map(|led| (led as f32 * 6.0, 0.0));
+    /// let right = (0..6).map(|led| (60.0, led as f32 * 5.0));
+    /// let bottom = (0..10).map(|led| (60.0 - led as f32 * 6.0, 30.0));
+    /// let left = (0..6).map(|led| (0.0, 30.0 - led as f32 * 5.0));
+    ///
+    /// let positions: Vec<(f32, f32)> = top.chain(right).chain(bottom).chain(left).collect();
+    /// blinkstick.set_layout(&positions).unwrap();
+    /// ```
+    pub fn set_layout(&self, positions: &[(f32, f32)]) -> Result<(), FeatureError> {
+        check_positions(positions, self.max_leds as usize)?;
+
+        *self.layout.lock().unwrap() = Some(positions.to_vec());
+        Ok(())
+    }
+
+    /// Forgets the layout, placing the leds in a line again
+    pub fn clear_layout(&self) {
+        *self.layout.lock().unwrap() = None;
+    }
+
+    /// The `(x, y)` position of every led, see `set_layout`. Without a layout the leds sit one unit apart on the
+    /// x axis, in the order of their numbers.
+    pub fn layout(&self) -> Vec<(f32, f32)> {
+        match &*self.layout.lock().unwrap() {
+            Some(positions) => positions.clone(),
+            None => (0..self.max_leds).map(|led| (led as f32, 0.0)).collect(),
+        }
+    }
+}
+
+/// Fails unless there is a position for each of `led_count` leds and every position is a finite point
+fn check_positions(positions: &[(f32, f32)], led_count: usize) -> Result<(), FeatureError> {
+    if positions.len() != led_count {
+        return Err(FeatureError {
+            kind: FeatureErrorType::FrameLength {
+                expected: led_count,
+                actual: positions.len(),
+            },
+        });
+    }
+
+    match positions.iter().position(|(x, y)| !x.is_finite() || !y.is_finite()) {
+        Some(led) => Err(FeatureError {
+            kind: FeatureErrorType::InvalidPosition { led },
+        }),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn layouts_need_a_finite_position_for_every_led() {
+        assert!(check_positions(&[(0.0, 0.0), (1.5, -2.0)], 2).is_ok());
+        assert_eq!(
+            check_positions(&[(0.0, 0.0)], 2).unwrap_err().kind,
+            FeatureErrorType::FrameLength { expected: 2, actual: 1 }
+        );
+        assert_eq!(
+            check_positions(&[(0.0, 0.0), (f32::NAN, 1.0)], 2).unwrap_err().kind,
+            FeatureErrorType::InvalidPosition { led: 1 }
+        );
+        assert_eq!(
+            check_positions(&[(0.0, f32::INFINITY), (1.0, 1.0)], 2)
+                .unwrap_err()
+                .kind,
+            FeatureErrorType::InvalidPosition { led: 0 }
+        );
+    }
+}
@@ -1,19 +1,177 @@
 //! This crate provides a rust toolkit for interacting with the BlinkStick device.
 //! The implementation should support all types of BlinkStick devices. It was however
-//! implemented and tested using a BlinkStick Square. If a BlinkStick device acts incorrectly, please contact me.
+//! implemented and tested using a BlinkStick Square. If a BlinkStick device acts incorrectly, please contact me and
+//! attach the file written by `BlinkStick::export_diagnostics`.
 //! Requires libusb when using blinkstick-rs on Linux machines, check README for more information.
 
+#[cfg(feature = "bevy")]
+extern crate bevy;
+extern crate blinkstick_core;
+#[cfg(feature = "stream")]
+extern crate futures_core;
+#[cfg(feature = "plugins")]
+extern crate libloading;
+#[cfg(feature = "rand")]
 extern crate rand;
+#[cfg(feature = "serde")]
+extern crate serde;
+#[cfg(feature = "rand")]
 use rand::Rng;
 
+use crate::wire::{encode_channel_led_report, encode_channel_reports, LEDS_REPORT_ID};
 use crate::FeatureErrorType::{Get, Send};
+use blinkstick_core::COLOR_OFF;
+use std::collections::HashMap;
 use std::error::Error;
+use std::ffi::CString;
 use std::fmt::Formatter;
-use std::ops::{Div, Sub};
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+use std::sync::{Arc, Mutex};
 use std::{time::Duration, time::Instant};
 
 // extern crate hidapi;
 
+mod alias;
+mod ambient;
+#[cfg(any(feature = "effects", feature = "animator"))]
+mod animation;
+#[cfg(feature = "animator")]
+mod animator;
+#[cfg(feature = "bevy")]
+pub mod bevy_plugin;
+mod builder;
+mod calibration;
+mod channel;
+mod channel_order;
+mod clock;
+mod composite;
+mod config;
+#[cfg(feature = "daemon")]
+pub mod daemon;
+mod device;
+mod device_events;
+mod diagnostics;
+mod dimming;
+mod discovery;
+mod dither;
+#[cfg(any(feature = "effects", feature = "animator"))]
+pub mod effects;
+#[cfg(feature = "animator")]
+mod event;
+mod frame;
+mod global;
+mod group;
+#[cfg(feature = "http")]
+pub mod http;
+mod json;
+mod label;
+mod layout;
+mod mask;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+mod mode;
+mod observer;
+#[cfg(feature = "osc")]
+pub mod osc;
+#[cfg(any(feature = "effects", feature = "animator"))]
+mod pipeline;
+#[cfg(feature = "plugins")]
+pub mod plugins;
+mod preset;
+#[cfg(feature = "animator")]
+mod program;
+mod proxy;
+mod random;
+mod reader;
+#[cfg(feature = "realtime")]
+pub mod realtime;
+mod reconnect;
+mod recovery;
+mod response;
+#[cfg(feature = "animator")]
+mod runtime;
+mod scene;
+#[cfg(feature = "animator")]
+mod schedule;
+pub mod sequence;
+mod sink;
+mod smoother;
+mod state;
+#[cfg(feature = "animator")]
+mod status;
+#[cfg(any(feature = "effects", feature = "animator"))]
+mod stepper;
+#[cfg(feature = "stream")]
+pub mod stream;
+mod strip;
+mod throttle;
+mod timing;
+#[cfg(feature = "animator")]
+mod transient;
+mod transport;
+mod tuning;
+mod variant;
+mod verify;
+mod watch;
+pub mod wire;
+#[cfg(feature = "wled")]
+pub mod wled;
+
+pub use alias::DeviceAliases;
+pub use ambient::{AdaptiveBrightness, AmbientLight};
+#[cfg(any(feature = "effects", feature = "animator"))]
+pub use animation::{AnimationIter, AssembleOrder};
+#[cfg(feature = "animator")]
+pub use animator::{AnimationEvent, AnimationHandle, AnimationOutcome, Animator};
+pub use blinkstick_core::{palette, render, Color, LedFrame, WipeDirection};
+pub use builder::{BlinkStickBuilder, DEFAULT_MAX_STROBE_FREQUENCY};
+pub use calibration::ColorProfile;
+pub use channel_order::ChannelOrder;
+pub use clock::{Clock, ManualClock, SystemClock};
+pub use composite::CompositeStrip;
+pub use config::{Config, ConfigError};
+pub use device::{DeviceCall, LedDevice, NoopBlinkStick};
+pub use device_events::DeviceEvent;
+pub use dimming::DimmingSchedule;
+pub use discovery::{DeviceDescriptor, UsbLocation};
+pub use dither::Dithering;
+#[cfg(feature = "animator")]
+pub use event::{EventBus, LightEvent, Severity};
+pub use group::{BlinkStickGroup, FailurePolicy, GroupError, GroupEvent};
+pub use label::LedState;
+pub use mode::Mode;
+pub use observer::{ObserverId, StateEvent, StateObserver};
+#[cfg(any(feature = "effects", feature = "animator"))]
+pub use pipeline::{FilterConfig, Pipeline, PipelineConfig, PipelineError, SinkConfig, SourceConfig};
+pub use preset::DevicePreset;
+#[cfg(feature = "animator")]
+pub use program::Program;
+pub use proxy::{CapturedReport, ProxyTransport, ReportDirection};
+pub use reader::{parse_frame, FrameReadError, FrameReader};
+pub use reconnect::{Backoff, ConnectionState, ReconnectingBlinkStick};
+pub use render::Easing;
+pub use response::ResponseCurves;
+#[cfg(feature = "animator")]
+pub use runtime::{FinalState, Runtime, ShutdownError};
+pub use scene::Transition;
+#[cfg(feature = "animator")]
+pub use schedule::{Schedule, ScheduleFrames};
+pub use sink::{FrameRecorder, FrameSink};
+pub use smoother::{SmoothedSink, Smoother};
+pub use state::DeviceState;
+#[cfg(feature = "animator")]
+pub use status::{Status, StatusLight, StatusStyle};
+#[cfg(any(feature = "effects", feature = "animator"))]
+pub use stepper::EffectStepper;
+pub use strip::{StripLayout, StripSegment};
+pub use throttle::DEFAULT_MIN_REPORT_GAP;
+pub use timing::{Fps, StepEvery, StepRate, TimingReport};
+pub use tuning::{Parameter, Parameters};
+pub use variant::Variant;
+pub use verify::VerificationStats;
+pub use watch::ColorChanges;
+pub use wire::ReportError;
+
 const VENDOR_ID: u16 = 0x20a0;
 const PRODUCT_ID: u16 = 0x41e5;
 
@@ -24,36 +182,211 @@ pub struct FeatureError {
     pub kind: FeatureErrorType,
 }
 
-#[derive(Debug, Eq, PartialEq)]
+/// New kinds of errors may be added in minor releases, so matches need a wildcard arm
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[non_exhaustive]
 pub enum FeatureErrorType {
     Get,
     Send,
+    Disconnected,
+    Timeout,
+    LedOutOfBounds { led: usize, max_leds: usize },
+    FrameLength { expected: usize, actual: usize },
+    UnknownScene(String),
+    UnknownAlias(String),
+    MalformedReport(ReportError),
+    NotReadable,
+    VerificationFailed,
+    NotABlinkStick { vendor_id: u16, product_id: u16 },
+    ChannelCount { channels: usize, max_channels: u8 },
+    InvalidPosition { led: usize },
+}
+
+impl FeatureError {
+    /// Returns `true` if the operation failed for a reason that may go away when it is retried, like a timeout or
+    /// a busy device. Returns `false` if retrying cannot help, for example because the device was unplugged and
+    /// has to be opened again.
+    ///
+    /// # Example
+    /// Retries setting a color for as long as the failure is transient
+    /// ```
+    /// use blinkstick_rs::{BlinkStick, Color};
+    /// let blinkstick = BlinkStick::new().unwrap();
+    ///
+    /// let mut result = blinkstick.set_all_leds_color(Color {r: 0, g: 50, b: 0});
+    /// while let Err(e) = &result {
+    ///     if !e.is_transient() {
+    ///         break;
+    ///     }
+    ///     result = blinkstick.set_all_leds_color(Color {r: 0, g: 50, b: 0});
+    /// }
+    /// ```
+    pub fn is_transient(&self) -> bool {
+        match self.kind {
+            FeatureErrorType::Get
+            | FeatureErrorType::Send
+            | FeatureErrorType::Timeout
+            | FeatureErrorType::VerificationFailed => true,
+            FeatureErrorType::Disconnected
+            | FeatureErrorType::LedOutOfBounds { .. }
+            | FeatureErrorType::FrameLength { .. }
+            | FeatureErrorType::UnknownScene(_)
+            | FeatureErrorType::UnknownAlias(_)
+            | FeatureErrorType::MalformedReport(_)
+            | FeatureErrorType::NotReadable
+            | FeatureErrorType::NotABlinkStick { .. }
+            | FeatureErrorType::ChannelCount { .. }
+            | FeatureErrorType::InvalidPosition { .. } => false,
+        }
+    }
+
+    /// Returns `true` if the device is still present but stopped answering, which `BlinkStick::reset` may fix by
+    /// reopening it
+    pub fn is_wedged(&self) -> bool {
+        self.kind == FeatureErrorType::Timeout
+    }
+
+    /// Classifies a failed hid transfer, `kind` being the error reported when the device is still present
+    fn from_hid_error(kind: FeatureErrorType, error: &hidapi::HidError) -> FeatureError {
+        let disconnected = match error {
+            hidapi::HidError::IoError { error } => matches!(
+                error.kind(),
+                std::io::ErrorKind::NotFound
+                    | std::io::ErrorKind::NotConnected
+                    | std::io::ErrorKind::BrokenPipe
+                    | std::io::ErrorKind::ConnectionReset
+            ),
+            hidapi::HidError::HidApiError { message } => {
+                let message = message.to_lowercase();
+                ["no such device", "not connected", "disconnected", "not configured"]
+                    .iter()
+                    .any(|gone| message.contains(gone))
+            }
+            _ => false,
+        };
+
+        if disconnected {
+            FeatureError {
+                kind: FeatureErrorType::Disconnected,
+            }
+        } else {
+            FeatureError { kind }
+        }
+    }
 }
 
 impl std::fmt::Display for FeatureError {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        if self.kind == FeatureErrorType::Get {
-            write!(f, "Failed retrieving data from BlinkStick device")
-        } else {
-            write!(f, "Failed setting data for BlinkStick device")
+        match &self.kind {
+            FeatureErrorType::Get => write!(f, "Failed retrieving data from BlinkStick device"),
+            FeatureErrorType::Send => write!(f, "Failed setting data for BlinkStick device"),
+            FeatureErrorType::Disconnected => write!(f, "BlinkStick device is not connected"),
+            FeatureErrorType::Timeout => write!(f, "BlinkStick device did not respond in time"),
+            FeatureErrorType::LedOutOfBounds { led, max_leds } => write!(
+                f,
+                "BlinkStick device does not contain led {}. Valid leds are 0-{} (zero-indexed)",
+                led,
+                max_leds.saturating_sub(1)
+            ),
+            FeatureErrorType::FrameLength { expected, actual } => write!(
+                f,
+                "Expected a color for each of the {} leds of the BlinkStick device, got {}",
+                expected, actual
+            ),
+            FeatureErrorType::UnknownScene(name) => write!(f, "No scene named \"{}\" has been saved", name),
+            FeatureErrorType::UnknownAlias(alias) => write!(f, "No device is known by the alias \"{}\"", alias),
+            FeatureErrorType::MalformedReport(error) => {
+                write!(f, "BlinkStick device sent a malformed report: {}", error)
+            }
+            FeatureErrorType::NotReadable => write!(f, "BlinkStick device is write-only and cannot report its colors"),
+            FeatureErrorType::VerificationFailed => {
+                write!(f, "BlinkStick device kept reporting other colors than were written")
+            }
+            FeatureErrorType::NotABlinkStick { vendor_id, product_id } => write!(
+                f,
+                "The device {:04x}:{:04x} is not a BlinkStick device",
+                vendor_id, product_id
+            ),
+            FeatureErrorType::ChannelCount { channels, max_channels } => write!(
+                f,
+                "BlinkStick device drives leds on {} channel(s), {} were configured",
+                max_channels, channels
+            ),
+            FeatureErrorType::InvalidPosition { led } => {
+                write!(f, "The position of led {} is not made of finite numbers", led)
+            }
         }
     }
 }
 
 impl Error for FeatureError {}
 
-#[derive(Debug, Clone, Copy, PartialEq)]
-pub struct Color {
-    pub r: u8,
-    pub g: u8,
-    pub b: u8,
+impl From<ReportError> for FeatureError {
+    fn from(error: ReportError) -> FeatureError {
+        FeatureError {
+            kind: FeatureErrorType::MalformedReport(error),
+        }
+    }
 }
-const COLOR_OFF: Color = Color { r: 0, g: 0, b: 0 };
 
 pub struct BlinkStick {
-    device: hidapi::HidDevice,
+    device: Mutex<transport::Transport>,
+    /// The hid path the device was opened at, to reopen it
+    path: Mutex<Option<CString>>,
+    variant: Variant,
+    led_override: Option<u8>,
+    /// The number of leds on each channel, set with `BlinkStickBuilder::channels`
+    channel_layout: Option<Vec<u8>>,
+    /// Whether the leds were reset when the device was opened, see `BlinkStickBuilder::reset_on_open`
+    reset_on_open: bool,
+    /// The colors shown when the device was opened, see `BlinkStickBuilder::scene`
+    opening_scene: Option<Vec<Color>>,
+    /// Whether the latency was measured when the device was opened, see `BlinkStickBuilder::calibrate_latency`
+    calibrate_latency: bool,
     pub max_leds: u8,
     report_length: usize,
+    #[cfg(feature = "effects")]
+    max_strobe_frequency: f32,
+    brightness: f32,
+    gamma: f32,
+    /// Rounds the colors written to the device, see `set_dithering`
+    quantizer: Mutex<dither::Quantizer>,
+    channel_order: Mutex<ChannelOrder>,
+    color_profile: Mutex<ColorProfile>,
+    response_curves: Mutex<Option<ResponseCurves>>,
+    /// Cleared by a `Runtime` that leaves the device in its final state itself
+    turn_off_on_drop: AtomicBool,
+    scenes: Mutex<HashMap<String, LedFrame>>,
+    labels: Mutex<HashMap<u8, String>>,
+    masked_leds: Mutex<Vec<u8>>,
+    layout: Mutex<Option<Vec<(f32, f32)>>>,
+    dimming_schedule: Mutex<Option<DimmingSchedule>>,
+    ambient_light: Mutex<Option<AmbientLight>>,
+    io_timeout: Mutex<Option<Duration>>,
+    /// The time a single feature report takes, once measured
+    report_latency: Mutex<Option<Duration>>,
+    write_only: AtomicBool,
+    /// Whether the device is in `Mode::Inverse`
+    inverted: AtomicBool,
+    throttle: throttle::ReportThrottle,
+    /// Held while the reports of a frame are sent, if frames are serialized
+    frame_lock: channel::FrameLock,
+    /// The last color written to every led, and the dimmed color the device was actually sent
+    written_colors: Mutex<Vec<(Color, Color)>>,
+    /// The brightness the last write was scaled by, `None` until the first write
+    written_brightness: Mutex<Option<f32>>,
+    event_listeners: device_events::EventListeners,
+    observers: observer::Observers,
+    diagnostics: Arc<diagnostics::Diagnostics>,
+    /// Mirrors the reports exchanged with the device, see `set_proxy`
+    proxy: Mutex<Option<ProxyTransport>>,
+    /// How often a frame is written again when its read back differs, 0 if writes aren't verified
+    verify_retries: AtomicU8,
+    verification: verify::VerificationCounters,
+    /// The preset read from the device when it was opened, see `BlinkStickBuilder::apply_device_preset`
+    device_preset: Option<DevicePreset>,
+    #[cfg(feature = "metrics")]
+    metrics: Arc<metrics::Counters>,
 }
 
 unsafe impl std::marker::Send for BlinkStick {}
@@ -61,6 +394,10 @@ unsafe impl std::marker::Sync for BlinkStick {}
 
 impl Drop for BlinkStick {
     fn drop(&mut self) {
+        if !*self.turn_off_on_drop.get_mut() {
+            return;
+        }
+
         match self.set_all_leds_color(COLOR_OFF) {
             Ok(()) => (),
             Err(e) => eprintln!("Could not drop due to error: {}", e),
@@ -79,34 +416,177 @@ impl BlinkStick {
     /// # Panics
     /// When there is no connected BlinkStick device, the call to new will panic.
     pub fn new() -> Result<BlinkStick, FeatureError> {
-        let api = hidapi::HidApi::new().expect("Could not create a hid api");
-
-        match api.open(VENDOR_ID, PRODUCT_ID) {
-            Ok(device) => {
-                // Determines the number of leds for a device. The BlinkStick Flex has 32 leds with 3 channels, which is the maximum of any device.
-                // 32 * 3 + 2 = 98 bytes
-                let mut buf: [u8; REPORT_ARRAY_BYTES] = [0; REPORT_ARRAY_BYTES];
-                buf[0] = 0x6;
-                let bytes_read = device.get_feature_report(&mut buf).unwrap();
-
-                // First two bytes are meta information
-                let max_leds = ((bytes_read - 2) / 3) as u8;
-                let report_length = ((max_leds * 3) + 2).into();
-
-                let blinkstick = BlinkStick {
-                    device,
-                    max_leds,
-                    report_length,
-                };
-
-                // If the light is already on, we want to reset it before giving the user a way to interact with it.
-                blinkstick.set_all_leds_color(COLOR_OFF)?;
-
-                Ok(blinkstick)
-            }
-            Err(_) => Err(FeatureError {
-                kind: FeatureErrorType::Get,
+        BlinkStickBuilder::new().open()
+    }
+
+    /// Returns a builder to configure how the `BlinkStick Device` is opened
+    pub fn builder() -> BlinkStickBuilder {
+        BlinkStickBuilder::new()
+    }
+
+    /// Opens communication with a `BlinkStick Device` through an existing hid api, see `BlinkStickBuilder::open_with_api`
+    pub fn open_with_api(api: &hidapi::HidApi) -> Result<BlinkStick, FeatureError> {
+        BlinkStickBuilder::new().open_with_api(api)
+    }
+
+    /// Opens communication with the `BlinkStick Device` with the given serial number, see
+    /// `BlinkStickBuilder::open_by_serial`
+    pub fn open_by_serial(serial: &str) -> Result<BlinkStick, FeatureError> {
+        BlinkStickBuilder::new().open_by_serial(serial)
+    }
+
+    /// Opens communication with the `BlinkStick Device` at a platform specific hid path, see
+    /// `BlinkStickBuilder::open_by_path`
+    ///
+    /// # Example
+    /// ```
+    /// use blinkstick_rs::BlinkStick;
+    ///
+    /// let blinkstick = BlinkStick::open_by_path("/dev/hidraw0").unwrap();
+    /// ```
+    pub fn open_by_path(path: &str) -> Result<BlinkStick, FeatureError> {
+        BlinkStickBuilder::new().open_by_path(path)
+    }
+
+    /// Takes over communication with a BlinkStick that was already opened through hidapi
+    ///
+    /// # Example
+    /// ```
+    /// use blinkstick_rs::BlinkStick;
+    ///
+    /// let api = hidapi::HidApi::new().unwrap();
+    /// let device = api.open(0x20a0, 0x41e5).unwrap();
+    /// let blinkstick = BlinkStick::from_hid_device(device).unwrap();
+    /// ```
+    pub fn from_hid_device(device: hidapi::HidDevice) -> Result<BlinkStick, FeatureError> {
+        BlinkStickBuilder::new().from_hid_device(device)
+    }
+
+    /// Bounds how long a single read or write may spend retrying a device that does not respond, or removes the
+    /// bound with `None`. Operations that run out of time fail with `FeatureErrorType::Timeout`.
+    ///
+    /// Every transfer is attempted at least once, so the worst-case latency of a call is the timeout plus the
+    /// duration of a single transfer.
+    ///
+    /// # Example
+    /// Keeps every call from a UI thread below roughly 50 milliseconds
+    /// ```
+    /// use blinkstick_rs::{BlinkStick, Color};
+    /// let blinkstick = BlinkStick::new().unwrap();
+    ///
+    /// blinkstick.set_io_timeout(Some(std::time::Duration::from_millis(50)));
+    /// blinkstick.set_led_color(0, Color {r: 0, g: 0, b: 50}).unwrap();
+    /// ```
+    pub fn set_io_timeout(&self, timeout: Option<Duration>) {
+        *self.io_timeout.lock().unwrap() = timeout;
+    }
+
+    /// The longest time a single read or write may spend retrying, if bounded
+    pub fn io_timeout(&self) -> Option<Duration> {
+        *self.io_timeout.lock().unwrap()
+    }
+
+    /// Treats the device as write-only, for leds that cannot be read back like WS2812 strips on a BlinkStick Pro.
+    ///
+    /// Effects and scenes then start from the colors last written instead of reading the device, and
+    /// `get_all_led_colors` and the other explicit reads fail with `FeatureErrorType::NotReadable`.
+    ///
+    /// # Example
+    /// Pulses a WS2812 strip without reading it
+    #[cfg_attr(feature = "effects", doc = "```")]
+    #[cfg_attr(not(feature = "effects"), doc = "```ignore")]
+    /// use blinkstick_rs::{AnimationIter, BlinkStick, Color, FeatureErrorType, Mode};
+    ///
+    /// let mut blinkstick = BlinkStick::new().unwrap();
+    /// blinkstick.set_mode(Mode::Ws2812).unwrap();
+    /// blinkstick.set_write_only(true);
+    ///
+    /// let blue = vec![Color {r: 0, g: 0, b: 50}; blinkstick.max_leds as usize];
+    /// let pulse = AnimationIter::pulse(blinkstick.get_color_vec(), blue, std::time::Duration::from_secs(1), 25);
+    /// blinkstick.play_animation(pulse).unwrap();
+    /// assert_eq!(blinkstick.get_all_led_colors().unwrap_err().kind, FeatureErrorType::NotReadable);
+    /// ```
+    pub fn set_write_only(&self, write_only: bool) {
+        self.write_only.store(write_only, Ordering::Relaxed);
+    }
+
+    /// Returns `true` if the device is treated as write-only, see `set_write_only`
+    pub fn is_write_only(&self) -> bool {
+        self.write_only.load(Ordering::Relaxed)
+    }
+
+    /// The highest number of flashes per second the device may strobe at, to pass to `AnimationIter::strobe`, see
+    /// `BlinkStickBuilder::max_strobe_frequency`
+    #[cfg(feature = "effects")]
+    pub fn max_strobe_frequency(&self) -> f32 {
+        self.max_strobe_frequency
+    }
+
+    /// The colors the leds show, read from the device or, if it is write-only, the colors last written
+    fn current_colors(&self) -> Result<Vec<Color>, FeatureError> {
+        if self.is_write_only() {
+            return Ok(self
+                .written_colors
+                .lock()
+                .unwrap()
+                .iter()
+                .map(|(color, _)| *color)
+                .collect());
+        }
+
+        self.get_all_led_colors()
+    }
+
+    /// The serial number of the device, such as `BS000001-3.0`, if it reports one
+    pub fn serial_number(&self) -> Option<String> {
+        self.device
+            .lock()
+            .unwrap()
+            .get_serial_number_string()
+            .ok()
+            .flatten()
+            .filter(|serial| !serial.is_empty())
+    }
+
+    /// Checks that every led exists on the BlinkStick device
+    ///
+    /// # Arguments
+    /// * `leds` - Zero-indexed led numbers
+    ///
+    /// # Example
+    /// ```
+    /// use blinkstick_rs::{BlinkStick, FeatureErrorType};
+    /// let blinkstick = BlinkStick::new().unwrap();
+    ///
+    /// assert!(blinkstick.validate_leds(&[0, 1]).is_ok());
+    /// assert_eq!(blinkstick.validate_leds(&[blinkstick.max_leds]).unwrap_err().kind, FeatureErrorType::LedOutOfBounds { led: blinkstick.max_leds as usize, max_leds: blinkstick.max_leds as usize });
+    /// ```
+    pub fn validate_leds(&self, leds: &[u8]) -> Result<(), FeatureError> {
+        match leds.iter().find(|led| **led >= self.max_leds) {
+            Some(led) => Err(FeatureError {
+                kind: FeatureErrorType::LedOutOfBounds {
+                    led: *led as usize,
+                    max_leds: self.max_leds as usize,
+                },
             }),
+            None => Ok(()),
+        }
+    }
+
+    /// Checks that a frame holds exactly one color for every led on the BlinkStick device
+    ///
+    /// # Arguments
+    /// * `colors` - A vector of `Color`, one for every led
+    pub fn validate_frame(&self, colors: &[Color]) -> Result<(), FeatureError> {
+        if colors.len() == self.max_leds as usize {
+            Ok(())
+        } else {
+            Err(FeatureError {
+                kind: FeatureErrorType::FrameLength {
+                    expected: self.max_leds as usize,
+                    actual: colors.len(),
+                },
+            })
         }
     }
 
@@ -141,9 +621,29 @@ impl BlinkStick {
     ///
     /// let color = BlinkStick::get_random_color();
     /// ```
+    #[cfg(feature = "rand")]
     pub fn get_random_color() -> Color {
-        let mut rng = rand::thread_rng();
+        BlinkStick::get_random_color_with(&mut rand::thread_rng())
+    }
 
+    /// Generates a random color drawn from `rng`, so a seeded generator yields the same colors on every run
+    ///
+    /// # Example
+    /// Returns the same `Color` for the same seed
+    /// ```
+    /// extern crate rand;
+    ///
+    /// use blinkstick_rs::BlinkStick;
+    /// use rand::rngs::StdRng;
+    /// use rand::SeedableRng;
+    ///
+    /// let first = BlinkStick::get_random_color_with(&mut StdRng::seed_from_u64(42));
+    /// let second = BlinkStick::get_random_color_with(&mut StdRng::seed_from_u64(42));
+    ///
+    /// assert_eq!(first, second);
+    /// ```
+    #[cfg(feature = "rand")]
+    pub fn get_random_color_with<R: Rng + ?Sized>(rng: &mut R) -> Color {
         Color {
             r: rng.gen_range(0..255),
             g: rng.gen_range(0..255),
@@ -189,7 +689,21 @@ impl BlinkStick {
             panic!("Led {} is out of bounds for Blinkstick device", led)
         }
 
-        self.send_feature_to_blinkstick(&[0x5, 0, led, color.r, color.g, color.b])
+        let brightness = self.output_brightness();
+        let output =
+            self.quantizer
+                .lock()
+                .unwrap()
+                .led(led as usize, color, brightness, self.gamma, self.output_channels());
+        let output = self.output_color(output);
+        let _frame = self.frame_lock.hold();
+        let (channel, index) = self.channel_position(led)?;
+        self.send_feature_to_blinkstick(&encode_channel_led_report(channel, index, output))?;
+        self.written_colors.lock().unwrap()[led as usize] = (color, output);
+        *self.written_brightness.lock().unwrap() = Some(brightness);
+        self.record(|| StateEvent::LedSet { led, color });
+
+        Ok(())
     }
 
     /// Sets the RGB color of one or more leds to a single color
@@ -210,26 +724,19 @@ impl BlinkStick {
     /// blinkstick.set_multiple_leds_color(&vec![0, 2, 4, 6], Color {r: 0, g: 50, b: 0}).unwrap();
     /// ```
     pub fn set_multiple_leds_color(&self, leds: &[u8], color: Color) -> Result<(), FeatureError> {
-        let mut data_vec: [u8; REPORT_ARRAY_BYTES] = [0; REPORT_ARRAY_BYTES];
-        data_vec[0] = 0x6;
-
-        for led in leds {
-            let led_offset: usize = ((led * 3) + 2).into();
-
-            if led_offset >= ((self.max_leds * 3) + 2).into() {
-                panic!(
-                    "BlinkStick device does not contain led {}. Valid leds are 0-{} (zero-indexed)",
-                    led,
-                    self.max_leds - 1
-                );
-            }
+        self.assert_leds(leds);
+        self.write_all_leds(&frame::with_leds(&self.get_color_vec(), leds, color))
+    }
 
-            data_vec[led_offset] = color.g;
-            data_vec[led_offset + 1] = color.r;
-            data_vec[led_offset + 2] = color.b;
+    /// Panics if any of the leds does not exist on the BlinkStick device
+    fn assert_leds(&self, leds: &[u8]) {
+        if let Some(led) = leds.iter().find(|led| **led >= self.max_leds) {
+            panic!(
+                "BlinkStick device does not contain led {}. Valid leds are 0-{} (zero-indexed)",
+                led,
+                self.max_leds.saturating_sub(1)
+            );
         }
-
-        self.send_feature_to_blinkstick(&data_vec[0..self.report_length])
     }
 
     /// Sets the same color for all leds available on the BlinkStick device
@@ -255,12 +762,13 @@ impl BlinkStick {
     /// # Arguments
     /// * `colors` - A vector of equal length to the number of leds available on the device.
     ///
-    /// # Panics
-    /// The call to `blink_led_color` will panic if the length of the color vector is greater then the number of available leds
+    /// # Errors
+    /// Returns a `FeatureErrorType::FrameLength` error if the length of the color vector differs from the number of available leds
     ///
     /// # Example
     /// Sets a different color for each led on the device
-    /// ```
+    #[cfg_attr(feature = "rand", doc = "```")]
+    #[cfg_attr(not(feature = "rand"), doc = "```ignore")]
     /// use blinkstick_rs::{BlinkStick, Color};
     ///
     /// let blinkstick = BlinkStick::new().unwrap();
@@ -274,27 +782,91 @@ impl BlinkStick {
     /// blinkstick.set_all_leds_colors(&colors).unwrap();
     /// ```
     pub fn set_all_leds_colors(&self, colors: &[Color]) -> Result<(), FeatureError> {
-        let mut data_vec: [u8; REPORT_ARRAY_BYTES] = [0; REPORT_ARRAY_BYTES];
-        data_vec[0] = 0x6;
-
-        for (led_index, led_color) in colors.iter().enumerate().take(self.max_leds as usize) {
-            //for led in 0..self.max_leds as usize {
-            let led_offset = (led_index * 3) + 2;
-
-            if led_offset >= ((self.max_leds * 3) + 2).into() {
-                panic!(
-                    "BlinkStick device does not contain led {}. Valid leds are 0-{} (zero-indexed)",
-                    led_index,
-                    self.max_leds - 1
-                );
-            }
+        self.validate_frame(colors)?;
+        self.write_all_leds(colors)
+    }
+
+    /// Writes a color for every led on the device, in a single report or one per channel of `channel_leds`,
+    /// applying the dimming schedule
+    fn write_all_leds(&self, colors: &[Color]) -> Result<(), FeatureError> {
+        self.write_all_leds_at(colors, self.output_brightness())
+    }
 
-            data_vec[led_offset] = led_color.g;
-            data_vec[led_offset + 1] = led_color.r;
-            data_vec[led_offset + 2] = led_color.b;
+    /// Writes a color for every led on the device like `write_all_leds`, scaled by `brightness`
+    fn write_all_leds_at(&self, colors: &[Color], brightness: f32) -> Result<(), FeatureError> {
+        let outputs: Vec<Color> = self
+            .quantizer
+            .lock()
+            .unwrap()
+            .frame(colors, brightness, self.gamma, self.output_channels())
+            .into_iter()
+            .map(|color| self.output_color(color))
+            .collect();
+
+        let reports: Vec<Vec<u8>> = encode_channel_reports(&outputs, &self.channel_leds())
+            .into_iter()
+            .map(|mut report| {
+                report.resize(self.report_length, 0);
+                report
+            })
+            .collect();
+
+        // Every report is ready before the first is sent, and other writes wait until the frame is complete
+        let _frame = self.frame_lock.hold();
+        for report in reports.iter() {
+            self.send_feature_to_blinkstick(report)?;
         }
+        self.verify_written(&outputs, &reports)?;
+        *self.written_colors.lock().unwrap() = colors.iter().copied().zip(outputs).collect();
+        *self.written_brightness.lock().unwrap() = Some(brightness);
+        self.record(|| StateEvent::FrameSet(colors.to_vec()));
+
+        Ok(())
+    }
 
-        self.send_feature_to_blinkstick(&data_vec[0..self.report_length])
+    /// The brightness every written color is scaled by, combining the configured brightness with the dimming
+    /// schedule and the ambient light
+    fn output_brightness(&self) -> f32 {
+        let scheduled = self
+            .dimming_schedule
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map_or(1.0, |schedule| schedule.brightness_now());
+        let ambient = self
+            .ambient_light
+            .lock()
+            .unwrap()
+            .as_mut()
+            .map_or(1.0, |ambient_light| ambient_light.brightness());
+
+        self.brightness * scheduled * ambient
+    }
+
+    /// Maps the exact channels of a color scaled by the brightness and gamma corrected to the channels the device
+    /// is sent, before the quantizer rounds them: white balanced, linearized by the response curves and reordered
+    fn output_channels(&self) -> impl Fn([f32; 3]) -> [f32; 3] {
+        let profile = self.color_profile();
+        let curves = self.response_curves.lock().unwrap().clone();
+        let order = self.channel_order();
+
+        move |exact| {
+            let balanced = profile.apply_exact(exact);
+            let linear = match &curves {
+                Some(curves) => curves.apply_exact(balanced),
+                None => balanced,
+            };
+            order.apply_exact(linear)
+        }
+    }
+
+    /// The color the device is sent for a color rounded by the quantizer, inverted for `Mode::Inverse`
+    fn output_color(&self, rounded: Color) -> Color {
+        if self.is_inverted() {
+            mode::invert(rounded)
+        } else {
+            rounded
+        }
     }
 
     /// Makes a specified led blink in a single color
@@ -311,20 +883,18 @@ impl BlinkStick {
     /// # Example
     /// Makes the 0th led blink 5 times, once every second, with a purple glow
     /// ```
+    /// # #![allow(deprecated)]
     /// use blinkstick_rs::{BlinkStick, Color};
     ///
     /// let blinkstick = BlinkStick::new().unwrap();
     /// blinkstick.blink_led_color(0, std::time::Duration::from_secs(1), 5, Color {r: 25, g: 0, b: 25}).unwrap();
     /// ```
+    #[cfg(feature = "effects")]
+    #[deprecated(note = "play `AnimationIter::blink` instead")]
     pub fn blink_led_color(&self, led: u8, delay: Duration, blinks: u32, color: Color) -> Result<(), FeatureError> {
-        for _ in 0..blinks {
-            self.set_led_color(led, color)?;
-            std::thread::sleep(delay);
-            self.set_led_color(led, Color { r: 0, g: 0, b: 0 })?;
-            std::thread::sleep(delay);
-        }
-
-        Ok(())
+        self.assert_leds(&[led]);
+        let frame = self.current_colors()?;
+        self.play_animation(AnimationIter::blink(frame, &[led], color, delay, blinks))
     }
 
     /// Makes the specified leds blink in a single color
@@ -341,11 +911,14 @@ impl BlinkStick {
     /// # Example
     /// Makes the zeroth and first led blink 2 times, once every 200 milliseconds, with a yellow glow
     /// ```
+    /// # #![allow(deprecated)]
     /// use blinkstick_rs::{BlinkStick, Color};
     ///
     /// let blinkstick = BlinkStick::new().unwrap();
     /// blinkstick.blink_multiple_leds_color(&vec![0, 1], std::time::Duration::from_millis(200), 2, Color {r: 50, g: 50, b: 0}).unwrap();
     /// ```
+    #[cfg(feature = "effects")]
+    #[deprecated(note = "play `AnimationIter::blink` instead")]
     pub fn blink_multiple_leds_color(
         &self,
         leds: &[u8],
@@ -353,14 +926,8 @@ impl BlinkStick {
         blinks: u32,
         color: Color,
     ) -> Result<(), FeatureError> {
-        for _ in 0..blinks {
-            self.set_multiple_leds_color(leds, color)?;
-            std::thread::sleep(delay);
-            self.set_multiple_leds_color(leds, Color { r: 0, g: 0, b: 0 })?;
-            std::thread::sleep(delay);
-        }
-
-        Ok(())
+        self.assert_leds(leds);
+        self.play_animation(AnimationIter::blink(self.get_color_vec(), leds, color, delay, blinks))
     }
 
     /// Makes all leds blink in a single color
@@ -373,11 +940,15 @@ impl BlinkStick {
     /// # Example
     /// Makes all leds blink 2 times, once every 200 milliseconds, with a yellow glow
     /// ```
+    /// # #![allow(deprecated)]
     /// use blinkstick_rs::{BlinkStick, Color};
     ///
     /// let blinkstick = BlinkStick::new().unwrap();
     /// blinkstick.blink_all_leds_color(std::time::Duration::from_millis(200), 2, Color {r: 50, g: 50, b: 0}).unwrap();
     /// ```
+    #[cfg(feature = "effects")]
+    #[deprecated(note = "play `AnimationIter::blink` instead")]
+    #[allow(deprecated)]
     pub fn blink_all_leds_color(&self, delay: Duration, blinks: u32, color: Color) -> Result<(), FeatureError> {
         let leds: Vec<u8> = (0..self.max_leds).collect();
         self.blink_multiple_leds_color(&leds, delay, blinks, color)
@@ -387,7 +958,7 @@ impl BlinkStick {
     /// # Arguments
     /// * `led` - A zero-indexed led number (within bounds for the BlinkStick product)
     /// * `duration` - The time it takes for the entire animation cycle to finish
-    /// * `steps` - The number of times the color changes are interpolated between the old and new color value
+    /// * `steps` - The number of times the color changes are interpolated between the old and new color value, or a rate like `Fps(30)`, see `StepRate`
     /// * `color` - A struct holding color values for R,G and B channel respectively
     ///
     ///
@@ -398,17 +969,23 @@ impl BlinkStick {
     /// # Example
     /// Makes the 2nd led, pulse from an off state, to a blue glow, and then return back again to the off state with a two second animation time
     /// ```
+    /// # #![allow(deprecated)]
     /// use blinkstick_rs::{BlinkStick, Color};
     ///
     /// let blinkstick = BlinkStick::new().unwrap();
     /// blinkstick.pulse_led_color(2, std::time::Duration::from_secs(2), 20, Color {r: 0, g: 0, b: 155}).unwrap();
     /// ```
-    pub fn pulse_led_color(&self, led: u8, duration: Duration, steps: u16, color: Color) -> Result<(), FeatureError> {
-        let old_color = self.get_led_color(led)?;
-        self.transform_led_color(led, duration / 2, steps, color)?;
-        self.transform_led_color(led, duration / 2, steps, old_color)?;
-
-        Ok(())
+    #[cfg(feature = "effects")]
+    #[deprecated(note = "play `AnimationIter::pulse` instead")]
+    #[allow(deprecated)]
+    pub fn pulse_led_color<S: Into<StepRate>>(
+        &self,
+        led: u8,
+        duration: Duration,
+        steps: S,
+        color: Color,
+    ) -> Result<(), FeatureError> {
+        self.pulse_multiple_leds_color(&[led], duration, steps, color)
     }
 
     /// Makes the specified leds pulse to a single color and back to their original color
@@ -416,7 +993,7 @@ impl BlinkStick {
     /// # Arguments
     /// * `leds` - A vector of zero-indexed led numbers (within bounds for the BlinkStick product)
     /// * `duration` - The time it takes for the entire animation cycle to finish
-    /// * `steps` - The number of times the color value is update during the transformation
+    /// * `steps` - The number of times the color value is update during the transformation, or a rate like `Fps(30)`, see `StepRate`
     /// * `color` - A struct holding color values for R,G and B channel respectively
     ///
     /// # Panics
@@ -425,7 +1002,9 @@ impl BlinkStick {
     ///
     /// # Example
     /// Gives the zeroth and fourth led a random color, and makes them pulse to a blue color
-    /// ```
+    #[cfg_attr(feature = "rand", doc = "```")]
+    #[cfg_attr(not(feature = "rand"), doc = "```ignore")]
+    /// # #![allow(deprecated)]
     /// use blinkstick_rs::{BlinkStick, Color};
     ///
     /// let blinkstick = BlinkStick::new().unwrap();
@@ -442,24 +1021,31 @@ impl BlinkStick {
     /// assert_eq!(blinkstick.get_led_color(0).unwrap(), colors[0]);
     /// assert_eq!(blinkstick.get_led_color(4).unwrap(), colors[4]);
     /// ```
-    pub fn pulse_multiple_leds_color(
+    #[cfg(feature = "effects")]
+    #[deprecated(note = "play `AnimationIter::pulse` instead")]
+    pub fn pulse_multiple_leds_color<S: Into<StepRate>>(
         &self,
         leds: &[u8],
         duration: Duration,
-        steps: u16,
+        steps: S,
         color: Color,
     ) -> Result<(), FeatureError> {
-        let old_colors = self.get_all_led_colors()?;
-
-        self.transform_multiple_leds_color(leds, duration.div(2), steps, color)?;
-        self.transform_all_leds_colors(duration.div(2), steps, &old_colors)
+        self.assert_leds(leds);
+        let old_colors = self.current_colors()?;
+        let new_colors = frame::with_leds(&old_colors, leds, color);
+        self.play_animation(AnimationIter::pulse(
+            old_colors,
+            new_colors,
+            duration,
+            self.steps_for(duration / 2, steps),
+        ))
     }
 
     /// Makes all leds pulse between their current color and a specified color
     ///
     /// #Arguments
     /// * `duration` - The time it takes for the entire animation cycle to finish
-    /// * `steps` - The number of times the color value is update during the transformation
+    /// * `steps` - The number of times the color value is update during the transformation, or a rate like `Fps(30)`, see `StepRate`
     /// * `color` - A struct holding color values for R,G and B channel respectively
     ///
     /// # Panics
@@ -468,6 +1054,7 @@ impl BlinkStick {
     /// # Example
     /// Makes every led pulse between being turned off and a green color
     /// ```
+    /// # #![allow(deprecated)]
     /// use blinkstick_rs::{BlinkStick, Color};
     ///
     /// let blinkstick = BlinkStick::new().unwrap();
@@ -476,23 +1063,29 @@ impl BlinkStick {
     ///
     /// assert_eq!(blinkstick.get_all_led_colors().unwrap(), vec![Color {r: 0, g: 0, b: 0}; blinkstick.max_leds as usize]);
     /// ```
-    pub fn pulse_all_leds_color(
+    #[cfg(feature = "effects")]
+    #[deprecated(note = "play `AnimationIter::pulse` instead")]
+    pub fn pulse_all_leds_color<S: Into<StepRate>>(
         &self,
         duration: Duration,
-        steps: u16,
+        steps: S,
         target_color: Color,
     ) -> Result<(), FeatureError> {
-        let old_colors = self.get_all_led_colors()?;
-
-        self.transform_all_leds_color(duration.div(2), steps, target_color)?;
-        self.transform_all_leds_colors(duration.div(2), steps, &old_colors)
+        let old_colors = self.current_colors()?;
+        let new_colors = vec![target_color; old_colors.len()];
+        self.play_animation(AnimationIter::pulse(
+            old_colors,
+            new_colors,
+            duration,
+            self.steps_for(duration / 2, steps),
+        ))
     }
 
     /// Makes the specified led shift into a different color
     /// # Arguments
     /// * `led` - A zero-indexed led number (within bounds for the BlinkStick product)
     /// * `duration` - The time it takes for the entire animation cycle to finish
-    /// * `steps` - The number of times the color value is update during the transformation
+    /// * `steps` - The number of times the color value is update during the transformation, or a rate like `Fps(30)`, see `StepRate`
     /// * `color` - A struct holding color values for R,G and B channel respectively
     ///
     /// # Panics
@@ -506,43 +1099,31 @@ impl BlinkStick {
     /// # Example
     /// Makes the first led transform from a red color into a green color over a period of five seconds, with 50 color updates.
     /// ```
+    /// # #![allow(deprecated)]
     /// use blinkstick_rs::{BlinkStick, Color};
     ///
     /// let blinkstick = BlinkStick::new().unwrap();
     /// blinkstick.set_led_color(1, Color {r: 50, g: 0, b: 0}).unwrap();
     /// blinkstick.transform_led_color(1, std::time::Duration::from_secs(5), 50, Color {r: 0, g: 50, b: 0}).unwrap();
     /// ```
-    pub fn transform_led_color(
+    #[cfg(feature = "effects")]
+    #[deprecated(note = "play `AnimationIter::transform` instead")]
+    #[allow(deprecated)]
+    pub fn transform_led_color<S: Into<StepRate>>(
         &self,
         led: u8,
         duration: Duration,
-        steps: u16,
+        steps: S,
         target_color: Color,
     ) -> Result<(), FeatureError> {
-        let interval = duration.div(steps as u32);
-        let start_led_color = self.get_led_color(led)?;
-
-        let gradient: Vec<Color> = calculate_gradients(start_led_color, target_color, steps);
-
-        for color in gradient {
-            let start = Instant::now();
-            self.set_led_color(led, color)?;
-            let elapsed = start.elapsed();
-
-            let subtracted_duration = interval.saturating_sub(elapsed);
-            if subtracted_duration != Duration::ZERO {
-                std::thread::sleep(subtracted_duration);
-            }
-        }
-
-        Ok(())
+        self.transform_multiple_leds_color(&[led], duration, steps, target_color)
     }
 
     /// Transforms the color of all leds into a specified color on a per led basis
     ///
     /// # Arguments
     /// * `duration` - The time it takes for the entire animation cycle to finish
-    /// * `steps` - The number of times the color value is update during the transformation
+    /// * `steps` - The number of times the color value is update during the transformation, or a rate like `Fps(30)`, see `StepRate`
     /// * `colors` - A vector of `Color` with equal length to the number of leds available on the device.
     ///
     /// # Panics
@@ -554,7 +1135,9 @@ impl BlinkStick {
     ///     
     /// # Example
     /// Sets a random color for each available led then transforms each individual led into a different random `Color`.
-    /// ```
+    #[cfg_attr(feature = "rand", doc = "```")]
+    #[cfg_attr(not(feature = "rand"), doc = "```ignore")]
+    /// # #![allow(deprecated)]
     /// use blinkstick_rs::{BlinkStick, Color};
     /// let blinkstick = BlinkStick::new().unwrap();
     ///
@@ -571,26 +1154,30 @@ impl BlinkStick {
     /// blinkstick.set_all_leds_colors(&colors).unwrap();
     /// blinkstick.transform_all_leds_colors(std::time::Duration::from_secs(2), 50, &new_colors).unwrap();
     /// ```
-    pub fn transform_all_leds_colors(
+    #[cfg(feature = "effects")]
+    #[deprecated(note = "play `AnimationIter::transform` instead")]
+    pub fn transform_all_leds_colors<S: Into<StepRate>>(
         &self,
         duration: Duration,
-        steps: u16,
+        steps: S,
         target_colors: &[Color],
     ) -> Result<(), FeatureError> {
-        let mut led_gradients: Vec<Color> = Vec::with_capacity((self.max_leds as u16 * steps) as usize);
-        for (led, target_color) in target_colors.iter().enumerate().take(self.max_leds as usize) {
-            let current_led_color = self.get_led_color(led as u8)?;
-            led_gradients.append(&mut calculate_gradients(current_led_color, *target_color, steps));
-        }
-
-        self.transform_leds(&led_gradients, duration, steps)
+        self.validate_frame(target_colors)?;
+
+        let old_colors = self.current_colors()?;
+        self.play_animation(AnimationIter::transform(
+            old_colors,
+            target_colors.to_vec(),
+            duration,
+            self.steps_for(duration, steps),
+        ))
     }
 
     /// Transforms the color of all leds into a specified color
     ///
     /// # Arguments
     /// * `duration` - The time it takes for the entire animation cycle to finish
-    /// * `steps` - The number of times the color value is update during the transformation
+    /// * `steps` - The number of times the color value is update during the transformation, or a rate like `Fps(30)`, see `StepRate`
     /// * `color` - A struct holding color values for R,G and B channel respectively
     ///
     /// # Panics
@@ -599,47 +1186,28 @@ impl BlinkStick {
     /// # Example
     /// Transforms all leds from "off" to a blue `Color`.
     /// ```
+    /// # #![allow(deprecated)]
     /// use blinkstick_rs::{BlinkStick, Color};
     /// let blinkstick = BlinkStick::new().unwrap();
     ///
     /// blinkstick.transform_all_leds_color(std::time::Duration::from_secs(2), 50, Color { r: 0, g: 0, b: 100 }).unwrap();
     /// ```
-    pub fn transform_all_leds_color(
+    #[cfg(feature = "effects")]
+    #[deprecated(note = "play `AnimationIter::transform` instead")]
+    pub fn transform_all_leds_color<S: Into<StepRate>>(
         &self,
         duration: Duration,
-        steps: u16,
+        steps: S,
         target_color: Color,
     ) -> Result<(), FeatureError> {
-        let mut led_gradients: Vec<Color> = Vec::with_capacity((self.max_leds as u16 * steps) as usize);
-        for led in 0..self.max_leds {
-            let current_led_color = self.get_led_color(led as u8)?;
-            led_gradients.append(&mut calculate_gradients(current_led_color, target_color, steps));
-        }
-
-        self.transform_leds(&led_gradients, duration, steps)
-    }
-
-    /// Performs the all leds transformation using a pre-computed gradient vector
-    fn transform_leds(&self, led_gradients: &[Color], duration: Duration, steps: u16) -> Result<(), FeatureError> {
-        let interval = duration.div(steps as u32);
-        for step in 0..steps {
-            let start = Instant::now();
-
-            let test: Vec<Color> = led_gradients
-                .iter()
-                .skip(step as usize)
-                .step_by(steps as usize)
-                .copied()
-                .collect();
-            self.set_all_leds_colors(&test)?;
-
-            let subtracted_duration = interval.saturating_sub(start.elapsed());
-            if subtracted_duration != Duration::ZERO {
-                std::thread::sleep(subtracted_duration);
-            }
-        }
-
-        Ok(())
+        let old_colors = self.current_colors()?;
+        let new_colors = vec![target_color; old_colors.len()];
+        self.play_animation(AnimationIter::transform(
+            old_colors,
+            new_colors,
+            duration,
+            self.steps_for(duration, steps),
+        ))
     }
 
     /// Transforms the color of the specified leds into a single color
@@ -647,7 +1215,7 @@ impl BlinkStick {
     /// # Arguments
     /// * `leds` - A vector of zero-indexed led numbers (within bounds for the BlinkStick product)
     /// * `duration` - The time it takes for the entire animation cycle to finish
-    /// * `steps` - The number of times the color value is update during the transformation
+    /// * `steps` - The number of times the color value is update during the transformation, or a rate like `Fps(30)`, see `StepRate`
     /// * `color` - A struct holding color values for R,G and B channel respectively
     ///
     /// # Panics
@@ -656,7 +1224,9 @@ impl BlinkStick {
     ///
     /// # Example
     /// Sets a random color for each available led then transforms it all into a single `Color`.
-    /// ```
+    #[cfg_attr(feature = "rand", doc = "```")]
+    #[cfg_attr(not(feature = "rand"), doc = "```ignore")]
+    /// # #![allow(deprecated)]
     /// use blinkstick_rs::{BlinkStick, Color};
     /// let blinkstick = BlinkStick::new().unwrap();
     ///
@@ -671,36 +1241,28 @@ impl BlinkStick {
     /// let led_vec: Vec<u8> = (0..blinkstick.max_leds).collect();
     /// blinkstick.transform_multiple_leds_color(&led_vec, std::time::Duration::from_secs(2), 50, Color {r: 55, g: 0, b: 55}).unwrap();
     /// ```
-    pub fn transform_multiple_leds_color(
+    #[cfg(feature = "effects")]
+    #[deprecated(note = "play `AnimationIter::transform` instead")]
+    pub fn transform_multiple_leds_color<S: Into<StepRate>>(
         &self,
         leds: &[u8],
         duration: Duration,
-        steps: u16,
+        steps: S,
         target_color: Color,
     ) -> Result<(), FeatureError> {
-        let interval = duration.div(steps as u32);
-
-        let mut led_gradients: Vec<Color> = Vec::with_capacity((leds.len() * steps as usize) as usize);
-        for led in leds.iter() {
-            let current_led_color = self.get_led_color(*led)?;
-            led_gradients.append(&mut calculate_gradients(current_led_color, target_color, steps));
-        }
-
-        for step in 0..steps as usize {
-            let start = Instant::now();
-            let mut all_led_colors = self.get_all_led_colors()?;
-            for (index, led) in leds.iter().enumerate() {
-                all_led_colors[*led as usize] = led_gradients[index * steps as usize + step];
-            }
-            self.set_all_leds_colors(&all_led_colors)?;
-
-            std::thread::sleep(interval.sub(start.elapsed()));
-        }
-
-        Ok(())
+        self.assert_leds(leds);
+        let old_colors = self.current_colors()?;
+        let new_colors = frame::with_leds(&old_colors, leds, target_color);
+        self.play_animation(AnimationIter::transform(
+            old_colors,
+            new_colors,
+            duration,
+            self.steps_for(duration, steps),
+        ))
     }
 
-    /// Makes the blinkstick device carousel. A Carousel utilizes all leds to transition between `start_color`, `stop_color` and back to `start_color`
+    /// Makes the blinkstick device carousel. A Carousel utilizes all leds to transition between `start_color`, `stop_color` and back to `start_color`,
+    /// skipping the leds masked with `mask_leds`
     ///
     /// # Arguments
     /// * `start_color` - The start color to transition from
@@ -710,6 +1272,7 @@ impl BlinkStick {
     ///
     /// Carousels the BlinkStick device Blue -> Green -> Blue 10 times
     /// ```
+    /// # #![allow(deprecated)]
     /// use blinkstick_rs::{BlinkStick, Color};
     /// let blinkstick = BlinkStick::default();
     /// let color_one = Color { r: 0, g: 0, b: 50 };
@@ -718,32 +1281,25 @@ impl BlinkStick {
     ///     blinkstick.carousel(color_one, color_two, std::time::Duration::from_millis(20)).unwrap();
     /// }
     /// ```
+    #[cfg(feature = "effects")]
+    #[deprecated(note = "play `AnimationIter::carousel` instead")]
     pub fn carousel(&self, start_color: Color, target_color: Color, delay: Duration) -> Result<(), FeatureError> {
-        let mut carousel_colors = calculate_gradients(start_color, target_color, self.max_leds as u16);
-
-        self.color_lap(&carousel_colors, &delay)?;
-        carousel_colors.reverse();
-        self.color_lap(&carousel_colors, &delay)
-    }
-
-    /// Helper function for carousel
-    fn color_lap(&self, lap_colors: &[Color], delay: &Duration) -> Result<(), FeatureError> {
-        self.set_led_color(0_u8, lap_colors[0])?;
-        for i in 1..self.max_leds {
-            std::thread::sleep(*delay);
-
-            self.turn_off_led(i - 1)?;
-            self.set_led_color(i, lap_colors[i as usize])?;
-        }
-
-        self.turn_off_led(self.max_leds - 1)
+        let frame = self.current_colors()?;
+        let live_leds = self.live_leds();
+        let live_frame = live_leds.iter().map(|led| frame[*led]).collect();
+
+        let carousel = AnimationIter::carousel(live_frame, start_color, target_color, delay);
+        self.play_animation(
+            carousel.map(|(live_colors, delay)| (mask::fill_live_leds(&frame, &live_leds, &live_colors), delay)),
+        )
     }
 
     /// Gets the color of every single led on the BlinkStick device
     ///
     /// # Example
     /// Gets the color of every single led
-    /// ```
+    #[cfg_attr(feature = "rand", doc = "```")]
+    #[cfg_attr(not(feature = "rand"), doc = "```ignore")]
     /// use blinkstick_rs::{BlinkStick, Color};
     /// let blinkstick = BlinkStick::new().unwrap();
     ///    
@@ -759,18 +1315,28 @@ impl BlinkStick {
     /// assert_eq!(led_colors[2], random_color);
     /// ```
     pub fn get_all_led_colors(&self) -> Result<Vec<Color>, FeatureError> {
-        let buf = self.get_feature_from_blinkstick(0x6)?;
-
-        let mut led_colors: Vec<Color> = Vec::with_capacity(self.max_leds as usize);
-        for led in 0..self.max_leds as usize {
-            let led_color_index = (led * 3) + 2;
-
-            led_colors.push(Color {
-                r: buf[led_color_index + 1],
-                g: buf[led_color_index],
-                b: buf[led_color_index + 2],
+        if self.is_write_only() {
+            return Err(FeatureError {
+                kind: FeatureErrorType::NotReadable,
             });
         }
+
+        let report = self.get_feature_from_blinkstick(LEDS_REPORT_ID)?;
+        let written_colors = self.written_colors.lock().unwrap();
+        // Only the channel written last can be read back, leds on the other channels report what was last written
+        let sent: Vec<Color> = written_colors.iter().map(|(_, output)| *output).collect();
+        let device_colors = channel::read_back_outputs(&report, &sent, &self.channel_leds())?;
+        // A led still showing the dimmed output of our last write reports the color it was set to
+        let led_colors = device_colors
+            .into_iter()
+            .enumerate()
+            .map(|(led, device_color)| match written_colors.get(led) {
+                Some((color, output)) if *output == device_color => *color,
+                _ if self.is_inverted() => mode::invert(device_color),
+                _ => device_color,
+            })
+            .collect();
+
         Ok(led_colors)
     }
 
@@ -778,7 +1344,8 @@ impl BlinkStick {
     ///
     /// # Example
     /// Gets the color of the zeroth led
-    /// ```
+    #[cfg_attr(feature = "rand", doc = "```")]
+    #[cfg_attr(not(feature = "rand"), doc = "```ignore")]
     /// use blinkstick_rs::{BlinkStick, Color};
     /// let blinkstick = BlinkStick::new().unwrap();
     ///    
@@ -805,55 +1372,86 @@ impl BlinkStick {
     }
 
     fn send_feature_to_blinkstick(&self, feature: &[u8]) -> Result<(), FeatureError> {
-        for _ in 0..5 {
-            if self.device.send_feature_report(feature).is_ok() {
-                return Ok(());
-            }
-        }
+        let result =
+            self.send_paced(|| self.retry_feature(Send, || self.device.lock().unwrap().send_feature_report(feature)));
+        self.log_report(true, feature, &result);
+        result
+    }
+
+    /// Reads a feature report, returning only the bytes the device sent
+    fn get_feature_from_blinkstick(&self, id: u8) -> Result<Vec<u8>, FeatureError> {
+        // Devices with more leds than detected, see `BlinkStickBuilder::leds`, send longer reports
+        let mut buf = vec![0u8; self.report_length.max(REPORT_ARRAY_BYTES)];
+        buf[0] = id;
 
-        // If we still dont have a successful attempt at communicating with the device
-        // we try one last time after a short sleep
-        std::thread::sleep(std::time::Duration::from_millis(10));
-        if self.device.send_feature_report(feature).is_ok() {
+        let mut bytes_read = 0;
+        let result = self.retry_feature(Get, || {
+            bytes_read = self.device.lock().unwrap().get_feature_report(&mut buf)?;
             Ok(())
-        } else {
-            Err(FeatureError { kind: Send })
+        });
+        buf.truncate(bytes_read);
+        self.log_report(false, &buf, &result);
+        result.map(|()| buf)
+    }
+
+    /// Repeats a feature report transfer until it succeeds, giving up early once the device is gone
+    /// or the io timeout has passed
+    fn retry_feature<F>(&self, kind: FeatureErrorType, transfer: F) -> Result<(), FeatureError>
+    where
+        F: FnMut() -> hidapi::HidResult<()>,
+    {
+        let result = self.retry_transfer(kind, transfer);
+        match &result {
+            Ok(()) => self.record(|| StateEvent::TransferSucceeded),
+            Err(error) => self.record(|| StateEvent::TransferFailed(error.kind.clone())),
         }
+
+        result
     }
 
-    fn get_feature_from_blinkstick(&self, id: u8) -> Result<[u8; REPORT_ARRAY_BYTES], FeatureError> {
-        let mut buf = [0u8; REPORT_ARRAY_BYTES];
-        buf[0] = id;
+    fn retry_transfer<F>(&self, kind: FeatureErrorType, mut transfer: F) -> Result<(), FeatureError>
+    where
+        F: FnMut() -> hidapi::HidResult<()>,
+    {
+        // A timeout too long to reach a deadline is no timeout at all
+        let deadline = self
+            .io_timeout()
+            .and_then(|timeout| Instant::now().checked_add(timeout));
+        let timed_out = || deadline.is_some_and(|deadline| Instant::now() >= deadline);
+
+        let mut error = FeatureError { kind };
+        for attempt in 0..6 {
+            if attempt > 0 && timed_out() {
+                return Err(FeatureError {
+                    kind: FeatureErrorType::Timeout,
+                });
+            }
+            if attempt > 0 {
+                self.record(|| StateEvent::TransferRetried(error.kind.clone()));
+            }
 
-        for _ in 0..5 {
-            if self.device.get_feature_report(&mut buf).is_ok() {
-                return Ok(buf);
+            // If we still dont have a successful attempt at communicating with the device
+            // we try one last time after a short sleep
+            if attempt == 5 {
+                let pause = Duration::from_millis(10);
+                std::thread::sleep(deadline.map_or(pause, |deadline| {
+                    pause.min(deadline.saturating_duration_since(Instant::now()))
+                }));
             }
-        }
 
-        // If we still dont have a successful attempt at communicating with the device
-        // we try one last time after a short sleep
-        std::thread::sleep(std::time::Duration::from_millis(10));
-        if self.device.get_feature_report(&mut buf).is_ok() {
-            Ok(buf)
-        } else {
-            Err(FeatureError { kind: Get })
+            match transfer() {
+                Ok(()) => return Ok(()),
+                Err(e) => {
+                    error = FeatureError::from_hid_error(error.kind, &e);
+                    if !error.is_transient() {
+                        return Err(error);
+                    }
+                }
+            }
         }
-    }
-}
 
-fn calculate_gradients(start_color: Color, target_color: Color, steps: u16) -> Vec<Color> {
-    (1..=steps)
-        .into_iter()
-        .map(|step| {
-            let step_percent = step as f32 / steps as f32;
-            Color {
-                r: ((start_color.r as f32 * (1.0 - step_percent)) + (target_color.r as f32 * step_percent)) as u8,
-                g: ((start_color.g as f32 * (1.0 - step_percent)) + (target_color.g as f32 * step_percent)) as u8,
-                b: ((start_color.b as f32 * (1.0 - step_percent)) + (target_color.b as f32 * step_percent)) as u8,
-            }
-        })
-        .collect()
+        Err(error)
+    }
 }
 
 #[cfg(test)]
@@ -922,6 +1520,25 @@ mod blinkstick {
     }
 
     #[test]
+    fn set_all_leds_colors_rejects_wrong_length() {
+        let blinkstick = BlinkStick::new().expect("Could not create connection");
+
+        let error = blinkstick
+            .set_all_leds_colors(&[Color { r: 5, g: 5, b: 5 }])
+            .expect_err("Could set a frame of the wrong length");
+
+        assert_eq!(
+            error.kind,
+            FeatureErrorType::FrameLength {
+                expected: blinkstick.max_leds as usize,
+                actual: 1
+            }
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "effects")]
+    #[allow(deprecated)]
     fn blink_led_color() {
         let blinkstick = BlinkStick::new().expect("Could not create connection");
 
@@ -935,6 +1552,8 @@ mod blinkstick {
     }
 
     #[test]
+    #[cfg(feature = "effects")]
+    #[allow(deprecated)]
     fn blink_all_leds_color() {
         let blinkstick = BlinkStick::new().expect("Could not create connection");
 
@@ -950,6 +1569,8 @@ mod blinkstick {
 
     #[test]
     #[should_panic]
+    #[cfg(feature = "effects")]
+    #[allow(deprecated)]
     fn blink_single_led_out_of_bounds() {
         let blinkstick = BlinkStick::new().expect("Could not create connection");
 
@@ -965,6 +1586,8 @@ mod blinkstick {
 
     #[test]
     #[should_panic]
+    #[cfg(feature = "effects")]
+    #[allow(deprecated)]
     fn blink_multiple_leds_out_of_bounds() {
         let blinkstick = BlinkStick::new().expect("Could not create connection");
 
@@ -979,6 +1602,54 @@ mod blinkstick {
     }
 
     #[test]
+    #[cfg(feature = "effects")]
+    fn alternate_flash() {
+        let blinkstick = BlinkStick::new().expect("Could not create connection");
+
+        let untouched_color = Color { r: 5, g: 5, b: 5 };
+        blinkstick
+            .set_led_color(7, untouched_color)
+            .expect("Could not set led color");
+
+        let frame = blinkstick.get_all_led_colors().expect("Could not get led colors");
+        blinkstick
+            .play_animation(AnimationIter::alternate_flash(
+                frame,
+                &[&[0, 1], &[2, 3]],
+                &[Color { r: 50, g: 0, b: 0 }, Color { r: 0, g: 0, b: 50 }],
+                std::time::Duration::from_millis(200),
+                3,
+            ))
+            .expect("Could not alternate flash");
+
+        let led_colors = blinkstick.get_all_led_colors().expect("Could not get led colors");
+        assert_eq!(led_colors[0..4], [Color { r: 0, g: 0, b: 0 }; 4]);
+        assert_eq!(led_colors[7], untouched_color);
+    }
+
+    #[test]
+    fn hid_errors_are_classified() {
+        let unplugged = hidapi::HidError::IoError {
+            error: std::io::Error::from(std::io::ErrorKind::NotFound),
+        };
+        let busy = hidapi::HidError::HidApiError {
+            message: "Resource temporarily unavailable".to_string(),
+        };
+        let gone = hidapi::HidError::HidApiError {
+            message: "The device is not connected.".to_string(),
+        };
+
+        assert!(!FeatureError::from_hid_error(FeatureErrorType::Send, &unplugged).is_transient());
+        assert!(!FeatureError::from_hid_error(FeatureErrorType::Get, &gone).is_transient());
+        assert_eq!(
+            FeatureError::from_hid_error(FeatureErrorType::Get, &busy).kind,
+            FeatureErrorType::Get
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "effects")]
+    #[allow(deprecated)]
     fn transform_led_color() {
         let blinkstick = BlinkStick::new().expect("Could not create connection");
 
@@ -1000,6 +1671,8 @@ mod blinkstick {
     }
 
     #[test]
+    #[cfg(feature = "effects")]
+    #[allow(deprecated)]
     fn transform_multiple_leds_color() {
         let blinkstick = BlinkStick::new().expect("Could not create connection");
 
@@ -1028,6 +1701,8 @@ mod blinkstick {
     }
 
     #[test]
+    #[cfg(feature = "effects")]
+    #[allow(deprecated)]
     fn pulse_led_color() {
         let blinkstick = BlinkStick::new().expect("Could not create connection");
 
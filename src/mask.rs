@@ -0,0 +1,114 @@
+use crate::{BlinkStick, Color, FeatureError};
+
+/// Two test colors with every bit of each channel flipped between them, so a stuck bit shows up in either
+const TEST_PATTERN: [Color; 2] = [Color::new(0x55, 0xaa, 0x55), Color::new(0xaa, 0x55, 0xaa)];
+
+impl BlinkStick {
+    /// Excludes known dead `leds` from the effects that move across the device, in addition to the leds masked
+    /// before. Carousels and the segments of `strip_layout` skip masked leds as if they weren't there, and leave
+    /// their color alone.
+    ///
+    /// Writing to a masked led directly still works.
+    ///
+    /// # Panics
+    /// The call to `mask_leds` will panic if any of the specified `leds` is out of bounds for the BlinkStick device.
+    ///
+    /// # Example
+    /// Masks the leds that don't respond
+    /// ```
+    /// use blinkstick_rs::BlinkStick;
+    ///
+    /// let blinkstick = BlinkStick::new().unwrap();
+    /// let dead_leds = blinkstick.find_unresponsive_leds().unwrap();
+    /// blinkstick.mask_leds(&dead_leds);
+    /// ```
+    pub fn mask_leds(&self, leds: &[u8]) {
+        self.assert_leds(leds);
+        let mut masked = self.masked_leds.lock().unwrap();
+        masked.extend_from_slice(leds);
+        masked.sort_unstable();
+        masked.dedup();
+    }
+
+    /// Includes every led in effects again
+    pub fn clear_mask(&self) {
+        self.masked_leds.lock().unwrap().clear();
+    }
+
+    /// The leds excluded from effects, in ascending order, see `mask_leds`
+    pub fn masked_leds(&self) -> Vec<u8> {
+        self.masked_leds.lock().unwrap().clone()
+    }
+
+    /// The leds not excluded from effects, in ascending order
+    #[cfg(feature = "effects")]
+    pub(crate) fn live_leds(&self) -> Vec<usize> {
+        let masked = self.masked_leds.lock().unwrap();
+        (0..self.max_leds)
+            .filter(|led| !masked.contains(led))
+            .map(|led| led as usize)
+            .collect()
+    }
+
+    /// Writes a test pattern to every led and reads it back, returning the leds whose colors came back different
+    /// as a suggestion for `mask_leds`. Afterwards every led shows its previous color again.
+    ///
+    /// Only what the device stores is read back, so leds that are connected but broken go unnoticed, as do leds
+    /// past the first channel of a BlinkStick Pro, which cannot be read. Fails with `FeatureErrorType::NotReadable`
+    /// on a write-only device.
+    pub fn find_unresponsive_leds(&self) -> Result<Vec<u8>, FeatureError> {
+        let previous_colors = self.get_all_led_colors()?;
+
+        let mut unresponsive = vec![false; previous_colors.len()];
+        for color in TEST_PATTERN.iter() {
+            let tested = self.set_all_leds_color(*color).and_then(|()| self.get_all_led_colors());
+            let read_colors = match tested {
+                Ok(read_colors) => read_colors,
+                Err(e) => {
+                    // The device may not take the previous colors either, the test failure is what matters
+                    let _ = self.set_all_leds_colors(&previous_colors);
+                    return Err(e);
+                }
+            };
+
+            for (led, read_color) in read_colors.iter().enumerate() {
+                unresponsive[led] |= read_color != color;
+            }
+        }
+        self.set_all_leds_colors(&previous_colors)?;
+
+        Ok(unresponsive
+            .iter()
+            .enumerate()
+            .filter(|(_, unresponsive)| **unresponsive)
+            .map(|(led, _)| led as u8)
+            .collect())
+    }
+}
+
+/// Places the colors of a frame of only the live leds into `frame`, leaving the masked leds as they are
+#[cfg(feature = "effects")]
+pub(crate) fn fill_live_leds(frame: &[Color], live_leds: &[usize], live_colors: &[Color]) -> Vec<Color> {
+    let mut frame = frame.to_vec();
+    for (led, color) in live_leds.iter().zip(live_colors.iter()) {
+        frame[*led] = *color;
+    }
+
+    frame
+}
+
+#[cfg(all(test, feature = "effects"))]
+mod tests {
+    use super::*;
+    use crate::COLOR_OFF;
+
+    #[test]
+    fn masked_leds_are_skipped() {
+        let live_leds = [0, 2, 3];
+
+        assert_eq!(
+            fill_live_leds(&[COLOR_OFF; 4], &live_leds, &[Color::RED, Color::GREEN, Color::BLUE]),
+            vec![Color::RED, COLOR_OFF, Color::GREEN, Color::BLUE]
+        );
+    }
+}
@@ -0,0 +1,254 @@
+//! Prometheus metrics for BlinkStick devices, so status light daemons can be monitored like any other service.
+//!
+//! Every BlinkStick counts the frames it sent, its failed transfers and its retries, see `BlinkStick::metrics`.
+//! A `MetricsExporter` serves them together with whether the device is connected and the color of every led on a
+//! `/metrics` endpoint in the Prometheus text format.
+
+use crate::http::{HttpServer, Response};
+use crate::{BlinkStick, Color, FeatureError, StateEvent, StateObserver};
+use std::fmt::Write;
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+
+/// The counters a BlinkStick updates as it talks to the device
+pub(crate) struct Counters {
+    frames_sent: AtomicU64,
+    errors: AtomicU64,
+    retries: AtomicU64,
+    connected: AtomicBool,
+}
+
+impl Counters {
+    pub(crate) fn new() -> Counters {
+        Counters {
+            frames_sent: AtomicU64::new(0),
+            errors: AtomicU64::new(0),
+            retries: AtomicU64::new(0),
+            connected: AtomicBool::new(true),
+        }
+    }
+}
+
+impl StateObserver for Arc<Counters> {
+    /// Counts the frames written, the retries and the failed transfers, and whether the last transfer reached the
+    /// device
+    fn observe(&mut self, event: &StateEvent) {
+        match event {
+            StateEvent::LedSet { .. } | StateEvent::FrameSet(_) => {
+                self.frames_sent.fetch_add(1, Ordering::Relaxed);
+            }
+            StateEvent::TransferRetried(_) => {
+                self.retries.fetch_add(1, Ordering::Relaxed);
+            }
+            StateEvent::TransferSucceeded => self.connected.store(true, Ordering::Relaxed),
+            StateEvent::TransferFailed(kind) => {
+                self.errors.fetch_add(1, Ordering::Relaxed);
+                let error = FeatureError { kind: kind.clone() };
+                if !error.is_transient() {
+                    self.connected.store(false, Ordering::Relaxed);
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
+/// A snapshot of the metrics of a BlinkStick
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeviceMetrics {
+    /// How many frames were written to the device
+    pub frames_sent: u64,
+    /// How many reads and writes failed, after retrying
+    pub errors: u64,
+    /// How many times a read or write was retried
+    pub retries: u64,
+    /// Whether the last read or write reached the device
+    pub connected: bool,
+    /// The color last written to every led, before brightness and gamma are applied
+    pub colors: Vec<Color>,
+}
+
+impl BlinkStick {
+    /// A snapshot of the metrics of the device, see the `metrics` module
+    pub fn metrics(&self) -> DeviceMetrics {
+        DeviceMetrics {
+            frames_sent: self.metrics.frames_sent.load(Ordering::Relaxed),
+            errors: self.metrics.errors.load(Ordering::Relaxed),
+            retries: self.metrics.retries.load(Ordering::Relaxed),
+            connected: self.metrics.connected.load(Ordering::Relaxed),
+            colors: self
+                .written_colors
+                .lock()
+                .unwrap()
+                .iter()
+                .map(|(color, _)| *color)
+                .collect(),
+        }
+    }
+}
+
+/// Writes the metrics of devices, each with its serial number, in the Prometheus text format
+pub fn encode_prometheus(devices: &[(String, DeviceMetrics)]) -> String {
+    let mut text = String::new();
+    let mut family = |name: &str, kind: &str, help: &str, sample: &dyn Fn(&DeviceMetrics) -> u64| {
+        let _ = writeln!(text, "# HELP {} {}\n# TYPE {} {}", name, help, name, kind);
+        for (serial, metrics) in devices {
+            let _ = writeln!(
+                text,
+                "{}{{serial=\"{}\"}} {}",
+                name,
+                escape_label(serial),
+                sample(metrics)
+            );
+        }
+    };
+
+    family(
+        "blinkstick_frames_sent_total",
+        "counter",
+        "Frames written to the device.",
+        &|m| m.frames_sent,
+    );
+    family(
+        "blinkstick_errors_total",
+        "counter",
+        "Failed reads and writes, after retrying.",
+        &|m| m.errors,
+    );
+    family(
+        "blinkstick_retries_total",
+        "counter",
+        "Retried reads and writes.",
+        &|m| m.retries,
+    );
+    family(
+        "blinkstick_connected",
+        "gauge",
+        "Whether the last transfer reached the device.",
+        &|m| m.connected as u64,
+    );
+
+    let name = "blinkstick_led_color";
+    let _ = writeln!(text, "# HELP {} The color channels last written to every led.", name);
+    let _ = writeln!(text, "# TYPE {} gauge", name);
+    for (serial, metrics) in devices {
+        for (led, color) in metrics.colors.iter().enumerate() {
+            for (channel, value) in [("red", color.r), ("green", color.g), ("blue", color.b)] {
+                let _ = writeln!(
+                    text,
+                    "{}{{serial=\"{}\",led=\"{}\",channel=\"{}\"}} {}",
+                    name,
+                    escape_label(serial),
+                    led,
+                    channel,
+                    value
+                );
+            }
+        }
+    }
+
+    text
+}
+
+fn escape_label(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+/// Serves the metrics of one or more BlinkSticks on `/metrics`
+///
+/// # Example
+/// Serves metrics on port 9101 while another thread drives the device
+/// ```no_run
+/// use blinkstick_rs::metrics::MetricsExporter;
+/// use blinkstick_rs::BlinkStick;
+/// use std::sync::atomic::AtomicBool;
+/// use std::sync::Arc;
+///
+/// let blinkstick = Arc::new(BlinkStick::new().unwrap());
+///
+/// let exporter = MetricsExporter::bind("0.0.0.0:9101").unwrap().device(blinkstick.clone());
+/// exporter.run(&AtomicBool::new(false)).unwrap();
+/// ```
+pub struct MetricsExporter {
+    http: HttpServer,
+    devices: Vec<(String, Arc<BlinkStick>)>,
+}
+
+impl MetricsExporter {
+    /// Listens for scrapes on `address`
+    pub fn bind<A: ToSocketAddrs>(address: A) -> std::io::Result<MetricsExporter> {
+        Ok(MetricsExporter {
+            http: HttpServer::bind(address)?,
+            devices: Vec::new(),
+        })
+    }
+
+    /// Exports the metrics of `stick` as well, labelled with its serial number
+    pub fn device(mut self, stick: Arc<BlinkStick>) -> MetricsExporter {
+        let serial = stick.serial_number().unwrap_or_default();
+        self.devices.push((serial, stick));
+        self
+    }
+
+    /// The address the exporter listens on, useful after binding to port 0
+    pub fn local_addr(&self) -> std::io::Result<SocketAddr> {
+        self.http.local_addr()
+    }
+
+    /// Answers scrapes until `stop` is set
+    pub fn run(&self, stop: &AtomicBool) -> std::io::Result<()> {
+        self.http
+            .run(stop, |request| match (request.method.as_str(), request.path.as_str()) {
+                ("GET", "/metrics") => {
+                    let devices: Vec<(String, DeviceMetrics)> = self
+                        .devices
+                        .iter()
+                        .map(|(serial, stick)| (serial.clone(), stick.metrics()))
+                        .collect();
+                    let mut response = Response::text(encode_prometheus(&devices));
+                    response.content_type = "text/plain; version=0.0.4; charset=utf-8";
+                    response
+                }
+                (_, "/metrics") => Response::error(405, "method not allowed"),
+                _ => Response::error(404, "not found"),
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_prometheus_text() {
+        let metrics = DeviceMetrics {
+            frames_sent: 12,
+            errors: 1,
+            retries: 3,
+            connected: true,
+            colors: vec![Color { r: 255, g: 0, b: 7 }],
+        };
+        let text = encode_prometheus(&[("BS0\"1".to_string(), metrics)]);
+
+        assert!(text.contains("# TYPE blinkstick_frames_sent_total counter\n"));
+        assert!(text.contains("blinkstick_frames_sent_total{serial=\"BS0\\\"1\"} 12\n"));
+        assert!(text.contains("blinkstick_connected{serial=\"BS0\\\"1\"} 1\n"));
+        assert!(text.contains("blinkstick_led_color{serial=\"BS0\\\"1\",led=\"0\",channel=\"blue\"} 7\n"));
+    }
+
+    #[test]
+    fn counts_the_recorded_writes_and_failures() {
+        let blinkstick = crate::BlinkStickBuilder::new().leds(4).open_disabled().unwrap();
+        let frames_sent = blinkstick.metrics().frames_sent;
+
+        blinkstick.set_all_leds_color(Color::RED).unwrap();
+        blinkstick.record(|| StateEvent::TransferRetried(crate::FeatureErrorType::Send));
+        blinkstick.record(|| StateEvent::TransferFailed(crate::FeatureErrorType::Disconnected));
+
+        let metrics = blinkstick.metrics();
+        assert_eq!(metrics.frames_sent, frames_sent + 1);
+        assert_eq!((metrics.retries, metrics.errors), (1, 1));
+        assert!(!metrics.connected);
+    }
+}
@@ -0,0 +1,145 @@
+use crate::builder::probe_leds;
+use crate::wire::{encode_led_count_report, encode_mode_report, MODE_REPORT_ID};
+use crate::{BlinkStick, Color, FeatureError, Variant, COLOR_OFF};
+use std::sync::atomic::Ordering;
+
+/// How a BlinkStick Pro drives the leds connected to it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Mode {
+    /// Drives a single common cathode RGB led
+    Normal,
+    /// Drives a single common anode RGB led, with every channel inverted
+    Inverse,
+    /// Drives WS2812 led strips
+    Ws2812,
+    /// Drives WS2812 led strips, mirroring the first channel on the others
+    Ws2812Mirror,
+}
+
+impl Mode {
+    /// The mode as it is stored on the device
+    pub fn to_byte(self) -> u8 {
+        match self {
+            Mode::Normal => 0,
+            Mode::Inverse => 1,
+            Mode::Ws2812 => 2,
+            Mode::Ws2812Mirror => 3,
+        }
+    }
+
+    /// The mode stored on a device as `byte`, `None` if the firmware reports an unknown mode
+    pub fn from_byte(byte: u8) -> Option<Mode> {
+        match byte {
+            0 => Some(Mode::Normal),
+            1 => Some(Mode::Inverse),
+            2 => Some(Mode::Ws2812),
+            3 => Some(Mode::Ws2812Mirror),
+            _ => None,
+        }
+    }
+}
+
+impl BlinkStick {
+    /// Reads the mode of the device, `None` if the firmware reports a mode this crate doesn't know
+    pub fn mode(&self) -> Result<Option<Mode>, FeatureError> {
+        let report = self.get_feature_from_blinkstick(MODE_REPORT_ID)?;
+        Ok(report.get(1).copied().and_then(Mode::from_byte))
+    }
+
+    /// Returns `true` if the device is in `Mode::Inverse`, as detected when it was opened or set with `set_mode`
+    ///
+    /// Colors written to an inverted device are inverted before they are sent, and colors read back inverted
+    /// again, so `set_led_color(0, Color::RED)` shows red in either mode.
+    pub fn is_inverted(&self) -> bool {
+        self.inverted.load(Ordering::Relaxed)
+    }
+
+    /// Reads the mode of the devices that have one, to invert colors for `Mode::Inverse`. A mode that cannot be
+    /// read is taken as normal.
+    pub(crate) fn detect_inversion(&self) {
+        let inverted = match self.variant {
+            Variant::BlinkStick | Variant::Pro | Variant::Unknown => {
+                !self.is_write_only() && matches!(self.mode(), Ok(Some(Mode::Inverse)))
+            }
+            _ => false,
+        };
+        self.inverted.store(inverted, Ordering::Relaxed);
+    }
+
+    /// Changes the mode of a BlinkStick Pro and re-probes the device, as the mode decides how many leds it drives
+    ///
+    /// # Example
+    /// ```
+    /// use blinkstick_rs::{BlinkStick, Mode};
+    ///
+    /// let mut blinkstick = BlinkStick::new().unwrap();
+    /// blinkstick.set_mode(Mode::Ws2812).unwrap();
+    /// ```
+    pub fn set_mode(&mut self, mode: Mode) -> Result<(), FeatureError> {
+        self.send_feature_to_blinkstick(&encode_mode_report(mode.to_byte()))?;
+        self.inverted.store(mode == Mode::Inverse, Ordering::Relaxed);
+        self.reprobe()
+    }
+
+    /// Changes the number of leds a BlinkStick Pro or Flex drives and re-probes the device
+    ///
+    /// The count replaces any number of leds configured with `BlinkStickBuilder::leds` or `BlinkStickBuilder::channels`.
+    ///
+    /// # Panics
+    /// The `set_led_count` function will panic if `count` is zero.
+    pub fn set_led_count(&mut self, count: u8) -> Result<(), FeatureError> {
+        assert!(count > 0, "A BlinkStick needs at least one led");
+
+        self.send_feature_to_blinkstick(&encode_led_count_report(count))?;
+        self.led_override = Some(count);
+        self.channel_layout = None;
+        self.reprobe()
+    }
+
+    /// Re-reads the number of leds and the size of the leds report from the device
+    ///
+    /// `set_mode` and `set_led_count` re-probe by themselves. Call this after the device was reconfigured by
+    /// another program, so `max_leds` matches the device again. Leds that are new since the last probe are
+    /// treated as off.
+    pub fn reprobe(&mut self) -> Result<(), FeatureError> {
+        let (max_leds, report_length) = probe_leds(
+            &self.device.lock().unwrap(),
+            self.variant,
+            self.led_override,
+            self.channel_layout.as_deref(),
+        )?;
+        self.max_leds = max_leds;
+        self.report_length = report_length;
+        self.written_colors
+            .get_mut()
+            .unwrap()
+            .resize(max_leds as usize, (COLOR_OFF, COLOR_OFF));
+
+        Ok(())
+    }
+}
+
+/// The color a device in `Mode::Inverse` is sent to show `color`, and the color it shows for a color it reports
+pub(crate) fn invert(color: Color) -> Color {
+    Color::new(u8::MAX - color.r, u8::MAX - color.g, u8::MAX - color.b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn modes_round_trip_through_bytes() {
+        for mode in [Mode::Normal, Mode::Inverse, Mode::Ws2812, Mode::Ws2812Mirror].iter() {
+            assert_eq!(Mode::from_byte(mode.to_byte()), Some(*mode));
+        }
+        assert_eq!(Mode::from_byte(4), None);
+    }
+
+    #[test]
+    fn inverting_twice_shows_the_color() {
+        let orange = Color::new(255, 128, 0);
+        assert_eq!(invert(orange), Color::new(0, 127, 255));
+        assert_eq!(invert(invert(orange)), orange);
+    }
+}
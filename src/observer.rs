@@ -0,0 +1,279 @@
+use crate::{BlinkStick, ChannelOrder, Color, ColorProfile, DeviceEvent, Dithering, FeatureErrorType, FrameRecorder};
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::ThreadId;
+use std::time::{Duration, Instant};
+
+/// A change to the state of a BlinkStick, handed to every observer in the order the changes happened, see
+/// `BlinkStick::observe`
+///
+/// Every write to the leds is recorded as one of these events, so replaying them from the start rebuilds what the
+/// device was told to show. Of the configuration only the color profile, channel order and dithering are recorded,
+/// response curves, dimming schedules, adaptive brightness, the mode, the led count, write-only mode and the
+/// layout change without an event.
+#[derive(Debug, Clone, PartialEq)]
+pub enum StateEvent {
+    /// A single led was set, with the color as written, before brightness, gamma and dithering
+    LedSet { led: u8, color: Color },
+    /// Every led was set, with the colors as written
+    FrameSet(Vec<Color>),
+    /// The color profile changed, see `BlinkStick::set_color_profile`
+    ColorProfileChanged(ColorProfile),
+    /// The channel order changed, see `BlinkStick::set_channel_order`
+    ChannelOrderChanged(ChannelOrder),
+    /// The dithering changed, see `BlinkStick::set_dithering`
+    DitheringChanged(Dithering),
+    /// An `Animator` rendering to the BlinkStick started an effect
+    EffectStarted { id: u64, name: String },
+    /// An effect started by an `Animator` ended, whether it completed, was cancelled or failed
+    EffectEnded { id: u64 },
+    /// A transfer with the device failed transiently and is tried again
+    TransferRetried(FeatureErrorType),
+    /// A transfer with the device succeeded, after any retries
+    TransferSucceeded,
+    /// A transfer with the device failed, after any retries
+    TransferFailed(FeatureErrorType),
+}
+
+/// Receives every `StateEvent` of a BlinkStick it was registered with, see `BlinkStick::observe`
+///
+/// Observers are called on the thread that changed the state, while the change is recorded, so they should hand
+/// slow work to another thread. Closures taking a `&StateEvent` are observers.
+pub trait StateObserver: Send {
+    fn observe(&mut self, event: &StateEvent);
+}
+
+impl<F: FnMut(&StateEvent) + Send> StateObserver for F {
+    fn observe(&mut self, event: &StateEvent) {
+        self(event)
+    }
+}
+
+/// Identifies an observer registered with `BlinkStick::observe`, to remove it again
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ObserverId(u64);
+
+type SharedObserver = Arc<Mutex<Box<dyn StateObserver>>>;
+
+/// The observers registered with a BlinkStick
+#[derive(Default)]
+pub(crate) struct Observers {
+    next_id: AtomicU64,
+    observers: Mutex<Vec<(ObserverId, SharedObserver)>>,
+    /// The events recorded by observers while they are notified, per notifying thread, handed out once the event
+    /// being notified is done
+    queued: Mutex<HashMap<ThreadId, VecDeque<StateEvent>>>,
+}
+
+impl Observers {
+    fn add(&self, observer: Box<dyn StateObserver>) -> ObserverId {
+        let id = ObserverId(self.next_id.fetch_add(1, Ordering::Relaxed));
+        self.observers
+            .lock()
+            .unwrap()
+            .push((id, Arc::new(Mutex::new(observer))));
+        id
+    }
+
+    fn remove(&self, id: ObserverId) -> bool {
+        let mut observers = self.observers.lock().unwrap();
+        let count = observers.len();
+        observers.retain(|(observer, _)| *observer != id);
+        observers.len() < count
+    }
+
+    fn is_empty(&self) -> bool {
+        self.observers.lock().unwrap().is_empty()
+    }
+
+    /// Hands an event to every observer without holding the list, so observers may write to the BlinkStick or
+    /// register observers themselves. Events recorded while an observer runs are handed out after the current one.
+    fn notify(&self, event: &StateEvent) {
+        let thread = std::thread::current().id();
+        {
+            let mut queued = self.queued.lock().unwrap();
+            if let Some(events) = queued.get_mut(&thread) {
+                events.push_back(event.clone());
+                return;
+            }
+            queued.insert(thread, VecDeque::new());
+        }
+        let _dispatching = Dispatching(self, thread);
+
+        let mut event = event.clone();
+        loop {
+            let observers: Vec<SharedObserver> = self
+                .observers
+                .lock()
+                .unwrap()
+                .iter()
+                .map(|(_, observer)| observer.clone())
+                .collect();
+            for observer in observers {
+                observer.lock().unwrap().observe(&event);
+            }
+
+            match self
+                .queued
+                .lock()
+                .unwrap()
+                .get_mut(&thread)
+                .and_then(VecDeque::pop_front)
+            {
+                Some(next) => event = next,
+                None => return,
+            }
+        }
+    }
+}
+
+/// Ends the notification on a thread when dropped, even if an observer panicked
+struct Dispatching<'a>(&'a Observers, ThreadId);
+
+impl Drop for Dispatching<'_> {
+    fn drop(&mut self) {
+        self.0.queued.lock().unwrap().remove(&self.1);
+    }
+}
+
+impl BlinkStick {
+    /// Hands every change to the state of the BlinkStick from now on to `observer`, until it is removed with
+    /// `unobserve`
+    ///
+    /// UIs, loggers and recorders all learn about writes, configuration changes, effects and failures this way,
+    /// without hooking into the write path themselves. `subscribe`, the diagnostics and the metrics are fed from the
+    /// same events.
+    ///
+    /// # Example
+    /// Logs every change of the device
+    /// ```
+    /// use blinkstick_rs::{BlinkStick, Color, StateEvent};
+    ///
+    /// let blinkstick = BlinkStick::new().unwrap();
+    /// let id = blinkstick.observe(|event: &StateEvent| println!("{:?}", event));
+    ///
+    /// blinkstick.set_led_color(0, Color {r: 0, g: 30, b: 0}).unwrap();
+    /// assert!(blinkstick.unobserve(id));
+    /// ```
+    pub fn observe<O: StateObserver + 'static>(&self, observer: O) -> ObserverId {
+        self.observers.add(Box::new(observer))
+    }
+
+    /// Stops handing changes to the observer `id`, returning whether it was registered
+    pub fn unobserve(&self, id: ObserverId) -> bool {
+        self.observers.remove(id)
+    }
+
+    /// Records a change to the state of the BlinkStick, only creating the event if anyone observes it
+    pub(crate) fn record<F: FnOnce() -> StateEvent>(&self, event: F) {
+        if self.observers.is_empty() && !self.event_listeners.is_listened() {
+            return;
+        }
+
+        let event = event();
+        // The device events are the part of the state events `subscribe` has always reported
+        match &event {
+            StateEvent::LedSet { .. } => self
+                .event_listeners
+                .emit(|| DeviceEvent::FrameWritten(self.written_frame())),
+            StateEvent::FrameSet(colors) => self.event_listeners.emit(|| DeviceEvent::FrameWritten(colors.clone())),
+            StateEvent::TransferFailed(kind) => self.event_listeners.emit(|| DeviceEvent::Error(kind.clone())),
+            _ => (),
+        }
+        self.observers.notify(&event);
+    }
+}
+
+impl StateObserver for FrameRecorder {
+    /// Records every frame written to a BlinkStick, single leds changing the frame recorded last. A frame stays
+    /// visible until the next one is recorded, the last one for no time at all.
+    fn observe(&mut self, event: &StateEvent) {
+        let mut frames = self.frames.lock().unwrap();
+        let frame = match event {
+            StateEvent::FrameSet(colors) => colors.clone(),
+            StateEvent::LedSet { led, color } => {
+                let mut frame = frames.last().map(|(frame, _)| frame.clone()).unwrap_or_default();
+                if frame.len() <= *led as usize {
+                    frame.resize(*led as usize + 1, Color::default());
+                }
+                frame[*led as usize] = *color;
+                frame
+            }
+            _ => return,
+        };
+
+        let now = Instant::now();
+        let mut observed = self.observed.lock().unwrap();
+        if let (Some((_, visible)), Some(previous)) = (frames.last_mut(), *observed) {
+            *visible = now - previous;
+        }
+        *observed = Some(now);
+        frames.push((frame, Duration::ZERO));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recorders_replay_led_changes_onto_the_last_frame() {
+        let mut recorder = FrameRecorder::new();
+        recorder.observe(&StateEvent::FrameSet(vec![Color::BLUE; 3]));
+        recorder.observe(&StateEvent::ColorProfileChanged(ColorProfile::default()));
+        recorder.observe(&StateEvent::LedSet {
+            led: 1,
+            color: Color::RED,
+        });
+
+        let frames: Vec<Vec<Color>> = recorder.frames().into_iter().map(|(frame, _)| frame).collect();
+        assert_eq!(
+            frames,
+            vec![vec![Color::BLUE; 3], vec![Color::BLUE, Color::RED, Color::BLUE]]
+        );
+    }
+
+    #[test]
+    fn removed_observers_see_no_further_events() {
+        let observers = Observers::default();
+        let seen = std::sync::Arc::new(Mutex::new(Vec::new()));
+        let id = {
+            let seen = seen.clone();
+            observers.add(Box::new(move |event: &StateEvent| {
+                seen.lock().unwrap().push(event.clone())
+            }))
+        };
+
+        observers.notify(&StateEvent::EffectEnded { id: 4 });
+        assert!(observers.remove(id));
+        observers.notify(&StateEvent::EffectEnded { id: 5 });
+
+        assert_eq!(*seen.lock().unwrap(), vec![StateEvent::EffectEnded { id: 4 }]);
+        assert!(!observers.remove(id));
+    }
+
+    #[test]
+    fn observers_may_record_events_themselves() {
+        let observers = Arc::new(Observers::default());
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        {
+            let (inner, seen) = (observers.clone(), seen.clone());
+            observers.add(Box::new(move |event: &StateEvent| {
+                seen.lock().unwrap().push(event.clone());
+                if let StateEvent::EffectEnded { id: 1 } = event {
+                    inner.notify(&StateEvent::EffectEnded { id: 2 });
+                    inner.add(Box::new(|_: &StateEvent| ()));
+                }
+            }));
+        }
+
+        observers.notify(&StateEvent::EffectEnded { id: 1 });
+
+        assert_eq!(
+            *seen.lock().unwrap(),
+            vec![StateEvent::EffectEnded { id: 1 }, StateEvent::EffectEnded { id: 2 }]
+        );
+        assert_eq!(observers.observers.lock().unwrap().len(), 2);
+    }
+}
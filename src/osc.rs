@@ -0,0 +1,356 @@
+//! Open Sound Control input over UDP, so lighting and VJ software like TouchOSC can drive a BlinkStick live.
+//!
+//! Understood addresses, with colors given as three integers (0 - 255) or three floats (0.0 - 1.0):
+//! * `/blinkstick/color r g b` sets every led
+//! * `/blinkstick/led/{led}/color r g b` sets a single led
+//! * `/blinkstick/off` turns every led off
+//! * `/blinkstick/effect/pulse r g b [seconds]` pulses every led to a color and back
+//! * `/blinkstick/effect/blink r g b [blinks]` blinks every led in a color
+//! * `/blinkstick/effect/{name}` plays a theme or registered effect, see `effects::animation`
+
+use crate::{effects, AnimationIter, Animator, Color, COLOR_OFF};
+use std::convert::TryFrom;
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+/// The largest packet a receiver accepts
+const MAX_PACKET_BYTES: usize = 1536;
+
+/// How often `OscReceiver::run` checks its stop flag while no packets arrive
+const STOP_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// An argument of an OSC message
+#[derive(Debug, Clone, PartialEq)]
+pub enum OscArgument {
+    Int(i32),
+    Float(f32),
+    String(String),
+}
+
+impl OscArgument {
+    fn as_f32(&self) -> Option<f32> {
+        match self {
+            OscArgument::Int(int) => Some(*int as f32),
+            OscArgument::Float(float) => Some(*float),
+            OscArgument::String(_) => None,
+        }
+    }
+}
+
+/// An OSC message: an address and its arguments
+#[derive(Debug, Clone, PartialEq)]
+pub struct OscMessage {
+    pub address: String,
+    pub arguments: Vec<OscArgument>,
+}
+
+/// What an OSC message asks the BlinkStick to do
+#[derive(Debug, Clone, PartialEq)]
+pub enum OscCommand {
+    SetAll(Color),
+    SetLed { led: u8, color: Color },
+    Off,
+    Pulse { color: Color, duration: Duration },
+    Blink { color: Color, blinks: u32 },
+    Effect(String),
+}
+
+impl OscCommand {
+    /// Maps a message onto a command, `None` for addresses and arguments that are not understood
+    pub fn from_message(message: &OscMessage) -> Option<OscCommand> {
+        let path: Vec<&str> = message.address.trim_start_matches('/').split('/').collect();
+        let arguments = &message.arguments;
+        let number = |index: usize| arguments.get(index).and_then(OscArgument::as_f32);
+
+        match path.as_slice() {
+            ["blinkstick", "color"] => Some(OscCommand::SetAll(color_argument(arguments)?)),
+            ["blinkstick", "led", led, "color"] => Some(OscCommand::SetLed {
+                led: led.parse().ok()?,
+                color: color_argument(arguments)?,
+            }),
+            ["blinkstick", "off"] => Some(OscCommand::Off),
+            ["blinkstick", "effect", "pulse"] => Some(OscCommand::Pulse {
+                color: color_argument(arguments)?,
+                duration: Duration::try_from_secs_f32(number(3).unwrap_or(1.0)).ok()?,
+            }),
+            ["blinkstick", "effect", "blink"] => Some(OscCommand::Blink {
+                color: color_argument(arguments)?,
+                blinks: number(3).unwrap_or(1.0).max(0.0) as u32,
+            }),
+            ["blinkstick", "effect", name] => Some(OscCommand::Effect(name.to_string())),
+            _ => None,
+        }
+    }
+}
+
+/// Reads a color from the first three arguments, integers (0 - 255) or floats (0.0 - 1.0)
+fn color_argument(arguments: &[OscArgument]) -> Option<Color> {
+    let channel = |argument: &OscArgument| match argument {
+        OscArgument::Int(int) => Some((*int).clamp(0, 255) as u8),
+        OscArgument::Float(float) => Some((float.clamp(0.0, 1.0) * 255.0).round() as u8),
+        OscArgument::String(_) => None,
+    };
+
+    match arguments {
+        [r, g, b, ..] => Some(Color {
+            r: channel(r)?,
+            g: channel(g)?,
+            b: channel(b)?,
+        }),
+        _ => None,
+    }
+}
+
+/// Decodes the messages of an OSC packet, a single message or a bundle of them, which may contain bundles itself.
+/// Decoding stops at the first malformed message: a malformed packet decodes to no messages, and a bundle to the
+/// messages before the malformed one.
+pub fn decode_packet(packet: &[u8]) -> Vec<OscMessage> {
+    let mut messages = Vec::new();
+    decode_into(packet, &mut messages);
+    messages
+}
+
+fn decode_into(packet: &[u8], messages: &mut Vec<OscMessage>) -> Option<()> {
+    let mut cursor = 0;
+    let address = read_string(packet, &mut cursor)?;
+
+    if address == "#bundle" {
+        // The time tag is ignored, every message is applied as it arrives
+        cursor += 8;
+        while cursor < packet.len() {
+            let size = usize::try_from(read_i32(packet, &mut cursor)?).ok()?;
+            decode_into(packet.get(cursor..cursor.checked_add(size)?)?, messages)?;
+            cursor += size;
+        }
+        return Some(());
+    }
+
+    let type_tags = read_string(packet, &mut cursor)?;
+    let mut arguments = Vec::new();
+    for tag in type_tags.strip_prefix(',')?.chars() {
+        arguments.push(match tag {
+            'i' => OscArgument::Int(read_i32(packet, &mut cursor)?),
+            'f' => OscArgument::Float(f32::from_bits(read_i32(packet, &mut cursor)? as u32)),
+            's' => OscArgument::String(read_string(packet, &mut cursor)?),
+            _ => return None,
+        });
+    }
+
+    messages.push(OscMessage { address, arguments });
+    Some(())
+}
+
+/// Reads a nul terminated string padded to four bytes
+fn read_string(packet: &[u8], cursor: &mut usize) -> Option<String> {
+    let rest = packet.get(*cursor..)?;
+    let length = rest.iter().position(|byte| *byte == 0)?;
+    let string = std::str::from_utf8(&rest[..length]).ok()?.to_string();
+    *cursor += (length + 4) & !3;
+    Some(string)
+}
+
+/// Reads a big endian 32 bit integer
+fn read_i32(packet: &[u8], cursor: &mut usize) -> Option<i32> {
+    let bytes = packet.get(*cursor..*cursor + 4)?;
+    *cursor += 4;
+    Some(i32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+/// Receives OSC messages over UDP and plays them on an `Animator`
+///
+/// # Example
+/// Listens on port 9000 until the program is stopped
+/// ```no_run
+/// use blinkstick_rs::osc::OscReceiver;
+/// use blinkstick_rs::{Animator, BlinkStick};
+/// use std::sync::atomic::AtomicBool;
+///
+/// let blinkstick = BlinkStick::new().unwrap();
+/// let leds = blinkstick.max_leds as usize;
+///
+/// let mut receiver = OscReceiver::bind("0.0.0.0:9000", Animator::new(blinkstick), leds).unwrap();
+/// receiver.run(&AtomicBool::new(false)).unwrap();
+/// ```
+pub struct OscReceiver {
+    socket: UdpSocket,
+    animator: Animator,
+    led_count: u8,
+    frame: Vec<Color>,
+}
+
+impl OscReceiver {
+    /// Listens for OSC messages on `address`, driving the `led_count` leds of `animator`. Fails with
+    /// `ErrorKind::InvalidInput` for more leds than a BlinkStick drives.
+    pub fn bind<A: ToSocketAddrs>(address: A, animator: Animator, led_count: usize) -> std::io::Result<OscReceiver> {
+        let led_count = u8::try_from(led_count).map_err(|_| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("A BlinkStick drives at most {} leds, not {}", u8::MAX, led_count),
+            )
+        })?;
+        let socket = UdpSocket::bind(address)?;
+        socket.set_read_timeout(Some(STOP_POLL_INTERVAL))?;
+
+        Ok(OscReceiver {
+            socket,
+            animator,
+            led_count,
+            frame: vec![COLOR_OFF; led_count as usize],
+        })
+    }
+
+    /// The address the receiver listens on, useful after binding to port 0
+    pub fn local_addr(&self) -> std::io::Result<SocketAddr> {
+        self.socket.local_addr()
+    }
+
+    /// Receives and applies messages until `stop` is set
+    pub fn run(&mut self, stop: &AtomicBool) -> std::io::Result<()> {
+        let mut packet = [0u8; MAX_PACKET_BYTES];
+        while !stop.load(Ordering::Relaxed) {
+            match self.socket.recv(&mut packet) {
+                Ok(length) => {
+                    for message in decode_packet(&packet[..length]) {
+                        if let Some(command) = OscCommand::from_message(&message) {
+                            self.apply(command);
+                        }
+                    }
+                }
+                Err(e) if matches!(e.kind(), std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut) => (),
+                Err(e) => return Err(e),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Plays a command, replacing whatever the animator was playing. Effects that are not known are ignored.
+    pub fn apply(&mut self, command: OscCommand) {
+        let led_count = self.led_count as usize;
+        let leds: Vec<u8> = (0..self.led_count).collect();
+        let (name, animation) = match command {
+            OscCommand::SetAll(color) => {
+                self.frame = vec![color; led_count];
+                (
+                    "osc color",
+                    AnimationIter::new(vec![(self.frame.clone(), Duration::ZERO)]),
+                )
+            }
+            OscCommand::SetLed { led, color } => {
+                if let Some(current) = self.frame.get_mut(led as usize) {
+                    *current = color;
+                }
+                (
+                    "osc led",
+                    AnimationIter::new(vec![(self.frame.clone(), Duration::ZERO)]),
+                )
+            }
+            OscCommand::Off => {
+                self.frame = vec![COLOR_OFF; led_count];
+                (
+                    "osc off",
+                    AnimationIter::new(vec![(self.frame.clone(), Duration::ZERO)]),
+                )
+            }
+            OscCommand::Pulse { color, duration } => (
+                "osc pulse",
+                AnimationIter::pulse(self.frame.clone(), vec![color; led_count], duration, 25),
+            ),
+            OscCommand::Blink { color, blinks } => (
+                "osc blink",
+                AnimationIter::blink(self.frame.clone(), &leds, color, Duration::from_millis(250), blinks),
+            ),
+            OscCommand::Effect(name) => match effects::animation(&name) {
+                Some(animation) => ("osc effect", animation.frames(led_count)),
+                None => return,
+            },
+        };
+
+        self.animator.play(name, animation);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Encodes a message the way OSC senders do
+    fn encode(address: &str, arguments: &[OscArgument]) -> Vec<u8> {
+        let pad = |bytes: &mut Vec<u8>, string: &str| {
+            bytes.extend_from_slice(string.as_bytes());
+            bytes.extend(std::iter::repeat_n(0, 4 - string.len() % 4));
+        };
+
+        let mut packet = Vec::new();
+        pad(&mut packet, address);
+        let tags: String = arguments
+            .iter()
+            .map(|argument| match argument {
+                OscArgument::Int(_) => 'i',
+                OscArgument::Float(_) => 'f',
+                OscArgument::String(_) => 's',
+            })
+            .collect();
+        pad(&mut packet, &format!(",{}", tags));
+        for argument in arguments {
+            match argument {
+                OscArgument::Int(int) => packet.extend_from_slice(&int.to_be_bytes()),
+                OscArgument::Float(float) => packet.extend_from_slice(&float.to_bits().to_be_bytes()),
+                OscArgument::String(string) => pad(&mut packet, string),
+            }
+        }
+        packet
+    }
+
+    #[test]
+    fn decodes_led_colors() {
+        let arguments = [OscArgument::Int(255), OscArgument::Float(0.5), OscArgument::Int(0)];
+        let messages = decode_packet(&encode("/blinkstick/led/3/color", &arguments));
+
+        assert_eq!(
+            OscCommand::from_message(&messages[0]),
+            Some(OscCommand::SetLed {
+                led: 3,
+                color: Color { r: 255, g: 128, b: 0 }
+            })
+        );
+    }
+
+    #[test]
+    fn decodes_bundles_and_ignores_garbage() {
+        let message = encode("/blinkstick/off", &[]);
+        let mut bundle = encode("#bundle", &[])[..8].to_vec();
+        bundle.extend_from_slice(&[0; 8]);
+        bundle.extend_from_slice(&(message.len() as i32).to_be_bytes());
+        bundle.extend_from_slice(&message);
+
+        assert_eq!(decode_packet(&bundle).len(), 1);
+        assert!(decode_packet(&[0xff, 1, 2]).is_empty());
+        assert!(decode_packet(&bundle[..bundle.len() - 2]).is_empty());
+
+        let mut garbage = bundle.clone();
+        garbage.extend_from_slice(&(-4i32).to_be_bytes());
+        garbage.extend_from_slice(&[0; 4]);
+        assert_eq!(decode_packet(&garbage).len(), 1);
+    }
+
+    #[test]
+    fn receivers_drive_at_most_the_leds_of_a_blinkstick() {
+        let animator = || Animator::headless_with_clock(std::sync::Arc::new(crate::ManualClock::new()));
+
+        let error = OscReceiver::bind("127.0.0.1:0", animator(), 256).err().unwrap();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidInput);
+        assert!(OscReceiver::bind("127.0.0.1:0", animator(), 255).is_ok());
+    }
+
+    #[test]
+    fn effects_are_launched_by_name() {
+        let messages = decode_packet(&encode("/blinkstick/effect/christmas", &[]));
+
+        assert_eq!(
+            OscCommand::from_message(&messages[0]),
+            Some(OscCommand::Effect("christmas".to_string()))
+        );
+    }
+}
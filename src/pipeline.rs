@@ -0,0 +1,449 @@
+use crate::render::{gamma_correct, scale_color};
+use crate::{
+    effects, BlinkStick, Color, EffectStepper, FeatureError, FrameRecorder, FrameSink, LedDevice, NoopBlinkStick,
+    Smoother,
+};
+use blinkstick_core::COLOR_OFF;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fmt::Formatter;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// The description of a whole effect pipeline: the effects rendering the frame, the filters every frame passes
+/// through in order and the sinks it is sent to, see `Pipeline::from_config`
+///
+/// With the `serde` feature the description deserializes from any format serde reads, so the lighting of an
+/// application is configured instead of compiled in. Filters and sinks are tagged by their `type`.
+///
+/// # Example
+/// The pride colors over the first four leds and Christmas over the rest, dimmed and smoothed, shown on a device
+/// ```toml
+/// leds = 8
+///
+/// [[sources]]
+/// effect = "pride"
+/// leds = 4
+///
+/// [[sources]]
+/// effect = "christmas"
+/// offset = 4
+///
+/// [[filters]]
+/// type = "brightness"
+/// factor = 0.3
+///
+/// [[filters]]
+/// type = "smoothing"
+/// time_constant_ms = 80
+///
+/// [[sinks]]
+/// type = "device"
+/// ```
+#[derive(Debug, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct PipelineConfig {
+    /// The number of leds of every frame, the led count of the first device sink if not given. The leds of a device
+    /// sink past the end of the frame stay off.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub leds: Option<usize>,
+    /// The effects rendering the frame, later sources drawing over earlier ones where they overlap
+    pub sources: Vec<SourceConfig>,
+    /// The filters every frame passes through, in order
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub filters: Vec<FilterConfig>,
+    /// The destinations every filtered frame is sent to
+    pub sinks: Vec<SinkConfig>,
+}
+
+/// An effect rendering a range of the leds of a pipeline
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SourceConfig {
+    /// The name of the effect, anything `effects::animation` knows
+    pub effect: String,
+    /// The first led the effect renders
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub offset: usize,
+    /// The number of leds the effect renders, every led from `offset` to the end of the frame if not given
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub leds: Option<usize>,
+}
+
+/// A step every frame of a pipeline passes through
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "type", rename_all = "snake_case"))]
+pub enum FilterConfig {
+    /// Scales every color by `factor` (0.0 - 1.0)
+    Brightness { factor: f32 },
+    /// Corrects every color with `gamma`, see `render::gamma_correct`
+    Gamma { gamma: f32 },
+    /// Eases every led towards its new color over `time_constant_ms`, see `Smoother`
+    Smoothing { time_constant_ms: u64 },
+}
+
+/// A destination for the frames of a pipeline
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "type", rename_all = "snake_case"))]
+pub enum SinkConfig {
+    /// A BlinkStick, the one with the serial number if given or the first one found otherwise
+    Device {
+        #[cfg_attr(feature = "serde", serde(default))]
+        serial: Option<String>,
+    },
+    /// A `NoopBlinkStick` standing in for a device, see `Pipeline::simulators`
+    Simulator,
+    /// A `FrameRecorder` keeping every frame, see `Pipeline::recorders`
+    Recorder,
+}
+
+/// An error while building a `Pipeline` from its description
+#[derive(Debug)]
+pub enum PipelineError {
+    /// No effect is known by the name of a source
+    UnknownEffect(String),
+    /// The source at this position renders leds outside of the frame
+    SourceOutOfRange(usize),
+    /// The number of leds was not given and there is no device sink to take it from
+    NoLedCount,
+    /// The frame has more leds than the sink at this position can show
+    TooManyLeds { sink: usize, max_leds: usize },
+    /// A device sink could not be opened
+    Device(FeatureError),
+}
+
+impl std::fmt::Display for PipelineError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PipelineError::UnknownEffect(name) => write!(f, "Unknown effect {:?}", name),
+            PipelineError::SourceOutOfRange(source) => write!(f, "Source {} renders leds outside of the frame", source),
+            PipelineError::NoLedCount => write!(f, "The number of leds is neither given nor known from a device"),
+            PipelineError::TooManyLeds { sink, max_leds } => {
+                write!(
+                    f,
+                    "Sink {} shows at most {} leds, fewer than the frame has",
+                    sink, max_leds
+                )
+            }
+            PipelineError::Device(e) => write!(f, "Could not open a device sink: {}", e),
+        }
+    }
+}
+
+impl Error for PipelineError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            PipelineError::Device(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<FeatureError> for PipelineError {
+    fn from(e: FeatureError) -> PipelineError {
+        PipelineError::Device(e)
+    }
+}
+
+/// A filter of a pipeline, ready to run
+enum Filter {
+    Brightness(f32),
+    Gamma(f32),
+    Smoothing(Smoother),
+}
+
+impl Filter {
+    fn apply(&mut self, frame: Vec<Color>, dt: Duration) -> Vec<Color> {
+        match self {
+            Filter::Brightness(factor) => frame.into_iter().map(|color| scale_color(color, *factor)).collect(),
+            Filter::Gamma(gamma) => frame.into_iter().map(|color| gamma_correct(color, *gamma)).collect(),
+            Filter::Smoothing(smoother) => smoother.apply(&frame, dt),
+        }
+    }
+}
+
+/// A device sink with at least as many leds as the frame, the leds past the end of the frame turned off
+struct PaddedDevice<D: LedDevice + ?Sized>(Arc<D>);
+
+impl<D: LedDevice + ?Sized> FrameSink for PaddedDevice<D> {
+    fn send_frame(&mut self, frame: &[Color], duration: Duration) -> Result<(), FeatureError> {
+        let mut padded = frame.to_vec();
+        padded.resize(self.0.led_count() as usize, COLOR_OFF);
+        self.0.send_frame(&padded, duration)
+    }
+
+    fn recover(&mut self) -> Result<(), FeatureError> {
+        self.0.recover()
+    }
+}
+
+/// Effects composited into one frame, filtered and sent to every sink, built from a `PipelineConfig`
+///
+/// A pipeline is stepped by `tick` from an application's own loop, or plays until its effects end with `run`.
+///
+/// # Example
+/// Records a dimmed theme without any hardware
+/// ```
+/// use blinkstick_rs::{FilterConfig, Pipeline, PipelineConfig, SinkConfig, SourceConfig};
+/// use std::time::Duration;
+///
+/// let config = PipelineConfig {
+///     leds: Some(8),
+///     sources: vec![SourceConfig {effect: "christmas".to_string(), offset: 0, leds: None}],
+///     filters: vec![FilterConfig::Brightness {factor: 0.5}],
+///     sinks: vec![SinkConfig::Recorder],
+/// };
+///
+/// let mut pipeline = Pipeline::from_config(&config).unwrap();
+/// pipeline.tick(Duration::from_millis(20)).unwrap();
+/// assert_eq!(pipeline.recorders()[0].frames().len(), 1);
+/// ```
+pub struct Pipeline {
+    led_count: usize,
+    sources: Vec<(usize, EffectStepper)>,
+    filters: Vec<Filter>,
+    sinks: Vec<Box<dyn FrameSink>>,
+    simulators: Vec<Arc<NoopBlinkStick>>,
+    recorders: Vec<FrameRecorder>,
+}
+
+impl Pipeline {
+    /// Builds the pipeline `config` describes, opening its device sinks
+    pub fn from_config(config: &PipelineConfig) -> Result<Pipeline, PipelineError> {
+        let mut devices = Vec::new();
+        for sink in &config.sinks {
+            if let SinkConfig::Device { serial } = sink {
+                devices.push(Arc::new(match serial {
+                    Some(serial) => BlinkStick::open_by_serial(serial)?,
+                    None => BlinkStick::new()?,
+                }));
+            }
+        }
+
+        let led_count = match (config.leds, devices.first()) {
+            (Some(leds), _) => leds,
+            (None, Some(device)) => device.max_leds as usize,
+            (None, None) => return Err(PipelineError::NoLedCount),
+        };
+
+        let mut device_leds = devices.iter().map(|device| device.max_leds as usize);
+        for (sink, config) in config.sinks.iter().enumerate() {
+            let max_leds = match config {
+                SinkConfig::Device { .. } => device_leds.next(),
+                SinkConfig::Simulator => Some(u8::MAX as usize),
+                SinkConfig::Recorder => None,
+            };
+            if let Some(max_leds) = max_leds.filter(|max_leds| led_count > *max_leds) {
+                return Err(PipelineError::TooManyLeds { sink, max_leds });
+            }
+        }
+
+        let sources = config
+            .sources
+            .iter()
+            .enumerate()
+            .map(|(index, source)| {
+                let leds = source.leds.unwrap_or(led_count.saturating_sub(source.offset));
+                let fits = source.offset.checked_add(leds).is_some_and(|end| end <= led_count);
+                if leds == 0 || !fits {
+                    return Err(PipelineError::SourceOutOfRange(index));
+                }
+                let animation = effects::animation(&source.effect)
+                    .ok_or_else(|| PipelineError::UnknownEffect(source.effect.clone()))?;
+
+                Ok((source.offset, animation.stepper(leds)))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let filters = config
+            .filters
+            .iter()
+            .map(|filter| match filter {
+                FilterConfig::Brightness { factor } => Filter::Brightness(factor.clamp(0.0, 1.0)),
+                FilterConfig::Gamma { gamma } => Filter::Gamma(*gamma),
+                FilterConfig::Smoothing { time_constant_ms } => {
+                    Filter::Smoothing(Smoother::new(Duration::from_millis(*time_constant_ms)))
+                }
+            })
+            .collect();
+
+        let mut pipeline = Pipeline {
+            led_count,
+            sources,
+            filters,
+            sinks: Vec::new(),
+            simulators: Vec::new(),
+            recorders: Vec::new(),
+        };
+        let mut devices = devices.into_iter();
+        for sink in &config.sinks {
+            match sink {
+                SinkConfig::Device { .. } => pipeline.sinks.push(Box::new(PaddedDevice(devices.next().unwrap()))),
+                SinkConfig::Simulator => {
+                    let simulator = Arc::new(NoopBlinkStick::new(led_count as u8));
+                    pipeline.simulators.push(Arc::clone(&simulator));
+                    pipeline.sinks.push(Box::new(simulator));
+                }
+                SinkConfig::Recorder => {
+                    let recorder = FrameRecorder::new();
+                    pipeline.recorders.push(recorder.clone());
+                    pipeline.sinks.push(Box::new(recorder));
+                }
+            }
+        }
+
+        Ok(pipeline)
+    }
+
+    /// Advances every effect by `dt`, then filters the composited frame and sends it to every sink. Returns the
+    /// frame sent.
+    pub fn tick(&mut self, dt: Duration) -> Result<Vec<Color>, FeatureError> {
+        let mut frame = vec![COLOR_OFF; self.led_count];
+        for (offset, stepper) in &mut self.sources {
+            stepper.tick(dt);
+            if let Some(colors) = stepper.frame() {
+                for (led, color) in frame[*offset..].iter_mut().zip(colors) {
+                    *led = *color;
+                }
+            }
+        }
+
+        for filter in &mut self.filters {
+            frame = filter.apply(frame, dt);
+        }
+        for sink in &mut self.sinks {
+            sink.send_frame(&frame, dt)?;
+        }
+
+        Ok(frame)
+    }
+
+    /// Ticks the pipeline every `frame_interval` until every effect has ended. Themes never end, so a pipeline
+    /// with one of them as a source runs until a sink fails.
+    pub fn run(&mut self, frame_interval: Duration) -> Result<(), FeatureError> {
+        let mut last_tick = Instant::now();
+        let mut dt = Duration::ZERO;
+        loop {
+            self.tick(dt)?;
+            if self.is_finished() {
+                return Ok(());
+            }
+
+            std::thread::sleep(frame_interval.saturating_sub(last_tick.elapsed()));
+            dt = last_tick.elapsed();
+            last_tick = Instant::now();
+        }
+    }
+
+    /// Returns `true` once every effect has ended
+    pub fn is_finished(&self) -> bool {
+        self.sources.iter().all(|(_, stepper)| stepper.is_finished())
+    }
+
+    /// The number of leds of every frame
+    pub fn led_count(&self) -> usize {
+        self.led_count
+    }
+
+    /// The simulator sinks, in the order they were described
+    pub fn simulators(&self) -> &[Arc<NoopBlinkStick>] {
+        &self.simulators
+    }
+
+    /// The recorder sinks, in the order they were described
+    pub fn recorders(&self) -> &[FrameRecorder] {
+        &self.recorders
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LedDevice;
+
+    #[test]
+    fn sources_render_their_own_leds_through_the_filters() {
+        let config = PipelineConfig {
+            leds: Some(6),
+            sources: vec![SourceConfig {
+                effect: "christmas".to_string(),
+                offset: 2,
+                leds: Some(3),
+            }],
+            filters: vec![FilterConfig::Brightness { factor: 0.0 }],
+            sinks: vec![SinkConfig::Simulator, SinkConfig::Recorder],
+        };
+
+        let mut pipeline = Pipeline::from_config(&config).unwrap();
+        let frame = pipeline.tick(Duration::ZERO).unwrap();
+
+        assert_eq!(frame, vec![COLOR_OFF; 6]);
+        assert_eq!(pipeline.simulators()[0].get_all_led_colors().unwrap(), frame);
+        assert_eq!(pipeline.recorders()[0].frames(), vec![(frame, Duration::ZERO)]);
+    }
+
+    #[test]
+    fn devices_with_more_leds_than_the_frame_turn_the_rest_off() {
+        let device = Arc::new(NoopBlinkStick::new(5));
+        device.set_all_leds_color(Color::RED).unwrap();
+        let mut sink = PaddedDevice(device.clone());
+
+        sink.send_frame(&[Color::GREEN; 3], Duration::ZERO).unwrap();
+        assert_eq!(
+            device.get_all_led_colors().unwrap(),
+            vec![Color::GREEN, Color::GREEN, Color::GREEN, COLOR_OFF, COLOR_OFF]
+        );
+    }
+
+    #[test]
+    fn descriptions_are_checked_before_running() {
+        let mut config = PipelineConfig {
+            leds: Some(4),
+            sources: vec![SourceConfig {
+                effect: "christmas".to_string(),
+                offset: 3,
+                leds: Some(2),
+            }],
+            filters: Vec::new(),
+            sinks: vec![SinkConfig::Recorder],
+        };
+        assert!(matches!(
+            Pipeline::from_config(&config),
+            Err(PipelineError::SourceOutOfRange(0))
+        ));
+
+        config.sources[0] = SourceConfig {
+            effect: "no such effect".to_string(),
+            offset: 0,
+            leds: None,
+        };
+        assert!(matches!(
+            Pipeline::from_config(&config),
+            Err(PipelineError::UnknownEffect(_))
+        ));
+
+        config.sources[0] = SourceConfig {
+            effect: "christmas".to_string(),
+            offset: usize::MAX,
+            leds: Some(2),
+        };
+        assert!(matches!(
+            Pipeline::from_config(&config),
+            Err(PipelineError::SourceOutOfRange(0))
+        ));
+
+        config.leds = Some(256);
+        config.sources.clear();
+        config.sinks = vec![SinkConfig::Recorder, SinkConfig::Simulator];
+        assert!(matches!(
+            Pipeline::from_config(&config),
+            Err(PipelineError::TooManyLeds { sink: 1, max_leds: 255 })
+        ));
+
+        config.leds = None;
+        assert!(matches!(Pipeline::from_config(&config), Err(PipelineError::NoLedCount)));
+    }
+}
@@ -0,0 +1,396 @@
+//! Effects loaded from dynamic libraries, so new effects can be dropped into a daemon without recompiling it.
+//!
+//! A plugin is a `cdylib` exporting two functions with the C ABI:
+//! * `blinkstick_plugin_abi_version() -> u32` returns the `PLUGIN_ABI_VERSION` the plugin was built against
+//! * `blinkstick_plugin_effects(count: *mut usize) -> *const PluginEffect` returns the plugin's effects and writes
+//!   their number to `count`
+//!
+//! Plugins of another ABI version are refused before any of their effects is touched. The effects of a loaded
+//! plugin are registered by name like any other effect, see `effects::register`, and the library stays loaded
+//! for as long as one of them is registered or playing.
+//!
+//! # Example
+//! The effect of a plugin crate that lights every led in a dim blue
+//! ```
+//! use blinkstick_rs::plugins::{PluginEffect, PLUGIN_ABI_VERSION};
+//! use std::os::raw::{c_char, c_void};
+//!
+//! unsafe extern "C" fn start(_led_count: usize) -> *mut c_void {
+//!     std::ptr::null_mut()
+//! }
+//!
+//! unsafe extern "C" fn next_frame(_state: *mut c_void, frame: *mut u8, led_count: usize) -> i64 {
+//!     let frame = std::slice::from_raw_parts_mut(frame, led_count * 3);
+//!     for led in frame.chunks_mut(3) {
+//!         led.copy_from_slice(&[0, 0, 40]);
+//!     }
+//!     1000
+//! }
+//!
+//! unsafe extern "C" fn stop(_state: *mut c_void) {}
+//!
+//! struct Effects([PluginEffect; 1]);
+//! unsafe impl Sync for Effects {}
+//!
+//! static EFFECTS: Effects = Effects([PluginEffect {
+//!     name: b"dim_blue\0".as_ptr() as *const c_char,
+//!     description: b"Every led in a dim blue\0".as_ptr() as *const c_char,
+//!     start,
+//!     next_frame,
+//!     stop,
+//! }]);
+//!
+//! #[no_mangle]
+//! pub extern "C" fn blinkstick_plugin_abi_version() -> u32 {
+//!     PLUGIN_ABI_VERSION
+//! }
+//!
+//! #[no_mangle]
+//! pub unsafe extern "C" fn blinkstick_plugin_effects(count: *mut usize) -> *const PluginEffect {
+//!     *count = EFFECTS.0.len();
+//!     EFFECTS.0.as_ptr()
+//! }
+//! ```
+
+use crate::effects::{self, Animation};
+use crate::{AnimationIter, Color};
+use libloading::Library;
+use std::error::Error;
+use std::ffi::CStr;
+use std::fmt::Formatter;
+use std::os::raw::{c_char, c_void};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// The version of the plugin ABI, raised whenever `PluginEffect` or the exported functions change
+pub const PLUGIN_ABI_VERSION: u32 = 1;
+
+/// An effect of a plugin, laid out for the C ABI
+///
+/// Every play of the effect calls `start` once, then `next_frame` for every frame, and `stop` once when the frames
+/// are dropped. The state `start` returns is handed to the other two and may be used from any thread.
+#[repr(C)]
+pub struct PluginEffect {
+    /// The nul terminated name the effect is registered under
+    pub name: *const c_char,
+    /// The nul terminated description of the effect, may be null
+    pub description: *const c_char,
+    /// Starts a play of the effect on `led_count` leds, returning its state
+    pub start: unsafe extern "C" fn(led_count: usize) -> *mut c_void,
+    /// Writes the next frame as `r, g, b` bytes of every led to `frame`, returning the milliseconds to show it for,
+    /// or a negative number once the effect has ended
+    pub next_frame: unsafe extern "C" fn(state: *mut c_void, frame: *mut u8, led_count: usize) -> i64,
+    /// Ends a play of the effect, freeing its state
+    pub stop: unsafe extern "C" fn(state: *mut c_void),
+}
+
+/// An error while loading a plugin
+#[derive(Debug)]
+pub enum PluginError {
+    /// The library could not be loaded or lacks one of the exported functions
+    Library(libloading::Error),
+    /// The plugin was built against another version of the plugin ABI
+    AbiVersion { expected: u32, actual: u32 },
+    /// An effect of the plugin has no name or one that is not UTF-8
+    InvalidName,
+    /// The directory of plugins could not be read
+    Io(std::io::Error),
+    /// A plugin of a directory failed to load, after the plugins before it registered the effects in `registered`
+    Directory {
+        plugin: PathBuf,
+        registered: Vec<String>,
+        error: Box<PluginError>,
+    },
+}
+
+impl std::fmt::Display for PluginError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PluginError::Library(e) => write!(f, "Could not load plugin: {}", e),
+            PluginError::AbiVersion { expected, actual } => write!(
+                f,
+                "Plugin was built for plugin ABI version {}, expected version {}",
+                actual, expected
+            ),
+            PluginError::InvalidName => write!(f, "Plugin has an effect without a valid name"),
+            PluginError::Io(e) => write!(f, "Could not read plugin directory: {}", e),
+            PluginError::Directory {
+                plugin,
+                registered,
+                error,
+            } => write!(
+                f,
+                "{} ({}), after registering {} effects of other plugins",
+                error,
+                plugin.display(),
+                registered.len()
+            ),
+        }
+    }
+}
+
+impl Error for PluginError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            PluginError::Library(e) => Some(e),
+            PluginError::Io(e) => Some(e),
+            PluginError::Directory { error, .. } => Some(&**error),
+            PluginError::AbiVersion { .. } | PluginError::InvalidName => None,
+        }
+    }
+}
+
+impl From<libloading::Error> for PluginError {
+    fn from(e: libloading::Error) -> PluginError {
+        PluginError::Library(e)
+    }
+}
+
+impl From<std::io::Error> for PluginError {
+    fn from(e: std::io::Error) -> PluginError {
+        PluginError::Io(e)
+    }
+}
+
+/// Loads the plugin at `path` and registers its effects, returning their names
+///
+/// # Safety
+/// Loading a library runs its initialization code, and the plugin's exported functions are trusted to follow the
+/// plugin ABI. Only load plugins you trust.
+pub unsafe fn load_plugin<P: AsRef<Path>>(path: P) -> Result<Vec<String>, PluginError> {
+    let library = Arc::new(Library::new(path.as_ref())?);
+
+    let abi_version = library.get::<unsafe extern "C" fn() -> u32>(b"blinkstick_plugin_abi_version\0")?;
+    check_abi_version(abi_version())?;
+
+    let plugin_effects =
+        library.get::<unsafe extern "C" fn(*mut usize) -> *const PluginEffect>(b"blinkstick_plugin_effects\0")?;
+    let mut count = 0;
+    let first = plugin_effects(&mut count);
+    let plugin_effects: &[PluginEffect] = if first.is_null() {
+        &[]
+    } else {
+        std::slice::from_raw_parts(first, count)
+    };
+
+    // Every name is checked before anything is registered, so a broken plugin registers nothing
+    let mut animations = Vec::new();
+    for effect in plugin_effects {
+        let name = c_string(effect.name).ok_or(PluginError::InvalidName)?;
+        let animation = PluginAnimation {
+            description: c_string(effect.description).unwrap_or_default(),
+            start: effect.start,
+            next_frame: effect.next_frame,
+            stop: effect.stop,
+            library: Arc::clone(&library),
+        };
+        animations.push((name, animation));
+    }
+
+    Ok(animations
+        .into_iter()
+        .map(|(name, animation)| {
+            effects::register(&name, animation);
+            name
+        })
+        .collect())
+}
+
+/// Fails unless a plugin was built against the `PLUGIN_ABI_VERSION` of this crate
+fn check_abi_version(actual: u32) -> Result<(), PluginError> {
+    if actual != PLUGIN_ABI_VERSION {
+        return Err(PluginError::AbiVersion {
+            expected: PLUGIN_ABI_VERSION,
+            actual,
+        });
+    }
+    Ok(())
+}
+
+/// Loads every plugin in the directory `path`, the files with the platform's dynamic library extension, returning
+/// the names of the effects registered. Stops at the first plugin that fails to load with
+/// `PluginError::Directory`, which names the effects the plugins before it registered.
+///
+/// # Safety
+/// See `load_plugin`.
+pub unsafe fn load_plugins<P: AsRef<Path>>(path: P) -> Result<Vec<String>, PluginError> {
+    let mut libraries: Vec<_> = std::fs::read_dir(path)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<_, _>>()?;
+    libraries.retain(|library| {
+        library
+            .extension()
+            .is_some_and(|extension| extension == std::env::consts::DLL_EXTENSION)
+    });
+    libraries.sort();
+
+    let mut names = Vec::new();
+    for library in libraries {
+        match load_plugin(&library) {
+            Ok(registered) => names.extend(registered),
+            Err(error) => {
+                return Err(PluginError::Directory {
+                    plugin: library,
+                    registered: names,
+                    error: Box::new(error),
+                })
+            }
+        }
+    }
+
+    Ok(names)
+}
+
+/// Reads a nul terminated UTF-8 string, `None` for a null pointer or invalid UTF-8
+unsafe fn c_string(string: *const c_char) -> Option<String> {
+    if string.is_null() {
+        return None;
+    }
+
+    CStr::from_ptr(string).to_str().ok().map(str::to_string)
+}
+
+/// An effect of a loaded plugin, keeping its library loaded
+struct PluginAnimation {
+    description: String,
+    start: unsafe extern "C" fn(usize) -> *mut c_void,
+    next_frame: unsafe extern "C" fn(*mut c_void, *mut u8, usize) -> i64,
+    stop: unsafe extern "C" fn(*mut c_void),
+    library: Arc<Library>,
+}
+
+impl Animation for PluginAnimation {
+    fn frames(&self, led_count: usize) -> AnimationIter {
+        AnimationIter::new(PluginFrames {
+            play: PluginPlay {
+                state: unsafe { (self.start)(led_count) },
+                next_frame: self.next_frame,
+                stop: self.stop,
+                led_count,
+                finished: false,
+            },
+            _library: Arc::clone(&self.library),
+        })
+    }
+
+    fn description(&self) -> &str {
+        &self.description
+    }
+}
+
+/// The frames of a play of a plugin effect, keeping its library loaded until the play is stopped
+struct PluginFrames {
+    // Fields are dropped in order, so the play is stopped before the library may be unloaded
+    play: PluginPlay,
+    _library: Arc<Library>,
+}
+
+impl Iterator for PluginFrames {
+    type Item = (Vec<Color>, Duration);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.play.next()
+    }
+}
+
+/// A play of a plugin effect, stopping it when dropped. Once the effect has ended, it is not asked for frames again.
+struct PluginPlay {
+    state: *mut c_void,
+    next_frame: unsafe extern "C" fn(*mut c_void, *mut u8, usize) -> i64,
+    stop: unsafe extern "C" fn(*mut c_void),
+    led_count: usize,
+    finished: bool,
+}
+
+// The plugin ABI requires the state of an effect to be usable from any thread
+unsafe impl Send for PluginPlay {}
+
+impl Iterator for PluginPlay {
+    type Item = (Vec<Color>, Duration);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+
+        let mut bytes = vec![0u8; self.led_count * 3];
+        let delay = unsafe { (self.next_frame)(self.state, bytes.as_mut_ptr(), self.led_count) };
+        if delay < 0 {
+            self.finished = true;
+            return None;
+        }
+
+        let frame = bytes
+            .chunks_exact(3)
+            .map(|rgb| Color::new(rgb[0], rgb[1], rgb[2]))
+            .collect();
+        Some((frame, Duration::from_millis(delay as u64)))
+    }
+}
+
+impl Drop for PluginPlay {
+    fn drop(&mut self) {
+        unsafe { (self.stop)(self.state) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_plugins_fail_to_load() {
+        let directory = std::env::temp_dir().join("blinkstick_rs_no_plugins");
+        std::fs::create_dir_all(&directory).unwrap();
+
+        assert!(matches!(
+            unsafe { load_plugin(directory.join("missing.so")) },
+            Err(PluginError::Library(_))
+        ));
+        assert!(unsafe { load_plugins(&directory) }.unwrap().is_empty());
+    }
+
+    #[test]
+    fn plugins_of_another_abi_version_are_refused() {
+        assert!(check_abi_version(PLUGIN_ABI_VERSION).is_ok());
+        assert!(matches!(
+            check_abi_version(PLUGIN_ABI_VERSION + 1),
+            Err(PluginError::AbiVersion { expected: PLUGIN_ABI_VERSION, actual }) if actual == PLUGIN_ABI_VERSION + 1
+        ));
+    }
+
+    /// An effect that shows one frame, then ends, counting how often it was asked for a frame
+    unsafe extern "C" fn one_frame(state: *mut c_void, frame: *mut u8, led_count: usize) -> i64 {
+        let calls = &mut *(state as *mut u32);
+        *calls += 1;
+        if *calls > 1 {
+            return -1;
+        }
+        std::slice::from_raw_parts_mut(frame, led_count * 3).fill(9);
+        10
+    }
+
+    unsafe extern "C" fn keep(_state: *mut c_void) {}
+
+    #[test]
+    fn ended_plugin_effects_are_not_asked_again() {
+        let mut calls = 0u32;
+        let mut play = PluginPlay {
+            state: &mut calls as *mut u32 as *mut c_void,
+            next_frame: one_frame,
+            stop: keep,
+            led_count: 2,
+            finished: false,
+        };
+
+        assert_eq!(
+            play.next(),
+            Some((vec![Color::new(9, 9, 9); 2], Duration::from_millis(10)))
+        );
+        assert_eq!(play.next(), None);
+        assert_eq!(play.next(), None);
+        drop(play);
+        assert_eq!(calls, 2);
+    }
+}
@@ -0,0 +1,134 @@
+use crate::calibration::PROFILE_OFFSET;
+use crate::wire::{encode_info_block_report, parse_info_block_report, INFO_BLOCK_2_REPORT_ID};
+use crate::{BlinkStick, Color, FeatureError};
+
+/// Marks a preset stored in the second info block
+const PRESET_MAGIC: &[u8; 2] = b"DP";
+
+/// The bytes a preset takes: its marker, the color, the brightness and the effect id
+const PRESET_LENGTH: usize = PRESET_MAGIC.len() + 5;
+
+/// Where a preset is stored in the second info block: right before the color profile, leaving the first bytes to
+/// applications
+const PRESET_OFFSET: usize = PROFILE_OFFSET - PRESET_LENGTH;
+
+/// The effect id stored for a preset without an effect
+const NO_EFFECT: u8 = 0xff;
+
+/// The default behavior a BlinkStick carries in its second info block, so it looks the same on every computer it
+/// is plugged into, see `BlinkStick::save_device_preset` and `BlinkStickBuilder::apply_device_preset`
+///
+/// # Example
+/// Stores a dim orange with the Halloween theme on the device
+/// ```
+/// use blinkstick_rs::{BlinkStick, Color, DevicePreset};
+///
+/// let blinkstick = BlinkStick::new().unwrap();
+/// let preset = DevicePreset {
+///     color: Color::new(255, 80, 0),
+///     brightness: 0.2,
+///     effect: Some(1),
+/// };
+/// blinkstick.save_device_preset(&preset).unwrap();
+///
+/// assert_eq!(blinkstick.read_device_preset().unwrap(), Some(preset));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DevicePreset {
+    /// The color every led shows when the device is opened
+    pub color: Color,
+    /// The brightness (0.0 - 1.0) of every color written, stored in steps of 1/255
+    pub brightness: f32,
+    /// The effect the device plays by default, 0 to 254. Ids below the number of themes are the index of a theme
+    /// in `effects::THEMES`, others are left to applications.
+    pub effect: Option<u8>,
+}
+
+impl DevicePreset {
+    /// The theme the effect id of the preset names, if it names one
+    #[cfg(any(feature = "effects", feature = "animator"))]
+    pub fn theme(&self) -> Option<&'static crate::effects::Theme> {
+        crate::effects::THEMES.get(self.effect? as usize)
+    }
+
+    /// The bytes a preset is stored as in an info block
+    fn to_bytes(self) -> [u8; PRESET_LENGTH] {
+        [
+            PRESET_MAGIC[0],
+            PRESET_MAGIC[1],
+            self.color.r,
+            self.color.g,
+            self.color.b,
+            (self.brightness.clamp(0.0, 1.0) * 255.0).round() as u8,
+            self.effect.unwrap_or(NO_EFFECT),
+        ]
+    }
+
+    /// Reads a preset from the bytes written by `to_bytes`, `None` if they hold none
+    fn from_bytes(bytes: &[u8]) -> Option<DevicePreset> {
+        match bytes {
+            [m1, m2, r, g, b, brightness, effect, ..] if [*m1, *m2] == *PRESET_MAGIC => Some(DevicePreset {
+                color: Color::new(*r, *g, *b),
+                brightness: *brightness as f32 / 255.0,
+                effect: match *effect {
+                    NO_EFFECT => None,
+                    effect => Some(effect),
+                },
+            }),
+            _ => None,
+        }
+    }
+}
+
+impl BlinkStick {
+    /// Stores `preset` in the device's second info block, next to the color profile. The rest of the info block
+    /// is kept.
+    pub fn save_device_preset(&self, preset: &DevicePreset) -> Result<(), FeatureError> {
+        let mut data = parse_info_block_report(
+            INFO_BLOCK_2_REPORT_ID,
+            &self.get_feature_from_blinkstick(INFO_BLOCK_2_REPORT_ID)?,
+        )?;
+        data[PRESET_OFFSET..PROFILE_OFFSET].copy_from_slice(&preset.to_bytes());
+
+        self.send_feature_to_blinkstick(&encode_info_block_report(INFO_BLOCK_2_REPORT_ID, &data))
+    }
+
+    /// Reads the preset stored by `save_device_preset`, `None` if the device holds none
+    pub fn read_device_preset(&self) -> Result<Option<DevicePreset>, FeatureError> {
+        let data = parse_info_block_report(
+            INFO_BLOCK_2_REPORT_ID,
+            &self.get_feature_from_blinkstick(INFO_BLOCK_2_REPORT_ID)?,
+        )?;
+
+        Ok(DevicePreset::from_bytes(&data[PRESET_OFFSET..PROFILE_OFFSET]))
+    }
+
+    /// The preset the device was opened with, see `BlinkStickBuilder::apply_device_preset`. Its color and
+    /// brightness are already applied, its effect is left to the application to play.
+    pub fn device_preset(&self) -> Option<DevicePreset> {
+        self.device_preset
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wire::INFO_BLOCK_LENGTH;
+
+    #[test]
+    fn presets_survive_the_info_block() {
+        let preset = DevicePreset {
+            color: Color::new(255, 80, 0),
+            brightness: 0.2,
+            effect: None,
+        };
+        let mut data = [0; INFO_BLOCK_LENGTH];
+        data[PRESET_OFFSET..PROFILE_OFFSET].copy_from_slice(&preset.to_bytes());
+
+        assert_eq!(
+            DevicePreset::from_bytes(&data[PRESET_OFFSET..PROFILE_OFFSET]),
+            Some(preset)
+        );
+        assert_eq!(DevicePreset::from_bytes(&[0; PRESET_LENGTH]), None);
+    }
+}
@@ -0,0 +1,220 @@
+use crate::config::{config_lines, decode_word, encode_word, ConfigError};
+use crate::{AnimationHandle, Animator, Color, Parameter, Parameters};
+use std::fmt::Formatter;
+use std::path::Path;
+use std::time::Duration;
+
+/// The effect an `Animator` is playing, saved so a restarted program can pick it up where it left off instead of
+/// dropping to black
+///
+/// The frames of an effect can't be saved, so a program records the name of the effect, the values of its
+/// tunable parameters and how far it has played. The textual form holds an `effect` line, a `phase` line with the
+/// played seconds and a `parameter` line for every parameter, with colors written as `r,g,b`. Whitespace, `#` and `%`
+/// in names are percent-encoded, like `effect night%20light`.
+///
+/// # Example
+/// Saves the running effect before shutting down and resumes it on the next start
+/// ```no_run
+/// use blinkstick_rs::{Animator, BlinkStick, Color, Parameter, Parameters, Program};
+/// use std::time::Duration;
+///
+/// fn glow(parameters: &Parameters, leds: usize) -> impl Iterator<Item = (Vec<Color>, Duration)> {
+///     let parameters = parameters.clone();
+///     std::iter::repeat_with(move || (vec![parameters.color("color").unwrap(); leds], Duration::from_millis(50)))
+/// }
+///
+/// let blinkstick = BlinkStick::new().unwrap();
+/// let leds = blinkstick.max_leds as usize;
+/// let animator = Animator::new(blinkstick);
+/// let parameters = Parameters::new().declare("color", Parameter::Color(Color {r: 0, g: 0, b: 50}));
+///
+/// match Program::load("program.txt") {
+///     Ok(program) => animator.resume(&program, parameters.clone(), glow(&parameters, leds)),
+///     Err(_) => animator.play_tunable("glow", parameters.clone(), glow(&parameters, leds)),
+/// };
+///
+/// // Later, before the program exits
+/// if let Some(program) = animator.program() {
+///     program.save("program.txt").unwrap();
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Program {
+    /// The name the effect was played with
+    pub effect: String,
+    /// The values of the tunable parameters of the effect, ordered by name
+    pub parameters: Vec<(String, Parameter)>,
+    /// How long the effect had been playing
+    pub phase: Duration,
+}
+
+impl Program {
+    /// Parses a program from its textual form
+    ///
+    /// ```
+    /// use blinkstick_rs::{Color, Parameter, Program};
+    /// use std::time::Duration;
+    ///
+    /// let program = Program::parse("effect glow\nphase 1.5\nparameter color 0,0,50\nparameter speed 2\n").unwrap();
+    ///
+    /// assert_eq!(program.effect, "glow");
+    /// assert_eq!(program.phase, Duration::from_millis(1500));
+    /// assert_eq!(program.parameters[0].1, Parameter::Color(Color {r: 0, g: 0, b: 50}));
+    /// ```
+    pub fn parse(contents: &str) -> Result<Program, ConfigError> {
+        let mut effect = None;
+        let mut phase = Duration::ZERO;
+        let mut parameters = Vec::new();
+
+        for (line, text) in config_lines(contents) {
+            let parse_error = |message: &str| ConfigError::Parse {
+                line,
+                message: message.to_string(),
+            };
+
+            let fields: Vec<&str> = text.split_whitespace().collect();
+            match fields.as_slice() {
+                ["effect", name] => {
+                    effect = Some(decode_word(name).ok_or_else(|| parse_error("invalid effect name"))?);
+                }
+                ["phase", seconds] => {
+                    phase = seconds
+                        .parse::<f64>()
+                        .ok()
+                        .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok())
+                        .ok_or_else(|| parse_error("invalid phase, expected a number of seconds"))?;
+                }
+                ["parameter", name, value] => {
+                    let name = decode_word(name).ok_or_else(|| parse_error("invalid parameter name"))?;
+                    let value = parse_parameter(value).ok_or_else(|| parse_error("invalid parameter value"))?;
+                    parameters.push((name, value));
+                }
+                _ => return Err(parse_error("expected an effect, a phase or a parameter")),
+            }
+        }
+
+        parameters.sort_by(|(a, _), (b, _)| a.cmp(b));
+        let effect = effect.ok_or(ConfigError::Parse {
+            line: 0,
+            message: "missing effect".to_string(),
+        })?;
+
+        Ok(Program {
+            effect,
+            parameters,
+            phase,
+        })
+    }
+
+    /// Reads a program from a file in the format accepted by `parse`
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Program, ConfigError> {
+        Program::parse(&std::fs::read_to_string(path)?)
+    }
+
+    /// Writes the program to a file in the format accepted by `parse`
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), ConfigError> {
+        Ok(std::fs::write(path, self.to_string())?)
+    }
+}
+
+impl std::fmt::Display for Program {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "effect {}", encode_word(&self.effect))?;
+        writeln!(f, "phase {}", self.phase.as_secs_f64())?;
+        for (name, value) in self.parameters.iter() {
+            let name = encode_word(name);
+            match value {
+                Parameter::Number(number) => writeln!(f, "parameter {} {}", name, number)?,
+                Parameter::Color(color) => writeln!(f, "parameter {} {},{},{}", name, color.r, color.g, color.b)?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Parses a number, or a color written as `r,g,b`
+fn parse_parameter(value: &str) -> Option<Parameter> {
+    if !value.contains(',') {
+        return value.parse().ok().map(Parameter::Number);
+    }
+
+    let channels: Vec<u8> = value.split(',').map(str::parse).collect::<Result<_, _>>().ok()?;
+    match channels.as_slice() {
+        [r, g, b] => Some(Parameter::Color(Color { r: *r, g: *g, b: *b })),
+        _ => None,
+    }
+}
+
+impl Animator {
+    /// Plays an effect from the phase and with the parameter values saved in `program`, see `Program`
+    ///
+    /// `parameters` and `frames` are the effect as it is played from the start. Saved values replace the values
+    /// of the declared parameters, and frames that had already finished at the saved phase are skipped.
+    pub fn resume<I>(&self, program: &Program, parameters: Parameters, frames: I) -> AnimationHandle
+    where
+        I: IntoIterator<Item = (Vec<Color>, Duration)>,
+        I::IntoIter: Send + 'static,
+    {
+        for (name, value) in program.parameters.iter() {
+            parameters.set(name, *value);
+        }
+
+        let phase = program.phase;
+        let mut played = Duration::ZERO;
+        let frames = frames.into_iter().skip_while(move |(_, delay)| {
+            played += *delay;
+            played <= phase
+        });
+
+        self.play_tunable(&program.effect, parameters, frames)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FrameRecorder, ManualClock};
+    use std::sync::Arc;
+
+    #[test]
+    fn programs_round_trip_through_text() {
+        let program = Program {
+            effect: "rainbow".to_string(),
+            parameters: vec![
+                ("color".to_string(), Parameter::Color(Color { r: 1, g: 2, b: 3 })),
+                ("speed".to_string(), Parameter::Number(0.25)),
+            ],
+            phase: Duration::from_millis(12500),
+        };
+
+        assert_eq!(Program::parse(&program.to_string()).unwrap(), program);
+        assert!(Program::parse("phase 2\n").is_err());
+
+        let program = Program {
+            effect: "night light #2".to_string(),
+            parameters: vec![("warm tone".to_string(), Parameter::Number(1.0))],
+            phase: Duration::ZERO,
+        };
+        assert_eq!(Program::parse(&program.to_string()).unwrap(), program);
+    }
+
+    #[test]
+    fn resume_skips_played_frames() {
+        let animator = Animator::headless_with_clock(Arc::new(ManualClock::new()));
+        let recorder = FrameRecorder::new();
+        animator.tee(recorder.clone());
+
+        let frames: Vec<(Vec<Color>, Duration)> = (0..5u8)
+            .map(|level| (vec![Color { r: level, g: 0, b: 0 }], Duration::from_secs(1)))
+            .collect();
+        let program = Program {
+            effect: "count".to_string(),
+            parameters: Vec::new(),
+            phase: Duration::from_millis(2500),
+        };
+        animator.resume(&program, Parameters::new(), frames.clone()).wait();
+
+        assert_eq!(recorder.frames(), frames[2..].to_vec());
+    }
+}
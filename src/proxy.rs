@@ -0,0 +1,176 @@
+use crate::{BlinkStick, FeatureError, FeatureErrorType};
+use std::fmt::Write as _;
+use std::io::Write;
+use std::path::Path;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The number of bytes on a line of the report log
+const LOG_LINE_BYTES: usize = 16;
+
+/// Whether a report went to the device or came from it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportDirection {
+    /// A feature report sent to the device
+    Sent,
+    /// A feature report read from the device
+    Received,
+}
+
+/// A feature report exchanged with a device, mirrored by a `ProxyTransport`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CapturedReport {
+    /// When the transfer finished
+    pub timestamp: SystemTime,
+    pub direction: ReportDirection,
+    /// The report id followed by the report, as sent or as far as it was received
+    pub bytes: Vec<u8>,
+    /// Why the transfer failed, after retrying
+    pub error: Option<FeatureErrorType>,
+}
+
+impl CapturedReport {
+    /// The report as a block of the report log: a line with its timestamp and direction, then its bytes in hex
+    /// with their offsets, 16 to a line
+    pub fn to_log(&self) -> String {
+        let since_epoch = self.timestamp.duration_since(UNIX_EPOCH).unwrap_or_default();
+        let direction = match self.direction {
+            ReportDirection::Sent => "sent",
+            ReportDirection::Received => "received",
+        };
+
+        let mut log = format!(
+            "# {}.{:06} {} report {}",
+            since_epoch.as_secs(),
+            since_epoch.subsec_micros(),
+            direction,
+            self.bytes.first().map_or("-".to_string(), |id| format!("0x{:02x}", id))
+        );
+        if let Some(error) = &self.error {
+            let _ = write!(log, " failed: {:?}", error);
+        }
+        log.push('\n');
+
+        for (line, bytes) in self.bytes.chunks(LOG_LINE_BYTES).enumerate() {
+            let hex: Vec<String> = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+            let _ = writeln!(log, "{:04x}  {}", line * LOG_LINE_BYTES, hex.join(" "));
+        }
+
+        log
+    }
+}
+
+enum Mirror {
+    Channel(Sender<CapturedReport>),
+    Log(Box<dyn Write + Send>),
+}
+
+/// Mirrors every feature report a BlinkStick exchanges with its device, while the reports still go to the device
+/// as before, see `BlinkStick::set_proxy`
+///
+/// Reports are mirrored to a channel or written to a log, to compare what this crate sends with what the official
+/// tools send when a device acts up. A log that cannot be written to, or a channel nobody receives from, never
+/// keeps a report from reaching the device.
+///
+/// # Example
+/// Logs the reports of a single color change to a file
+/// ```
+/// use blinkstick_rs::{BlinkStick, Color, ProxyTransport};
+///
+/// let blinkstick = BlinkStick::new().unwrap();
+/// let log = std::env::temp_dir().join("blinkstick-reports.log");
+/// blinkstick.set_proxy(Some(ProxyTransport::create(&log).unwrap()));
+///
+/// blinkstick.set_all_leds_color(Color {r: 0, g: 50, b: 0}).unwrap();
+/// blinkstick.set_proxy(None);
+/// ```
+pub struct ProxyTransport {
+    mirror: Mirror,
+}
+
+impl ProxyTransport {
+    /// Mirrors every report to the returned channel
+    pub fn channel() -> (ProxyTransport, Receiver<CapturedReport>) {
+        let (sender, receiver) = channel();
+        (
+            ProxyTransport {
+                mirror: Mirror::Channel(sender),
+            },
+            receiver,
+        )
+    }
+
+    /// Writes every report to `writer` as a block of the report log, see `CapturedReport::to_log`
+    pub fn log<W: Write + Send + 'static>(writer: W) -> ProxyTransport {
+        ProxyTransport {
+            mirror: Mirror::Log(Box::new(writer)),
+        }
+    }
+
+    /// Writes every report to a log file at `path`, replacing the file if it exists
+    pub fn create<P: AsRef<Path>>(path: P) -> std::io::Result<ProxyTransport> {
+        Ok(ProxyTransport::log(std::fs::File::create(path)?))
+    }
+
+    fn mirror(&mut self, report: CapturedReport) {
+        match &mut self.mirror {
+            Mirror::Channel(sender) => {
+                let _ = sender.send(report);
+            }
+            Mirror::Log(writer) => {
+                let _ = writer
+                    .write_all(report.to_log().as_bytes())
+                    .and_then(|()| writer.flush());
+            }
+        }
+    }
+}
+
+impl BlinkStick {
+    /// Mirrors every feature report exchanged with the device from now on through `proxy`, `None` stops
+    /// mirroring. Reports are mirrored after the transfer, failed transfers with their error.
+    pub fn set_proxy(&self, proxy: Option<ProxyTransport>) {
+        *self.proxy.lock().unwrap() = proxy;
+    }
+
+    /// Keeps a report exchanged with the device for diagnostics and mirrors it through the proxy, if one is set
+    pub(crate) fn log_report(&self, sent: bool, bytes: &[u8], result: &Result<(), FeatureError>) {
+        self.diagnostics.report(sent, bytes, result);
+
+        if let Some(proxy) = self.proxy.lock().unwrap().as_mut() {
+            proxy.mirror(CapturedReport {
+                timestamp: SystemTime::now(),
+                direction: if sent {
+                    ReportDirection::Sent
+                } else {
+                    ReportDirection::Received
+                },
+                bytes: bytes.to_vec(),
+                error: result.as_ref().err().map(|error| error.kind.clone()),
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn logs_hold_the_bytes_with_their_offsets() {
+        let report = CapturedReport {
+            timestamp: UNIX_EPOCH + Duration::from_micros(1_500_000),
+            direction: ReportDirection::Sent,
+            bytes: (0..18).collect(),
+            error: Some(FeatureErrorType::Timeout),
+        };
+
+        assert_eq!(
+            report.to_log(),
+            "# 1.500000 sent report 0x00 failed: Timeout\n\
+             0000  00 01 02 03 04 05 06 07 08 09 0a 0b 0c 0d 0e 0f\n\
+             0010  10 11\n"
+        );
+    }
+}
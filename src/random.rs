@@ -0,0 +1,72 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A xorshift generator, random enough for flickers, jitter and shuffles, without depending on `rand`
+pub(crate) struct Xorshift(u32);
+
+impl Xorshift {
+    /// A generator that yields the same numbers on every run for the same `seed`
+    pub(crate) fn new(seed: u32) -> Xorshift {
+        // A xorshift generator never leaves a state of 0
+        Xorshift(seed | 1)
+    }
+
+    /// A generator seeded from the system clock, which yields different numbers on every run
+    pub(crate) fn from_time() -> Xorshift {
+        let seed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .subsec_nanos();
+        Xorshift::new(seed)
+    }
+
+    pub(crate) fn next_u32(&mut self) -> u32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 17;
+        self.0 ^= self.0 << 5;
+        self.0
+    }
+
+    /// A number between 0.0 and 1.0
+    pub(crate) fn next_unit(&mut self) -> f32 {
+        self.next_u32() as f32 / u32::MAX as f32
+    }
+
+    /// Puts `items` in a random order
+    #[cfg(any(feature = "effects", feature = "animator"))]
+    pub(crate) fn shuffle<T>(&mut self, items: &mut [T]) {
+        for last in (1..items.len()).rev() {
+            let other = self.next_u32() as usize % (last + 1);
+            items.swap(last, other);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(any(feature = "effects", feature = "animator"))]
+    fn shuffles_keep_every_item_and_repeat_for_a_seed() {
+        let shuffled = |seed: u32| {
+            let mut items: Vec<u32> = (0..32).collect();
+            Xorshift::new(seed).shuffle(&mut items);
+            items
+        };
+
+        let mut sorted = shuffled(7);
+        assert_eq!(shuffled(7), sorted);
+        assert_ne!(sorted, (0..32).collect::<Vec<u32>>());
+        sorted.sort_unstable();
+        assert_eq!(sorted, (0..32).collect::<Vec<u32>>());
+    }
+
+    #[test]
+    fn units_stay_between_zero_and_one() {
+        let mut random = Xorshift::new(0x2512_2024);
+
+        assert!((0..1000)
+            .map(|_| random.next_unit())
+            .all(|unit| (0.0..=1.0).contains(&unit)));
+    }
+}
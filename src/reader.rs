@@ -0,0 +1,199 @@
+use crate::Color;
+use std::error::Error;
+use std::fmt::Formatter;
+use std::io::BufRead;
+
+/// An error while reading a frame with a `FrameReader`
+#[derive(Debug)]
+pub enum FrameReadError {
+    /// The input could not be read
+    Io(std::io::Error),
+    /// A line could not be understood as a frame
+    Parse { line: usize, message: String },
+}
+
+impl std::fmt::Display for FrameReadError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FrameReadError::Io(e) => write!(f, "Could not read frames: {}", e),
+            FrameReadError::Parse { line, message } => write!(f, "Invalid frame on line {}: {}", line, message),
+        }
+    }
+}
+
+impl Error for FrameReadError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            FrameReadError::Io(e) => Some(e),
+            FrameReadError::Parse { .. } => None,
+        }
+    }
+}
+
+/// Reads newline-delimited frames, so any program can stream animations to a BlinkStick through a pipe
+///
+/// Every non-empty line is one frame, written either as hex colors separated by spaces or commas
+/// (`#ff0000 #00ff00`), as a JSON array of hex colors or of `[r, g, b]` arrays, or as a JSON object with such an
+/// array under `colors`, which is what `blinkstick --json watch` prints.
+///
+/// # Example
+/// ```
+/// use blinkstick_rs::{Color, FrameReader};
+///
+/// let input = "#ff0000 #000000\n[[0, 0, 255], [0, 0, 0]]\n";
+/// let frames: Vec<Vec<Color>> = FrameReader::new(input.as_bytes()).map(Result::unwrap).collect();
+///
+/// assert_eq!(frames[0][0], Color {r: 255, g: 0, b: 0});
+/// assert_eq!(frames[1][0], Color {r: 0, g: 0, b: 255});
+/// ```
+pub struct FrameReader<R> {
+    input: R,
+    line: usize,
+}
+
+impl<R: BufRead> FrameReader<R> {
+    /// Reads frames from `input`, for example `std::io::stdin().lock()`
+    pub fn new(input: R) -> FrameReader<R> {
+        FrameReader { input, line: 0 }
+    }
+}
+
+impl<R: BufRead> Iterator for FrameReader<R> {
+    type Item = Result<Vec<Color>, FrameReadError>;
+
+    /// Reads the next frame, skipping empty lines. A line that is not a frame is yielded as an error, and reading
+    /// continues with the next line on the next call.
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut text = String::new();
+        loop {
+            text.clear();
+            match self.input.read_line(&mut text) {
+                Ok(0) => return None,
+                Ok(_) => self.line += 1,
+                Err(e) => return Some(Err(FrameReadError::Io(e))),
+            }
+
+            if !text.trim().is_empty() {
+                return Some(parse_frame(text.trim()).map_err(|message| FrameReadError::Parse {
+                    line: self.line,
+                    message: message.to_string(),
+                }));
+            }
+        }
+    }
+}
+
+/// Parses a single frame in any of the formats accepted by `FrameReader`
+pub fn parse_frame(text: &str) -> Result<Vec<Color>, &'static str> {
+    let colors = if text.starts_with('{') {
+        let (_, colors) = text.split_once("\"colors\"").ok_or("expected a colors array")?;
+        let colors = colors.trim_start().strip_prefix(':').ok_or("expected a colors array")?;
+        let end = colors.rfind('}').ok_or("unterminated object")?;
+        parse_json_colors(colors[..end].trim())?
+    } else if text.starts_with('[') {
+        parse_json_colors(text)?
+    } else {
+        text.split(|c: char| c.is_whitespace() || c == ',')
+            .filter(|color| !color.is_empty())
+            .map(|color| parse_hex(color).ok_or("invalid hex color"))
+            .collect::<Result<Vec<Color>, _>>()?
+    };
+
+    if colors.is_empty() {
+        Err("frame holds no colors")
+    } else {
+        Ok(colors)
+    }
+}
+
+/// Parses a JSON array of hex color strings or of `[r, g, b]` arrays
+fn parse_json_colors(array: &str) -> Result<Vec<Color>, &'static str> {
+    let inner = array
+        .strip_prefix('[')
+        .and_then(|array| array.strip_suffix(']'))
+        .ok_or("expected a JSON array")?;
+
+    if inner.contains('[') {
+        inner
+            .split(']')
+            .map(|color| color.trim().trim_start_matches(',').trim().trim_start_matches('['))
+            .filter(|color| !color.is_empty())
+            .map(|color| {
+                let channels: Vec<u8> = color
+                    .split(',')
+                    .map(|channel| channel.trim().parse())
+                    .collect::<Result<_, _>>()
+                    .map_err(|_| "invalid color channel")?;
+                match channels.as_slice() {
+                    [r, g, b] => Ok(Color { r: *r, g: *g, b: *b }),
+                    _ => Err("expected three color channels"),
+                }
+            })
+            .collect()
+    } else {
+        inner
+            .split(',')
+            .map(str::trim)
+            .filter(|color| !color.is_empty())
+            .map(|color| {
+                color
+                    .strip_prefix('"')
+                    .and_then(|color| color.strip_suffix('"'))
+                    .and_then(parse_hex)
+                    .ok_or("invalid hex color")
+            })
+            .collect()
+    }
+}
+
+/// Parses a color written as `rrggbb`, with or without a leading `#`
+fn parse_hex(color: &str) -> Option<Color> {
+    parse_hex_digits(color.strip_prefix('#').unwrap_or(color))
+}
+
+/// Parses the six hex digits of a color
+pub(crate) fn parse_hex_digits(hex: &str) -> Option<Color> {
+    if hex.len() != 6 || !hex.is_ascii() {
+        return None;
+    }
+
+    let channel = |range: std::ops::Range<usize>| u8::from_str_radix(&hex[range], 16).ok();
+    Some(Color {
+        r: channel(0..2)?,
+        g: channel(2..4)?,
+        b: channel(4..6)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RED: Color = Color { r: 255, g: 0, b: 0 };
+    const TEAL: Color = Color { r: 0, g: 128, b: 128 };
+
+    #[test]
+    fn parses_every_format() {
+        assert_eq!(parse_frame("#ff0000 008080"), Ok(vec![RED, TEAL]));
+        assert_eq!(parse_frame("ff0000,#008080"), Ok(vec![RED, TEAL]));
+        assert_eq!(parse_frame("[\"#ff0000\", \"#008080\"]"), Ok(vec![RED, TEAL]));
+        assert_eq!(parse_frame("[[255, 0, 0], [0, 128, 128]]"), Ok(vec![RED, TEAL]));
+        assert_eq!(
+            parse_frame("{\"colors\":[\"#ff0000\",\"#008080\"]}"),
+            Ok(vec![RED, TEAL])
+        );
+    }
+
+    #[test]
+    fn reports_invalid_lines_and_continues() {
+        let mut reader = FrameReader::new("#ff0000\n\nnot a color\n#008080\n".as_bytes());
+
+        assert_eq!(reader.next().unwrap().unwrap(), vec![RED]);
+        match reader.next() {
+            Some(Err(FrameReadError::Parse { line, .. })) => assert_eq!(line, 3),
+            other => panic!("Unexpected frame {:?}", other),
+        }
+        assert_eq!(reader.next().unwrap().unwrap(), vec![TEAL]);
+        assert!(reader.next().is_none());
+    }
+}
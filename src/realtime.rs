@@ -0,0 +1,331 @@
+//! A receiver for the UDP realtime protocols of WLED, so LedFx and other audio visualizers can stream pixels to a
+//! BlinkStick at high frame rates.
+//!
+//! Every packet starts with the protocol and a timeout in seconds, 255 for none. When no packet arrives within the
+//! timeout the leds are turned off. The supported protocols are:
+//! * WARLS (1): `index r g b` for every changed led
+//! * DRGB (2): `r g b` for every led, starting with the first
+//! * DRGBW (3): `r g b w` for every led, with the white channel mixed into the color
+//! * DNRGB (4): a big endian start index followed by `r g b` for every led from there
+
+use crate::{AnimationIter, Animator, Color, COLOR_OFF};
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+/// The port WLED listens on for realtime packets, which is where LedFx sends them by default
+pub const DEFAULT_REALTIME_PORT: u16 = 21324;
+
+/// The frame rate a receiver is capped at unless changed with `RealtimeReceiver::max_fps`
+const DEFAULT_MAX_FPS: f32 = 60.0;
+
+/// How often `RealtimeReceiver::run` checks its stop flag and timeouts while no packets arrive
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// The largest packet a receiver accepts, enough for DNRGB packets of 489 leds
+const MAX_PACKET_BYTES: usize = 1472;
+
+/// A WLED realtime protocol
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RealtimeProtocol {
+    Warls,
+    Drgb,
+    Drgbw,
+    Dnrgb,
+}
+
+/// A decoded realtime packet
+#[derive(Debug, Clone, PartialEq)]
+pub struct RealtimePacket {
+    /// The protocol the packet was sent with
+    pub protocol: RealtimeProtocol,
+    /// How long the colors stay on after the last packet, `None` until further notice
+    pub timeout: Option<Duration>,
+    /// The changed leds and their new colors
+    pub leds: Vec<(usize, Color)>,
+}
+
+impl RealtimePacket {
+    /// Decodes a packet, `None` for packets of other protocols or without any led data
+    pub fn decode(packet: &[u8]) -> Option<RealtimePacket> {
+        let (&protocol, rest) = packet.split_first()?;
+        let (&timeout, data) = rest.split_first()?;
+
+        let (protocol, leds): (RealtimeProtocol, Vec<(usize, Color)>) = match protocol {
+            1 => (
+                RealtimeProtocol::Warls,
+                data.chunks_exact(4)
+                    .map(|led| {
+                        (
+                            led[0] as usize,
+                            Color {
+                                r: led[1],
+                                g: led[2],
+                                b: led[3],
+                            },
+                        )
+                    })
+                    .collect(),
+            ),
+            2 => (
+                RealtimeProtocol::Drgb,
+                data.chunks_exact(3)
+                    .map(|led| Color {
+                        r: led[0],
+                        g: led[1],
+                        b: led[2],
+                    })
+                    .enumerate()
+                    .collect(),
+            ),
+            3 => (
+                RealtimeProtocol::Drgbw,
+                data.chunks_exact(4)
+                    .map(|led| Color {
+                        r: led[0].saturating_add(led[3]),
+                        g: led[1].saturating_add(led[3]),
+                        b: led[2].saturating_add(led[3]),
+                    })
+                    .enumerate()
+                    .collect(),
+            ),
+            4 => {
+                let start = u16::from_be_bytes([*data.first()?, *data.get(1)?]) as usize;
+                (
+                    RealtimeProtocol::Dnrgb,
+                    data[2..]
+                        .chunks_exact(3)
+                        .enumerate()
+                        .map(|(offset, led)| {
+                            (
+                                start + offset,
+                                Color {
+                                    r: led[0],
+                                    g: led[1],
+                                    b: led[2],
+                                },
+                            )
+                        })
+                        .collect(),
+                )
+            }
+            _ => return None,
+        };
+
+        if leds.is_empty() {
+            return None;
+        }
+
+        Some(RealtimePacket {
+            protocol,
+            timeout: match timeout {
+                255 => None,
+                // A timeout of zero would turn the leds off before they are shown
+                seconds => Some(Duration::from_secs(seconds.max(1) as u64)),
+            },
+            leds,
+        })
+    }
+}
+
+/// The frame assembled from packets, and when it is due to be shown
+struct Stream {
+    frame: Vec<Color>,
+    shown: Vec<Color>,
+    min_frame_interval: Duration,
+    last_shown: Option<Instant>,
+    expires: Option<Instant>,
+}
+
+impl Stream {
+    fn receive(&mut self, packet: &RealtimePacket, now: Instant) {
+        for (led, color) in packet.leds.iter() {
+            if let Some(current) = self.frame.get_mut(*led) {
+                *current = *color;
+            }
+        }
+        self.expires = packet.timeout.map(|timeout| now + timeout);
+    }
+
+    /// The frame to show, if it changed and the frame rate allows showing it. Frames received in between are
+    /// coalesced, so only the latest one is shown.
+    fn due(&mut self, now: Instant) -> Option<Vec<Color>> {
+        if self.expires.is_some_and(|expires| now >= expires) {
+            self.expires = None;
+            self.frame.iter_mut().for_each(|led| *led = COLOR_OFF);
+        }
+
+        let ready = self
+            .last_shown
+            .is_none_or(|last_shown| now.duration_since(last_shown) >= self.min_frame_interval);
+        if !ready || self.frame == self.shown {
+            return None;
+        }
+
+        self.last_shown = Some(now);
+        self.shown.clone_from(&self.frame);
+        Some(self.frame.clone())
+    }
+
+    /// How long to wait for packets before the next frame could be due
+    fn wait_time(&self, now: Instant) -> Duration {
+        if self.frame == self.shown {
+            return POLL_INTERVAL;
+        }
+
+        self.last_shown
+            .map_or(Duration::ZERO, |last_shown| {
+                self.min_frame_interval.saturating_sub(now.duration_since(last_shown))
+            })
+            .clamp(Duration::from_millis(1), POLL_INTERVAL)
+    }
+}
+
+/// Receives WLED realtime packets over UDP and shows them on an `Animator`
+///
+/// Packets arriving faster than the frame rate cap are coalesced, and frames that don't change any led are not
+/// written at all.
+///
+/// # Example
+/// Receives pixels from LedFx on the default WLED port until the program is stopped
+/// ```no_run
+/// use blinkstick_rs::realtime::{RealtimeReceiver, DEFAULT_REALTIME_PORT};
+/// use blinkstick_rs::{Animator, BlinkStick};
+/// use std::sync::atomic::AtomicBool;
+///
+/// let blinkstick = BlinkStick::new().unwrap();
+/// let leds = blinkstick.max_leds as usize;
+///
+/// let mut receiver = RealtimeReceiver::bind(("0.0.0.0", DEFAULT_REALTIME_PORT), Animator::new(blinkstick), leds)
+///     .unwrap()
+///     .max_fps(40.0);
+/// receiver.run(&AtomicBool::new(false)).unwrap();
+/// ```
+pub struct RealtimeReceiver {
+    socket: UdpSocket,
+    animator: Animator,
+    stream: Stream,
+}
+
+impl RealtimeReceiver {
+    /// Listens for realtime packets on `address`, driving the `led_count` leds of `animator`
+    pub fn bind<A: ToSocketAddrs>(
+        address: A,
+        animator: Animator,
+        led_count: usize,
+    ) -> std::io::Result<RealtimeReceiver> {
+        Ok(RealtimeReceiver {
+            socket: UdpSocket::bind(address)?,
+            animator,
+            stream: Stream {
+                frame: vec![COLOR_OFF; led_count],
+                shown: vec![COLOR_OFF; led_count],
+                min_frame_interval: Duration::from_secs_f32(1.0 / DEFAULT_MAX_FPS),
+                last_shown: None,
+                expires: None,
+            },
+        })
+    }
+
+    /// Caps how many frames per second are written to the device, 60 by default
+    ///
+    /// # Panics
+    /// The call panics if `fps` is not positive, or so small that the time between two frames overflows a `Duration`
+    pub fn max_fps(mut self, fps: f32) -> RealtimeReceiver {
+        assert!(fps > 0.0, "The frame rate must be positive");
+        self.stream.min_frame_interval =
+            Duration::try_from_secs_f32(1.0 / fps).unwrap_or_else(|_| panic!("The frame rate {} is too low", fps));
+        self
+    }
+
+    /// The address the receiver listens on, useful after binding to port 0
+    pub fn local_addr(&self) -> std::io::Result<SocketAddr> {
+        self.socket.local_addr()
+    }
+
+    /// Receives and shows packets until `stop` is set
+    pub fn run(&mut self, stop: &AtomicBool) -> std::io::Result<()> {
+        let mut packet = [0u8; MAX_PACKET_BYTES];
+        while !stop.load(Ordering::Relaxed) {
+            self.socket
+                .set_read_timeout(Some(self.stream.wait_time(Instant::now())))?;
+            match self.socket.recv(&mut packet) {
+                Ok(length) => {
+                    if let Some(packet) = RealtimePacket::decode(&packet[..length]) {
+                        self.stream.receive(&packet, Instant::now());
+                    }
+                }
+                Err(e) if matches!(e.kind(), std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut) => (),
+                Err(e) => return Err(e),
+            }
+
+            if let Some(frame) = self.stream.due(Instant::now()) {
+                self.animator
+                    .play("realtime", AnimationIter::new(vec![(frame, Duration::ZERO)]));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RED: Color = Color { r: 255, g: 0, b: 0 };
+    const WHITE: Color = Color { r: 255, g: 255, b: 255 };
+
+    #[test]
+    fn decodes_every_protocol() {
+        let warls = RealtimePacket::decode(&[1, 2, 3, 255, 0, 0]).unwrap();
+        assert_eq!(warls.timeout, Some(Duration::from_secs(2)));
+        assert_eq!(warls.leds, vec![(3, RED)]);
+
+        let drgb = RealtimePacket::decode(&[2, 255, 255, 0, 0, 255, 255, 255]).unwrap();
+        assert_eq!(drgb.timeout, None);
+        assert_eq!(drgb.leds, vec![(0, RED), (1, WHITE)]);
+
+        let drgbw = RealtimePacket::decode(&[3, 1, 0, 0, 0, 255]).unwrap();
+        assert_eq!(drgbw.leds, vec![(0, WHITE)]);
+
+        let dnrgb = RealtimePacket::decode(&[4, 1, 1, 0, 255, 0, 0]).unwrap();
+        assert_eq!(dnrgb.leds, vec![(256, RED)]);
+
+        assert!(RealtimePacket::decode(&[2, 1]).is_none());
+        assert!(RealtimePacket::decode(&[9, 1, 0, 0, 0]).is_none());
+    }
+
+    #[test]
+    fn coalesces_frames_and_skips_unchanged_ones() {
+        let start = Instant::now();
+        let mut stream = Stream {
+            frame: vec![COLOR_OFF; 2],
+            shown: vec![COLOR_OFF; 2],
+            min_frame_interval: Duration::from_millis(20),
+            last_shown: None,
+            expires: None,
+        };
+        let packet = |color: Color| RealtimePacket {
+            protocol: RealtimeProtocol::Drgb,
+            timeout: Some(Duration::from_secs(1)),
+            leds: vec![(0, color)],
+        };
+
+        stream.receive(&packet(RED), start);
+        assert_eq!(stream.due(start), Some(vec![RED, COLOR_OFF]));
+
+        // Too soon after the last frame, the latest packet is shown once the interval has passed
+        stream.receive(&packet(WHITE), start + Duration::from_millis(5));
+        assert_eq!(stream.due(start + Duration::from_millis(5)), None);
+        assert_eq!(
+            stream.due(start + Duration::from_millis(20)),
+            Some(vec![WHITE, COLOR_OFF])
+        );
+
+        stream.receive(&packet(WHITE), start + Duration::from_millis(50));
+        assert_eq!(stream.due(start + Duration::from_millis(50)), None);
+
+        assert_eq!(stream.due(start + Duration::from_secs(2)), Some(vec![COLOR_OFF; 2]));
+    }
+}
@@ -0,0 +1,397 @@
+use crate::random::Xorshift;
+use crate::{BlinkStick, BlinkStickBuilder, Color, FeatureError, FeatureErrorType, LedDevice};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::Duration;
+
+/// How long to wait between attempts to reopen a device, growing exponentially up to a maximum
+///
+/// Every delay is spread randomly by the jitter, so daemons that lost their devices at the same moment, like after a
+/// hub reset, don't all retry at once.
+///
+/// # Example
+/// Starts at half a second and doubles up to a minute, give or take a tenth
+/// ```
+/// use blinkstick_rs::Backoff;
+/// use std::time::Duration;
+///
+/// let backoff = Backoff::new(Duration::from_millis(500), Duration::from_secs(60)).jitter(0.1);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Backoff {
+    initial: Duration,
+    max: Duration,
+    multiplier: f32,
+    jitter: f32,
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Backoff {
+            initial: Duration::from_millis(250),
+            max: Duration::from_secs(30),
+            multiplier: 2.0,
+            jitter: 0.2,
+        }
+    }
+}
+
+impl Backoff {
+    /// Waits `initial` before the first attempt and doubles the delay after every failed attempt, up to `max`
+    pub fn new(initial: Duration, max: Duration) -> Backoff {
+        Backoff {
+            initial,
+            max,
+            ..Backoff::default()
+        }
+    }
+
+    /// Sets the factor the delay grows by after every failed attempt, 2.0 by default
+    pub fn multiplier(mut self, multiplier: f32) -> Backoff {
+        self.multiplier = multiplier.max(1.0);
+        self
+    }
+
+    /// Sets the fraction (0.0 - 1.0) every delay is randomly lengthened or shortened by, 0.2 by default
+    pub fn jitter(mut self, jitter: f32) -> Backoff {
+        self.jitter = jitter.clamp(0.0, 1.0);
+        self
+    }
+
+    /// The delay before the one-indexed `attempt`, spread by `unit` (0.0 - 1.0), where 0.5 is no spread at all
+    pub(crate) fn delay(&self, attempt: u32, unit: f32) -> Duration {
+        let exponent = attempt.saturating_sub(1).min(i32::MAX as u32) as i32;
+        let grown = self.initial.as_secs_f64() * (self.multiplier as f64).powi(exponent);
+        let spread = 1.0 + self.jitter as f64 * (2.0 * unit as f64 - 1.0);
+        let seconds = (grown.min(self.max.as_secs_f64()) * spread).min(self.max.as_secs_f64());
+
+        Duration::try_from_secs_f64(seconds).unwrap_or(self.max)
+    }
+
+    /// Makes up to `attempts` attempts, waiting before each of them as long as the backoff says, until one of them
+    /// ends the retrying. Returns the value of the attempt that succeeded.
+    pub(crate) fn retry<T>(&self, attempts: u32, mut attempt: impl FnMut(u32) -> Attempt<T>) -> Option<T> {
+        let mut random = Xorshift::from_time();
+
+        for number in 1..=attempts {
+            std::thread::sleep(self.delay(number, random.next_unit()));
+            match attempt(number) {
+                Attempt::Done(value) => return Some(value),
+                Attempt::Abandoned => return None,
+                Attempt::Failed => (),
+            }
+        }
+
+        None
+    }
+}
+
+/// How an attempt made by `Backoff::retry` ended
+pub(crate) enum Attempt<T> {
+    /// The attempt succeeded, retrying ends with its value
+    Done(T),
+    /// The attempt failed, the next one is made after the next delay
+    Failed,
+    /// Retrying ends without a value, like when nobody is left to use the device
+    Abandoned,
+}
+
+/// The connection of a `ReconnectingBlinkStick`, as reported by `ReconnectingBlinkStick::subscribe`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionState {
+    /// The device is open and can be written to
+    Connected,
+    /// The device was unplugged, writes fail until it is reopened
+    Disconnected,
+    /// The device is about to be reopened, the number counts the attempts since it was unplugged
+    Retrying(u32),
+}
+
+struct Shared {
+    builder: BlinkStickBuilder,
+    serial: Option<String>,
+    led_count: u8,
+    backoff: Backoff,
+    stick: Mutex<Option<Arc<BlinkStick>>>,
+    listeners: Mutex<Vec<Sender<ConnectionState>>>,
+}
+
+impl Shared {
+    fn emit(&self, state: ConnectionState) {
+        self.listeners
+            .lock()
+            .unwrap()
+            .retain(|listener| listener.send(state).is_ok());
+    }
+
+    /// Opens the device again, by its serial number if it has one, and makes it the open device
+    fn reopen(&self) -> Result<(), FeatureError> {
+        let builder = self.builder.clone();
+        let stick = match &self.serial {
+            Some(serial) => builder.open_by_serial(serial)?,
+            None => builder.open()?,
+        };
+
+        *self.stick.lock().unwrap() = Some(Arc::new(stick));
+        Ok(())
+    }
+}
+
+/// A BlinkStick that is reopened in the background whenever it is unplugged, for daemons that have to survive
+/// a device being replugged
+///
+/// Writes fail with `FeatureErrorType::Disconnected` while the device is gone. Reopening waits according to a
+/// `Backoff`, and every change of the connection is reported to the channels returned by `subscribe`, so daemons
+/// can show the device's availability in their UI or health endpoints.
+///
+/// # Example
+/// ```
+/// use blinkstick_rs::{BlinkStick, Color, ConnectionState, LedDevice, ReconnectingBlinkStick};
+///
+/// let blinkstick = ReconnectingBlinkStick::open(BlinkStick::builder()).unwrap();
+/// let states = blinkstick.subscribe();
+/// std::thread::spawn(move || {
+///     for state in states {
+///         println!("BlinkStick is {:?}", state);
+///     }
+/// });
+///
+/// blinkstick.set_all_leds_color(Color {r: 0, g: 50, b: 0}).unwrap();
+/// ```
+pub struct ReconnectingBlinkStick {
+    shared: Arc<Shared>,
+}
+
+impl ReconnectingBlinkStick {
+    /// Opens the first BlinkStick with the settings of `builder`, reopening it by its serial number once it was
+    /// unplugged. Uses the default `Backoff`.
+    pub fn open(builder: BlinkStickBuilder) -> Result<ReconnectingBlinkStick, FeatureError> {
+        ReconnectingBlinkStick::open_with_backoff(builder, Backoff::default())
+    }
+
+    /// Opens the first BlinkStick like `open`, waiting according to `backoff` between attempts to reopen it
+    pub fn open_with_backoff(
+        builder: BlinkStickBuilder,
+        backoff: Backoff,
+    ) -> Result<ReconnectingBlinkStick, FeatureError> {
+        let stick = builder.clone().open()?;
+
+        Ok(ReconnectingBlinkStick {
+            shared: Arc::new(Shared {
+                builder,
+                serial: stick.serial_number(),
+                led_count: stick.max_leds,
+                backoff,
+                stick: Mutex::new(Some(Arc::new(stick))),
+                listeners: Mutex::new(Vec::new()),
+            }),
+        })
+    }
+
+    /// Returns a channel receiving every change of the connection, until the receiver is dropped
+    pub fn subscribe(&self) -> Receiver<ConnectionState> {
+        let (sender, receiver) = channel();
+        self.shared.listeners.lock().unwrap().push(sender);
+        receiver
+    }
+
+    /// Returns `true` while the device is open
+    pub fn is_connected(&self) -> bool {
+        self.shared.stick.lock().unwrap().is_some()
+    }
+
+    /// The open device, for everything beyond the operations of `LedDevice`. `None` while it is unplugged.
+    pub fn stick(&self) -> Option<Arc<BlinkStick>> {
+        self.shared.stick.lock().unwrap().clone()
+    }
+
+    /// Runs `operation` on the open device, starting to reopen it if the operation finds it unplugged
+    fn with_stick<T, F>(&self, operation: F) -> Result<T, FeatureError>
+    where
+        F: FnOnce(&BlinkStick) -> Result<T, FeatureError>,
+    {
+        let stick = self.stick().ok_or(FeatureError {
+            kind: FeatureErrorType::Disconnected,
+        })?;
+
+        let result = operation(&stick);
+        if let Err(FeatureError {
+            kind: FeatureErrorType::Disconnected,
+        }) = &result
+        {
+            let mut current = self.shared.stick.lock().unwrap();
+            // Only the first operation to notice starts reopening, later ones see the device gone already
+            if current.as_ref().is_some_and(|current| Arc::ptr_eq(current, &stick)) {
+                *current = None;
+                drop(current);
+                self.shared.emit(ConnectionState::Disconnected);
+                let shared = Arc::downgrade(&self.shared);
+                std::thread::spawn(move || reconnect(&shared));
+            }
+        }
+
+        result
+    }
+}
+
+/// Reopens the device until it is plugged back in, or until the `ReconnectingBlinkStick` is dropped
+fn reconnect(shared: &Weak<Shared>) {
+    let backoff = match shared.upgrade() {
+        Some(shared) => shared.backoff,
+        None => return,
+    };
+
+    reconnect_with(
+        &backoff,
+        |state| {
+            if let Some(shared) = shared.upgrade() {
+                shared.emit(state);
+            }
+        },
+        || shared.upgrade().map(|shared| shared.reopen()),
+    );
+}
+
+/// The connection states of reopening a device: every attempt is reported as `Retrying`, and a successful one as
+/// `Connected`. `reopen` returns `None` once nobody is left to use the device, which ends the retrying.
+fn reconnect_with(
+    backoff: &Backoff,
+    emit: impl Fn(ConnectionState),
+    mut reopen: impl FnMut() -> Option<Result<(), FeatureError>>,
+) -> bool {
+    backoff
+        .retry(u32::MAX, |attempt| {
+            emit(ConnectionState::Retrying(attempt));
+            match reopen() {
+                Some(Ok(())) => {
+                    emit(ConnectionState::Connected);
+                    Attempt::Done(())
+                }
+                Some(Err(_)) => Attempt::Failed,
+                None => Attempt::Abandoned,
+            }
+        })
+        .is_some()
+}
+
+impl LedDevice for ReconnectingBlinkStick {
+    fn led_count(&self) -> u8 {
+        self.shared.led_count
+    }
+
+    fn set_led_color(&self, led: u8, color: Color) -> Result<(), FeatureError> {
+        self.with_stick(|stick| stick.set_led_color(led, color))
+    }
+
+    fn set_all_leds_colors(&self, colors: &[Color]) -> Result<(), FeatureError> {
+        self.with_stick(|stick| stick.set_all_leds_colors(colors))
+    }
+
+    fn get_all_led_colors(&self) -> Result<Vec<Color>, FeatureError> {
+        self.with_stick(|stick| stick.get_all_led_colors())
+    }
+
+    fn set_all_leds_color(&self, color: Color) -> Result<(), FeatureError> {
+        self.with_stick(|stick| stick.set_all_leds_color(color))
+    }
+
+    fn reset(&self) -> Result<(), FeatureError> {
+        self.with_stick(|stick| stick.reset())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_grows_up_to_the_maximum() {
+        let backoff = Backoff::new(Duration::from_millis(100), Duration::from_secs(1)).jitter(0.5);
+
+        assert_eq!(backoff.delay(1, 0.5), Duration::from_millis(100));
+        assert_eq!(backoff.delay(3, 0.5), Duration::from_millis(400));
+        assert_eq!(backoff.delay(50, 0.5), Duration::from_secs(1));
+        assert_eq!(backoff.delay(1, 0.0), Duration::from_millis(50));
+        assert_eq!(backoff.delay(50, 1.0), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn retries_stop_at_the_first_success_or_the_last_attempt() {
+        let backoff = Backoff::new(Duration::ZERO, Duration::ZERO);
+
+        assert_eq!(
+            backoff.retry(5, |attempt| if attempt == 3 {
+                Attempt::Done(attempt)
+            } else {
+                Attempt::Failed
+            }),
+            Some(3)
+        );
+        let mut made = 0;
+        assert_eq!(
+            backoff.retry(5, |_| {
+                made += 1;
+                Attempt::<()>::Failed
+            }),
+            None
+        );
+        assert_eq!(made, 5);
+    }
+
+    #[test]
+    fn reconnecting_retries_until_the_device_is_reopened() {
+        let states = std::cell::RefCell::new(Vec::new());
+        let mut failures = 2;
+
+        let reconnected = reconnect_with(
+            &Backoff::new(Duration::ZERO, Duration::ZERO),
+            |state| states.borrow_mut().push(state),
+            || {
+                if failures == 0 {
+                    return Some(Ok(()));
+                }
+                failures -= 1;
+                Some(Err(FeatureError {
+                    kind: FeatureErrorType::Disconnected,
+                }))
+            },
+        );
+
+        assert!(reconnected);
+        assert_eq!(
+            states.into_inner(),
+            vec![
+                ConnectionState::Retrying(1),
+                ConnectionState::Retrying(2),
+                ConnectionState::Retrying(3),
+                ConnectionState::Connected
+            ]
+        );
+    }
+
+    #[test]
+    fn reconnecting_ends_once_the_device_is_dropped() {
+        let states = std::cell::RefCell::new(Vec::new());
+        let mut owners = 1;
+
+        let reconnected = reconnect_with(
+            &Backoff::new(Duration::ZERO, Duration::ZERO),
+            |state| states.borrow_mut().push(state),
+            || {
+                if owners == 0 {
+                    return None;
+                }
+                owners -= 1;
+                Some(Err(FeatureError {
+                    kind: FeatureErrorType::Disconnected,
+                }))
+            },
+        );
+
+        assert!(!reconnected);
+        assert_eq!(
+            states.into_inner(),
+            vec![ConnectionState::Retrying(1), ConnectionState::Retrying(2)]
+        );
+    }
+}
@@ -0,0 +1,204 @@
+use crate::reconnect::Attempt;
+use crate::transport::Transport;
+use crate::{Backoff, BlinkStick, FeatureError, FeatureErrorType, PRODUCT_ID, VENDOR_ID};
+use std::time::Duration;
+
+/// The number of times `BlinkStick::reset` looks for the device by its serial number after it could not be reopened
+/// at its path
+const REOPEN_BY_SERIAL_ATTEMPTS: u32 = 8;
+
+/// The time before every attempt to find the device by its serial number, giving it time to enumerate again
+const REOPEN_BY_SERIAL_INTERVAL: Duration = Duration::from_millis(250);
+
+impl BlinkStick {
+    /// Closes and reopens the device, then shows the colors written last again. Recovers a device that stopped
+    /// answering while it stayed plugged in, see `FeatureError::is_wedged`. An `Animator` resets its device by
+    /// itself.
+    ///
+    /// The device is reopened at the hid path it was opened at. If that fails, the device is looked for by its
+    /// serial number, in case it enumerated again. See `reset_with_port_power_cycle` to also switch its USB port
+    /// off and on.
+    ///
+    /// # Example
+    /// ```
+    /// use blinkstick_rs::{BlinkStick, Color};
+    ///
+    /// let blinkstick = BlinkStick::new().unwrap();
+    /// if let Err(e) = blinkstick.set_all_leds_color(Color {r: 0, g: 50, b: 0}) {
+    ///     if e.is_wedged() {
+    ///         blinkstick.reset().unwrap();
+    ///     }
+    /// }
+    /// ```
+    pub fn reset(&self) -> Result<(), FeatureError> {
+        self.reset_with(false)
+    }
+
+    /// Resets the device like `reset`, switching its USB port off and on before it is looked for by its serial
+    /// number. This disconnects every other device behind the same port for a moment, so it is never done unless
+    /// asked for.
+    ///
+    /// The port is only switched on Linux, through sysfs, which usually needs root. Elsewhere, or without the
+    /// permission, this is the same as `reset`.
+    pub fn reset_with_port_power_cycle(&self) -> Result<(), FeatureError> {
+        self.reset_with(true)
+    }
+
+    fn reset_with(&self, power_cycle: bool) -> Result<(), FeatureError> {
+        // A disabled BlinkStick has no device that could stop answering
+        if self.device.lock().unwrap().is_loopback() {
+            return Ok(());
+        }
+
+        let frame = self.written_frame();
+        let serial = self.serial_number();
+
+        recover(
+            || self.reopen_device(),
+            || {
+                #[cfg(target_os = "linux")]
+                if power_cycle {
+                    if let Some(path) = self.path.lock().unwrap().as_ref() {
+                        toggle_usb_port(path);
+                    }
+                }
+                #[cfg(not(target_os = "linux"))]
+                let _ = power_cycle;
+            },
+            || self.reopen_by_serial(serial.as_deref()),
+            || self.set_all_leds_colors(&frame),
+        )
+    }
+
+    /// Replaces the hid handle of the device with a freshly opened one, found by the path it was opened at
+    pub(crate) fn reopen_device(&self) -> Result<(), FeatureError> {
+        let path = self.path.lock().unwrap().clone().ok_or(FeatureError {
+            kind: FeatureErrorType::Disconnected,
+        })?;
+        let api = hidapi::HidApi::new().map_err(|e| FeatureError::from_hid_error(FeatureErrorType::Send, &e))?;
+        let device = api
+            .open_path(&path)
+            .map_err(|e| FeatureError::from_hid_error(FeatureErrorType::Disconnected, &e))?;
+
+        *self.device.lock().unwrap() = Transport::Hid(device);
+        Ok(())
+    }
+
+    /// Replaces the hid handle of the device with one opened by its serial number, waiting for it to appear
+    fn reopen_by_serial(&self, serial: Option<&str>) -> Result<(), FeatureError> {
+        let serial = serial.ok_or(FeatureError {
+            kind: FeatureErrorType::Disconnected,
+        })?;
+
+        let backoff = Backoff::new(REOPEN_BY_SERIAL_INTERVAL, REOPEN_BY_SERIAL_INTERVAL).jitter(0.0);
+        let reopened = backoff.retry(REOPEN_BY_SERIAL_ATTEMPTS, |_| {
+            let api = match hidapi::HidApi::new() {
+                Ok(api) => api,
+                Err(_) => return Attempt::Failed,
+            };
+            let info = api
+                .device_list()
+                .find(|info| {
+                    info.vendor_id() == VENDOR_ID
+                        && info.product_id() == PRODUCT_ID
+                        && info.serial_number() == Some(serial)
+                })
+                .cloned();
+            let device = match info.as_ref().map(|info| info.open_device(&api)) {
+                Some(Ok(device)) => device,
+                _ => return Attempt::Failed,
+            };
+
+            *self.device.lock().unwrap() = Transport::Hid(device);
+            *self.path.lock().unwrap() = info.map(|info| info.path().to_owned());
+            Attempt::Done(())
+        });
+
+        reopened.ok_or(FeatureError {
+            kind: FeatureErrorType::Disconnected,
+        })
+    }
+}
+
+/// The steps of a reset: the device is reopened at its path, or else its port is power cycled and it is looked for
+/// by its serial number. Once it is open again, the colors written last are restored.
+fn recover(
+    reopen: impl FnOnce() -> Result<(), FeatureError>,
+    power_cycle: impl FnOnce(),
+    reopen_by_serial: impl FnOnce() -> Result<(), FeatureError>,
+    restore: impl FnOnce() -> Result<(), FeatureError>,
+) -> Result<(), FeatureError> {
+    if reopen().is_err() {
+        power_cycle();
+        reopen_by_serial()?;
+    }
+
+    restore()
+}
+
+/// Switches the USB port of the hidraw device at `path` off and on through sysfs, which usually needs root.
+/// Does nothing if the port cannot be found or switched.
+#[cfg(target_os = "linux")]
+fn toggle_usb_port(path: &std::ffi::CStr) {
+    let name = match path.to_str().ok().and_then(|path| path.rsplit('/').next()) {
+        Some(name) if name.starts_with("hidraw") => name,
+        _ => return,
+    };
+    let device = match std::fs::canonicalize(format!("/sys/class/hidraw/{}/device", name)) {
+        Ok(device) => device,
+        Err(_) => return,
+    };
+
+    // The USB device is the closest ancestor naming a vendor, its interfaces can be authorized as well
+    if let Some(usb_device) = device.ancestors().find(|dir| dir.join("idVendor").exists()) {
+        let authorized = usb_device.join("authorized");
+        if std::fs::write(&authorized, "0").is_ok() {
+            let _ = std::fs::write(&authorized, "1");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    fn disconnected() -> Result<(), FeatureError> {
+        Err(FeatureError {
+            kind: FeatureErrorType::Disconnected,
+        })
+    }
+
+    fn steps(reopen: Result<(), FeatureError>, by_serial: Result<(), FeatureError>) -> (Vec<&'static str>, bool) {
+        let steps = RefCell::new(Vec::new());
+        let result = recover(
+            || {
+                steps.borrow_mut().push("reopen");
+                reopen
+            },
+            || steps.borrow_mut().push("power cycle"),
+            || {
+                steps.borrow_mut().push("by serial");
+                by_serial
+            },
+            || {
+                steps.borrow_mut().push("restore");
+                Ok(())
+            },
+        );
+        (steps.into_inner(), result.is_ok())
+    }
+
+    #[test]
+    fn resets_reopen_the_device_before_restoring_its_colors() {
+        assert_eq!(steps(Ok(()), disconnected()), (vec!["reopen", "restore"], true));
+        assert_eq!(
+            steps(disconnected(), Ok(())),
+            (vec!["reopen", "power cycle", "by serial", "restore"], true)
+        );
+        assert_eq!(
+            steps(disconnected(), disconnected()),
+            (vec!["reopen", "power cycle", "by serial"], false)
+        );
+    }
+}
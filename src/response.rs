@@ -0,0 +1,207 @@
+use crate::config::config_lines;
+use crate::{BlinkStick, Color, ConfigError};
+use std::path::Path;
+
+/// The measured brightness of every channel of a device's leds over the values it is sent, inverted so the leds
+/// show brightness linearly in the color written
+///
+/// Leds rarely get brighter in proportion to the value they are sent. Once their response was measured, for
+/// example with a photometer for signage or visual experiments, every channel is sent the value whose measured
+/// brightness is the requested share of the brightest measurement. Values between measurements are interpolated
+/// linearly.
+///
+/// # Example
+/// Leds measured at four points, where red saturates early and blue responds late
+/// ```
+/// use blinkstick_rs::{BlinkStick, Color, ResponseCurves};
+///
+/// let curves = ResponseCurves::parse("\
+/// input, red, green, blue
+/// 0, 0.0, 0.0, 0.0
+/// 64, 40.0, 20.0, 5.0
+/// 128, 70.0, 55.0, 30.0
+/// 255, 100.0, 100.0, 100.0
+/// ").unwrap();
+///
+/// let blinkstick = BlinkStick::new().unwrap();
+/// blinkstick.set_response_curves(Some(curves));
+/// blinkstick.set_all_leds_color(Color {r: 128, g: 128, b: 128}).unwrap();
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResponseCurves {
+    /// The value sent for every requested value of every channel
+    tables: [[u8; 256]; 3],
+}
+
+impl ResponseCurves {
+    /// Builds the curves from measurements of the red, green and blue brightness for a rising series of inputs
+    ///
+    /// Returns `None` if there are fewer than two measurements, the inputs don't rise, a channel gets darker at
+    /// a higher input or never gets brighter at all.
+    pub fn from_measurements(measurements: &[(u8, [f32; 3])]) -> Option<ResponseCurves> {
+        let rising = measurements.windows(2).all(|pair| pair[0].0 < pair[1].0);
+        if measurements.len() < 2 || !rising {
+            return None;
+        }
+
+        let mut tables = [[0; 256]; 3];
+        for (channel, table) in tables.iter_mut().enumerate() {
+            let curve: Vec<(f32, f32)> = measurements
+                .iter()
+                .map(|(input, brightness)| (*input as f32, brightness[channel]))
+                .collect();
+            *table = inverse_table(&curve)?;
+        }
+
+        Some(ResponseCurves { tables })
+    }
+
+    /// Parses measurements from CSV lines of an input (0 - 255) and the measured red, green and blue brightness, in
+    /// any unit. A header line and everything after a `#` are ignored.
+    pub fn parse(contents: &str) -> Result<ResponseCurves, ConfigError> {
+        let mut measurements = Vec::new();
+        let mut last_line = 0;
+
+        for (line, text) in config_lines(contents) {
+            last_line = line;
+            let parse_error = |message: &str| ConfigError::Parse {
+                line,
+                message: message.to_string(),
+            };
+
+            let fields: Vec<&str> = text.split(',').map(str::trim).collect();
+            let header = measurements.is_empty() && fields.first().is_some_and(|field| field.parse::<f32>().is_err());
+            if header {
+                continue;
+            }
+
+            let (input, red, green, blue) = match fields[..] {
+                [input, red, green, blue] => (input, red, green, blue),
+                _ => return Err(parse_error("expected an input and the red, green and blue brightness")),
+            };
+            let input = input
+                .parse()
+                .map_err(|_| parse_error("invalid input, expected 0 - 255"))?;
+            let mut brightness = [0.0; 3];
+            for (channel, value) in brightness.iter_mut().zip([red, green, blue].iter()) {
+                *channel = value
+                    .parse::<f32>()
+                    .ok()
+                    .filter(|value| value.is_finite())
+                    .ok_or_else(|| parse_error("invalid brightness"))?;
+            }
+            measurements.push((input, brightness));
+        }
+
+        ResponseCurves::from_measurements(&measurements).ok_or_else(|| ConfigError::Parse {
+            line: last_line,
+            message: "expected at least two measurements of rising inputs and brightness".to_string(),
+        })
+    }
+
+    /// Reads the curves from a CSV file in the format accepted by `parse`
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<ResponseCurves, ConfigError> {
+        ResponseCurves::parse(&std::fs::read_to_string(path)?)
+    }
+
+    /// The color to send for the leds to show `color` at linear brightness
+    pub fn apply(&self, color: Color) -> Color {
+        Color {
+            r: self.tables[0][color.r as usize],
+            g: self.tables[1][color.g as usize],
+            b: self.tables[2][color.b as usize],
+        }
+    }
+
+    /// Looks up exact channel values like `apply`, interpolating between the values of the tables
+    pub(crate) fn apply_exact(&self, channels: [f32; 3]) -> [f32; 3] {
+        let mut linear = [0.0; 3];
+        for ((value, table), channel) in linear.iter_mut().zip(self.tables.iter()).zip(channels.iter()) {
+            let channel = channel.clamp(0.0, 255.0);
+            let low = channel.floor() as usize;
+            let high = (low + 1).min(255);
+            let (from, to) = (table[low] as f32, table[high] as f32);
+            *value = from + (to - from) * (channel - low as f32);
+        }
+
+        linear
+    }
+}
+
+/// The input reaching every share (0 - 255) of the brightness range of a rising `curve` of inputs and brightness.
+/// A share of 0 is always sent as 0, so black stays off even when the curve starts above it.
+fn inverse_table(curve: &[(f32, f32)]) -> Option<[u8; 256]> {
+    let darkest = curve.first()?.1;
+    let brightest = curve.last()?.1;
+    let falling = curve.windows(2).any(|pair| pair[1].1 < pair[0].1);
+    if falling || brightest <= darkest {
+        return None;
+    }
+
+    let mut table = [0; 256];
+    for (value, entry) in table.iter_mut().enumerate() {
+        let target = darkest + (brightest - darkest) * value as f32 / 255.0;
+        let segment = curve
+            .windows(2)
+            .find(|pair| target <= pair[1].1)
+            .unwrap_or(&curve[curve.len() - 2..]);
+        let ((input_low, low), (input_high, high)) = (segment[0], segment[1]);
+
+        let share = if high > low { (target - low) / (high - low) } else { 0.0 };
+        *entry = (input_low + (input_high - input_low) * share.clamp(0.0, 1.0)).round() as u8;
+    }
+    table[0] = 0;
+
+    Some(table)
+}
+
+impl BlinkStick {
+    /// Sends every color written from now on through the inverse of measured response curves, or stops with `None`.
+    /// The curves apply last, after brightness, gamma and the color profile.
+    pub fn set_response_curves(&self, curves: Option<ResponseCurves>) {
+        *self.response_curves.lock().unwrap() = curves;
+    }
+
+    /// The response curves currently applied to the device
+    pub fn response_curves(&self) -> Option<ResponseCurves> {
+        self.response_curves.lock().unwrap().clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use blinkstick_core::COLOR_OFF;
+
+    #[test]
+    fn linear_requests_reach_measured_brightness() {
+        // Every channel reaches half its brightness at a quarter of its input
+        let curves = ResponseCurves::parse("input,red,green,blue\n0,0,0,0\n64,50,50,50\n255,100,100,100\n").unwrap();
+
+        assert_eq!(curves.apply(Color::new(0, 255, 51)), Color::new(0, 255, 26));
+        assert_eq!(curves.apply(Color::new(128, 0, 0)).r, 65);
+    }
+
+    #[test]
+    fn black_stays_off_when_measurements_start_above_it() {
+        let curves = ResponseCurves::parse(
+            "16,1,1,1
+255,100,100,100
+",
+        )
+        .unwrap();
+
+        assert_eq!(curves.apply(COLOR_OFF), COLOR_OFF);
+        assert_eq!(curves.apply(Color::new(1, 1, 1)), Color::new(17, 17, 17));
+    }
+
+    #[test]
+    fn falling_or_flat_curves_are_rejected() {
+        assert!(ResponseCurves::from_measurements(&[(0, [0.0; 3]), (255, [1.0, 1.0, 0.0])]).is_none());
+        assert!(ResponseCurves::from_measurements(&[(0, [0.0; 3]), (128, [2.0; 3]), (255, [1.0; 3])]).is_none());
+        assert!(matches!(
+            ResponseCurves::parse("0,0,0,0\n255,1,1\n"),
+            Err(ConfigError::Parse { line: 2, .. })
+        ));
+    }
+}
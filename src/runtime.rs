@@ -0,0 +1,239 @@
+use crate::{Animator, Color, FeatureError, COLOR_OFF};
+use std::error::Error;
+use std::fmt::Formatter;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+/// How long a `Runtime` that is dropped without `shutdown` waits for its workers
+const DROP_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How often `Runtime::shutdown` checks whether its workers have stopped
+const JOIN_POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+/// What a `Runtime` leaves the leds showing once it has shut down
+#[derive(Debug, Clone, PartialEq)]
+pub enum FinalState {
+    /// Turns every led off
+    Off,
+    /// Leaves the leds showing the last frame
+    Unchanged,
+    /// Shows a frame, a single color lights up every led
+    Frame(Vec<Color>),
+}
+
+/// The workers that did not shut down cleanly
+#[derive(Debug)]
+pub struct ShutdownError {
+    /// Workers still running when the timeout passed, which are left detached
+    pub timed_out: Vec<String>,
+    /// Workers that stopped with an error or panicked, with what went wrong
+    pub failed: Vec<(String, String)>,
+    /// The error leaving the device in its final state, if that failed
+    pub final_state: Option<FeatureError>,
+}
+
+impl std::fmt::Display for ShutdownError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Could not shut down cleanly:")?;
+        if !self.timed_out.is_empty() {
+            write!(f, " still running: {};", self.timed_out.join(", "))?;
+        }
+        for (name, message) in self.failed.iter() {
+            write!(f, " {} failed: {};", name, message)?;
+        }
+        if let Some(e) = &self.final_state {
+            write!(f, " final state not shown: {};", e)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Error for ShutdownError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.final_state.as_ref().map(|e| e as &(dyn Error + 'static))
+    }
+}
+
+/// Owns an `Animator` and the background workers feeding it, like network listeners and hotplug watchers, and
+/// stops all of them in order with a single `shutdown`
+///
+/// Workers receive a stop flag they must check regularly, which is what the `run` functions of the receivers and
+/// servers in this crate do. Shutting down sets the flag, waits for the workers so nothing submits new effects,
+/// cancels the effects of the animator and finally leaves the device in its `FinalState`. A runtime that is
+/// dropped shuts down the same way.
+///
+/// # Example
+/// Plays effects pushed by a worker until shut down, then turns the leds off
+/// ```no_run
+/// use blinkstick_rs::{Animator, BlinkStick, Color, Runtime};
+/// use std::sync::atomic::Ordering;
+/// use std::time::Duration;
+///
+/// let blinkstick = BlinkStick::new().unwrap();
+/// let leds = blinkstick.max_leds as usize;
+/// let mut runtime = Runtime::new(Animator::new(blinkstick));
+///
+/// let animator = runtime.animator().clone();
+/// runtime.spawn("ticker", move |stop| {
+///     let (tick, tock) = (Color {r: 0, g: 0, b: 30}, Color {r: 30, g: 10, b: 0});
+///     while !stop.load(Ordering::Relaxed) {
+///         let frames = vec![(vec![tick; leds], Duration::from_secs(1)), (vec![tock; leds], Duration::from_secs(1))];
+///         animator.play("tick", frames).wait();
+///     }
+///     Ok::<(), std::io::Error>(())
+/// });
+///
+/// runtime.shutdown(Duration::from_secs(2)).unwrap();
+/// ```
+pub struct Runtime {
+    animator: Option<Arc<Animator>>,
+    stop: Arc<AtomicBool>,
+    workers: Vec<(String, JoinHandle<Result<(), String>>)>,
+    final_state: FinalState,
+}
+
+impl Runtime {
+    /// Creates a runtime owning `animator`, which turns the leds off when it shuts down
+    pub fn new(animator: Animator) -> Runtime {
+        Runtime {
+            animator: Some(Arc::new(animator)),
+            stop: Arc::new(AtomicBool::new(false)),
+            workers: Vec::new(),
+            final_state: FinalState::Off,
+        }
+    }
+
+    /// Changes what the leds show once the runtime has shut down
+    pub fn final_state(mut self, final_state: FinalState) -> Runtime {
+        self.final_state = final_state;
+        self
+    }
+
+    /// The animator of the runtime, clone it to hand it to workers
+    pub fn animator(&self) -> &Arc<Animator> {
+        self.animator.as_ref().unwrap()
+    }
+
+    /// The flag that is set when the runtime shuts down, for work that doesn't run on a worker of the runtime
+    pub fn stop_flag(&self) -> Arc<AtomicBool> {
+        self.stop.clone()
+    }
+
+    /// Runs `worker` on its own thread until it returns, it should return soon after the stop flag it receives is
+    /// set
+    ///
+    /// # Arguments
+    /// * `name` - A name for the worker, reported in a `ShutdownError`
+    /// * `worker` - The work to run, for example `move |stop| receiver.run(stop)`
+    pub fn spawn<F, E>(&mut self, name: &str, worker: F)
+    where
+        F: FnOnce(&AtomicBool) -> Result<(), E> + Send + 'static,
+        E: std::fmt::Display,
+    {
+        let stop = self.stop.clone();
+        let handle = std::thread::spawn(move || worker(&stop).map_err(|e| e.to_string()));
+        self.workers.push((name.to_string(), handle));
+    }
+
+    /// Stops every worker, then the animator, and leaves the device in the final state
+    ///
+    /// # Arguments
+    /// * `timeout` - How long to wait for the workers, workers that are still running after it are left detached
+    ///
+    /// # Errors
+    /// Returns a `ShutdownError` naming the workers that did not stop in time or failed, and the error showing the
+    /// final state, if any. Every step is attempted regardless.
+    pub fn shutdown(mut self, timeout: Duration) -> Result<(), ShutdownError> {
+        self.stop_all(timeout)
+    }
+
+    fn stop_all(&mut self, timeout: Duration) -> Result<(), ShutdownError> {
+        let mut error = ShutdownError {
+            timed_out: Vec::new(),
+            failed: Vec::new(),
+            final_state: None,
+        };
+
+        // Input stops first, so nothing submits effects while the animator winds down
+        self.stop.store(true, Ordering::Relaxed);
+        let deadline = Instant::now().checked_add(timeout);
+        for (name, worker) in self.workers.drain(..) {
+            while !worker.is_finished() && deadline.is_none_or(|deadline| Instant::now() < deadline) {
+                std::thread::sleep(JOIN_POLL_INTERVAL);
+            }
+
+            if !worker.is_finished() {
+                error.timed_out.push(name);
+                continue;
+            }
+            match worker.join() {
+                Ok(Ok(())) => (),
+                Ok(Err(message)) => error.failed.push((name, message)),
+                Err(_) => error.failed.push((name, "panicked".to_string())),
+            }
+        }
+
+        if let Some(animator) = self.animator.take() {
+            animator.cancel_all();
+            let stick = animator.stick().cloned();
+            // Joins the animator thread, unless a worker that timed out still holds a clone
+            drop(animator);
+
+            if let Some(stick) = stick {
+                // The final state is shown here, dropping the last handle to the stick must not turn it off again
+                stick.turn_off_on_drop.store(false, Ordering::Relaxed);
+                let shown = match &self.final_state {
+                    FinalState::Off => stick.set_all_leds_color(COLOR_OFF),
+                    FinalState::Unchanged => Ok(()),
+                    FinalState::Frame(frame) if frame.len() == 1 => stick.set_all_leds_color(frame[0]),
+                    FinalState::Frame(frame) => stick.set_all_leds_colors(frame),
+                };
+                error.final_state = shown.err();
+            }
+        }
+
+        if error.timed_out.is_empty() && error.failed.is_empty() && error.final_state.is_none() {
+            Ok(())
+        } else {
+            Err(error)
+        }
+    }
+}
+
+impl Drop for Runtime {
+    fn drop(&mut self) {
+        if let Err(e) = self.stop_all(DROP_SHUTDOWN_TIMEOUT) {
+            eprintln!("{}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shutdown_stops_workers_and_reports_stragglers() {
+        let mut runtime = Runtime::new(Animator::headless());
+        runtime.spawn("listener", |stop| {
+            while !stop.load(Ordering::Relaxed) {
+                std::thread::sleep(Duration::from_millis(1));
+            }
+            Ok::<(), String>(())
+        });
+        runtime.spawn("broken", |_| Err("port in use"));
+        runtime.spawn("stuck", |_| {
+            std::thread::sleep(Duration::from_secs(1));
+            Ok::<(), String>(())
+        });
+
+        let error = runtime.shutdown(Duration::from_millis(50)).unwrap_err();
+
+        assert_eq!(error.timed_out, vec!["stuck".to_string()]);
+        assert_eq!(error.failed, vec![("broken".to_string(), "port in use".to_string())]);
+        assert!(error.final_state.is_none());
+    }
+}
@@ -0,0 +1,279 @@
+use crate::render::{mix_linear_light, wipe_stages};
+use crate::{BlinkStick, Color, Easing, FeatureError, FeatureErrorType, LedFrame, WipeDirection};
+use std::time::{Duration, Instant};
+
+/// Time between two frames of a scene transition
+const TRANSITION_FRAME_INTERVAL: Duration = Duration::from_millis(20);
+
+/// How `apply_scene` and `write_frame_with_transition` move from the current colors to new ones
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Transition {
+    /// Switches to the scene at once
+    Cut,
+    /// Crossfades to the scene over the given duration, easing in and out
+    Fade(Duration),
+    /// Crossfades to the scene over the given duration with the specified easing
+    EasedFade(Duration, Easing),
+    /// Reveals the scene led by led in a sweep in the given direction, over the given duration
+    Wipe(Duration, WipeDirection),
+    /// Switches the leds to the scene one at a time in a scattered order, over the given duration
+    Dissolve(Duration),
+    /// Slides the scene in from the last led towards the zeroth, pushing the current colors out, over the given
+    /// duration
+    PushLeft(Duration),
+    /// Slides the scene in from the zeroth led towards the last, pushing the current colors out, over the given
+    /// duration
+    PushRight(Duration),
+}
+
+impl Transition {
+    /// The time the transition takes
+    pub fn duration(&self) -> Duration {
+        match *self {
+            Transition::Cut => Duration::ZERO,
+            Transition::Fade(duration)
+            | Transition::EasedFade(duration, _)
+            | Transition::Wipe(duration, _)
+            | Transition::Dissolve(duration)
+            | Transition::PushLeft(duration)
+            | Transition::PushRight(duration) => duration,
+        }
+    }
+
+    /// The frames of the transition from `from` to `to`, one every 20 milliseconds and ending at `to`. Frames of
+    /// different lengths are cut to the shorter one, and equal frames need no transition at all.
+    ///
+    /// The frames are rendered as they are taken, so even transitions of days take no memory up front.
+    ///
+    /// # Example
+    /// ```
+    /// use blinkstick_rs::{Color, Transition};
+    /// use std::time::Duration;
+    ///
+    /// let from = vec![Color::RED; 4];
+    /// let to = vec![Color::BLUE; 4];
+    /// let frames: Vec<Vec<Color>> = Transition::PushRight(Duration::from_millis(80)).frames(&from, &to).collect();
+    ///
+    /// assert_eq!(frames[1], vec![Color::BLUE, Color::BLUE, Color::RED, Color::RED]);
+    /// assert_eq!(frames.last(), Some(&to));
+    /// ```
+    pub fn frames(&self, from: &[Color], to: &[Color]) -> impl Iterator<Item = Vec<Color>> {
+        let leds = from.len().min(to.len());
+        let (from, to) = (from[..leds].to_vec(), to[..leds].to_vec());
+        let steps = if from == to {
+            0
+        } else {
+            (self.duration().as_millis() / TRANSITION_FRAME_INTERVAL.as_millis()).clamp(1, usize::MAX as u128) as usize
+        };
+        let progress = move |step: usize| step as f32 / steps as f32;
+
+        let frame: Box<dyn Fn(usize) -> Vec<Color>> = match *self {
+            Transition::Cut => Box::new(move |_| to.clone()),
+            Transition::Fade(_) => Box::new(move |step| crossfade(&from, &to, Easing::EaseInOut.apply(progress(step)))),
+            Transition::EasedFade(_, easing) => {
+                Box::new(move |step| crossfade(&from, &to, easing.apply(progress(step))))
+            }
+            Transition::Wipe(_, direction) => {
+                let stages = wipe_stages(leds, direction);
+                Box::new(move |step| reveal_frame(&from, &to, &stages, step, steps))
+            }
+            Transition::Dissolve(_) => {
+                let mut order: Vec<usize> = (0..leds).collect();
+                order.sort_by_key(|led| scatter(*led));
+                let stages: Vec<Vec<usize>> = order.into_iter().map(|led| vec![led]).collect();
+                Box::new(move |step| reveal_frame(&from, &to, &stages, step, steps))
+            }
+            Transition::PushLeft(_) => {
+                let strip = [from, to].concat();
+                Box::new(move |step| {
+                    let offset = (leds as f32 * progress(step)).round() as usize;
+                    strip[offset..offset + leds].to_vec()
+                })
+            }
+            Transition::PushRight(_) => {
+                let strip = [to, from].concat();
+                Box::new(move |step| {
+                    let offset = leds - (leds as f32 * progress(step)).round() as usize;
+                    strip[offset..offset + leds].to_vec()
+                })
+            }
+        };
+
+        (1..=steps).map(frame)
+    }
+}
+
+/// The frame `progress` (0.0 - 1.0) of the way from `from` to `to`, mixed in linear light
+fn crossfade(from: &[Color], to: &[Color], progress: f32) -> Vec<Color> {
+    from.iter()
+        .zip(to.iter())
+        .map(|(start, target)| {
+            if start == target {
+                *start
+            } else {
+                mix_linear_light(*start, *target, progress)
+            }
+        })
+        .collect()
+}
+
+/// The frame revealing `to` over `from` one stage of leds at a time, spread evenly over `steps` frames
+fn reveal_frame(from: &[Color], to: &[Color], stages: &[Vec<usize>], step: usize, steps: usize) -> Vec<Color> {
+    let revealed = (stages.len() as u128 * step as u128).div_ceil(steps as u128) as usize;
+    let mut frame = from.to_vec();
+    for led in stages[..revealed].iter().flatten() {
+        frame[*led] = to[*led];
+    }
+    frame
+}
+
+/// A sort key that scatters the leds of a dissolve in an order that looks random, but is the same every time
+fn scatter(led: usize) -> u32 {
+    (led as u32).wrapping_mul(0x9E37_79B1).rotate_right(16)
+}
+
+impl BlinkStick {
+    /// Stores a frame under `name`, replacing any scene with the same name
+    ///
+    /// # Arguments
+    /// * `name` - The name the scene is applied by
+    /// * `frame` - A frame, or `Color` vector, of equal length to the number of leds available on the device.
+    pub fn save_scene<F: Into<LedFrame>>(&self, name: &str, frame: F) {
+        self.scenes.lock().unwrap().insert(name.to_string(), frame.into());
+    }
+
+    /// Stores the current colors of the device under `name`, replacing any scene with the same name
+    pub fn capture_scene(&self, name: &str) -> Result<(), FeatureError> {
+        let colors = self.current_colors()?;
+        self.save_scene(name, colors);
+
+        Ok(())
+    }
+
+    /// Returns the frame stored under `name`
+    pub fn scene(&self, name: &str) -> Option<LedFrame> {
+        self.scenes.lock().unwrap().get(name).cloned()
+    }
+
+    /// Removes the scene stored under `name`, returning its frame
+    pub fn remove_scene(&self, name: &str) -> Option<LedFrame> {
+        self.scenes.lock().unwrap().remove(name)
+    }
+
+    /// Moves from the current colors to the colors of a stored scene
+    ///
+    /// Only leds that differ between the current colors and the scene are faded, and fades are computed in linear
+    /// light so the overall brightness does not dip halfway through.
+    ///
+    /// # Arguments
+    /// * `name` - The name the scene was stored under
+    /// * `transition` - How the device moves to the scene
+    ///
+    /// # Example
+    /// Stores a red and a blue scene and fades between them
+    /// ```
+    /// use blinkstick_rs::{BlinkStick, Color, Transition};
+    /// use std::time::Duration;
+    ///
+    /// let blinkstick = BlinkStick::new().unwrap();
+    /// blinkstick.save_scene("red", vec![Color {r: 50, g: 0, b: 0}; blinkstick.max_leds as usize]);
+    /// blinkstick.save_scene("blue", vec![Color {r: 0, g: 0, b: 50}; blinkstick.max_leds as usize]);
+    ///
+    /// blinkstick.apply_scene("red", Transition::Cut).unwrap();
+    /// blinkstick.apply_scene("blue", Transition::Fade(Duration::from_secs(2))).unwrap();
+    /// ```
+    pub fn apply_scene(&self, name: &str, transition: Transition) -> Result<(), FeatureError> {
+        let target_colors = self
+            .scene(name)
+            .ok_or(FeatureError {
+                kind: FeatureErrorType::UnknownScene(name.to_string()),
+            })?
+            .render();
+        let current_colors = self.current_colors()?;
+
+        let mut target_frame = current_colors.clone();
+        for (led, color) in target_colors.iter().enumerate().take(target_frame.len()) {
+            target_frame[led] = *color;
+        }
+
+        self.play_transition(&current_colors, &target_frame, transition)
+    }
+
+    /// Shows the frames of a transition from `from` to `to`, one every 20 milliseconds
+    pub(crate) fn play_transition(
+        &self,
+        from: &[Color],
+        to: &[Color],
+        transition: Transition,
+    ) -> Result<(), FeatureError> {
+        for frame in transition.frames(from, to) {
+            let start = Instant::now();
+            self.set_all_leds_colors(&frame)?;
+            std::thread::sleep(TRANSITION_FRAME_INTERVAL.saturating_sub(start.elapsed()));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FROM: [Color; 4] = [Color::RED; 4];
+    const TO: [Color; 4] = [Color::BLUE; 4];
+
+    #[test]
+    fn every_transition_ends_at_the_target() {
+        let duration = Duration::from_millis(100);
+        for transition in [
+            Transition::Cut,
+            Transition::Fade(duration),
+            Transition::Wipe(duration, WipeDirection::CenterOut),
+            Transition::Dissolve(duration),
+            Transition::PushLeft(duration),
+            Transition::PushRight(duration),
+        ]
+        .iter()
+        {
+            let frames: Vec<Vec<Color>> = transition.frames(&FROM, &TO).collect();
+            assert_eq!(frames.last().map(Vec::as_slice), Some(&TO[..]), "{:?}", transition);
+        }
+    }
+
+    #[test]
+    fn long_fades_keep_every_step() {
+        let frames = Transition::Fade(Duration::from_millis(20 * 65_537)).frames(&FROM[..1], &TO[..1]);
+
+        assert_eq!(frames.last(), Some(TO[..1].to_vec()));
+        assert_eq!(
+            Transition::Fade(Duration::MAX)
+                .frames(&FROM, &TO)
+                .nth(1)
+                .map(|frame| frame.len()),
+            Some(4)
+        );
+    }
+
+    #[test]
+    fn dissolves_switch_every_led_once() {
+        let frames: Vec<Vec<Color>> = Transition::Dissolve(Duration::from_millis(80))
+            .frames(&FROM, &TO)
+            .collect();
+
+        assert_eq!(frames.len(), 4);
+        for (step, frame) in frames.iter().enumerate() {
+            assert_eq!(frame.iter().filter(|color| **color == Color::BLUE).count(), step + 1);
+        }
+    }
+
+    #[test]
+    fn pushes_slide_the_target_in_from_the_end() {
+        let mut frames = Transition::PushLeft(Duration::from_millis(80)).frames(&FROM, &TO);
+
+        assert_eq!(
+            frames.next().unwrap(),
+            vec![Color::RED, Color::RED, Color::RED, Color::BLUE]
+        );
+    }
+}
@@ -0,0 +1,285 @@
+use crate::config::{config_lines, decode_word, encode_word, ConfigError};
+use crate::dimming::{local_time_of_day, parse_time, parse_utc_offset};
+use crate::{AnimationIter, Color, COLOR_OFF};
+use std::fmt::Formatter;
+use std::path::Path;
+use std::time::Duration;
+
+const SECONDS_PER_DAY: u32 = 24 * 60 * 60;
+
+/// The largest UTC offset in minutes, the most the `HH:MM` of the textual form holds
+const MAX_UTC_OFFSET_MINUTES: i32 = 23 * 60 + 59;
+
+/// How often a schedule checks the time while no effect plays, or the effect has finished
+const SCHEDULE_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Effects by time of day, like an ambient scene during work hours and nothing at night
+///
+/// Every entry names an effect and the time range it plays in, every day. Times are in UTC unless a UTC offset is
+/// configured. When ranges overlap the entry added first wins, and outside of every range the leds are off.
+///
+/// A schedule is played by an `Animator` like any other effect, see `frames`. It starts with the entry of the
+/// current time, so a schedule loaded from a file picks up where it left off when the program restarts.
+///
+/// # Example
+/// An ambient effect from 9 to 17 and a dim glow in the evening, in UTC+2
+/// ```
+/// use blinkstick_rs::Schedule;
+///
+/// let schedule = Schedule::new()
+///     .utc_offset(120)
+///     .between((9, 0), (17, 0), "ambient")
+///     .between((17, 0), (23, 0), "evening");
+///
+/// assert_eq!(schedule.entry_at(std::time::Duration::from_secs(12 * 3600)), Some("ambient"));
+/// assert_eq!(schedule.entry_at(std::time::Duration::from_secs(3 * 3600)), None);
+/// ```
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Schedule {
+    entries: Vec<(u32, u32, String)>,
+    utc_offset_minutes: i32,
+}
+
+impl Schedule {
+    /// Creates an empty schedule, which keeps the leds off all day
+    pub fn new() -> Schedule {
+        Schedule::default()
+    }
+
+    /// Plays the effect `effect` every day from `start` until `end`, both given as `(hour, minute)`. A range
+    /// that ends before it starts lasts past midnight, and one that ends when it starts lasts all day.
+    pub fn between(mut self, start: (u8, u8), end: (u8, u8), effect: &str) -> Schedule {
+        let second_of_day = |(hour, minute): (u8, u8)| (hour as u32 % 24) * 3600 + (minute as u32 % 60) * 60;
+
+        self.entries
+            .push((second_of_day(start), second_of_day(end), effect.to_string()));
+        self
+    }
+
+    /// Sets the offset in minutes between UTC and the local time the schedule is written in, at most 23:59 hours
+    /// either way
+    pub fn utc_offset(mut self, minutes: i32) -> Schedule {
+        self.utc_offset_minutes = minutes.clamp(-MAX_UTC_OFFSET_MINUTES, MAX_UTC_OFFSET_MINUTES);
+        self
+    }
+
+    /// Parses a schedule from its textual form
+    ///
+    /// Every line holds either a time range and an effect (`09:00-17:00 ambient`) or a UTC offset
+    /// (`utc_offset +02:00`). Everything after a `#` is ignored. Whitespace, `#` and `%` in effect names are
+    /// percent-encoded, like `22:00-06:00 night%20light`.
+    ///
+    /// # Example
+    /// ```
+    /// use blinkstick_rs::Schedule;
+    ///
+    /// let schedule = Schedule::parse("utc_offset +01:00\n09:00-17:00 ambient # work\n").unwrap();
+    ///
+    /// assert_eq!(schedule, Schedule::new().utc_offset(60).between((9, 0), (17, 0), "ambient"));
+    /// ```
+    pub fn parse(contents: &str) -> Result<Schedule, ConfigError> {
+        let mut schedule = Schedule::new();
+
+        for (line, text) in config_lines(contents) {
+            let parse_error = |message: &str| ConfigError::Parse {
+                line,
+                message: message.to_string(),
+            };
+
+            let fields: Vec<&str> = text.split_whitespace().collect();
+            match fields.as_slice() {
+                ["utc_offset", offset] => {
+                    let minutes = parse_utc_offset(offset).ok_or_else(|| parse_error("invalid utc offset"))?;
+                    schedule = schedule.utc_offset(minutes);
+                }
+                [range, effect] => {
+                    let effect = decode_word(effect).ok_or_else(|| parse_error("invalid effect name"))?;
+                    let (start, end) = range
+                        .split_once('-')
+                        .and_then(|(start, end)| Some((parse_time(start)?, parse_time(end)?)))
+                        .ok_or_else(|| parse_error("invalid time range, expected HH:MM-HH:MM"))?;
+                    schedule = schedule.between(start, end, &effect);
+                }
+                _ => return Err(parse_error("expected a time range and an effect")),
+            }
+        }
+
+        Ok(schedule)
+    }
+
+    /// Reads a schedule from a file in the format accepted by `parse`
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Schedule, ConfigError> {
+        Schedule::parse(&std::fs::read_to_string(path)?)
+    }
+
+    /// Writes the schedule to a file in the format accepted by `parse`
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), ConfigError> {
+        Ok(std::fs::write(path, self.to_string())?)
+    }
+
+    /// The effect playing at a time of day, given as the time since midnight in the schedule's local time
+    pub fn entry_at(&self, time_of_day: Duration) -> Option<&str> {
+        let second_of_day = (time_of_day.as_secs() % SECONDS_PER_DAY as u64) as u32;
+
+        self.entries
+            .iter()
+            .find(|(start, end, _)| {
+                if start < end {
+                    (*start..*end).contains(&second_of_day)
+                } else {
+                    second_of_day >= *start || second_of_day < *end
+                }
+            })
+            .map(|(_, _, effect)| effect.as_str())
+    }
+
+    /// The effect playing at this moment
+    pub fn entry_now(&self) -> Option<&str> {
+        self.entry_at(local_time_of_day(self.utc_offset_minutes))
+    }
+
+    /// The frames of the schedule for `leds` leds, which never end, for an `Animator` to play
+    ///
+    /// Whenever another entry starts, `effects` is asked for the frames of its effect. The last frame stays visible
+    /// when an effect ends before its entry does, and the leds are turned off outside of every entry or when
+    /// `effects` doesn't know an effect. The schedule switches between frames, so effects with long frames switch
+    /// late.
+    ///
+    /// # Example
+    /// Plays a schedule stored in a file on the animator thread
+    /// ```no_run
+    /// use blinkstick_rs::{AnimationIter, Animator, BlinkStick, Color, Schedule};
+    /// use std::time::Duration;
+    ///
+    /// let blinkstick = BlinkStick::new().unwrap();
+    /// let leds = blinkstick.max_leds as usize;
+    /// let animator = Animator::new(blinkstick);
+    ///
+    /// let schedule = Schedule::load("schedule.txt").unwrap();
+    /// animator.play("schedule", schedule.frames(leds, move |effect| match effect {
+    ///     "ambient" => Some(AnimationIter::heartbeat(Color {r: 0, g: 0, b: 30}, leds, 20)),
+    ///     "evening" => Some(AnimationIter::new(vec![(vec![Color {r: 20, g: 5, b: 0}; leds], Duration::ZERO)])),
+    ///     _ => None,
+    /// }));
+    /// ```
+    pub fn frames<F>(self, leds: usize, effects: F) -> ScheduleFrames<F>
+    where
+        F: FnMut(&str) -> Option<AnimationIter>,
+    {
+        ScheduleFrames {
+            schedule: self,
+            leds,
+            effects,
+            active: None,
+            playing: None,
+            shown: vec![COLOR_OFF; leds],
+        }
+    }
+}
+
+impl std::fmt::Display for Schedule {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let offset = self.utc_offset_minutes.unsigned_abs();
+        let sign = if self.utc_offset_minutes < 0 { '-' } else { '+' };
+        writeln!(f, "utc_offset {}{:02}:{:02}", sign, offset / 60, offset % 60)?;
+
+        for (start, end, effect) in self.entries.iter() {
+            writeln!(
+                f,
+                "{:02}:{:02}-{:02}:{:02} {}",
+                start / 3600,
+                start % 3600 / 60,
+                end / 3600,
+                end % 3600 / 60,
+                encode_word(effect)
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The never ending frames of a `Schedule`, see `Schedule::frames`
+pub struct ScheduleFrames<F> {
+    schedule: Schedule,
+    leds: usize,
+    effects: F,
+    active: Option<String>,
+    playing: Option<AnimationIter>,
+    shown: Vec<Color>,
+}
+
+impl<F> Iterator for ScheduleFrames<F>
+where
+    F: FnMut(&str) -> Option<AnimationIter>,
+{
+    type Item = (Vec<Color>, Duration);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let entry = self.schedule.entry_now().map(str::to_string);
+        if entry != self.active {
+            self.playing = entry.as_deref().and_then(&mut self.effects);
+            if self.playing.is_none() {
+                self.shown = vec![COLOR_OFF; self.leds];
+            }
+            self.active = entry;
+        }
+
+        match self.playing.as_mut().and_then(Iterator::next) {
+            Some((frame, delay)) => {
+                self.shown = frame.clone();
+                Some((frame, delay))
+            }
+            None => {
+                self.playing = None;
+                Some((self.shown.clone(), SCHEDULE_POLL_INTERVAL))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hours(hours: u64) -> Duration {
+        Duration::from_secs(hours * 3600)
+    }
+
+    #[test]
+    fn entries_wrap_around_midnight_and_the_first_wins() {
+        let schedule = Schedule::new()
+            .between((22, 0), (6, 0), "night")
+            .between((5, 0), (9, 0), "morning");
+
+        assert_eq!(schedule.entry_at(hours(23)), Some("night"));
+        assert_eq!(schedule.entry_at(hours(5)), Some("night"));
+        assert_eq!(schedule.entry_at(hours(7)), Some("morning"));
+        assert_eq!(schedule.entry_at(hours(12)), None);
+    }
+
+    #[test]
+    fn schedules_round_trip_through_text() {
+        let schedule = Schedule::new()
+            .utc_offset(-330)
+            .between((9, 30), (17, 0), "ambient")
+            .between((22, 0), (6, 0), "night");
+
+        assert_eq!(Schedule::parse(&schedule.to_string()).unwrap(), schedule);
+        assert!(Schedule::parse("09:00 ambient\n").is_err());
+
+        let schedule = Schedule::new()
+            .utc_offset(i32::MIN)
+            .between((22, 0), (6, 0), "night light #2");
+        assert_eq!(Schedule::parse(&schedule.to_string()).unwrap(), schedule);
+        assert!(schedule.to_string().starts_with("utc_offset -23:59\n"));
+    }
+
+    #[test]
+    fn unknown_effects_are_off() {
+        let always = Schedule::new().between((0, 0), (0, 0), "unknown");
+        let mut frames = always.frames(2, |_| None);
+
+        assert_eq!(frames.next(), Some((vec![COLOR_OFF; 2], SCHEDULE_POLL_INTERVAL)));
+    }
+}
@@ -0,0 +1,232 @@
+use crate::render::ColorSpace;
+use crate::{Color, Easing};
+#[cfg(feature = "rand")]
+use rand::rngs::ThreadRng;
+#[cfg(feature = "rand")]
+use rand::Rng;
+use std::time::Duration;
+
+/// Repeats a palette of colors forever
+///
+/// # Example
+/// Alternates between red and blue
+/// ```
+/// use blinkstick_rs::{sequence, Color};
+///
+/// let red = Color {r: 50, g: 0, b: 0};
+/// let blue = Color {r: 0, g: 0, b: 50};
+/// let colors: Vec<Color> = sequence::cycle(&[red, blue]).take(3).collect();
+///
+/// assert_eq!(colors, vec![red, blue, red]);
+/// ```
+pub fn cycle<P: Into<Vec<Color>>>(palette: P) -> std::iter::Cycle<std::vec::IntoIter<Color>> {
+    palette.into().into_iter().cycle()
+}
+
+/// Interpolates from `start_color` to `target_color` in `steps` colors, ending at `target_color`
+pub fn gradient(start_color: Color, target_color: Color, steps: u16) -> std::vec::IntoIter<Color> {
+    start_color
+        .gradient_to(target_color, steps, Easing::Linear, ColorSpace::Srgb)
+        .into_iter()
+}
+
+/// Wanders around the color wheel forever, keeping the saturation and value of `start_color`
+///
+/// # Arguments
+/// * `start_color` - The color the walk starts from
+/// * `max_hue_step` - The largest change in hue (in degrees) between two consecutive colors, at most 180
+///
+/// # Panics
+/// The call to `hsv_random_walk` will panic if `max_hue_step` is NaN or infinite.
+#[cfg(feature = "rand")]
+pub fn hsv_random_walk(start_color: Color, max_hue_step: f32) -> HsvRandomWalk {
+    hsv_random_walk_with(start_color, max_hue_step, rand::thread_rng())
+}
+
+/// Like `hsv_random_walk`, but draws the hue steps from `rng`, so a seeded generator repeats the same walk
+///
+/// # Panics
+/// The call to `hsv_random_walk_with` will panic if `max_hue_step` is NaN or infinite.
+///
+/// # Example
+/// Two walks from the same seed yield the same colors
+/// ```
+/// extern crate rand;
+///
+/// use blinkstick_rs::{sequence, Color};
+/// use rand::rngs::StdRng;
+/// use rand::SeedableRng;
+///
+/// let start = Color {r: 50, g: 0, b: 0};
+/// let first: Vec<Color> = sequence::hsv_random_walk_with(start, 20.0, StdRng::seed_from_u64(7)).take(10).collect();
+/// let second: Vec<Color> = sequence::hsv_random_walk_with(start, 20.0, StdRng::seed_from_u64(7)).take(10).collect();
+///
+/// assert_eq!(first, second);
+/// ```
+#[cfg(feature = "rand")]
+pub fn hsv_random_walk_with<R: Rng>(start_color: Color, max_hue_step: f32, rng: R) -> HsvRandomWalk<R> {
+    assert!(
+        max_hue_step.is_finite(),
+        "A random walk steps by a finite hue, not {}",
+        max_hue_step
+    );
+
+    let (hue, saturation, value) = start_color.to_hsv();
+    HsvRandomWalk {
+        hue,
+        saturation,
+        value,
+        // A step of half the color wheel reaches every hue already
+        max_hue_step: max_hue_step.abs().min(180.0),
+        rng,
+    }
+}
+
+/// An infinite color sequence created by `hsv_random_walk`
+#[cfg(feature = "rand")]
+#[derive(Debug, Clone)]
+pub struct HsvRandomWalk<R = ThreadRng> {
+    hue: f32,
+    saturation: f32,
+    value: f32,
+    max_hue_step: f32,
+    rng: R,
+}
+
+#[cfg(feature = "rand")]
+impl<R: Rng> Iterator for HsvRandomWalk<R> {
+    type Item = Color;
+
+    fn next(&mut self) -> Option<Color> {
+        let color = Color::from_hsv(self.hue, self.saturation, self.value);
+        if self.max_hue_step > 0.0 {
+            let step = self.rng.gen_range(-self.max_hue_step..=self.max_hue_step);
+            self.hue = (self.hue + step).rem_euclid(360.0);
+        }
+
+        Some(color)
+    }
+}
+
+/// Adapters available on every iterator of colors
+pub trait ColorSequence: Iterator<Item = Color> + Sized {
+    /// Crossfades between consecutive colors, yielding `steps` colors for every color of the sequence
+    ///
+    /// # Example
+    /// Smoothly cycles between red and blue
+    /// ```
+    /// use blinkstick_rs::sequence::{self, ColorSequence};
+    /// use blinkstick_rs::Color;
+    ///
+    /// let colors: Vec<Color> = sequence::cycle(&[Color {r: 50, g: 0, b: 0}, Color {r: 0, g: 0, b: 50}]).smooth(10).take(40).collect();
+    /// ```
+    fn smooth(self, steps: u16) -> Smooth<Self> {
+        Smooth {
+            colors: self,
+            previous: None,
+            fade: Vec::new().into_iter(),
+            steps,
+        }
+    }
+
+    /// Turns every color into a frame for `led_count` leds shown for `interval`, ready for `Animator::play`
+    fn frames(self, led_count: usize, interval: Duration) -> Frames<Self> {
+        Frames {
+            colors: self,
+            led_count,
+            interval,
+        }
+    }
+}
+
+impl<I: Iterator<Item = Color>> ColorSequence for I {}
+
+/// A color sequence created by `ColorSequence::smooth`
+#[derive(Debug, Clone)]
+pub struct Smooth<I> {
+    colors: I,
+    previous: Option<Color>,
+    fade: std::vec::IntoIter<Color>,
+    steps: u16,
+}
+
+impl<I: Iterator<Item = Color>> Iterator for Smooth<I> {
+    type Item = Color;
+
+    fn next(&mut self) -> Option<Color> {
+        if let Some(color) = self.fade.next() {
+            return Some(color);
+        }
+
+        let target_color = self.colors.next()?;
+        let start_color = self.previous.replace(target_color).unwrap_or(target_color);
+        self.fade = start_color
+            .gradient_to(target_color, self.steps.max(1), Easing::Linear, ColorSpace::Srgb)
+            .into_iter();
+        self.fade.next()
+    }
+}
+
+/// A frame sequence created by `ColorSequence::frames`
+#[derive(Debug, Clone)]
+pub struct Frames<I> {
+    colors: I,
+    led_count: usize,
+    interval: Duration,
+}
+
+impl<I: Iterator<Item = Color>> Iterator for Frames<I> {
+    type Item = (Vec<Color>, Duration);
+
+    fn next(&mut self) -> Option<(Vec<Color>, Duration)> {
+        self.colors
+            .next()
+            .map(|color| (vec![color; self.led_count], self.interval))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn smooth_fades_between_colors() {
+        let red = Color { r: 100, g: 0, b: 0 };
+        let blue = Color { r: 0, g: 0, b: 100 };
+
+        let colors: Vec<Color> = vec![red, blue].into_iter().smooth(2).collect();
+
+        assert_eq!(colors, vec![red, red, Color { r: 50, g: 0, b: 50 }, blue]);
+    }
+
+    #[test]
+    #[cfg(feature = "rand")]
+    fn random_walk_keeps_saturation_and_value() {
+        let start = Color { r: 200, g: 0, b: 0 };
+
+        for color in hsv_random_walk(start, 30.0).take(50) {
+            let (_, saturation, value) = color.to_hsv();
+            assert!((saturation - 1.0).abs() < 0.01);
+            assert!((value - 200.0 / 255.0).abs() < 0.01);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "rand")]
+    fn random_walks_take_huge_steps_around_the_wheel() {
+        let start = Color { r: 200, g: 0, b: 0 };
+
+        assert_eq!(hsv_random_walk(start, -1e30).take(50).count(), 50);
+        assert_eq!(
+            hsv_random_walk(start, 0.0).take(3).collect::<Vec<Color>>(),
+            vec![start; 3]
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    #[cfg(feature = "rand")]
+    fn random_walks_refuse_endless_steps() {
+        hsv_random_walk(Color { r: 200, g: 0, b: 0 }, f32::INFINITY);
+    }
+}
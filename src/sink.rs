@@ -0,0 +1,81 @@
+use crate::{Color, FeatureError, LedDevice};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+type Recording = Arc<Mutex<Vec<(Vec<Color>, Duration)>>>;
+
+/// A destination for the frames an `Animator` renders, next to or instead of a BlinkStick
+///
+/// Sinks receive every frame together with the time it stays visible, so visualizers and tests see exactly what
+/// an effect produced without depending on when it was shown.
+pub trait FrameSink: Send {
+    /// Receives a frame, with one color per led, and the time it stays visible
+    fn send_frame(&mut self, frame: &[Color], duration: Duration) -> Result<(), FeatureError>;
+
+    /// Recovers a destination that stopped answering, called by the `Animator` when a frame failed with an error
+    /// that `FeatureError::is_wedged`. Does nothing unless overridden.
+    fn recover(&mut self) -> Result<(), FeatureError> {
+        Ok(())
+    }
+}
+
+impl<D: LedDevice + ?Sized> FrameSink for Arc<D> {
+    fn send_frame(&mut self, frame: &[Color], _duration: Duration) -> Result<(), FeatureError> {
+        self.set_all_leds_colors(frame)
+    }
+
+    fn recover(&mut self) -> Result<(), FeatureError> {
+        self.reset()
+    }
+}
+
+/// A `FrameSink` that keeps every frame it receives, for assertions on the output of an effect. It records the
+/// frames written to a BlinkStick as well when registered with `BlinkStick::observe`.
+///
+/// Clones share the recorded frames, so one clone can be handed to the `Animator` while another is inspected.
+///
+/// # Example
+#[cfg_attr(feature = "animator", doc = "```")]
+#[cfg_attr(not(feature = "animator"), doc = "```ignore")]
+/// use blinkstick_rs::{Animator, Color, FrameRecorder};
+/// use std::time::Duration;
+///
+/// let animator = Animator::headless();
+/// let recorder = FrameRecorder::new();
+/// animator.tee(recorder.clone());
+///
+/// let frame = vec![Color {r: 1, g: 2, b: 3}; 8];
+/// animator.play("once", vec![(frame.clone(), Duration::from_millis(1))]).wait();
+///
+/// assert_eq!(recorder.frames(), vec![(frame, Duration::from_millis(1))]);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct FrameRecorder {
+    pub(crate) frames: Recording,
+    /// When the last frame observed on a BlinkStick was written, see `StateObserver`
+    pub(crate) observed: Arc<Mutex<Option<Instant>>>,
+}
+
+impl FrameRecorder {
+    /// Creates a recorder that has not received any frames
+    pub fn new() -> FrameRecorder {
+        FrameRecorder::default()
+    }
+
+    /// Every frame received so far with the time it stays visible, oldest first
+    pub fn frames(&self) -> Vec<(Vec<Color>, Duration)> {
+        self.frames.lock().unwrap().clone()
+    }
+
+    /// Forgets every frame received so far
+    pub fn clear(&self) {
+        self.frames.lock().unwrap().clear();
+    }
+}
+
+impl FrameSink for FrameRecorder {
+    fn send_frame(&mut self, frame: &[Color], duration: Duration) -> Result<(), FeatureError> {
+        self.frames.lock().unwrap().push((frame.to_vec(), duration));
+        Ok(())
+    }
+}
@@ -0,0 +1,146 @@
+use crate::{Color, FeatureError, FrameSink, LedDevice};
+use std::time::{Duration, Instant};
+
+/// A low-pass filter for streamed frames, so jittery inputs like audio levels or sensor readings produce stable
+/// light instead of flicker
+///
+/// Every channel of every led follows its input by an exponential moving average: after one `time_constant` a
+/// channel has covered about two thirds of a jump, after three about 95%. The first frame passes unchanged.
+///
+/// # Example
+/// Smooths a noisy sensor before it reaches the device
+/// ```
+/// use blinkstick_rs::{BlinkStick, Color, Smoother};
+/// use std::time::Duration;
+///
+/// let blinkstick = BlinkStick::new().unwrap();
+/// let mut smoother = Smoother::new(Duration::from_millis(300));
+///
+/// for level in [10u8, 200, 30, 180, 40] {
+///     let frame = vec![Color {r: level, g: 0, b: 0}; blinkstick.max_leds as usize];
+///     smoother.write(&blinkstick, &frame).unwrap();
+///     std::thread::sleep(Duration::from_millis(50));
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Smoother {
+    time_constant: Duration,
+    state: Vec<[f32; 3]>,
+    last_write: Option<Instant>,
+}
+
+impl Smoother {
+    /// Creates a filter that follows its input with the given time constant
+    pub fn new(time_constant: Duration) -> Smoother {
+        Smoother {
+            time_constant,
+            state: Vec::new(),
+            last_write: None,
+        }
+    }
+
+    /// Moves the smoothed frame towards `frame` by the time `dt` that passed since the last frame, returning it.
+    /// A frame of another length than the last starts over from `frame`.
+    pub fn apply(&mut self, frame: &[Color], dt: Duration) -> Vec<Color> {
+        let channels = |color: &Color| [color.r as f32, color.g as f32, color.b as f32];
+
+        if self.state.len() != frame.len() {
+            self.state = frame.iter().map(channels).collect();
+        } else {
+            let time_constant = self.time_constant.as_secs_f32();
+            let alpha = if time_constant > 0.0 {
+                1.0 - (-dt.as_secs_f32() / time_constant).exp()
+            } else {
+                1.0
+            };
+
+            for (state, color) in self.state.iter_mut().zip(frame.iter()) {
+                for (channel, target) in state.iter_mut().zip(channels(color).iter()) {
+                    *channel += (target - *channel) * alpha;
+                }
+            }
+        }
+
+        self.state
+            .iter()
+            .map(|[r, g, b]| Color::new(r.round() as u8, g.round() as u8, b.round() as u8))
+            .collect()
+    }
+
+    /// Writes the smoothed `frame` to a device, timed by the wall clock since the last write
+    pub fn write<D: LedDevice + ?Sized>(&mut self, device: &D, frame: &[Color]) -> Result<(), FeatureError> {
+        let now = Instant::now();
+        let dt = self.last_write.map_or(Duration::ZERO, |last_write| now - last_write);
+        self.last_write = Some(now);
+
+        device.set_all_leds_colors(&self.apply(frame, dt))
+    }
+
+    /// Forgets the smoothed frame, so the next frame passes unchanged
+    pub fn reset(&mut self) {
+        self.state.clear();
+        self.last_write = None;
+    }
+
+    /// Puts the filter in front of a sink, for example to smooth what an `Animator` plays, see `SmoothedSink`
+    pub fn sink<S: FrameSink>(self, sink: S) -> SmoothedSink<S> {
+        SmoothedSink {
+            smoother: self,
+            sink,
+            previous_duration: Duration::ZERO,
+        }
+    }
+}
+
+/// A `FrameSink` that smooths every frame before handing it on, created by `Smoother::sink`
+///
+/// The frames are timed by the durations they arrive with, so the result doesn't depend on when they were sent.
+pub struct SmoothedSink<S> {
+    smoother: Smoother,
+    sink: S,
+    previous_duration: Duration,
+}
+
+impl<S: FrameSink> FrameSink for SmoothedSink<S> {
+    fn send_frame(&mut self, frame: &[Color], duration: Duration) -> Result<(), FeatureError> {
+        let smoothed = self.smoother.apply(frame, self.previous_duration);
+        self.previous_duration = duration;
+        self.sink.send_frame(&smoothed, duration)
+    }
+
+    fn recover(&mut self) -> Result<(), FeatureError> {
+        self.sink.recover()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FrameRecorder, COLOR_OFF};
+
+    #[test]
+    fn channels_follow_their_input_by_the_time_constant() {
+        let mut smoother = Smoother::new(Duration::from_secs(1));
+
+        assert_eq!(smoother.apply(&[COLOR_OFF], Duration::ZERO), vec![COLOR_OFF]);
+        assert_eq!(
+            smoother.apply(&[Color::new(200, 0, 0)], Duration::from_secs(1)),
+            vec![Color::new(126, 0, 0)]
+        );
+        assert_eq!(
+            smoother.apply(&[Color::new(200, 0, 0)], Duration::from_secs(60)),
+            vec![Color::new(200, 0, 0)]
+        );
+    }
+
+    #[test]
+    fn sinks_are_timed_by_frame_durations() {
+        let recorder = FrameRecorder::new();
+        let mut sink = Smoother::new(Duration::ZERO).sink(recorder.clone());
+
+        sink.send_frame(&[Color::RED], Duration::from_millis(10)).unwrap();
+        sink.send_frame(&[Color::BLUE], Duration::from_millis(10)).unwrap();
+
+        assert_eq!(recorder.frames()[1].0, vec![Color::BLUE]);
+    }
+}
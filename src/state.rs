@@ -0,0 +1,47 @@
+use crate::{BlinkStick, FeatureError, LedFrame, Mode, Variant};
+use std::time::SystemTime;
+
+/// Everything known about a device at a single moment, see `BlinkStick::device_state`
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeviceState {
+    /// The colors the leds show, at full brightness
+    pub frame: LedFrame,
+    /// The mode of the device, `None` for variants without modes or modes this crate doesn't know
+    pub mode: Option<Mode>,
+    /// The kind of device
+    pub variant: Variant,
+    /// When the colors were read
+    pub timestamp: SystemTime,
+}
+
+impl BlinkStick {
+    /// Reads the colors and the mode of the device in one go, together with its variant
+    ///
+    /// Only the BlinkStick Pro has modes, so other variants read nothing but their leds report.
+    ///
+    /// # Example
+    /// Logs what a device shows
+    /// ```
+    /// use blinkstick_rs::BlinkStick;
+    ///
+    /// let blinkstick = BlinkStick::new().unwrap();
+    /// let state = blinkstick.device_state().unwrap();
+    ///
+    /// println!("{:?} in {:?} mode shows {}", state.variant, state.mode, state.frame);
+    /// ```
+    pub fn device_state(&self) -> Result<DeviceState, FeatureError> {
+        let frame = LedFrame::from(self.get_all_led_colors()?);
+        let timestamp = SystemTime::now();
+        let mode = match self.variant {
+            Variant::Pro | Variant::Unknown => self.mode()?,
+            _ => None,
+        };
+
+        Ok(DeviceState {
+            frame,
+            mode,
+            variant: self.variant,
+            timestamp,
+        })
+    }
+}
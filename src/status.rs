@@ -0,0 +1,219 @@
+use crate::event::repeat_for;
+use crate::palette::StatusColors;
+use crate::{AnimationHandle, AnimationIter, Animator, Color, COLOR_OFF};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// The number of frames of one run of a `StatusStyle::Pulse`
+const PULSE_STEPS: u16 = 25;
+
+/// The shortest period of a `StatusStyle::Blink` or `StatusStyle::Pulse`, shorter ones would keep the animator
+/// busy with frames nobody can see
+const MIN_PERIOD: Duration = Duration::from_millis(1);
+
+/// What a `StatusLight` shows
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Status {
+    /// Everything is fine
+    Ok,
+    /// Something needs attention soon
+    Warning,
+    /// Something failed
+    Error,
+    /// Something is in progress
+    Busy,
+    /// Nothing to show, the leds are turned off
+    Off,
+}
+
+/// How a `Status` is shown on every led
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatusStyle {
+    /// A steady color
+    Solid(Color),
+    /// Switches between the color and off, once per period of at least a millisecond
+    Blink(Color, Duration),
+    /// Fades from off to the color and back, once per period of at least a millisecond
+    Pulse(Color, Duration),
+}
+
+impl StatusStyle {
+    /// The color the style shows at its brightest
+    pub fn color(self) -> Color {
+        match self {
+            StatusStyle::Solid(color) | StatusStyle::Blink(color, _) | StatusStyle::Pulse(color, _) => color,
+        }
+    }
+
+    /// One run of the style on `led_count` leds, `None` for a steady color that doesn't repeat
+    fn run(self, led_count: usize) -> Option<Vec<(Vec<Color>, Duration)>> {
+        let off = vec![COLOR_OFF; led_count];
+        match self {
+            StatusStyle::Solid(_) => None,
+            StatusStyle::Blink(color, period) => {
+                let on_time = period / 2;
+                Some(vec![(vec![color; led_count], on_time), (off, period - on_time)])
+            }
+            StatusStyle::Pulse(color, period) => {
+                Some(AnimationIter::pulse(off, vec![color; led_count], period, PULSE_STEPS).collect())
+            }
+        }
+    }
+}
+
+/// Shows the state of something on an `Animator` in a single call, for the common case of keeping a light red
+/// until further notice
+///
+/// Every status has a style, which can be replaced with `set_style`. By default Ok, Warning and Error are steady
+/// `StatusColors::STANDARD` colors and Busy pulses blue. A status stays until the next one is set, or until it
+/// expires when set with `set_status_for`.
+///
+/// # Example
+/// Shows a failed build until the next build, and a deploy for at most a minute
+/// ```
+/// use blinkstick_rs::{Animator, BlinkStick, Status, StatusLight};
+/// use std::time::Duration;
+///
+/// let blinkstick = BlinkStick::new().unwrap();
+/// let leds = blinkstick.max_leds as usize;
+/// let light = StatusLight::new(Animator::new(blinkstick), leds);
+///
+/// light.set_status(Status::Error);
+/// light.set_status_for(Status::Busy, Duration::from_secs(60));
+/// ```
+pub struct StatusLight {
+    animator: Animator,
+    led_count: usize,
+    styles: Mutex<HashMap<Status, StatusStyle>>,
+}
+
+impl StatusLight {
+    /// Creates a light showing statuses on the `led_count` leds driven by `animator`
+    pub fn new(animator: Animator, led_count: usize) -> StatusLight {
+        let light = StatusLight {
+            animator,
+            led_count,
+            styles: Mutex::new(HashMap::new()),
+        };
+        light.set_colors(StatusColors::STANDARD);
+        light.set_style(
+            Status::Busy,
+            StatusStyle::Pulse(Color { r: 0, g: 0, b: 160 }, Duration::from_secs(2)),
+        );
+
+        light
+    }
+
+    /// The animator the statuses are shown on
+    pub fn animator(&self) -> &Animator {
+        &self.animator
+    }
+
+    /// Replaces the style `status` is shown in. `Status::Off` always turns the leds off.
+    ///
+    /// # Panics
+    /// The call to `set_style` will panic if `style` blinks or pulses with a period shorter than a millisecond.
+    pub fn set_style(&self, status: Status, style: StatusStyle) {
+        if let StatusStyle::Blink(_, period) | StatusStyle::Pulse(_, period) = style {
+            assert!(
+                period >= MIN_PERIOD,
+                "A status blinks or pulses with a period of at least {:?}",
+                MIN_PERIOD
+            );
+        }
+        self.styles.lock().unwrap().insert(status, style);
+    }
+
+    /// Shows Ok, Warning and Error in steady colors from `colors`, for example `StatusColors::for_vision`
+    pub fn set_colors(&self, colors: StatusColors) {
+        self.set_style(Status::Ok, StatusStyle::Solid(colors.ok));
+        self.set_style(Status::Warning, StatusStyle::Solid(colors.warn));
+        self.set_style(Status::Error, StatusStyle::Solid(colors.error));
+    }
+
+    /// The style `status` is shown in
+    pub fn style(&self, status: Status) -> StatusStyle {
+        match status {
+            Status::Off => StatusStyle::Solid(COLOR_OFF),
+            status => self.styles.lock().unwrap()[&status],
+        }
+    }
+
+    /// Shows `status` until another status is set, replacing whatever the animator plays
+    pub fn set_status(&self, status: Status) -> AnimationHandle {
+        let style = self.style(status);
+        match style.run(self.led_count) {
+            Some(run) => self.animator.play(&status_name(status), run.into_iter().cycle()),
+            None => self.animator.play(
+                &status_name(status),
+                vec![(vec![style.color(); self.led_count], Duration::ZERO)],
+            ),
+        }
+    }
+
+    /// Shows `status` for `duration` and turns the leds off after, unless another status is set before
+    pub fn set_status_for(&self, status: Status, duration: Duration) -> AnimationHandle {
+        let style = self.style(status);
+        let frames = match style.run(self.led_count) {
+            Some(run) => AnimationIter::new(repeat_for(run, duration)),
+            None => AnimationIter::new(vec![(vec![style.color(); self.led_count], duration)]),
+        };
+        let off = (vec![COLOR_OFF; self.led_count], Duration::ZERO);
+
+        self.animator.play(&status_name(status), frames.chain(Some(off)))
+    }
+}
+
+/// The name a status is played under, reported in `AnimationEvent::EffectStarted`
+fn status_name(status: Status) -> String {
+    format!("status {:?}", status).to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FrameRecorder, ManualClock};
+    use std::sync::Arc;
+
+    #[test]
+    fn statuses_set_for_a_while_turn_off_after() {
+        let animator = Animator::headless_with_clock(Arc::new(ManualClock::new()));
+        let recorder = FrameRecorder::new();
+        animator.tee(recorder.clone());
+        let light = StatusLight::new(animator, 2);
+
+        light.set_status_for(Status::Error, Duration::from_secs(5)).wait();
+
+        assert_eq!(
+            recorder.frames(),
+            vec![
+                (vec![StatusColors::STANDARD.error; 2], Duration::from_secs(5)),
+                (vec![COLOR_OFF; 2], Duration::ZERO)
+            ]
+        );
+    }
+
+    #[test]
+    fn blinks_light_every_led_for_half_the_period() {
+        let run = StatusStyle::Blink(Color::RED, Duration::from_millis(3))
+            .run(300)
+            .unwrap();
+
+        assert_eq!(
+            run,
+            vec![
+                (vec![Color::RED; 300], Duration::from_micros(1500)),
+                (vec![COLOR_OFF; 300], Duration::from_micros(1500))
+            ]
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn styles_without_a_period_are_refused() {
+        let light = StatusLight::new(Animator::headless_with_clock(Arc::new(ManualClock::new())), 2);
+
+        light.set_style(Status::Busy, StatusStyle::Pulse(Color::BLUE, Duration::ZERO));
+    }
+}
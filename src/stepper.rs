@@ -0,0 +1,150 @@
+use crate::{AnimationIter, BlinkStick, Color, FeatureError};
+use std::time::Duration;
+
+/// The most frames a single tick skips, so an effect of frames that take no time cannot stall a game loop
+const MAX_FRAMES_PER_TICK: usize = 1024;
+
+/// Advances an effect by the time a game loop passes in, instead of sleeping between frames on a thread
+///
+/// The first tick shows the first frame, every later tick moves on by the frames whose time has run out. Once the
+/// effect has ended its last frame stays visible.
+///
+/// # Example
+/// Advances a blink inside a fixed timestep loop of 60 ticks a second
+/// ```
+/// use blinkstick_rs::{AnimationIter, Color, EffectStepper};
+/// use std::time::Duration;
+///
+/// let off = vec![Color {r: 0, g: 0, b: 0}; 8];
+/// let blink = AnimationIter::blink(off, &[0], Color {r: 50, g: 0, b: 0}, Duration::from_millis(100), 2);
+/// let mut stepper = EffectStepper::new(blink);
+///
+/// let tick = Duration::from_secs(1) / 60;
+/// while !stepper.is_finished() {
+///     if let Some(frame) = stepper.tick(tick) {
+///         // show the frame in the game, or on a BlinkStick
+///         assert_eq!(frame.len(), 8);
+///     }
+/// }
+/// ```
+pub struct EffectStepper {
+    frames: AnimationIter,
+    current: Option<(Vec<Color>, Duration)>,
+    elapsed: Duration,
+    started: bool,
+    finished: bool,
+}
+
+impl EffectStepper {
+    /// Prepares the frames of an effect to be advanced by ticks
+    pub fn new(frames: AnimationIter) -> EffectStepper {
+        EffectStepper {
+            frames,
+            current: None,
+            elapsed: Duration::ZERO,
+            started: false,
+            finished: false,
+        }
+    }
+
+    /// Advances the effect by `dt`, returning the frame to show if it changed since the last tick
+    pub fn tick(&mut self, dt: Duration) -> Option<&[Color]> {
+        let mut changed = false;
+        if self.started {
+            self.elapsed += dt;
+        } else {
+            self.started = true;
+            self.current = self.frames.next();
+            changed = self.current.is_some();
+        }
+
+        let mut skipped = 0;
+        while let Some((_, delay)) = &self.current {
+            if self.finished || self.elapsed < *delay || skipped == MAX_FRAMES_PER_TICK {
+                break;
+            }
+
+            self.elapsed -= *delay;
+            match self.frames.next() {
+                Some(frame) => {
+                    self.current = Some(frame);
+                    changed = true;
+                }
+                None => self.finished = true,
+            }
+            skipped += 1;
+        }
+        if self.current.is_none() {
+            self.finished = true;
+        }
+
+        if changed {
+            self.frame()
+        } else {
+            None
+        }
+    }
+
+    /// The frame showing now, `None` before the first tick or for an effect without frames
+    pub fn frame(&self) -> Option<&[Color]> {
+        self.current.as_ref().map(|(frame, _)| frame.as_slice())
+    }
+
+    /// Returns `true` once the last frame of the effect has been shown for its time
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+}
+
+impl BlinkStick {
+    /// Advances an effect by `dt` and writes its frame if it changed, for game engines that step effects inside
+    /// their own loop. Returns whether the effect is still playing.
+    ///
+    /// # Example
+    /// ```
+    /// use blinkstick_rs::{AnimationIter, BlinkStick, Color, EffectStepper};
+    /// use std::time::Duration;
+    ///
+    /// let blinkstick = BlinkStick::new().unwrap();
+    /// let leds = blinkstick.max_leds as usize;
+    /// let mut pulse = EffectStepper::new(AnimationIter::heartbeat(Color {r: 50, g: 0, b: 0}, leds, 3));
+    ///
+    /// while blinkstick.step_effect(&mut pulse, Duration::from_millis(16)).unwrap() {
+    ///     std::thread::sleep(Duration::from_millis(16));
+    /// }
+    /// ```
+    pub fn step_effect(&self, stepper: &mut EffectStepper, dt: Duration) -> Result<bool, FeatureError> {
+        if let Some(frame) = stepper.tick(dt) {
+            self.set_all_leds_colors(frame)?;
+        }
+
+        Ok(!stepper.is_finished())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::COLOR_OFF;
+
+    #[test]
+    fn ticks_move_on_by_the_elapsed_frames() {
+        let ms = Duration::from_millis;
+        let frames = vec![
+            (vec![Color::RED], ms(100)),
+            (vec![Color::GREEN], ms(50)),
+            (vec![Color::BLUE], ms(100)),
+            (vec![COLOR_OFF], Duration::ZERO),
+        ];
+        let mut stepper = EffectStepper::new(AnimationIter::new(frames));
+
+        assert_eq!(stepper.tick(ms(16)), Some(&[Color::RED][..]));
+        assert_eq!(stepper.tick(ms(60)), None);
+        assert_eq!(stepper.tick(ms(60)), Some(&[Color::GREEN][..]));
+        assert_eq!(stepper.tick(ms(40)), Some(&[Color::BLUE][..]));
+        assert!(!stepper.is_finished());
+        assert_eq!(stepper.tick(ms(100)), Some(&[COLOR_OFF][..]));
+        assert!(stepper.is_finished());
+        assert_eq!(stepper.tick(ms(100)), None);
+    }
+}
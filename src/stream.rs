@@ -0,0 +1,191 @@
+//! Device events as a `futures::Stream`, so async applications can `select!` over them next to their other
+//! sources. The stream works with any async runtime, tokio included.
+//!
+//! # Example
+//! Reacts to BlinkSticks being plugged in and to failed writes inside a tokio task
+//! ```ignore
+//! use blinkstick_rs::{BlinkStick, DeviceEvent};
+//! use futures::StreamExt;
+//! use std::time::Duration;
+//!
+//! let blinkstick = BlinkStick::new().unwrap();
+//! let mut events = blinkstick.event_stream().with_hotplug(Duration::from_secs(1));
+//!
+//! tokio::spawn(async move {
+//!     while let Some(event) = events.next().await {
+//!         match event {
+//!             DeviceEvent::Arrived(device) => println!("Plugged in: {:?}", device.serial_number),
+//!             DeviceEvent::Error(kind) => eprintln!("BlinkStick failed: {:?}", kind),
+//!             _ => (),
+//!         }
+//!     }
+//! });
+//! ```
+
+use crate::device_events::spawn_hotplug_watcher;
+use crate::{BlinkStick, DeviceEvent};
+use futures_core::Stream;
+use std::collections::VecDeque;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::time::Duration;
+
+#[derive(Default)]
+struct Queue {
+    events: VecDeque<DeviceEvent>,
+    waker: Option<Waker>,
+    senders: usize,
+    receiver_dropped: bool,
+}
+
+/// Hands events to a `DeviceEventStream`, ending the stream once every sender is dropped
+struct Sender(Arc<Mutex<Queue>>);
+
+impl Sender {
+    fn new(queue: &Arc<Mutex<Queue>>) -> Sender {
+        queue.lock().unwrap().senders += 1;
+        Sender(queue.clone())
+    }
+
+    /// Queues an event, returning `false` once the stream was dropped
+    fn send(&self, event: DeviceEvent) -> bool {
+        let mut queue = self.0.lock().unwrap();
+        if queue.receiver_dropped {
+            return false;
+        }
+
+        queue.events.push_back(event);
+        if let Some(waker) = queue.waker.take() {
+            waker.wake();
+        }
+        true
+    }
+
+    /// Returns `true` once the stream was dropped
+    fn is_closed(&self) -> bool {
+        self.0.lock().unwrap().receiver_dropped
+    }
+}
+
+impl Drop for Sender {
+    fn drop(&mut self) {
+        let mut queue = self.0.lock().unwrap();
+        queue.senders -= 1;
+        if queue.senders == 0 {
+            if let Some(waker) = queue.waker.take() {
+                waker.wake();
+            }
+        }
+    }
+}
+
+/// A stream of `DeviceEvent`s, see `BlinkStick::event_stream`
+///
+/// The stream ends once every source of its events is gone: the BlinkStick was dropped and no hotplug watcher was
+/// added.
+pub struct DeviceEventStream {
+    queue: Arc<Mutex<Queue>>,
+}
+
+impl DeviceEventStream {
+    fn new() -> DeviceEventStream {
+        DeviceEventStream {
+            queue: Arc::new(Mutex::new(Queue::default())),
+        }
+    }
+
+    /// Adds an event whenever a BlinkStick is plugged in or unplugged, listing the connected BlinkSticks every
+    /// `interval`, see `BlinkStick::watch_hotplug`
+    pub fn with_hotplug(self, interval: Duration) -> DeviceEventStream {
+        let sender = Arc::new(Sender::new(&self.queue));
+        let watched = sender.clone();
+        spawn_hotplug_watcher(interval, move || watched.is_closed(), move |event| sender.send(event));
+        self
+    }
+}
+
+impl Stream for DeviceEventStream {
+    type Item = DeviceEvent;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<DeviceEvent>> {
+        let mut queue = self.queue.lock().unwrap();
+        match queue.events.pop_front() {
+            Some(event) => Poll::Ready(Some(event)),
+            None if queue.senders == 0 => Poll::Ready(None),
+            None => {
+                queue.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+impl Drop for DeviceEventStream {
+    fn drop(&mut self) {
+        self.queue.lock().unwrap().receiver_dropped = true;
+    }
+}
+
+impl BlinkStick {
+    /// Returns a stream of every frame written to the device and every transfer that failed, see
+    /// `BlinkStick::subscribe`. Add hotplug events with `DeviceEventStream::with_hotplug`.
+    pub fn event_stream(&self) -> DeviceEventStream {
+        let stream = DeviceEventStream::new();
+        let sender = Sender::new(&stream.queue);
+        self.event_listeners.add(move |event| sender.send(event.clone()));
+        stream
+    }
+
+    /// Returns a stream of an event whenever a BlinkStick is plugged in or unplugged, see
+    /// `BlinkStick::watch_hotplug`
+    pub fn hotplug_stream(interval: Duration) -> DeviceEventStream {
+        DeviceEventStream::new().with_hotplug(interval)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FeatureErrorType;
+    use std::task::Wake;
+
+    struct NoopWaker;
+
+    impl Wake for NoopWaker {
+        fn wake(self: Arc<Self>) {}
+    }
+
+    #[test]
+    fn streams_end_when_every_sender_is_gone() {
+        let mut stream = DeviceEventStream::new();
+        let sender = Sender::new(&stream.queue);
+        let waker = Waker::from(Arc::new(NoopWaker));
+        let mut cx = Context::from_waker(&waker);
+
+        assert_eq!(Pin::new(&mut stream).poll_next(&mut cx), Poll::Pending);
+        assert!(sender.send(DeviceEvent::Error(FeatureErrorType::Timeout)));
+        drop(sender);
+        assert_eq!(
+            Pin::new(&mut stream).poll_next(&mut cx),
+            Poll::Ready(Some(DeviceEvent::Error(FeatureErrorType::Timeout)))
+        );
+        assert_eq!(Pin::new(&mut stream).poll_next(&mut cx), Poll::Ready(None));
+    }
+
+    #[test]
+    fn hotplug_watchers_stop_with_their_stream() {
+        let stream = BlinkStick::hotplug_stream(Duration::from_millis(1));
+        let queue = stream.queue.clone();
+        drop(stream);
+
+        let start = std::time::Instant::now();
+        while queue.lock().unwrap().senders > 0 {
+            assert!(
+                start.elapsed() < Duration::from_secs(5),
+                "the hotplug watcher kept running"
+            );
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    }
+}
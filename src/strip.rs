@@ -0,0 +1,288 @@
+use crate::render::scale_color;
+use crate::{BlinkStick, Color, FeatureError};
+use blinkstick_core::COLOR_OFF;
+use std::ops::Range;
+
+/// The number of leds of a BlinkStick Strip
+const STRIP_LEDS: u8 = 8;
+
+/// The share of a level meter lit green, the rest up to `LEVEL_METER_YELLOW` is yellow and red above
+const LEVEL_METER_GREEN: f32 = 0.6;
+
+/// The share of a level meter lit green or yellow
+const LEVEL_METER_YELLOW: f32 = 0.85;
+
+/// The leds of a BlinkStick Strip as positions from left to right, for effects that think in positions along the
+/// strip rather than in led numbers
+///
+/// Position 0 is led 0, unless the layout is `reversed` for a strip mounted the other way around. Parts of the
+/// strip are handled through `segment`, which renders progress bars and level meters into a frame. Leds masked
+/// with `BlinkStick::mask_leds` are left out of every segment of `BlinkStick::strip_layout`.
+///
+/// # Example
+/// Shows the progress of a build on the left six leds and the state of a deploy on the right two
+/// ```
+/// use blinkstick_rs::{BlinkStick, Color};
+///
+/// let blinkstick = BlinkStick::new().unwrap();
+/// let strip = blinkstick.strip_layout();
+///
+/// let mut frame = vec![Color::default(); strip.len()];
+/// strip.segment(0..6).progress(&mut frame, 0.4, Color {r: 0, g: 0, b: 50});
+/// strip.segment(6..8).fill(&mut frame, Color {r: 0, g: 50, b: 0});
+/// blinkstick.set_all_leds_colors(&frame).unwrap();
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StripLayout {
+    leds: u8,
+    reversed: bool,
+    masked: Vec<u8>,
+}
+
+impl Default for StripLayout {
+    fn default() -> Self {
+        StripLayout {
+            leds: STRIP_LEDS,
+            reversed: false,
+            masked: Vec::new(),
+        }
+    }
+}
+
+impl StripLayout {
+    /// The eight leds of a BlinkStick Strip, led 0 on the left
+    pub fn new() -> StripLayout {
+        StripLayout::default()
+    }
+
+    /// A strip of `leds` leds, like a Flex or a Pro driving a strip
+    pub fn with_leds(leds: u8) -> StripLayout {
+        StripLayout {
+            leds,
+            ..StripLayout::default()
+        }
+    }
+
+    /// Counts the positions from the other end, for a strip mounted the other way around
+    pub fn reversed(mut self) -> StripLayout {
+        self.reversed = !self.reversed;
+        self
+    }
+
+    /// Leaves `leds` out of every segment, so bars and meters skip them and leave their color alone, see
+    /// `BlinkStick::mask_leds`
+    pub fn masked(mut self, leds: &[u8]) -> StripLayout {
+        self.masked.extend_from_slice(leds);
+        self
+    }
+
+    /// The number of positions on the strip
+    pub fn len(&self) -> usize {
+        self.leds as usize
+    }
+
+    /// Returns `true` if the strip has no leds
+    pub fn is_empty(&self) -> bool {
+        self.leds == 0
+    }
+
+    /// The led at a position, counted from the left
+    ///
+    /// # Panics
+    /// The call to `led` will panic if the `position` is not on the strip.
+    pub fn led(&self, position: u8) -> u8 {
+        assert!(
+            position < self.leds,
+            "Position {} is not on a strip of {} leds",
+            position,
+            self.leds
+        );
+
+        if self.reversed {
+            self.leds - 1 - position
+        } else {
+            position
+        }
+    }
+
+    /// Rearranges colors given from left to right into a frame of one color per led. Positions without a color are
+    /// turned off, colors past the end of the strip are dropped.
+    pub fn frame(&self, colors: &[Color]) -> Vec<Color> {
+        let mut frame = colors.to_vec();
+        frame.resize(self.len(), COLOR_OFF);
+        if self.reversed {
+            frame.reverse();
+        }
+
+        frame
+    }
+
+    /// The leds at the positions in `positions`, counted from the left, without the masked ones
+    ///
+    /// # Panics
+    /// The call to `segment` will panic if the range reaches beyond the strip.
+    pub fn segment(&self, positions: Range<u8>) -> StripSegment {
+        StripSegment {
+            leds: positions
+                .map(|position| self.led(position))
+                .filter(|led| !self.masked.contains(led))
+                .collect(),
+        }
+    }
+
+    /// Every position of the strip
+    pub fn all(&self) -> StripSegment {
+        self.segment(0..self.leds)
+    }
+}
+
+/// A part of a strip, its leds ordered from left to right, see `StripLayout::segment`
+///
+/// Segments render into frames of one color per led, which are then written with `set_all_leds_colors`, so
+/// several segments can show different things in a single write. Leds of the segment past the end of a frame are
+/// left out.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StripSegment {
+    leds: Vec<u8>,
+}
+
+impl StripSegment {
+    /// The leds of the segment, from left to right
+    pub fn leds(&self) -> &[u8] {
+        &self.leds
+    }
+
+    /// The number of leds in the segment
+    pub fn len(&self) -> usize {
+        self.leds.len()
+    }
+
+    /// Returns `true` if the segment has no leds
+    pub fn is_empty(&self) -> bool {
+        self.leds.is_empty()
+    }
+
+    /// Sets every led of the segment in `frame` to `color`
+    pub fn fill(&self, frame: &mut [Color], color: Color) {
+        for led in &self.leds {
+            if let Some(slot) = frame.get_mut(*led as usize) {
+                *slot = color;
+            }
+        }
+    }
+
+    /// Lights the segment from the left up to `fraction` (0.0 - 1.0) of its length, dimming the led the bar ends
+    /// on by the share of it that is reached. The rest of the segment is turned off.
+    pub fn progress(&self, frame: &mut [Color], fraction: f32, color: Color) {
+        let lit = fraction.clamp(0.0, 1.0) * self.leds.len() as f32;
+        for (position, led) in self.leds.iter().enumerate() {
+            if let Some(slot) = frame.get_mut(*led as usize) {
+                *slot = scale_color(color, (lit - position as f32).clamp(0.0, 1.0));
+            }
+        }
+    }
+
+    /// Lights the segment from the left up to `level` (0.0 - 1.0) of its length like the meter of a mixing desk:
+    /// green, yellow towards the top and red at the very top. The rest of the segment is turned off.
+    pub fn level_meter(&self, frame: &mut [Color], level: f32) {
+        let lit = (level.clamp(0.0, 1.0) * self.leds.len() as f32).round() as usize;
+        for (position, led) in self.leds.iter().enumerate() {
+            let slot = match frame.get_mut(*led as usize) {
+                Some(slot) => slot,
+                None => continue,
+            };
+            let height = (position as f32 + 1.0) / self.leds.len() as f32;
+            *slot = if position >= lit {
+                COLOR_OFF
+            } else if height <= LEVEL_METER_GREEN {
+                Color::GREEN
+            } else if height <= LEVEL_METER_YELLOW {
+                Color::new(255, 255, 0)
+            } else {
+                Color::RED
+            };
+        }
+    }
+
+    /// Sets every led of the segment on the device to `color`, leaving the others alone
+    pub fn set_color(&self, stick: &BlinkStick, color: Color) -> Result<(), FeatureError> {
+        stick.set_multiple_leds_color(&self.leds, color)
+    }
+}
+
+impl BlinkStick {
+    /// The leds of the device as positions from left to right, leaving out the masked leds, see `StripLayout`
+    pub fn strip_layout(&self) -> StripLayout {
+        StripLayout::with_leds(self.max_leds).masked(&self.masked_leds())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reversed_strips_count_from_the_other_end() {
+        let strip = StripLayout::new().reversed();
+
+        assert_eq!(strip.segment(0..3).leds(), &[7, 6, 5]);
+        let frame = strip.frame(&[Color::RED, Color::GREEN]);
+        assert_eq!(frame.len(), 8);
+        assert_eq!(frame[7], Color::RED);
+        assert_eq!(frame[6], Color::GREEN);
+        assert_eq!(frame[0], COLOR_OFF);
+    }
+
+    #[test]
+    fn masked_leds_are_left_out_of_segments() {
+        let strip = StripLayout::with_leds(4).masked(&[1]);
+        let mut frame = vec![Color::BLUE; 4];
+        strip.all().progress(&mut frame, 0.5, Color::RED);
+
+        assert_eq!(strip.all().leds(), &[0, 2, 3]);
+        assert_eq!(
+            frame,
+            vec![Color::RED, Color::BLUE, scale_color(Color::RED, 0.5), COLOR_OFF]
+        );
+    }
+
+    #[test]
+    fn segments_leave_out_leds_past_the_frame() {
+        let mut frame = vec![COLOR_OFF; 4];
+        let all = StripLayout::new().all();
+        all.fill(&mut frame, Color::RED);
+        all.progress(&mut frame, 1.0, Color::RED);
+        all.level_meter(&mut frame, 1.0);
+
+        assert_eq!(frame, vec![Color::GREEN; 4]);
+    }
+
+    #[test]
+    fn progress_dims_the_led_it_ends_on() {
+        let strip = StripLayout::new();
+        let mut frame = vec![COLOR_OFF; 8];
+        strip.segment(2..6).progress(&mut frame, 0.375, Color::new(200, 0, 0));
+
+        assert_eq!(
+            frame[..6],
+            [
+                COLOR_OFF,
+                COLOR_OFF,
+                Color::new(200, 0, 0),
+                Color::new(100, 0, 0),
+                COLOR_OFF,
+                COLOR_OFF
+            ]
+        );
+    }
+
+    #[test]
+    fn level_meters_turn_red_at_the_top() {
+        let mut frame = vec![COLOR_OFF; 8];
+        StripLayout::new().all().level_meter(&mut frame, 1.0);
+
+        assert_eq!(frame[0], Color::GREEN);
+        assert_eq!(frame[5], Color::new(255, 255, 0));
+        assert_eq!(frame[7], Color::RED);
+    }
+}
@@ -0,0 +1,102 @@
+use crate::{BlinkStick, FeatureError};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// The shortest time between two feature reports sent to a device unless overridden, some firmwares lock up when
+/// reports arrive back to back
+pub const DEFAULT_MIN_REPORT_GAP: Duration = Duration::from_millis(2);
+
+/// The number of sends in a row that have to fail before the device is reopened
+const REOPEN_AFTER_FAILED_SENDS: u32 = 3;
+
+/// Spaces the reports sent to a device and counts the sends that failed in a row
+pub(crate) struct ReportThrottle {
+    min_gap: Mutex<Duration>,
+    last_report: Mutex<Option<Instant>>,
+    failed_sends: AtomicU32,
+}
+
+impl ReportThrottle {
+    pub(crate) fn new(min_gap: Duration) -> ReportThrottle {
+        ReportThrottle {
+            min_gap: Mutex::new(min_gap),
+            last_report: Mutex::new(None),
+            failed_sends: AtomicU32::new(0),
+        }
+    }
+
+    /// Runs `send` once the minimum gap since the previous report has passed
+    pub(crate) fn pace<T, F: FnOnce() -> T>(&self, send: F) -> T {
+        let min_gap = *self.min_gap.lock().unwrap();
+        // Reserves the next free slot, so the lock isn't held while the report is sent and retried
+        let slot = {
+            let mut last_report = self.last_report.lock().unwrap();
+            let now = Instant::now();
+            let slot = last_report
+                .and_then(|last| last.checked_add(min_gap))
+                .map_or(now, |slot| slot.max(now));
+            *last_report = Some(slot);
+            slot
+        };
+        std::thread::sleep(slot.saturating_duration_since(Instant::now()));
+
+        let result = send();
+        let mut last_report = self.last_report.lock().unwrap();
+        *last_report = last_report.max(Some(Instant::now()));
+        result
+    }
+}
+
+impl BlinkStick {
+    /// Waits at least `gap` between two feature reports sent to the device, `DEFAULT_MIN_REPORT_GAP` unless set.
+    /// Some firmwares lock up when they are sent reports back to back. A gap of zero sends reports as fast as the
+    /// device takes them.
+    pub fn set_min_report_gap(&self, gap: Duration) {
+        *self.throttle.min_gap.lock().unwrap() = gap;
+    }
+
+    /// The shortest time between two feature reports sent to the device
+    pub fn min_report_gap(&self) -> Duration {
+        *self.throttle.min_gap.lock().unwrap()
+    }
+
+    /// Sends a feature report paced by the minimum report gap, reopening the device once several sends in a row
+    /// failed and sending the report again through the new handle
+    pub(crate) fn send_paced<F>(&self, send: F) -> Result<(), FeatureError>
+    where
+        F: Fn() -> Result<(), FeatureError>,
+    {
+        let error = match self.throttle.pace(&send) {
+            Ok(()) => {
+                self.throttle.failed_sends.store(0, Ordering::Relaxed);
+                return Ok(());
+            }
+            Err(error) => error,
+        };
+
+        let failed_sends = self.throttle.failed_sends.fetch_add(1, Ordering::Relaxed) + 1;
+        if failed_sends < REOPEN_AFTER_FAILED_SENDS || self.reopen_device().is_err() {
+            return Err(error);
+        }
+
+        self.throttle.failed_sends.store(0, Ordering::Relaxed);
+        self.throttle.pace(send)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_are_spaced_by_the_minimum_gap() {
+        let throttle = ReportThrottle::new(Duration::from_millis(20));
+
+        let start = Instant::now();
+        throttle.pace(|| ());
+        throttle.pace(|| ());
+        throttle.pace(|| ());
+        assert!(start.elapsed() >= Duration::from_millis(40));
+    }
+}
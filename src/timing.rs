@@ -0,0 +1,291 @@
+use crate::wire::LEDS_REPORT_ID;
+use crate::{BlinkStick, FeatureError};
+use std::fmt::{Display, Formatter};
+use std::time::{Duration, Instant};
+
+/// The number of round trips averaged by `BlinkStick::calibrate_latency`
+const CALIBRATION_ROUND_TRIPS: u32 = 5;
+
+/// How many frames an animation is split into, given as a number of steps, a frame rate or the time between two
+/// frames
+///
+/// Every animated api takes anything that converts into a `StepRate`: a plain `u16` number of steps as before,
+/// `Fps` or `StepEvery`. Rates are turned into a number of steps for the duration of the animation, and never into
+/// more frames than the device can show in time once its latency is measured, see `BlinkStick::steps_for`.
+///
+/// # Example
+/// ```
+/// use blinkstick_rs::{Fps, StepEvery, StepRate};
+/// use std::time::Duration;
+///
+/// let two_seconds = Duration::from_secs(2);
+///
+/// assert_eq!(StepRate::from(25).steps(two_seconds), 25);
+/// assert_eq!(StepRate::from(Fps(30)).steps(two_seconds), 60);
+/// assert_eq!(StepRate::from(StepEvery(Duration::from_millis(40))).steps(two_seconds), 50);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StepRate {
+    /// A fixed number of steps, however long the animation takes
+    Steps(u16),
+    /// A number of frames per second
+    Fps(u32),
+    /// The time between two frames
+    StepEvery(Duration),
+}
+
+/// A number of frames per second, see `StepRate`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fps(pub u32);
+
+/// The time between two frames, see `StepRate`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StepEvery(pub Duration);
+
+impl From<u16> for StepRate {
+    fn from(steps: u16) -> Self {
+        StepRate::Steps(steps)
+    }
+}
+
+impl From<Fps> for StepRate {
+    fn from(fps: Fps) -> Self {
+        StepRate::Fps(fps.0)
+    }
+}
+
+impl From<StepEvery> for StepRate {
+    fn from(interval: StepEvery) -> Self {
+        StepRate::StepEvery(interval.0)
+    }
+}
+
+impl StepRate {
+    /// The number of steps an animation taking `duration` is split into, at least one for a rate
+    pub fn steps(self, duration: Duration) -> u16 {
+        let frames = match self {
+            StepRate::Steps(steps) => return steps,
+            StepRate::Fps(fps) => (duration.as_secs_f64() * fps as f64).round(),
+            StepRate::StepEvery(interval) => (duration.as_secs_f64() / interval.as_secs_f64()).ceil(),
+        };
+
+        // A zero interval makes infinitely many frames, which saturates
+        frames.clamp(1.0, u16::MAX as f64) as u16
+    }
+
+    /// The number of steps an animation taking `duration` is split into on a device taking `frame_latency` to send
+    /// a frame. Rates are capped at the frames that can be sent in time, a fixed number of steps is kept.
+    pub fn steps_within(self, duration: Duration, frame_latency: Duration) -> u16 {
+        match self {
+            StepRate::Steps(steps) => steps,
+            rate => rate
+                .steps(duration)
+                .min(TimingReport::new(duration, 1, frame_latency).max_steps.max(1)),
+        }
+    }
+}
+
+/// How an animation of `steps` frames over `duration` fits the time a frame takes to send, see
+/// `BlinkStick::timing_report`
+///
+/// # Example
+/// ```
+/// use blinkstick_rs::TimingReport;
+/// use std::time::Duration;
+///
+/// let report = TimingReport::new(Duration::from_secs(1), 200, Duration::from_millis(10));
+///
+/// assert!(!report.is_feasible());
+/// assert_eq!(report.max_steps, 100);
+/// assert_eq!(report.overrun(), Duration::from_secs(1));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimingReport {
+    /// The time the whole animation should take
+    pub duration: Duration,
+    /// The number of frames requested
+    pub steps: u16,
+    /// The time each frame may take, `duration` divided by `steps`
+    pub interval: Duration,
+    /// The time it takes to send a frame to the device
+    pub frame_latency: Duration,
+    /// The most frames that can be sent within `duration`
+    pub max_steps: u16,
+}
+
+impl TimingReport {
+    /// Plans `steps` frames over `duration` for a device taking `frame_latency` to send a frame
+    pub fn new(duration: Duration, steps: u16, frame_latency: Duration) -> TimingReport {
+        let max_steps = (duration.as_nanos() / frame_latency.as_nanos().max(1)).min(u16::MAX as u128) as u16;
+
+        TimingReport {
+            duration,
+            steps,
+            interval: duration / steps.max(1) as u32,
+            frame_latency,
+            max_steps,
+        }
+    }
+
+    /// Whether every frame can be sent within its interval, so the animation finishes on time
+    pub fn is_feasible(&self) -> bool {
+        self.frame_latency <= self.interval
+    }
+
+    /// How much longer than `duration` the animation takes, zero if it is feasible. Saturates at `Duration::MAX`
+    /// for latencies too long to add up.
+    pub fn overrun(&self) -> Duration {
+        self.frame_latency
+            .checked_mul(self.steps as u32)
+            .unwrap_or(Duration::MAX)
+            .saturating_sub(self.duration)
+    }
+}
+
+impl Display for TimingReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.is_feasible() {
+            write!(
+                f,
+                "{} steps over {:?} fit, each frame takes {:?} of its {:?}",
+                self.steps, self.duration, self.frame_latency, self.interval
+            )
+        } else {
+            write!(
+                f,
+                "{} steps over {:?} take {:?} longer, at most {} steps fit as each frame takes {:?}",
+                self.steps,
+                self.duration,
+                self.overrun(),
+                self.max_steps,
+                self.frame_latency
+            )
+        }
+    }
+}
+
+impl BlinkStick {
+    /// Measures how long a feature report takes to travel to the device and back, averaged over a few round trips,
+    /// and keeps the estimate for `report_latency`, `timing_report` and the effects that plan their frame rate
+    ///
+    /// Only the leds report is read, so the colors of the device don't change. Opening the device with
+    /// `BlinkStickBuilder::calibrate_latency` calibrates right away.
+    pub fn calibrate_latency(&self) -> Result<Duration, FeatureError> {
+        let start = Instant::now();
+        for _ in 0..CALIBRATION_ROUND_TRIPS {
+            self.get_feature_from_blinkstick(LEDS_REPORT_ID)?;
+        }
+        let latency = start.elapsed() / CALIBRATION_ROUND_TRIPS;
+
+        *self.report_latency.lock().unwrap() = Some(latency);
+        Ok(latency)
+    }
+
+    /// The time a single feature report takes, if it was measured by `calibrate_latency`
+    pub fn report_latency(&self) -> Option<Duration> {
+        *self.report_latency.lock().unwrap()
+    }
+
+    /// The time a whole frame takes, which is a report per channel for frames longer than a channel
+    pub(crate) fn frame_latency(&self) -> Option<Duration> {
+        let reports = self.channel_leds().len() as u32;
+        self.report_latency().map(|latency| latency * reports)
+    }
+
+    /// The number of steps an animation taking `duration` is split into at `rate`, capped at the frames the device
+    /// can show in time once its latency was measured by `calibrate_latency`, see `StepRate::steps_within`
+    ///
+    /// # Example
+    /// Transforms the leds at 60 frames per second, or as fast as the device allows
+    #[cfg_attr(feature = "effects", doc = "```")]
+    #[cfg_attr(not(feature = "effects"), doc = "```ignore")]
+    /// use blinkstick_rs::{AnimationIter, BlinkStick, Color, Fps};
+    /// use std::time::Duration;
+    ///
+    /// let blinkstick = BlinkStick::builder().calibrate_latency(true).open().unwrap();
+    /// let from = blinkstick.get_color_vec();
+    /// let to = vec![Color {r: 0, g: 50, b: 0}; blinkstick.max_leds as usize];
+    ///
+    /// let second = Duration::from_secs(1);
+    /// let steps = blinkstick.steps_for(second, Fps(60));
+    /// blinkstick.play_animation(AnimationIter::transform(from, to, second, steps)).unwrap();
+    /// ```
+    pub fn steps_for<S: Into<StepRate>>(&self, duration: Duration, rate: S) -> u16 {
+        match self.frame_latency() {
+            Some(latency) => rate.into().steps_within(duration, latency),
+            None => rate.into().steps(duration),
+        }
+    }
+
+    /// Plans an animation of `steps` frames over `duration`, calibrating the latency first if it wasn't yet
+    ///
+    /// # Example
+    /// Warns before starting a transform that cannot finish on time
+    /// ```
+    /// use blinkstick_rs::BlinkStick;
+    /// use std::time::Duration;
+    ///
+    /// let blinkstick = BlinkStick::builder().calibrate_latency(true).open().unwrap();
+    /// let report = blinkstick.timing_report(Duration::from_millis(500), 200).unwrap();
+    ///
+    /// if !report.is_feasible() {
+    ///     eprintln!("{}", report);
+    /// }
+    /// ```
+    pub fn timing_report<S: Into<StepRate>>(&self, duration: Duration, steps: S) -> Result<TimingReport, FeatureError> {
+        let frame_latency = match self.frame_latency() {
+            Some(latency) => latency,
+            None => {
+                self.calibrate_latency()?;
+                self.frame_latency().unwrap_or_default()
+            }
+        };
+
+        Ok(TimingReport::new(duration, steps.into().steps(duration), frame_latency))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn feasible_reports_have_no_overrun() {
+        let report = TimingReport::new(Duration::from_secs(1), 50, Duration::from_millis(10));
+
+        assert!(report.is_feasible());
+        assert_eq!(report.interval, Duration::from_millis(20));
+        assert_eq!(report.max_steps, 100);
+        assert_eq!(report.overrun(), Duration::ZERO);
+    }
+
+    #[test]
+    fn rates_are_capped_by_the_latency() {
+        let second = Duration::from_secs(1);
+
+        assert_eq!(StepRate::Fps(100).steps_within(second, Duration::from_millis(20)), 50);
+        assert_eq!(StepRate::Fps(10).steps_within(second, Duration::from_millis(20)), 10);
+        assert_eq!(
+            StepRate::Steps(100).steps_within(second, Duration::from_millis(20)),
+            100
+        );
+        assert_eq!(StepRate::StepEvery(Duration::ZERO).steps(second), u16::MAX);
+        assert_eq!(StepRate::Fps(30).steps(Duration::ZERO), 1);
+    }
+
+    #[test]
+    fn zero_latency_fits_any_step_count() {
+        let report = TimingReport::new(Duration::from_secs(1), u16::MAX, Duration::ZERO);
+
+        assert!(report.is_feasible());
+        assert_eq!(report.max_steps, u16::MAX);
+    }
+
+    #[test]
+    fn endless_latencies_saturate_the_overrun() {
+        let report = TimingReport::new(Duration::from_secs(1), 2, Duration::MAX);
+
+        assert!(!report.is_feasible());
+        assert_eq!(report.overrun(), Duration::MAX - Duration::from_secs(1));
+    }
+}
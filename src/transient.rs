@@ -0,0 +1,124 @@
+use crate::frame::with_leds;
+use crate::{AnimationHandle, AnimationIter, Animator, Color, COLOR_OFF};
+use std::time::Duration;
+
+impl Animator {
+    /// Shows `color` on a single led for `duration`, then reverts the led to the color it showed before, replacing
+    /// whatever the animator plays. Transient indicators need no timer thread of their own this way.
+    ///
+    /// The color to revert to is read when the color is shown: the color written to the BlinkStick last, or the
+    /// color rendered last by a headless animator.
+    ///
+    /// # Example
+    /// Flashes the first led green for two seconds whenever a message arrives
+    /// ```
+    /// use blinkstick_rs::{Animator, BlinkStick, Color};
+    /// use std::time::Duration;
+    ///
+    /// let blinkstick = BlinkStick::new().unwrap();
+    /// blinkstick.set_all_leds_color(Color {r: 0, g: 0, b: 20}).unwrap();
+    /// let animator = Animator::new(blinkstick);
+    ///
+    /// animator.set_led_color_for(0, Color {r: 0, g: 80, b: 0}, Duration::from_secs(2)).wait();
+    /// ```
+    pub fn set_led_color_for(&self, led: u8, color: Color, duration: Duration) -> AnimationHandle {
+        self.show_for(&format!("led {} for {:?}", led, duration), duration, move |previous| {
+            if previous.len() <= led as usize {
+                previous.resize(led as usize + 1, COLOR_OFF);
+            }
+            with_leds(previous, &[led], color)
+        })
+    }
+
+    /// Shows `color` on every led for `duration`, then reverts the leds to the colors they showed before, see
+    /// `set_led_color_for`
+    pub fn set_all_leds_color_for(&self, color: Color, duration: Duration) -> AnimationHandle {
+        self.show_for(&format!("all leds for {:?}", duration), duration, move |previous| {
+            vec![color; previous.len()]
+        })
+    }
+
+    /// Plays the frame `transient` makes of the current frame for `duration`, then the current frame again
+    fn show_for<F>(&self, name: &str, duration: Duration, transient: F) -> AnimationHandle
+    where
+        F: FnOnce(&mut Vec<Color>) -> Vec<Color> + Send + 'static,
+    {
+        self.play_from_current(name, move |mut previous| {
+            let shown = transient(&mut previous);
+            vec![(shown, duration), (previous, Duration::ZERO)]
+        })
+    }
+
+    /// Fades every led to the color of each segment in turn and holds it before the next segment, starting from
+    /// the colors shown when the ramp starts, see `AnimationIter::ramp`. Replaces whatever the animator plays.
+    ///
+    /// # Example
+    /// Escalates from green through yellow to a red alert as a temperature rises
+    /// ```
+    /// use blinkstick_rs::{Animator, BlinkStick, Color};
+    /// use std::time::Duration;
+    ///
+    /// let animator = Animator::new(BlinkStick::new().unwrap());
+    /// let minute = Duration::from_secs(60);
+    ///
+    /// animator.ramp(&[
+    ///     (Color::GREEN, Duration::from_secs(1), minute),
+    ///     (Color::new(255, 200, 0), Duration::from_secs(5), minute),
+    ///     (Color::RED, Duration::from_secs(5), minute),
+    /// ]).wait();
+    /// ```
+    pub fn ramp(&self, segments: &[(Color, Duration, Duration)]) -> AnimationHandle {
+        let segments = segments.to_vec();
+        self.play_from_current("ramp", move |current| AnimationIter::ramp(current, &segments))
+    }
+
+    /// Plays the frames `frames` makes of the current frame
+    fn play_from_current<F, I>(&self, name: &str, frames: F) -> AnimationHandle
+    where
+        F: FnOnce(Vec<Color>) -> I + Send + 'static,
+        I: IntoIterator<Item = (Vec<Color>, Duration)> + 'static,
+        I::IntoIter: Send + 'static,
+    {
+        let stick = self.stick().cloned();
+        let headless_frame = self.last_frame();
+        let mut frames = Some(frames);
+
+        // The current frame is only read once the effect starts, after the effects it replaced were cancelled
+        let frames = std::iter::once(()).flat_map(move |_| {
+            let current = match &stick {
+                Some(stick) => stick.written_frame(),
+                None => headless_frame.lock().unwrap().clone(),
+            };
+            frames.take().unwrap()(current)
+        });
+
+        self.play(name, frames)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FrameRecorder, ManualClock};
+    use std::sync::Arc;
+
+    #[test]
+    fn transient_colors_revert_to_the_previous_frame() {
+        let animator = Animator::headless_with_clock(Arc::new(ManualClock::new()));
+        let recorder = FrameRecorder::new();
+        animator.tee(recorder.clone());
+        let blue = Color { r: 0, g: 0, b: 20 };
+        animator.play("blue", vec![(vec![blue; 3], Duration::ZERO)]).wait();
+        recorder.clear();
+
+        animator.set_led_color_for(1, Color::RED, Duration::from_secs(2)).wait();
+
+        assert_eq!(
+            recorder.frames(),
+            vec![
+                (vec![blue, Color::RED, blue], Duration::from_secs(2)),
+                (vec![blue; 3], Duration::ZERO)
+            ]
+        );
+    }
+}
@@ -0,0 +1,163 @@
+use crate::wire::{decode_leds_report, encode_leds_report, LEDS_REPORT_ID, LED_REPORT_ID};
+use crate::{Color, COLOR_OFF};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// What a `BlinkStick` exchanges its feature reports with
+pub(crate) enum Transport {
+    /// A device opened through hidapi
+    Hid(hidapi::HidDevice),
+    /// A stand-in for a device that isn't there, see `BlinkStickBuilder::open`
+    Loopback(Loopback),
+}
+
+impl Transport {
+    pub(crate) fn send_feature_report(&self, report: &[u8]) -> hidapi::HidResult<()> {
+        match self {
+            Transport::Hid(device) => device.send_feature_report(report),
+            Transport::Loopback(loopback) => {
+                loopback.send(report);
+                Ok(())
+            }
+        }
+    }
+
+    pub(crate) fn get_feature_report(&self, buf: &mut [u8]) -> hidapi::HidResult<usize> {
+        match self {
+            Transport::Hid(device) => device.get_feature_report(buf),
+            Transport::Loopback(loopback) => Ok(loopback.get(buf)),
+        }
+    }
+
+    pub(crate) fn get_serial_number_string(&self) -> hidapi::HidResult<Option<String>> {
+        match self {
+            Transport::Hid(device) => device.get_serial_number_string(),
+            Transport::Loopback(_) => Ok(None),
+        }
+    }
+
+    pub(crate) fn get_device_info(&self) -> hidapi::HidResult<hidapi::DeviceInfo> {
+        match self {
+            Transport::Hid(device) => device.get_device_info(),
+            Transport::Loopback(_) => Err(hidapi::HidError::HidApiError {
+                message: "The BlinkStick is disabled and has no hid device".to_string(),
+            }),
+        }
+    }
+
+    /// Returns `true` for a stand-in without a device, which has no connection to lose
+    pub(crate) fn is_loopback(&self) -> bool {
+        matches!(self, Transport::Loopback(_))
+    }
+}
+
+/// Answers every feature report like a BlinkStick would, without any hardware: the leds report holds the colors
+/// last sent to the channel written last, and every other report reads back as it was last sent, like a
+/// `NoopBlinkStick` at the level of reports
+pub(crate) struct Loopback {
+    channels: Mutex<Vec<Vec<Color>>>,
+    last_channel: Mutex<u8>,
+    reports: Mutex<HashMap<u8, Vec<u8>>>,
+}
+
+impl Loopback {
+    /// A stand-in for a device with `led_count` leds, all off
+    pub(crate) fn new(led_count: u8) -> Loopback {
+        Loopback {
+            channels: Mutex::new(vec![vec![COLOR_OFF; led_count as usize]]),
+            last_channel: Mutex::new(0),
+            reports: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn send(&self, report: &[u8]) {
+        let mut channels = self.channels.lock().unwrap();
+        match report {
+            [LEDS_REPORT_ID, channel, ..] => {
+                let channel = *channel as usize;
+                if channels.len() <= channel {
+                    channels.resize(channel + 1, Vec::new());
+                }
+                channels[channel] = decode_leds_report(report);
+                *self.last_channel.lock().unwrap() = channel as u8;
+            }
+            [LED_REPORT_ID, channel, index, r, g, b, ..] => {
+                let (channel, index) = (*channel as usize, *index as usize);
+                if channels.len() <= channel {
+                    channels.resize(channel + 1, Vec::new());
+                }
+                let leds = &mut channels[channel];
+                if leds.len() <= index {
+                    leds.resize(index + 1, COLOR_OFF);
+                }
+                leds[index] = Color { r: *r, g: *g, b: *b };
+            }
+            [id, ..] => {
+                self.reports.lock().unwrap().insert(*id, report.to_vec());
+            }
+            [] => (),
+        }
+    }
+
+    /// Fills `buf` with the report its first byte asks for, returning the number of bytes read
+    fn get(&self, buf: &mut [u8]) -> usize {
+        let report = match buf.first() {
+            Some(&LEDS_REPORT_ID) => {
+                let channel = *self.last_channel.lock().unwrap();
+                let mut report = encode_leds_report(&self.channels.lock().unwrap()[channel as usize]);
+                report[1] = channel;
+                report
+            }
+            Some(id) => match self.reports.lock().unwrap().get(id) {
+                Some(report) => report.clone(),
+                // A report that was never sent reads back as zeros, like on a device fresh from the factory
+                None => return buf.len(),
+            },
+            None => return 0,
+        };
+
+        let read = report.len().min(buf.len());
+        buf[..read].copy_from_slice(&report[..read]);
+        read
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wire::{encode_channel_led_report, encode_channel_reports, INFO_BLOCK_2_REPORT_ID};
+
+    fn leds_report(loopback: &Loopback) -> Vec<u8> {
+        let mut buf = vec![0; 100];
+        buf[0] = LEDS_REPORT_ID;
+        let read = loopback.get(&mut buf);
+        buf.truncate(read);
+        buf
+    }
+
+    #[test]
+    fn loopbacks_read_back_what_was_sent() {
+        let loopback = Loopback::new(8);
+        assert_eq!(decode_leds_report(&leds_report(&loopback)), vec![COLOR_OFF; 8]);
+
+        let frame: Vec<Color> = (0..90).map(|led| Color { r: led, g: 0, b: 1 }).collect();
+        for report in encode_channel_reports(&frame, &[30, 40, 20]) {
+            loopback.send(&report);
+        }
+        loopback.send(&encode_channel_led_report(2, 5, Color::RED));
+
+        // The leds report holds the channel written last
+        let report = leds_report(&loopback);
+        assert_eq!(report[1], 2);
+        let mut expected = frame[70..].to_vec();
+        expected[5] = Color::RED;
+        assert_eq!(decode_leds_report(&report), expected);
+
+        let mut info = vec![INFO_BLOCK_2_REPORT_ID; 33];
+        info[1] = 7;
+        loopback.send(&info);
+        let mut buf = vec![INFO_BLOCK_2_REPORT_ID; 33];
+        assert_eq!(loopback.get(&mut buf), 33);
+        assert_eq!(buf, info);
+    }
+}
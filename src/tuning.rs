@@ -0,0 +1,122 @@
+use crate::Color;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+/// The value of a tunable effect parameter
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Parameter {
+    Number(f32),
+    Color(Color),
+}
+
+/// Parameters of a running effect that can be changed while it plays, for example from sliders in a GUI
+///
+/// An effect declares its parameters with their initial values and reads them back whenever it renders a frame.
+/// Clones share their values, so the effect keeps one clone and the `AnimationHandle` of the effect another.
+///
+/// # Example
+/// A blink whose speed and color can be changed while it plays
+#[cfg_attr(feature = "animator", doc = "```")]
+#[cfg_attr(not(feature = "animator"), doc = "```ignore")]
+/// use blinkstick_rs::{Animator, BlinkStick, Color, Parameter, Parameters};
+/// use std::time::Duration;
+///
+/// let blinkstick = BlinkStick::new().unwrap();
+/// let leds = blinkstick.max_leds as usize;
+/// let animator = Animator::new(blinkstick);
+///
+/// let parameters = Parameters::new()
+///     .declare("speed", Parameter::Number(1.0))
+///     .declare("color", Parameter::Color(Color {r: 0, g: 0, b: 50}));
+/// let tuning = parameters.clone();
+/// let mut on = false;
+/// let frames = std::iter::repeat_with(move || {
+///     on = !on;
+///     let color = if on { tuning.color("color").unwrap() } else { Color {r: 0, g: 0, b: 0} };
+///     let delay = Duration::from_secs_f32(0.5 / tuning.number("speed").unwrap().max(0.1));
+///     (vec![color; leds], delay)
+/// });
+///
+/// let handle = animator.play_tunable("blink", parameters, frames);
+/// handle.parameters().set("speed", Parameter::Number(4.0));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Parameters {
+    values: Arc<Mutex<BTreeMap<String, Parameter>>>,
+}
+
+impl Parameters {
+    /// Creates a set without any parameters
+    pub fn new() -> Parameters {
+        Parameters::default()
+    }
+
+    /// Adds a parameter with its initial value
+    pub fn declare(self, name: &str, initial: Parameter) -> Parameters {
+        self.values.lock().unwrap().insert(name.to_string(), initial);
+        self
+    }
+
+    /// Changes a parameter, taking effect the next time the effect reads it
+    ///
+    /// Returns `false` without changing anything if the effect has no parameter called `name`, or if `value` is of
+    /// a different kind than the declared value.
+    pub fn set(&self, name: &str, value: Parameter) -> bool {
+        match self.values.lock().unwrap().get_mut(name) {
+            Some(current) if std::mem::discriminant(current) == std::mem::discriminant(&value) => {
+                *current = value;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// The current value of a parameter
+    pub fn get(&self, name: &str) -> Option<Parameter> {
+        self.values.lock().unwrap().get(name).copied()
+    }
+
+    /// The current value of a number parameter
+    pub fn number(&self, name: &str) -> Option<f32> {
+        match self.get(name) {
+            Some(Parameter::Number(number)) => Some(number),
+            _ => None,
+        }
+    }
+
+    /// The current value of a color parameter
+    pub fn color(&self, name: &str) -> Option<Color> {
+        match self.get(name) {
+            Some(Parameter::Color(color)) => Some(color),
+            _ => None,
+        }
+    }
+
+    /// Every parameter with its current value, ordered by name
+    pub fn values(&self) -> Vec<(String, Parameter)> {
+        self.values
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(name, value)| (name.clone(), *value))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clones_share_values() {
+        let parameters = Parameters::new().declare("speed", Parameter::Number(1.0));
+        let effect = parameters.clone();
+
+        assert!(parameters.set("speed", Parameter::Number(2.5)));
+        assert_eq!(effect.number("speed"), Some(2.5));
+
+        assert!(!parameters.set("speed", Parameter::Color(Color { r: 1, g: 2, b: 3 })));
+        assert!(!parameters.set("density", Parameter::Number(0.5)));
+        assert_eq!(effect.values(), vec![("speed".to_string(), Parameter::Number(2.5))]);
+    }
+}
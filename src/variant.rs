@@ -0,0 +1,151 @@
+use crate::wire::default_channel_leds;
+use crate::BlinkStick;
+
+/// The kinds of BlinkStick devices
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Variant {
+    /// The original BlinkStick with a single led
+    BlinkStick,
+    /// The BlinkStick Pro, which drives up to 64 leds on each of its three channels
+    Pro,
+    /// The BlinkStick Square with 8 leds
+    Square,
+    /// The BlinkStick Strip with 8 leds
+    Strip,
+    /// The BlinkStick Nano with 2 leds
+    Nano,
+    /// The BlinkStick Flex with up to 32 leds
+    Flex,
+    /// A device that could not be identified
+    Unknown,
+}
+
+impl Variant {
+    /// Identifies a device from the major version in its serial number (`BS000001-3.0`), its release number
+    /// (bcdDevice) and, when both are inconclusive, its product string
+    pub fn detect(serial_number: Option<&str>, release_number: u16, product: Option<&str>) -> Variant {
+        let major_version = serial_number
+            .and_then(|serial| serial.rsplit('-').next())
+            .and_then(|version| version.split('.').next())
+            .and_then(|major| major.parse::<u8>().ok());
+
+        match (major_version, release_number) {
+            (Some(1), _) => return Variant::BlinkStick,
+            (Some(2), _) => return Variant::Pro,
+            (Some(3), 0x200) => return Variant::Square,
+            (Some(3), 0x201) => return Variant::Strip,
+            (Some(3), 0x202) => return Variant::Nano,
+            (Some(3), 0x203) => return Variant::Flex,
+            _ => (),
+        }
+
+        let product = product.unwrap_or("").to_lowercase();
+        [
+            ("square", Variant::Square),
+            ("strip", Variant::Strip),
+            ("nano", Variant::Nano),
+            ("flex", Variant::Flex),
+            ("pro", Variant::Pro),
+        ]
+        .iter()
+        .find(|(name, _)| product.contains(name))
+        .map_or(Variant::Unknown, |(_, variant)| *variant)
+    }
+
+    /// The number of leds of variants that always have the same number of leds
+    pub fn fixed_led_count(self) -> Option<u8> {
+        match self {
+            Variant::BlinkStick => Some(1),
+            Variant::Square | Variant::Strip => Some(8),
+            Variant::Nano => Some(2),
+            Variant::Pro | Variant::Flex | Variant::Unknown => None,
+        }
+    }
+
+    /// The highest number of leds the variant can drive on a single channel
+    pub fn max_led_count(self) -> Option<u8> {
+        match self {
+            Variant::Pro => Some(64),
+            Variant::Flex => Some(32),
+            Variant::Unknown => None,
+            fixed => fixed.fixed_led_count(),
+        }
+    }
+
+    /// The number of channels the variant drives leds on, each holding up to 64 leds
+    pub fn channel_count(self) -> u8 {
+        match self {
+            Variant::Pro => 3,
+            _ => 1,
+        }
+    }
+
+    /// Decides on the number of leds of a device whose leds report holds colors for `report_leds` leds
+    ///
+    /// Variants with a fixed number of leds use it regardless of the report, which some firmwares pad. The others
+    /// trust the report, limited to what the variant can drive.
+    pub fn led_count(self, report_leds: u8) -> u8 {
+        match (self.fixed_led_count(), self.max_led_count()) {
+            (Some(fixed), _) => fixed,
+            (None, Some(max)) => report_leds.min(max),
+            (None, None) => report_leds,
+        }
+    }
+}
+
+impl BlinkStick {
+    /// The kind of BlinkStick device, as detected when it was opened
+    pub fn variant(&self) -> Variant {
+        self.variant
+    }
+
+    /// The length in bytes of the leds report written to the device, including the report id and the header
+    pub fn report_length(&self) -> usize {
+        self.report_length
+    }
+
+    /// The number of leds on each channel a frame is sent to, a single channel unless the device has more leds
+    /// than a channel holds or was opened with `BlinkStickBuilder::channels`
+    ///
+    /// # Example
+    /// ```
+    /// use blinkstick_rs::BlinkStick;
+    ///
+    /// let blinkstick = BlinkStick::builder().leds(150).open().unwrap();
+    /// assert_eq!(blinkstick.channel_leds(), vec![64, 64, 22]);
+    /// ```
+    pub fn channel_leds(&self) -> Vec<u8> {
+        match &self.channel_layout {
+            Some(layout) => layout.clone(),
+            None => default_channel_leds(self.max_leds as usize),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_variants() {
+        assert_eq!(Variant::detect(Some("BS000001-1.0"), 0x100, None), Variant::BlinkStick);
+        assert_eq!(Variant::detect(Some("BS000001-2.1"), 0x100, None), Variant::Pro);
+        assert_eq!(Variant::detect(Some("BS012345-3.0"), 0x202, None), Variant::Nano);
+        assert_eq!(Variant::detect(None, 0, Some("BlinkStick Square")), Variant::Square);
+        assert_eq!(Variant::detect(Some(""), 0, None), Variant::Unknown);
+    }
+
+    #[test]
+    fn fixed_variants_ignore_padded_reports() {
+        assert_eq!(Variant::Nano.led_count(8), 2);
+        assert_eq!(Variant::Flex.led_count(32), 32);
+        assert_eq!(Variant::Flex.led_count(64), 32);
+        assert_eq!(Variant::Unknown.led_count(5), 5);
+    }
+
+    #[test]
+    fn only_the_pro_has_several_channels() {
+        assert_eq!(Variant::Pro.channel_count(), 3);
+        assert_eq!(Variant::Flex.channel_count(), 1);
+    }
+}
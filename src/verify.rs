@@ -0,0 +1,152 @@
+use crate::channel;
+use crate::wire::LEDS_REPORT_ID;
+use crate::{BlinkStick, Color, FeatureError, FeatureErrorType};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// How often written frames were read back and found different, see `BlinkStick::set_verify_writes`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct VerificationStats {
+    /// Frames read back after writing them
+    pub frames_verified: u64,
+    /// Read backs that differed from the frame written, each followed by writing the frame again
+    pub mismatches: u64,
+    /// Frames that still differed after every retry
+    pub failures: u64,
+}
+
+/// The counters behind `VerificationStats`
+#[derive(Default)]
+pub(crate) struct VerificationCounters {
+    frames_verified: AtomicU64,
+    mismatches: AtomicU64,
+    failures: AtomicU64,
+}
+
+impl BlinkStick {
+    /// Reads every frame back after writing it, writing it again up to `retries` times while the device reports
+    /// other colors. Catches reports corrupted on the way, like through a flaky KVM switch, at the cost of a read
+    /// per frame. A `retries` of 0 turns verification off, which is the default.
+    ///
+    /// Only the leds that can be read back are compared, those of the first channel. Write-only devices are never
+    /// verified. A frame that still differs after every retry fails with `FeatureErrorType::VerificationFailed`.
+    ///
+    /// # Example
+    /// ```
+    /// use blinkstick_rs::{BlinkStick, Color};
+    ///
+    /// let blinkstick = BlinkStick::new().unwrap();
+    /// blinkstick.set_verify_writes(2);
+    /// blinkstick.set_all_leds_color(Color {r: 0, g: 50, b: 0}).unwrap();
+    ///
+    /// println!("{:?}", blinkstick.verification_stats());
+    /// ```
+    pub fn set_verify_writes(&self, retries: u8) {
+        self.verify_retries.store(retries, Ordering::Relaxed);
+    }
+
+    /// The number of times a frame is written again when its read back differs, 0 if writes aren't verified
+    pub fn verify_writes(&self) -> u8 {
+        self.verify_retries.load(Ordering::Relaxed)
+    }
+
+    /// How often written frames were verified and how often they differed, since the device was opened
+    pub fn verification_stats(&self) -> VerificationStats {
+        VerificationStats {
+            frames_verified: self.verification.frames_verified.load(Ordering::Relaxed),
+            mismatches: self.verification.mismatches.load(Ordering::Relaxed),
+            failures: self.verification.failures.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Reads back the colors the device was sent with `reports`, sending them again while they differ
+    pub(crate) fn verify_written(&self, outputs: &[Color], reports: &[Vec<u8>]) -> Result<(), FeatureError> {
+        let retries = self.verify_writes();
+        if retries == 0 || self.is_write_only() {
+            return Ok(());
+        }
+
+        self.verification.verify(
+            retries,
+            outputs,
+            &self.channel_leds(),
+            || self.get_feature_from_blinkstick(LEDS_REPORT_ID),
+            || {
+                reports
+                    .iter()
+                    .try_for_each(|report| self.send_feature_to_blinkstick(report))
+            },
+        )
+    }
+}
+
+impl VerificationCounters {
+    /// Compares the leds report returned by `read` with `outputs`, calling `resend` while they differ
+    fn verify(
+        &self,
+        retries: u8,
+        outputs: &[Color],
+        channel_leds: &[u8],
+        mut read: impl FnMut() -> Result<Vec<u8>, FeatureError>,
+        mut resend: impl FnMut() -> Result<(), FeatureError>,
+    ) -> Result<(), FeatureError> {
+        self.frames_verified.fetch_add(1, Ordering::Relaxed);
+        for attempt in 0..=retries {
+            let report = read()?;
+            if channel::read_back_outputs(&report, outputs, channel_leds)?[..outputs.len()] == outputs[..] {
+                return Ok(());
+            }
+
+            self.mismatches.fetch_add(1, Ordering::Relaxed);
+            if attempt < retries {
+                resend()?;
+            }
+        }
+
+        self.failures.fetch_add(1, Ordering::Relaxed);
+        Err(FeatureError {
+            kind: FeatureErrorType::VerificationFailed,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wire::encode_leds_report;
+    use std::cell::Cell;
+
+    fn stats(counters: &VerificationCounters) -> (u64, u64, u64) {
+        (
+            counters.frames_verified.load(Ordering::Relaxed),
+            counters.mismatches.load(Ordering::Relaxed),
+            counters.failures.load(Ordering::Relaxed),
+        )
+    }
+
+    #[test]
+    fn differing_read_backs_are_written_again() {
+        let outputs = [Color { r: 0, g: 50, b: 0 }, Color { r: 9, g: 8, b: 7 }];
+        let corrupted = [Color { r: 0, g: 50, b: 0 }, Color { r: 9, g: 8, b: 0 }];
+        let counters = VerificationCounters::default();
+        let resent = Cell::new(0);
+
+        // The device reports the frame correctly once it was sent again
+        let result = counters.verify(
+            2,
+            &outputs,
+            &[2],
+            || Ok(encode_leds_report(if resent.get() > 0 { &outputs } else { &corrupted })),
+            || {
+                resent.set(resent.get() + 1);
+                Ok(())
+            },
+        );
+        assert!(result.is_ok());
+        assert_eq!(resent.get(), 1);
+        assert_eq!(stats(&counters), (1, 1, 0));
+
+        let result = counters.verify(2, &outputs, &[2], || Ok(encode_leds_report(&corrupted)), || Ok(()));
+        assert_eq!(result.unwrap_err().kind, FeatureErrorType::VerificationFailed);
+        assert_eq!(stats(&counters), (2, 4, 1));
+    }
+}
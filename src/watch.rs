@@ -0,0 +1,116 @@
+use crate::{BlinkStick, Color, FeatureError, LedDevice};
+use std::time::{Duration, Instant};
+
+/// An iterator over the colors of a BlinkStick that yields whenever they change, see `BlinkStick::watch`
+pub struct ColorChanges<'a> {
+    stick: &'a dyn LedDevice,
+    interval: Duration,
+    last: Option<Vec<Color>>,
+}
+
+impl Iterator for ColorChanges<'_> {
+    type Item = Result<Vec<Color>, FeatureError>;
+
+    /// Blocks until the colors differ from the colors yielded last, polling once every interval. Never returns
+    /// `None`, a failed read is yielded as an error and polling continues with the next call.
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let start = Instant::now();
+            match self.stick.get_all_led_colors() {
+                Ok(colors) if self.last.as_ref() != Some(&colors) => {
+                    self.last = Some(colors.clone());
+                    return Some(Ok(colors));
+                }
+                Ok(_) => (),
+                Err(e) => return Some(Err(e)),
+            }
+
+            std::thread::sleep(self.interval.saturating_sub(start.elapsed()));
+        }
+    }
+}
+
+impl BlinkStick {
+    /// Polls the colors of the device every `interval` and yields them whenever they change, starting with the
+    /// current colors. Useful to follow what another program writes to the device.
+    ///
+    /// # Example
+    /// Prints the current colors
+    /// ```
+    /// use blinkstick_rs::BlinkStick;
+    /// use std::time::Duration;
+    ///
+    /// let blinkstick = BlinkStick::new().unwrap();
+    /// for colors in blinkstick.watch(Duration::from_millis(100)).take(1) {
+    ///     println!("{:?}", colors.unwrap());
+    /// }
+    /// ```
+    pub fn watch(&self, interval: Duration) -> ColorChanges<'_> {
+        ColorChanges {
+            stick: self,
+            interval,
+            last: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FeatureErrorType, COLOR_OFF};
+    use std::sync::Mutex;
+
+    /// A device whose reads return the given results in turn
+    struct ScriptedReads(Mutex<Vec<Result<Vec<Color>, FeatureError>>>);
+
+    impl LedDevice for ScriptedReads {
+        fn led_count(&self) -> u8 {
+            1
+        }
+
+        fn set_led_color(&self, _led: u8, _color: Color) -> Result<(), FeatureError> {
+            Ok(())
+        }
+
+        fn set_all_leds_colors(&self, _colors: &[Color]) -> Result<(), FeatureError> {
+            Ok(())
+        }
+
+        fn get_all_led_colors(&self) -> Result<Vec<Color>, FeatureError> {
+            self.0.lock().unwrap().remove(0)
+        }
+    }
+
+    #[test]
+    fn only_changed_colors_and_failures_are_yielded() {
+        let timeout = || FeatureError {
+            kind: FeatureErrorType::Timeout,
+        };
+        let device = ScriptedReads(Mutex::new(vec![
+            Ok(vec![COLOR_OFF]),
+            Ok(vec![COLOR_OFF]),
+            Ok(vec![Color::RED]),
+            Err(timeout()),
+            Ok(vec![Color::RED]),
+            Ok(vec![COLOR_OFF]),
+        ]));
+        let changes = ColorChanges {
+            stick: &device,
+            interval: Duration::ZERO,
+            last: None,
+        };
+
+        let yielded: Vec<Result<Vec<Color>, FeatureErrorType>> =
+            changes.take(4).map(|colors| colors.map_err(|e| e.kind)).collect();
+
+        assert_eq!(
+            yielded,
+            vec![
+                Ok(vec![COLOR_OFF]),
+                Ok(vec![Color::RED]),
+                Err(FeatureErrorType::Timeout),
+                Ok(vec![COLOR_OFF])
+            ]
+        );
+    }
+}
@@ -0,0 +1,366 @@
+//! The feature reports exchanged with a BlinkStick, as pure functions between frames and bytes.
+//!
+//! Every report starts with its report id. The colors of all leds travel in report 6, in GRB order after a
+//! two byte header. A single led is set with report 5, in RGB order. Frames of more than `CHANNEL_LEDS` leds are
+//! split into one report per channel of the BlinkStick Pro, the first `CHANNEL_LEDS` leds on channel 0.
+//!
+//! The `parse_` functions accept any bytes a device might send and report what is wrong with them instead of
+//! panicking, which also makes them suitable as fuzzing targets.
+
+use crate::Color;
+use std::error::Error;
+use std::fmt::Formatter;
+
+/// The id of the feature report holding the colors of every led
+pub const LEDS_REPORT_ID: u8 = 0x6;
+
+/// The id of the feature report setting the color of a single led
+pub const LED_REPORT_ID: u8 = 0x5;
+
+/// The id of the feature report holding the mode of a BlinkStick Pro
+pub const MODE_REPORT_ID: u8 = 0x4;
+
+/// The id of the feature report holding the number of leds of a BlinkStick Pro or Flex
+pub const LED_COUNT_REPORT_ID: u8 = 0x81;
+
+/// The id of the feature report holding the first info block, where BlinkStick tools keep the device's name
+pub const INFO_BLOCK_1_REPORT_ID: u8 = 0x2;
+
+/// The id of the feature report holding the second info block, free for applications to store data on the device
+pub const INFO_BLOCK_2_REPORT_ID: u8 = 0x3;
+
+/// The number of bytes an info block holds after its report id
+pub const INFO_BLOCK_LENGTH: usize = 32;
+
+/// The number of bytes in front of the colors of a leds report: the report id and a channel
+pub const LEDS_REPORT_HEADER: usize = 2;
+
+/// The number of leds a BlinkStick Pro drives on each of its channels
+pub const CHANNEL_LEDS: usize = 64;
+
+/// The number of channels of a BlinkStick Pro, the most of any BlinkStick
+pub const CHANNEL_COUNT: usize = 3;
+
+/// The largest number of leds a single report can describe
+const MAX_LEDS: usize = u8::MAX as usize;
+
+/// Why a report received from a device could not be understood
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportError {
+    /// The report is shorter than its header and a single color
+    TooShort { length: usize },
+    /// The report carries a different id than was asked for
+    UnexpectedId { expected: u8, actual: u8 },
+    /// The report holds colors for fewer leds than the device has
+    MissingLeds { expected: usize, actual: usize },
+    /// The report holds colors for more leds than a device can have
+    TooManyLeds { count: usize },
+}
+
+impl std::fmt::Display for ReportError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReportError::TooShort { length } => write!(f, "Report of {} bytes is too short to hold a color", length),
+            ReportError::UnexpectedId { expected, actual } => {
+                write!(f, "Expected report {}, the device sent report {}", expected, actual)
+            }
+            ReportError::MissingLeds { expected, actual } => {
+                write!(f, "Expected colors for {} leds, the report holds {}", expected, actual)
+            }
+            ReportError::TooManyLeds { count } => write!(f, "Report holds colors for {} leds", count),
+        }
+    }
+}
+
+impl Error for ReportError {}
+
+/// Encodes the colors of every led into a leds report
+pub fn encode_leds_report(colors: &[Color]) -> Vec<u8> {
+    let mut report = vec![LEDS_REPORT_ID, 0];
+    for color in colors {
+        report.extend_from_slice(&[color.g, color.r, color.b]);
+    }
+
+    report
+}
+
+/// Encodes the colors of every led into the reports that carry them, a single leds report unless the frame holds
+/// more than `CHANNEL_LEDS` leds, which are split into one report per channel. Leds past the last of the
+/// `CHANNEL_COUNT` channels are not encoded.
+pub fn encode_leds_reports(colors: &[Color]) -> Vec<Vec<u8>> {
+    encode_channel_reports(colors, &default_channel_leds(colors.len()))
+}
+
+/// Encodes the colors of every led into one leds report per channel, the first `channel_leds[0]` leds on channel
+/// 0, the next `channel_leds[1]` on channel 1 and so on. Leds past the last channel are not encoded.
+pub fn encode_channel_reports(colors: &[Color], channel_leds: &[u8]) -> Vec<Vec<u8>> {
+    let mut first = 0;
+    channel_leds
+        .iter()
+        .enumerate()
+        .map(|(channel, leds)| {
+            let last = (first + *leds as usize).min(colors.len());
+            let mut report = encode_leds_report(&colors[first.min(last)..last]);
+            report[1] = channel as u8;
+            first = last;
+            report
+        })
+        .collect()
+}
+
+/// The number of leds on each channel when every channel is filled up to `CHANNEL_LEDS` leds before the next,
+/// a single channel for up to `CHANNEL_LEDS` leds and never more than `CHANNEL_COUNT` channels
+pub fn default_channel_leds(led_count: usize) -> Vec<u8> {
+    if led_count <= CHANNEL_LEDS {
+        return vec![led_count as u8];
+    }
+
+    (0..led_count)
+        .step_by(CHANNEL_LEDS)
+        .take(CHANNEL_COUNT)
+        .map(|first| (led_count - first).min(CHANNEL_LEDS) as u8)
+        .collect()
+}
+
+/// Decodes the colors of every led from a leds report, ignoring a trailing incomplete color
+pub fn decode_leds_report(report: &[u8]) -> Vec<Color> {
+    report
+        .get(LEDS_REPORT_HEADER..)
+        .unwrap_or(&[])
+        .chunks_exact(3)
+        .map(|grb| Color {
+            r: grb[1],
+            g: grb[0],
+            b: grb[2],
+        })
+        .collect()
+}
+
+/// Parses the colors of every led from a leds report read from a device
+///
+/// Fails unless the report is a leds report with at least one complete color. Bytes after the last complete
+/// color are ignored.
+pub fn parse_leds_report(report: &[u8]) -> Result<Vec<Color>, ReportError> {
+    match report.first() {
+        Some(&LEDS_REPORT_ID) => (),
+        Some(&actual) => {
+            return Err(ReportError::UnexpectedId {
+                expected: LEDS_REPORT_ID,
+                actual,
+            })
+        }
+        None => return Err(ReportError::TooShort { length: 0 }),
+    }
+
+    let colors = decode_leds_report(report);
+    if colors.is_empty() {
+        Err(ReportError::TooShort { length: report.len() })
+    } else if colors.len() > MAX_LEDS {
+        Err(ReportError::TooManyLeds { count: colors.len() })
+    } else {
+        Ok(colors)
+    }
+}
+
+/// Parses the number of leds of a device from the leds report it sent when it was opened
+pub fn parse_led_count(report: &[u8]) -> Result<u8, ReportError> {
+    parse_leds_report(report).map(|colors| colors.len() as u8)
+}
+
+/// Encodes the report that sets a single led to a color, leds past `CHANNEL_LEDS` on the channel they are on
+pub fn encode_led_report(led: u8, color: Color) -> [u8; 6] {
+    let channel = led as usize / CHANNEL_LEDS;
+    let index = led as usize % CHANNEL_LEDS;
+    encode_channel_led_report(channel as u8, index as u8, color)
+}
+
+/// Encodes the report that sets the led at `index` on `channel` to a color
+pub fn encode_channel_led_report(channel: u8, index: u8, color: Color) -> [u8; 6] {
+    [LED_REPORT_ID, channel, index, color.r, color.g, color.b]
+}
+
+/// Decodes the led and color from a report that sets a single led, `None` if it is not such a report
+pub fn decode_led_report(report: &[u8]) -> Option<(u8, Color)> {
+    match report {
+        [LED_REPORT_ID, channel, index, r, g, b, ..] => {
+            let led = *channel as usize * CHANNEL_LEDS + *index as usize;
+            if led > MAX_LEDS {
+                return None;
+            }
+            Some((led as u8, Color { r: *r, g: *g, b: *b }))
+        }
+        _ => None,
+    }
+}
+
+/// Encodes the report that sets the mode of a BlinkStick Pro
+pub fn encode_mode_report(mode: u8) -> [u8; 2] {
+    [MODE_REPORT_ID, mode]
+}
+
+/// Encodes the report that sets the number of leds of a BlinkStick Pro or Flex
+pub fn encode_led_count_report(count: u8) -> [u8; 2] {
+    [LED_COUNT_REPORT_ID, count]
+}
+
+/// Encodes a report writing `data` to the info block of report `id`, padded with zeros or cut to
+/// `INFO_BLOCK_LENGTH` bytes
+pub fn encode_info_block_report(id: u8, data: &[u8]) -> Vec<u8> {
+    let mut report = vec![0; 1 + INFO_BLOCK_LENGTH];
+    report[0] = id;
+    let length = data.len().min(INFO_BLOCK_LENGTH);
+    report[1..1 + length].copy_from_slice(&data[..length]);
+    report
+}
+
+/// Parses the data of an info block from a report with the given `id`, padding missing bytes with zeros
+pub fn parse_info_block_report(id: u8, report: &[u8]) -> Result<[u8; INFO_BLOCK_LENGTH], ReportError> {
+    match report.first() {
+        Some(actual) if *actual != id => {
+            return Err(ReportError::UnexpectedId {
+                expected: id,
+                actual: *actual,
+            })
+        }
+        Some(_) => (),
+        None => return Err(ReportError::TooShort { length: 0 }),
+    }
+
+    let mut data = [0; INFO_BLOCK_LENGTH];
+    let length = (report.len() - 1).min(INFO_BLOCK_LENGTH);
+    data[..length].copy_from_slice(&report[1..1 + length]);
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Every known device variant with the golden leds report for its number of leds
+    const GOLDEN_REPORTS: [(usize, &str); 6] = [
+        (1, include_str!("../tests/golden/blinkstick.hex")),
+        (2, include_str!("../tests/golden/nano.hex")),
+        (8, include_str!("../tests/golden/square.hex")),
+        (8, include_str!("../tests/golden/strip.hex")),
+        (32, include_str!("../tests/golden/flex.hex")),
+        (64, include_str!("../tests/golden/pro.hex")),
+    ];
+
+    /// The frame the golden reports were made from, led n set to r=3n g=3n+1 b=3n+2
+    fn golden_frame(led_count: usize) -> Vec<Color> {
+        (0..led_count as u8)
+            .map(|led| Color {
+                r: 3 * led,
+                g: 3 * led + 1,
+                b: 3 * led + 2,
+            })
+            .collect()
+    }
+
+    fn parse_hex(golden: &str) -> Vec<u8> {
+        golden
+            .lines()
+            .filter(|line| !line.starts_with('#'))
+            .flat_map(|line| line.split_whitespace())
+            .map(|byte| u8::from_str_radix(byte, 16).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn leds_reports_match_golden_files() {
+        for (led_count, golden) in GOLDEN_REPORTS.iter() {
+            let frame = golden_frame(*led_count);
+            let report = parse_hex(golden);
+
+            assert_eq!(encode_leds_report(&frame), report, "{} leds", led_count);
+            assert_eq!(decode_leds_report(&report), frame, "{} leds", led_count);
+        }
+    }
+
+    #[test]
+    fn long_frames_are_split_across_channels() {
+        let frame = vec![Color { r: 1, g: 2, b: 3 }; 150];
+        let reports = encode_leds_reports(&frame);
+
+        assert_eq!(reports.len(), 3);
+        assert_eq!(reports[0][..5], [LEDS_REPORT_ID, 0, 2, 1, 3]);
+        assert_eq!(reports[2][..2], [LEDS_REPORT_ID, 2]);
+        assert_eq!(decode_leds_report(&reports[2]).len(), 150 - 2 * CHANNEL_LEDS);
+        assert_eq!(
+            encode_leds_reports(&frame[..64]),
+            vec![encode_leds_report(&frame[..64])]
+        );
+
+        let reports = encode_leds_reports(&vec![Color { r: 1, g: 2, b: 3 }; 255]);
+        assert_eq!(reports.len(), CHANNEL_COUNT);
+        assert_eq!(decode_leds_report(&reports[2]).len(), CHANNEL_LEDS);
+        assert_eq!(default_channel_leds(255), vec![64, 64, 64]);
+
+        let reports = encode_channel_reports(&frame[..30], &[10, 20]);
+        assert_eq!(reports.len(), 2);
+        assert_eq!(reports[1][..2], [LEDS_REPORT_ID, 1]);
+        assert_eq!(decode_leds_report(&reports[1]).len(), 20);
+
+        let report = encode_led_report(130, Color { r: 9, g: 8, b: 7 });
+        assert_eq!(report, [LED_REPORT_ID, 2, 2, 9, 8, 7]);
+        assert_eq!(decode_led_report(&report), Some((130, Color { r: 9, g: 8, b: 7 })));
+    }
+
+    #[test]
+    fn malformed_leds_reports_are_rejected() {
+        assert_eq!(parse_leds_report(&[]), Err(ReportError::TooShort { length: 0 }));
+        assert_eq!(
+            parse_leds_report(&[0x6, 0, 1, 2]),
+            Err(ReportError::TooShort { length: 4 })
+        );
+        assert_eq!(
+            parse_leds_report(&[0x1, 0, 1, 2, 3]),
+            Err(ReportError::UnexpectedId {
+                expected: 0x6,
+                actual: 0x1
+            })
+        );
+        assert_eq!(parse_led_count(&[0x6, 0, 1, 2, 3, 4]), Ok(1));
+    }
+
+    #[test]
+    #[cfg(feature = "rand")]
+    fn parsing_arbitrary_bytes_never_panics() {
+        use rand::Rng;
+
+        let mut rng = rand::thread_rng();
+        for length in 0..200 {
+            let mut report: Vec<u8> = (0..length).map(|_| rng.gen()).collect();
+            if let Some(id) = report.first_mut() {
+                *id = LEDS_REPORT_ID;
+            }
+
+            let _ = parse_leds_report(&report);
+            let _ = decode_led_report(&report);
+        }
+    }
+
+    #[test]
+    fn led_report_is_rgb_ordered() {
+        let color = Color {
+            r: 0x12,
+            g: 0x34,
+            b: 0x56,
+        };
+
+        assert_eq!(encode_led_report(3, color), [0x5, 0, 3, 0x12, 0x34, 0x56]);
+        assert_eq!(decode_led_report(&encode_led_report(3, color)), Some((3, color)));
+        assert_eq!(decode_led_report(&[0x6, 0, 1, 2, 3, 4]), None);
+    }
+
+    #[test]
+    fn info_blocks_are_padded_to_their_length() {
+        let report = encode_info_block_report(INFO_BLOCK_2_REPORT_ID, b"abc");
+        assert_eq!(report.len(), 1 + INFO_BLOCK_LENGTH);
+        assert_eq!(report[..5], [INFO_BLOCK_2_REPORT_ID, b'a', b'b', b'c', 0]);
+
+        let data = parse_info_block_report(INFO_BLOCK_2_REPORT_ID, &report[..3]).unwrap();
+        assert_eq!(data[..3], *b"ab\0");
+        assert!(parse_info_block_report(INFO_BLOCK_1_REPORT_ID, &report).is_err());
+    }
+}
@@ -0,0 +1,357 @@
+//! A subset of the WLED JSON API, so WLED integrations like Home Assistant and LedFx can control a BlinkStick as if
+//! it were a WLED node.
+//!
+//! The stick shows up as a WLED node with a single segment spanning every led and only the solid effect. Supported
+//! endpoints are `GET /json`, `GET /json/state`, `GET /json/info`, `GET /json/eff`, `GET /json/pal` and
+//! `POST /json/state` (also accepted on `/json`). A state update understands `on` (including `"t"` to toggle),
+//! `bri` and, on the first segment, `col` and the per-led `i` array. Other members are ignored.
+
+use crate::http::{HttpServer, Request, Response};
+use crate::json::{self, Json};
+use crate::render::scale_color;
+use crate::{AnimationIter, Animator, Color, COLOR_OFF};
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::sync::atomic::AtomicBool;
+use std::time::Duration;
+
+/// The WLED version reported to clients, the JSON API of this version is what is implemented
+const WLED_VERSION: &str = "0.14.0";
+
+/// The on/off state, brightness and colors of a BlinkStick as WLED sees them
+#[derive(Debug, Clone, PartialEq)]
+pub struct WledState {
+    /// Whether the leds are on
+    pub on: bool,
+    /// The master brightness, from 0 to 255
+    pub brightness: u8,
+    /// The color of every led at full brightness
+    pub leds: Vec<Color>,
+}
+
+impl WledState {
+    /// A state for `led_count` leds that are on at full brightness but black
+    pub fn new(led_count: usize) -> WledState {
+        WledState {
+            on: true,
+            brightness: 255,
+            leds: vec![COLOR_OFF; led_count],
+        }
+    }
+
+    /// Applies a WLED state update, like `{"on": true, "bri": 128, "seg": [{"col": [[255, 0, 0]]}]}`. An invalid
+    /// update changes nothing.
+    pub fn update(&mut self, body: &str) -> Result<(), &'static str> {
+        let update = Json::parse(body)?;
+        if !matches!(update, Json::Object(_)) {
+            return Err("expected a JSON object");
+        }
+
+        // Applied to a copy, so an invalid member doesn't leave the members before it applied
+        let mut next = self.clone();
+        next.apply(&update)?;
+        *self = next;
+
+        Ok(())
+    }
+
+    fn apply(&mut self, update: &Json) -> Result<(), &'static str> {
+        match update.get("on") {
+            Some(Json::Bool(on)) => self.on = *on,
+            Some(toggle) if toggle.as_str() == Some("t") => self.on = !self.on,
+            Some(_) => return Err("invalid on"),
+            None => (),
+        }
+        if let Some(brightness) = update.get("bri") {
+            self.brightness = brightness.as_f64().ok_or("invalid bri")?.clamp(0.0, 255.0) as u8;
+        }
+
+        // WLED accepts a single segment object as well as an array of them
+        let segment = match update.get("seg") {
+            Some(Json::Array(segments)) => segments.first(),
+            segment => segment,
+        };
+        if let Some(segment) = segment {
+            if let Some(colors) = segment.get("col").and_then(Json::as_array) {
+                if let Some(color) = colors.first() {
+                    let color = parse_color(color).ok_or("invalid col")?;
+                    self.leds.iter_mut().for_each(|led| *led = color);
+                }
+            }
+            if let Some(leds) = segment.get("i") {
+                self.set_individual(leds.as_array().ok_or("invalid i")?)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Sets leds from a WLED `i` array, a list of colors where an index, or a start and stop index, before a color
+    /// choose the leds it applies to
+    fn set_individual(&mut self, values: &[Json]) -> Result<(), &'static str> {
+        let led_count = self.leds.len();
+        let mut next: usize = 0;
+        let mut indices = Vec::new();
+        for value in values {
+            if let Some(index) = value.as_f64() {
+                // Indices past the last led address nothing, clamping them keeps huge ranges from looping
+                indices.push((index.max(0.0) as usize).min(led_count));
+                continue;
+            }
+
+            let color = parse_color(value).ok_or("invalid i")?;
+            let range = match indices.as_slice() {
+                [] => next..next.saturating_add(1).min(led_count),
+                [index] => *index..index.saturating_add(1).min(led_count),
+                [start, stop, ..] => *start..*stop,
+            };
+            next = range.end;
+            indices.clear();
+            if let Some(leds) = self.leds.get_mut(range) {
+                leds.iter_mut().for_each(|led| *led = color);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The colors shown on the leds, with the brightness applied
+    pub fn frame(&self) -> Vec<Color> {
+        if !self.on {
+            return vec![COLOR_OFF; self.leds.len()];
+        }
+
+        let factor = self.brightness as f32 / 255.0;
+        self.leds.iter().map(|color| scale_color(*color, factor)).collect()
+    }
+
+    /// The state as a WLED `/json/state` document
+    pub fn to_json(&self) -> String {
+        let color = self.leds.first().copied().unwrap_or(COLOR_OFF);
+        format!(
+            "{{\"on\":{},\"bri\":{},\"transition\":0,\"ps\":-1,\"pl\":-1,\"lor\":0,\"mainseg\":0,\"seg\":[{{\"id\":0,\
+             \"start\":0,\"stop\":{len},\"len\":{len},\"on\":true,\"bri\":255,\"col\":[[{},{},{}],[0,0,0],[0,0,0]],\
+             \"fx\":0,\"sx\":128,\"ix\":128,\"pal\":0,\"sel\":true}}]}}",
+            self.on,
+            self.brightness,
+            color.r,
+            color.g,
+            color.b,
+            len = self.leds.len()
+        )
+    }
+}
+
+/// Reads a WLED color, an `[r, g, b]` array or a hex string
+fn parse_color(value: &Json) -> Option<Color> {
+    match value {
+        Json::String(hex) => crate::reader::parse_hex_digits(hex.get(..6)?),
+        Json::Array(channels) => {
+            let channel = |index: usize| {
+                channels
+                    .get(index)?
+                    .as_f64()
+                    .map(|channel| channel.clamp(0.0, 255.0) as u8)
+            };
+            Some(Color {
+                r: channel(0)?,
+                g: channel(1)?,
+                b: channel(2)?,
+            })
+        }
+        _ => None,
+    }
+}
+
+/// Answers the WLED JSON API over HTTP and shows the resulting state on an `Animator`
+///
+/// # Example
+/// Serves the WLED API on port 80, where Home Assistant's WLED integration can find it
+/// ```no_run
+/// use blinkstick_rs::wled::WledServer;
+/// use blinkstick_rs::{Animator, BlinkStick};
+/// use std::sync::atomic::AtomicBool;
+///
+/// let blinkstick = BlinkStick::new().unwrap();
+/// let leds = blinkstick.max_leds as usize;
+///
+/// let mut server = WledServer::bind("0.0.0.0:80", Animator::new(blinkstick), leds).unwrap();
+/// server.run(&AtomicBool::new(false)).unwrap();
+/// ```
+pub struct WledServer {
+    http: HttpServer,
+    animator: Animator,
+    name: String,
+    state: WledState,
+}
+
+impl WledServer {
+    /// Listens for WLED requests on `address`, driving the `led_count` leds of `animator`
+    pub fn bind<A: ToSocketAddrs>(address: A, animator: Animator, led_count: usize) -> std::io::Result<WledServer> {
+        Ok(WledServer {
+            http: HttpServer::bind(address)?,
+            animator,
+            name: "BlinkStick".to_string(),
+            state: WledState::new(led_count),
+        })
+    }
+
+    /// Changes the node name shown by WLED clients
+    pub fn name(mut self, name: &str) -> WledServer {
+        self.name = name.to_string();
+        self
+    }
+
+    /// The address the server listens on, useful after binding to port 0
+    pub fn local_addr(&self) -> std::io::Result<SocketAddr> {
+        self.http.local_addr()
+    }
+
+    /// The current state
+    pub fn state(&self) -> &WledState {
+        &self.state
+    }
+
+    /// Answers requests until `stop` is set
+    pub fn run(&mut self, stop: &AtomicBool) -> std::io::Result<()> {
+        let WledServer {
+            http,
+            animator,
+            name,
+            state,
+        } = self;
+        http.run(stop, |request| handle(request, name, state, animator))
+    }
+
+    /// Answers a single request, showing the new state if it changed
+    pub fn handle(&mut self, request: &Request) -> Response {
+        handle(request, &self.name, &mut self.state, &self.animator)
+    }
+}
+
+fn handle(request: &Request, name: &str, state: &mut WledState, animator: &Animator) -> Response {
+    match (request.method.as_str(), request.path.trim_end_matches('/')) {
+        ("GET", "/json") => Response::json(format!(
+            "{{\"state\":{},\"info\":{},\"effects\":[\"Solid\"],\"palettes\":[\"Default\"]}}",
+            state.to_json(),
+            info_json(name, state.leds.len())
+        )),
+        ("GET", "/json/state") => Response::json(state.to_json()),
+        ("GET", "/json/info") => Response::json(info_json(name, state.leds.len())),
+        ("GET", "/json/eff") => Response::json("[\"Solid\"]".to_string()),
+        ("GET", "/json/pal") => Response::json("[\"Default\"]".to_string()),
+        ("POST", "/json") | ("POST", "/json/state") => match state.update(&request.body) {
+            Ok(()) => {
+                animator.play("wled", AnimationIter::new(vec![(state.frame(), Duration::ZERO)]));
+
+                // WLED answers with the new state when asked to with "v", and with a bare success otherwise
+                let verbose = Json::parse(&request.body)
+                    .ok()
+                    .and_then(|update| update.get("v").and_then(Json::as_bool))
+                    .unwrap_or(false);
+                if verbose {
+                    Response::json(state.to_json())
+                } else {
+                    Response::json("{\"success\":true}".to_string())
+                }
+            }
+            Err(message) => Response::error(400, message),
+        },
+        (_, "/json") | (_, "/json/state") | (_, "/json/info") | (_, "/json/eff") | (_, "/json/pal") => {
+            Response::error(405, "method not allowed")
+        }
+        _ => Response::error(404, "not found"),
+    }
+}
+
+fn info_json(name: &str, led_count: usize) -> String {
+    format!(
+        "{{\"ver\":\"{}\",\"leds\":{{\"count\":{},\"rgbw\":false,\"wv\":false,\"cct\":false,\"pwr\":0,\"fps\":0,\
+         \"maxpwr\":0,\"maxseg\":1}},\"name\":{},\"udpport\":21324,\"live\":false,\"fxcount\":1,\"palcount\":1,\
+         \"arch\":\"blinkstick-rs\",\"brand\":\"WLED\",\"product\":\"BlinkStick\",\"mac\":\"000000000000\"}}",
+        WLED_VERSION,
+        led_count,
+        json::string(name)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RED: Color = Color { r: 255, g: 0, b: 0 };
+    const BLUE: Color = Color { r: 0, g: 0, b: 255 };
+
+    #[test]
+    fn applies_state_updates() {
+        let mut state = WledState::new(4);
+
+        state
+            .update(r#"{"on": true, "bri": 51, "seg": [{"col": [[255, 0, 0], [0, 0, 0]]}]}"#)
+            .unwrap();
+        assert_eq!(state.leds, vec![RED; 4]);
+        assert_eq!(state.frame(), vec![Color { r: 51, g: 0, b: 0 }; 4]);
+
+        state.update(r#"{"seg": {"i": [2, "0000FF", "0000FF"]}}"#).unwrap();
+        assert_eq!(state.leds, vec![RED, RED, BLUE, BLUE]);
+
+        state.update(r#"{"on": "t"}"#).unwrap();
+        assert_eq!(state.frame(), vec![COLOR_OFF; 4]);
+        assert!(state.update(r#"{"bri": "full"}"#).is_err());
+    }
+
+    #[test]
+    fn invalid_updates_change_nothing() {
+        let mut state = WledState::new(4);
+
+        assert!(state
+            .update(r#"{"on": false, "bri": 9, "seg": {"col": [[0, 0, 255]], "i": ["FF0000", "nope"]}}"#)
+            .is_err());
+        assert!(state.update(r#"{"bri": 9, "seg": {"col": ["nope"]}}"#).is_err());
+        assert_eq!(state, WledState::new(4));
+    }
+
+    #[test]
+    fn clamps_individual_ranges_to_the_leds() {
+        let mut state = WledState::new(4);
+
+        state.update(r#"{"seg": {"i": [1e30, "0000FF"]}}"#).unwrap();
+        state
+            .update(r#"{"seg": {"i": [0, 4000000000, "FF0000", "0000FF"]}}"#)
+            .unwrap();
+        assert_eq!(state.leds, vec![RED; 4]);
+
+        state.update(r#"{"seg": {"i": [3, "0000FF", "0000FF"]}}"#).unwrap();
+        assert_eq!(state.leds, vec![RED, RED, RED, BLUE]);
+    }
+
+    #[test]
+    fn answers_wled_endpoints() {
+        let mut state = WledState::new(2);
+        let animator = Animator::headless();
+        let request = |method: &str, path: &str, body: &str| Request {
+            method: method.to_string(),
+            path: path.to_string(),
+            body: body.to_string(),
+        };
+
+        let info = handle(&request("GET", "/json/info", ""), "desk", &mut state, &animator);
+        let info = Json::parse(&info.body).unwrap();
+        assert_eq!(
+            info.get("leds").and_then(|leds| leds.get("count")),
+            Some(&Json::Number(2.0))
+        );
+        assert_eq!(info.get("name").and_then(Json::as_str), Some("desk"));
+
+        let update = handle(
+            &request("POST", "/json/state", r#"{"bri": 9, "v": true}"#),
+            "desk",
+            &mut state,
+            &animator,
+        );
+        assert_eq!(Json::parse(&update.body).unwrap().get("bri"), Some(&Json::Number(9.0)));
+        assert_eq!(
+            handle(&request("GET", "/win", ""), "desk", &mut state, &animator).status,
+            404
+        );
+    }
+}
@@ -0,0 +1,97 @@
+//! Compares the reports this crate writes with the reports of the official blinkstick-python library, recorded in
+//! `tests/golden/python`.
+//!
+//! The wire format is part of the public api: a failing test here means devices would receive different bytes
+//! than before, which needs a new major version unless the old bytes were wrong.
+//!
+//! Two layouts differ on purpose and are not compared. blinkstick-python sets led 0 of channel 0 with report 1,
+//! this crate uses report 5 for every led, which the firmware accepts just as well. blinkstick-python also picks
+//! one of the reports 6 to 9 by the size of a frame, while this crate always uses report 6 sized as the device
+//! reports it.
+
+extern crate blinkstick_rs;
+
+use blinkstick_rs::wire::{
+    decode_led_report, decode_leds_report, encode_info_block_report, encode_led_count_report, encode_led_report,
+    encode_leds_report, encode_mode_report, parse_info_block_report, INFO_BLOCK_2_REPORT_ID,
+};
+use blinkstick_rs::{Color, Mode};
+
+fn parse_hex(golden: &str) -> Vec<u8> {
+    golden
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .flat_map(|line| line.split_whitespace())
+        .map(|byte| u8::from_str_radix(byte, 16).unwrap())
+        .collect()
+}
+
+/// The frame the golden reports were made from, led n set to r=3n g=3n+1 b=3n+2
+fn golden_frame(led_count: usize) -> Vec<Color> {
+    (0..led_count as u8)
+        .map(|led| Color {
+            r: 3 * led,
+            g: 3 * led + 1,
+            b: 3 * led + 2,
+        })
+        .collect()
+}
+
+#[test]
+fn single_led_reports_match_set_color() {
+    let golden = parse_hex(include_str!("golden/python/set_color.hex"));
+    let color = Color {
+        r: 0x12,
+        g: 0x34,
+        b: 0x56,
+    };
+
+    assert_eq!(encode_led_report(2 * 64 + 5, color).to_vec(), golden);
+    assert_eq!(decode_led_report(&golden), Some((2 * 64 + 5, color)));
+}
+
+#[test]
+fn leds_reports_match_set_led_data() {
+    for (led_count, golden) in [
+        (2, include_str!("golden/python/set_led_data_nano.hex")),
+        (8, include_str!("golden/python/set_led_data_square.hex")),
+    ] {
+        let golden = parse_hex(golden);
+
+        // Reports are padded to the length the device reports, a full report 6 for the devices up to 8 leds
+        let mut report = encode_leds_report(&golden_frame(led_count));
+        report.resize(golden.len(), 0);
+        assert_eq!(report, golden, "{} leds", led_count);
+        assert_eq!(
+            decode_leds_report(&golden)[..led_count],
+            golden_frame(led_count)[..],
+            "{} leds",
+            led_count
+        );
+    }
+}
+
+#[test]
+fn mode_reports_match_set_mode() {
+    let golden = parse_hex(include_str!("golden/python/set_mode.hex"));
+
+    assert_eq!(encode_mode_report(Mode::Ws2812Mirror.to_byte()).to_vec(), golden);
+}
+
+#[test]
+fn led_count_reports_match_set_led_count() {
+    let golden = parse_hex(include_str!("golden/python/set_led_count.hex"));
+
+    assert_eq!(encode_led_count_report(32).to_vec(), golden);
+}
+
+#[test]
+fn info_block_reports_match_set_info_block2() {
+    let golden = parse_hex(include_str!("golden/python/set_info_block2.hex"));
+
+    assert_eq!(encode_info_block_report(INFO_BLOCK_2_REPORT_ID, b"blinkstick"), golden);
+    assert_eq!(
+        parse_info_block_report(INFO_BLOCK_2_REPORT_ID, &golden).unwrap()[..10],
+        b"blinkstick"[..]
+    );
+}
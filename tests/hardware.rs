@@ -0,0 +1,78 @@
+//! Writes to a connected BlinkStick and reads back what it shows, to catch protocol regressions on real devices
+//! that the golden files cannot.
+//!
+//! The tests only run with `BLINKSTICK_HARDWARE_TESTS=1` and pass without doing anything otherwise. They use the
+//! device `BLINKSTICK_SERIAL` selects, or the first one found, and turn its leds off when they are done. Run them
+//! once per variant, one at a time:
+//! ```sh
+//! BLINKSTICK_HARDWARE_TESTS=1 BLINKSTICK_SERIAL=BS000001-3.0 cargo test --test hardware -- --test-threads=1
+//! ```
+
+extern crate blinkstick_rs;
+
+use blinkstick_rs::{BlinkStick, Color};
+
+/// The environment variable enabling the tests in this file
+const HARDWARE_VARIABLE: &str = "BLINKSTICK_HARDWARE_TESTS";
+
+/// Opens the device under test, `None` unless hardware tests are enabled
+fn device_under_test() -> Option<BlinkStick> {
+    match std::env::var(HARDWARE_VARIABLE).as_deref() {
+        Ok("1") | Ok("true") => Some(BlinkStick::new().expect("Could not open the BlinkStick under test")),
+        _ => None,
+    }
+}
+
+/// A frame with a distinct color on every led, dim enough to look at
+fn distinct_frame(led_count: u8) -> Vec<Color> {
+    (0..led_count)
+        .map(|led| Color {
+            r: led.wrapping_mul(3) % 64,
+            g: led.wrapping_mul(5) % 64 + 1,
+            b: led.wrapping_mul(7) % 64 + 2,
+        })
+        .collect()
+}
+
+#[test]
+fn leds_count_matches_variant() {
+    let blinkstick = match device_under_test() {
+        Some(blinkstick) => blinkstick,
+        None => return,
+    };
+
+    if let Some(fixed) = blinkstick.variant().fixed_led_count() {
+        assert_eq!(blinkstick.max_leds, fixed, "{:?}", blinkstick.variant());
+    }
+}
+
+#[test]
+fn frames_read_back_as_written() {
+    let blinkstick = match device_under_test() {
+        Some(blinkstick) => blinkstick,
+        None => return,
+    };
+
+    let frame = distinct_frame(blinkstick.max_leds);
+    blinkstick.set_all_leds_colors(&frame).unwrap();
+    let read = blinkstick.get_all_led_colors().unwrap();
+    blinkstick.turn_off_all_leds().unwrap();
+
+    assert_eq!(read, frame, "{:?}", blinkstick.variant());
+}
+
+#[test]
+fn single_leds_read_back_as_written() {
+    let blinkstick = match device_under_test() {
+        Some(blinkstick) => blinkstick,
+        None => return,
+    };
+
+    let color = Color { r: 12, g: 34, b: 56 };
+    let led = blinkstick.max_leds - 1;
+    blinkstick.set_led_color(led, color).unwrap();
+    let read = blinkstick.get_led_color(led).unwrap();
+    blinkstick.turn_off_all_leds().unwrap();
+
+    assert_eq!(read, color, "{:?}", blinkstick.variant());
+}